@@ -0,0 +1,479 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of collaborative editing sessions of notes.
+//!
+//! Participants editing the same note join a session holding the content of the note as a [Document].
+//! Operations sent by a participant are applied to the document and forwarded to other participants.
+//! The content of the document is periodically saved into the note; the state of the document
+//! at the last save (the snapshot) and operations applied since then (pending updates) are sent
+//! to participants joining the session later.
+//!
+//! When the note is modified outside the session, the document is rebased onto the content of the note:
+//! changes of pending updates are merged line by line with changes of the note and expressed as new pending
+//! updates of the content of the note. Conflicting pending updates are discarded. All participants
+//! are synchronized again.
+//! Sessions are held in memory, participants of a note have to connect to the same server instance.
+
+use crate::crdt::{Document, Element, Id, Operation, INITIAL_SITE};
+use crate::diff::{diff, merge_lines, Step};
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::services::auth::Principal;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Maximum number of operations sent by a participant in a single update.
+pub const MAX_OPERATIONS: usize = 1000;
+
+/// Notification sent to a participant of a session.
+#[derive(Debug, Clone)]
+pub enum Notification {
+  /// The state of the document, sent when the participant joins the session
+  /// and when the document was replaced with the content of the note modified outside the session.
+  Sync {
+    /// Site identifier of the participant.
+    site_id: String,
+    /// Version of the note the snapshot was saved as.
+    version: i64,
+    /// Elements of the document at the last save.
+    snapshot: Vec<Element>,
+    /// Operations applied since the last save.
+    updates: Vec<Operation>,
+  },
+  /// Operations applied by another participant.
+  Update {
+    /// Site identifier of the participant who sent the operations.
+    site_id: String,
+    /// Applied operations.
+    operations: Vec<Operation>,
+  },
+  /// The document was saved into the note.
+  Saved {
+    /// New version of the note.
+    version: i64,
+  },
+}
+
+/// Collaborative editing session of a note.
+pub struct Session {
+  /// Identifier of the edited note.
+  note_id: String,
+  /// Version of the note the snapshot was saved as.
+  version: i64,
+  /// Elements of the document at the last save.
+  snapshot: Vec<Element>,
+  /// Operations applied since the last save.
+  pending: Vec<Operation>,
+  /// Current state of the document.
+  document: Document,
+  /// Senders of notifications to participants, by site identifiers.
+  participants: HashMap<String, mpsc::UnboundedSender<Notification>>,
+  /// Number of participants that joined the session, used to assign site identifiers.
+  joined: u64,
+  /// The user who applied the most recent pending operation, the author of the next save.
+  editor: Option<Principal>,
+}
+
+/// State of the document to be saved.
+pub struct PendingSave {
+  /// Version of the note the snapshot was saved as, expected when saving.
+  pub version: i64,
+  /// Content of the document.
+  pub content: String,
+  /// The user saving the document.
+  pub editor: Principal,
+  /// Elements of the document being saved.
+  elements: Vec<Element>,
+  /// Number of saved pending operations.
+  operations: usize,
+}
+
+impl Session {
+  /// Creates a session editing the content of the note.
+  fn new(note: &NoteEntity) -> Self {
+    let document = Document::new(&note.content);
+    Self {
+      note_id: note.note_id.clone(),
+      version: note.version,
+      snapshot: document.elements().to_vec(),
+      pending: vec![],
+      document,
+      participants: HashMap::new(),
+      joined: 0,
+      editor: None,
+    }
+  }
+  /// Returns the identifier of the edited note.
+  pub fn note_id(&self) -> &str {
+    &self.note_id
+  }
+  /// Returns the version of the note the snapshot was saved as.
+  pub fn version(&self) -> i64 {
+    self.version
+  }
+  /// Returns `true` when nobody participates in the session.
+  pub fn is_idle(&self) -> bool {
+    self.participants.is_empty()
+  }
+  /// Returns the synchronization notification for the participant.
+  fn sync(&self, site_id: &str) -> Notification {
+    Notification::Sync {
+      site_id: site_id.to_string(),
+      version: self.version,
+      snapshot: self.snapshot.clone(),
+      updates: self.pending.clone(),
+    }
+  }
+  /// Sends the notification to all participants except the one with specified site identifier.
+  fn notify(&mut self, notification: Notification, except: Option<&str>) {
+    // participants whose connections were dropped are removed
+    self
+      .participants
+      .retain(|site_id, sender| Some(site_id.as_str()) == except || sender.send(notification.clone()).is_ok());
+  }
+  /// Applies operations sent by the participant and forwards applied operations to other participants.
+  ///
+  /// Participants may insert only elements with their own site identifiers. Operations are applied in order,
+  /// when an operation is invalid, operations preceding it stay applied and the error is reported.
+  pub fn apply(&mut self, site_id: &str, operations: Vec<Operation>, principal: &Principal) -> Result<()> {
+    if operations.len() > MAX_OPERATIONS {
+      return Err(err_too_many_operations(operations.len()));
+    }
+    let mut applied = vec![];
+    let mut result = Ok(());
+    for operation in operations {
+      if let Operation::Insert { id, .. } = &operation {
+        if id.site != site_id {
+          result = Err(err_invalid_operation(&format!("foreign site identifier, id = {}", id)));
+          break;
+        }
+      }
+      match self.document.apply(&operation) {
+        Ok(true) => applied.push(operation),
+        Ok(false) => {}
+        Err(reason) => {
+          result = Err(reason);
+          break;
+        }
+      }
+    }
+    if !applied.is_empty() {
+      self.pending.extend(applied.iter().cloned());
+      self.editor = Some(principal.clone());
+      let notification = Notification::Update {
+        site_id: site_id.to_string(),
+        operations: applied,
+      };
+      self.notify(notification, Some(site_id));
+    }
+    result
+  }
+  /// Returns the state of the document to be saved, `None` when there are no pending operations.
+  pub fn pending_save(&self) -> Option<PendingSave> {
+    Some(PendingSave {
+      version: self.version,
+      content: self.document.text(),
+      editor: self.editor.clone().filter(|_| !self.pending.is_empty())?,
+      elements: self.document.elements().to_vec(),
+      operations: self.pending.len(),
+    })
+  }
+  /// Records the saved state of the document as the snapshot, operations applied after
+  /// the state was taken stay pending.
+  pub fn saved(&mut self, save: PendingSave, version: i64) {
+    self.version = version;
+    self.snapshot = save.elements;
+    self.pending.drain(..save.operations.min(self.pending.len()));
+    if self.pending.is_empty() {
+      self.editor = None;
+    }
+    self.notify(Notification::Saved { version }, None);
+  }
+  /// Replaces the document with the content of the note modified outside the session,
+  /// pending operations are discarded and all participants are synchronized again.
+  pub fn reset(&mut self, note: &NoteEntity) {
+    self.replace(note);
+    self.synchronize();
+  }
+  /// Rebases the document onto the content of the note modified outside the session.
+  ///
+  /// Changes made by pending operations are merged with changes of the note and applied to the content
+  /// of the note as new pending operations, so they are saved later. When the changes conflict,
+  /// pending operations are discarded. All participants are synchronized again.
+  pub fn rebase(&mut self, note: &NoteEntity) {
+    let base = Document::from_elements(self.snapshot.clone()).text();
+    let merged = (!self.pending.is_empty())
+      .then(|| merge_lines(&base, &self.document.text(), &note.content))
+      .flatten();
+    let editor = self.editor.take();
+    self.replace(note);
+    if let Some(merged) = merged {
+      let content: Vec<char> = note.content.chars().collect();
+      let operations = changes(&self.document, &content, &merged.chars().collect::<Vec<char>>());
+      for operation in &operations {
+        // operations are generated from the document, so they are always valid
+        let _ = self.document.apply(operation);
+      }
+      self.editor = editor.filter(|_| !operations.is_empty());
+      self.pending = operations;
+    }
+    self.synchronize();
+  }
+  /// Replaces the document with the content of the note, pending operations are discarded.
+  fn replace(&mut self, note: &NoteEntity) {
+    let document = Document::new(&note.content);
+    self.version = note.version;
+    self.snapshot = document.elements().to_vec();
+    self.pending.clear();
+    self.document = document;
+    self.editor = None;
+  }
+  /// Sends the state of the document to all participants.
+  fn synchronize(&mut self) {
+    let site_ids: Vec<String> = self.participants.keys().cloned().collect();
+    for site_id in site_ids {
+      let notification = self.sync(&site_id);
+      if self.participants[&site_id].send(notification).is_err() {
+        self.participants.remove(&site_id);
+      }
+    }
+  }
+  /// Ends the session, connections of all participants are closed.
+  pub fn close(&mut self) {
+    self.participants.clear();
+  }
+}
+
+/// Returns operations changing the text of the document created from the old content into the new content.
+fn changes(document: &Document, old: &[char], new: &[char]) -> Vec<Operation> {
+  let mut counter = document.max_counter();
+  let mut elements = document.elements().iter();
+  let mut values = new.iter();
+  let mut after: Option<Id> = None;
+  let mut operations = vec![];
+  for step in diff(old, new) {
+    match step {
+      Step::Equal => {
+        values.next();
+        after = elements.next().map(|element| element.id.clone());
+      }
+      Step::Delete => {
+        if let Some(element) = elements.next() {
+          operations.push(Operation::Delete { id: element.id.clone() });
+        }
+      }
+      Step::Insert => {
+        if let Some(value) = values.next() {
+          counter += 1;
+          let id = Id::new(counter, INITIAL_SITE);
+          operations.push(Operation::Insert {
+            id: id.clone(),
+            after: after.replace(id),
+            value: *value,
+          });
+        }
+      }
+    }
+  }
+  operations
+}
+
+/// Participant of a collaborative editing session.
+pub struct Participant {
+  /// Session the participant joined.
+  session: Arc<Mutex<Session>>,
+  /// Site identifier of the participant.
+  site_id: String,
+  /// Receiver of notifications.
+  receiver: mpsc::UnboundedReceiver<Notification>,
+}
+
+impl Participant {
+  /// Returns the site identifier of the participant.
+  pub fn site_id(&self) -> &str {
+    &self.site_id
+  }
+  /// Waits for the next notification, `None` when the session has ended.
+  pub async fn next(&mut self) -> Option<Notification> {
+    self.receiver.recv().await
+  }
+  /// Applies operations sent by the participant, see [Session::apply].
+  pub fn apply(&self, operations: Vec<Operation>, principal: &Principal) -> Result<()> {
+    self.session.lock().unwrap().apply(&self.site_id, operations, principal)
+  }
+}
+
+impl Drop for Participant {
+  /// Leaves the session.
+  fn drop(&mut self) {
+    self.session.lock().unwrap().participants.remove(&self.site_id);
+  }
+}
+
+/// Collaborative editing sessions of notes.
+#[derive(Default)]
+pub struct CollaborationHub {
+  /// Sessions by identifiers of edited notes.
+  sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+}
+
+impl CollaborationHub {
+  /// Joins the session editing the note, the session is started when nobody edits the note.
+  /// The participant receives the state of the document as the first notification.
+  pub fn join(&self, note: &NoteEntity) -> Participant {
+    let session = Arc::clone(
+      self
+        .sessions
+        .lock()
+        .unwrap()
+        .entry(note.note_id.clone())
+        .or_insert_with(|| Arc::new(Mutex::new(Session::new(note)))),
+    );
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let site_id = {
+      let mut session = session.lock().unwrap();
+      session.joined += 1;
+      let site_id = session.joined.to_string();
+      // the receiver is held by the participant, so sending can not fail
+      let _ = sender.send(session.sync(&site_id));
+      session.participants.insert(site_id.clone(), sender);
+      site_id
+    };
+    Participant { session, site_id, receiver }
+  }
+  /// Returns all sessions.
+  pub fn sessions(&self) -> Vec<Arc<Mutex<Session>>> {
+    self.sessions.lock().unwrap().values().cloned().collect()
+  }
+  /// Removes the session of the note, the session has to be closed or idle.
+  pub fn remove(&self, note_id: &str) {
+    self.sessions.lock().unwrap().remove(note_id);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::crdt::Id;
+  use std::collections::BTreeSet;
+
+  fn principal() -> Principal {
+    Principal {
+      session_id: "session".to_string(),
+      user_id: "alice".to_string(),
+      login: "alice".to_string(),
+      roles: BTreeSet::new(),
+      permissions: BTreeSet::new(),
+    }
+  }
+
+  fn insert(counter: u64, site: &str, after: Option<Id>, value: char) -> Operation {
+    Operation::Insert {
+      id: Id::new(counter, site),
+      after,
+      value,
+    }
+  }
+
+  #[tokio::test]
+  async fn test_session() {
+    let hub = CollaborationHub::default();
+    let note = NoteEntity::new("alice", "title", "ab", None);
+    let mut first = hub.join(&note);
+    let Some(Notification::Sync {
+      site_id, snapshot, updates, ..
+    }) = first.next().await
+    else {
+      panic!()
+    };
+    assert_eq!("1", site_id);
+    assert_eq!(2, snapshot.len());
+    assert!(updates.is_empty());
+    let operation = insert(3, "1", Some(Id::new(2, "")), 'c');
+    first.apply(vec![operation.clone()], &principal()).unwrap();
+    // late joiner receives the snapshot and pending updates
+    let mut second = hub.join(&note);
+    let Some(Notification::Sync {
+      site_id, snapshot, updates, ..
+    }) = second.next().await
+    else {
+      panic!()
+    };
+    assert_eq!("2", site_id);
+    assert_eq!(2, snapshot.len());
+    assert_eq!(vec![operation], updates);
+    // operations are forwarded to other participants
+    second.apply(vec![Operation::Delete { id: Id::new(1, "") }], &principal()).unwrap();
+    let Some(Notification::Update { site_id, operations }) = first.next().await else {
+      panic!()
+    };
+    assert_eq!("2", site_id);
+    assert_eq!(1, operations.len());
+    // participants may insert only elements of their own site
+    assert_eq!(
+      "invalid operation: foreign site identifier, id = 4@1",
+      second.apply(vec![insert(4, "1", None, 'x')], &principal()).unwrap_err().to_string()
+    );
+    let session = hub.sessions().pop().unwrap();
+    let save = session.lock().unwrap().pending_save().unwrap();
+    assert_eq!("bc", save.content);
+    assert_eq!(note.version, save.version);
+    session.lock().unwrap().saved(save, note.version + 1);
+    assert!(session.lock().unwrap().pending_save().is_none());
+    assert!(matches!(first.next().await, Some(Notification::Saved { .. })));
+    drop(first);
+    drop(second);
+    assert!(session.lock().unwrap().is_idle());
+  }
+
+  #[tokio::test]
+  async fn test_rebase() {
+    let hub = CollaborationHub::default();
+    let mut note = NoteEntity::new("alice", "title", "a\nb\n", None);
+    let mut participant = hub.join(&note);
+    participant.next().await.unwrap();
+    participant.apply(vec![insert(5, "1", Some(Id::new(1, "")), 'x')], &principal()).unwrap();
+    let session = hub.sessions().pop().unwrap();
+    // changes of other lines are merged
+    note.content = "a\nb\nc\n".to_string();
+    note.version += 1;
+    session.lock().unwrap().rebase(&note);
+    let Some(Notification::Sync {
+      version, snapshot, updates, ..
+    }) = participant.next().await
+    else {
+      panic!()
+    };
+    assert_eq!(note.version, version);
+    assert_eq!(6, snapshot.len());
+    assert_eq!(1, updates.len());
+    let save = session.lock().unwrap().pending_save().unwrap();
+    assert_eq!("ax\nb\nc\n", save.content);
+    assert_eq!(note.version, save.version);
+    // conflicting changes are discarded
+    note.content = "a\nbb\nc\n".to_string();
+    note.version += 1;
+    participant.apply(vec![Operation::Delete { id: Id::new(3, "") }], &principal()).unwrap();
+    session.lock().unwrap().rebase(&note);
+    let Some(Notification::Sync { updates, .. }) = participant.next().await else {
+      panic!()
+    };
+    assert!(updates.is_empty());
+    assert!(session.lock().unwrap().pending_save().is_none());
+  }
+}
@@ -0,0 +1,62 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for note attachments.
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::handlers::attachments::{parse_range, AttachmentContent, AttachmentDto, ContentRange};
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Controller for checking that files may be attached to a note editable by the user,
+/// returns the note and the number of its attachments.
+pub async fn prepare(note_id: &str, principal: &Principal, storage: &Storage) -> Result<(NoteEntity, usize)> {
+  services::attachments::prepare(note_id, principal, storage).await
+}
+
+/// Controller for attaching uploaded files to a note editable by the user.
+pub async fn create(note_id: String, uploaded: Vec<AttachmentEntity>, principal: Principal, storage: &Storage) -> Result<Vec<AttachmentDto>> {
+  let attachments = services::attachments::create(&note_id, uploaded, &principal, storage).await?;
+  Ok(attachments.into_iter().map(Into::into).collect())
+}
+
+/// Controller for retrieving attachments of a note accessible to the user.
+pub async fn list(note_id: String, principal: Principal, storage: &Storage) -> Result<Vec<AttachmentDto>> {
+  let attachments = services::attachments::list(&note_id, &principal, storage).await?;
+  Ok(attachments.into_iter().map(Into::into).collect())
+}
+
+/// Controller for reading the content of an attached file, `range` is the value of `Range` header.
+pub async fn download(note_id: String, attachment_id: String, range: Option<String>, principal: Principal, storage: &Storage) -> Result<AttachmentContent> {
+  let attachment = services::attachments::find(&note_id, &attachment_id, &principal, storage).await?;
+  let size = attachment.size as u64;
+  let range = parse_range(range.as_deref(), size);
+  let content = match &range {
+    ContentRange::Full => services::attachments::read(&attachment, 0..size, storage).await?,
+    ContentRange::Partial(range) => services::attachments::read(&attachment, range.clone(), storage).await?,
+    ContentRange::NotSatisfiable => vec![],
+  };
+  Ok(AttachmentContent { attachment, range, content })
+}
+
+/// Controller for deleting an attachment of a note editable by the user.
+pub async fn delete(note_id: String, attachment_id: String, principal: Principal, storage: &Storage) -> Result<String> {
+  services::attachments::delete(&note_id, &attachment_id, &principal, storage).await?;
+  Ok("attachment deleted".to_string())
+}
@@ -17,15 +17,40 @@
 //! Implementation of controllers for authorization.
 
 use crate::errors::*;
-use crate::handlers::auth::{LoginDto, LoginParams};
+use crate::handlers::auth::{LoginDto, LoginParams, RefreshParams, TokensDto};
+use crate::services::auth::Principal;
+use crate::services::{auth, sessions, tokens};
 use crate::storage::Storage;
 
 /// Controller for logging a user.
-pub async fn login(params: LoginParams, storage: &mut Storage) -> Result<LoginDto> {
+pub async fn login(params: LoginParams, storage: &Storage) -> Result<LoginDto> {
   let (login, password) = params.validate()?;
-  if let Some(token) = storage.get_token(&login, &password) {
-    Ok(LoginDto { token })
+  if let Some(session) = auth::login(&login, &password, storage).await? {
+    let tokens = tokens::issue(&session, storage).await?.map(Into::into);
+    Ok(LoginDto {
+      token: session.session_id,
+      tokens,
+    })
   } else {
     Err(err_invalid_login_or_password())
   }
 }
+
+/// Controller for exchanging the refresh token for new access token and refresh token.
+pub async fn refresh(params: RefreshParams, storage: &Storage) -> Result<TokensDto> {
+  let refresh_token = params.validate()?;
+  Ok(tokens::refresh(&refresh_token, storage).await?.into())
+}
+
+/// Controller for logging out the current session of the user.
+pub async fn logout(principal: Principal, storage: &Storage) -> Result<String> {
+  let session = storage.sessions_repository.find(&principal.session_id).await?;
+  sessions::delete(&session, storage).await?;
+  Ok("logged out".to_string())
+}
+
+/// Controller for logging out all sessions of the user.
+pub async fn logout_all(principal: Principal, storage: &Storage) -> Result<String> {
+  sessions::delete_all(&principal.user_id, storage).await?;
+  Ok("all sessions logged out".to_string())
+}
@@ -0,0 +1,28 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for note events.
+
+use crate::errors::*;
+use crate::services;
+use crate::services::events::Subscriber;
+use crate::storage::Storage;
+
+/// Controller for subscribing to events of notes readable by the user authenticated with specified token.
+pub async fn subscribe(token: Option<String>, storage: &Storage) -> Result<Subscriber> {
+  let token = token.filter(|token| !token.is_empty()).ok_or_else(err_not_authorized)?;
+  services::events::subscribe(token, storage).await
+}
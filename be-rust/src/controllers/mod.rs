@@ -25,7 +25,14 @@
 //! - convert output values into DTOs.
 //! - return DTOs (or collections of DTOs) as a result of processing.
 
+pub mod attachments;
 pub mod auth;
+pub mod collaboration;
+pub mod events;
+pub mod notebooks;
 pub mod notes;
+pub mod revisions;
 pub mod roles;
+pub mod tags;
+pub mod trash;
 pub mod users;
@@ -0,0 +1,62 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for notebooks.
+
+use crate::errors::*;
+use crate::handlers::notebooks::{NotebookDto, NotebookParams};
+use crate::handlers::notes::NoteDto;
+use crate::handlers::PageParams;
+use crate::repositories::Page;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Controller for creating a new notebook owned by the user.
+pub async fn create(params: NotebookParams, principal: Principal, storage: &mut Storage) -> Result<NotebookDto> {
+  let name = params.validate()?;
+  Ok(services::notebooks::create(&name, &principal, storage).await?.into())
+}
+
+/// Controller for retrieving all notebooks owned by the user.
+pub async fn list(principal: Principal, storage: &Storage) -> Result<Vec<NotebookDto>> {
+  Ok(services::notebooks::list(&principal, storage).await?.iter().map(Into::into).collect())
+}
+
+/// Controller for retrieving a single notebook owned by the user.
+pub async fn find(notebook_id: String, principal: Principal, storage: &Storage) -> Result<NotebookDto> {
+  Ok(services::notebooks::find(&notebook_id, &principal, storage).await?.into())
+}
+
+/// Controller for renaming a notebook owned by the user.
+pub async fn rename(notebook_id: String, params: NotebookParams, principal: Principal, storage: &mut Storage) -> Result<NotebookDto> {
+  let name = params.validate()?;
+  Ok(services::notebooks::rename(&notebook_id, &name, &principal, storage).await?.into())
+}
+
+/// Controller for deleting a notebook owned by the user, notes are removed from the notebook but not deleted.
+pub async fn delete(notebook_id: String, principal: Principal, storage: &mut Storage) -> Result<String> {
+  let removed = services::notebooks::delete(&notebook_id, &principal, storage).await?;
+  Ok(format!("notebook deleted, removed notes = {}", removed))
+}
+
+/// Controller for retrieving a page of notes belonging to a notebook owned by the user, ordered by creation time.
+pub async fn list_notes(notebook_id: String, params: PageParams, principal: Principal, storage: &Storage) -> Result<Page<NoteDto>> {
+  let page = params.page_request()?;
+  let order = params.sort_order()?;
+  let notes = services::notebooks::list_notes(&notebook_id, &principal, order, &page, storage).await?;
+  Ok(notes.map(|note| NoteDto::summary(&note)))
+}
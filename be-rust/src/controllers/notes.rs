@@ -16,44 +16,120 @@
 
 //! Implementation of controllers for notes.
 
+use crate::entities::share::{Access, GranteeType};
 use crate::errors::*;
-use crate::handlers::notes::{CreateNoteParams, NoteDto};
+use crate::events::EventKind;
+use crate::handlers::notes::{parse_if_match, CreateNoteParams, NoteDto, NoteParams, SearchParams, ShareDto, ShareParams, UpdateNoteParams};
+use crate::handlers::PageParams;
+use crate::repositories::Page;
+use crate::services;
+use crate::services::auth::Principal;
 use crate::storage::Storage;
+use crate::utils::to_rfc3339;
 
-/// Controller for deleting all notes.
+/// Controller for moving all notes to trash.
 pub async fn delete_all(storage: &mut Storage) -> Result<String> {
-  storage.delete_notes().await?;
-  Ok("all notes deleted".to_string())
+  let trashed = services::notes::delete_all(storage).await?;
+  Ok(format!("all notes moved to trash, notes = {}", trashed))
 }
 
-/// Controller for retrieving a list of notes.
-pub async fn list(storage: &Storage) -> Result<Vec<NoteDto>> {
+/// Controller for retrieving a page of notes accessible to the user, ordered by creation time.
+pub async fn list(params: PageParams, principal: Principal, storage: &Storage) -> Result<Page<NoteDto>> {
+  let page = params.page_request()?;
+  let order = params.sort_order()?;
   Ok(
-    storage
-      .get_notes()
+    services::notes::list(&principal, order, &page, storage)
       .await?
-      .iter()
-      .map(|note| NoteDto {
-        note_id: note.note_id.clone(),
-        title: Some(note.title.clone()),
-        content: None,
-      })
-      .collect(),
+      .map(|note| NoteDto::summary(&note)),
   )
 }
 
-/// Controller for retrieving a single note.
-pub async fn get_by_id(note_id: String, storage: &Storage) -> Result<NoteDto> {
-  let note = storage.get_note(&note_id).await?;
-  Ok(note.into())
+/// Controller for retrieving a page of notes accessible to the user matching the search query, best matches first.
+pub async fn search(params: SearchParams, principal: Principal, storage: &Storage) -> Result<Page<NoteDto>> {
+  let (query, page) = params.validate()?;
+  Ok(services::notes::search(&principal, &query, &page, storage).await?.map(|(note, score)| NoteDto {
+    score: Some(score),
+    ..NoteDto::summary(&note)
+  }))
+}
+
+/// Controller for rebuilding the full-text search index from all stored notes.
+pub async fn rebuild_search_index(storage: &Storage) -> Result<String> {
+  let indexed = services::system::rebuild_search_index(storage).await?;
+  Ok(format!("search index rebuilt, notes = {}", indexed))
+}
+
+/// Controller for retrieving a single note accessible to the user, with the content rendered into HTML when requested.
+pub async fn get_by_id(note_id: String, params: NoteParams, principal: Principal, storage: &Storage) -> Result<NoteDto> {
+  let render = params.validate()?;
+  let note = services::notes::find(&note_id, &principal, Access::Read, storage).await?;
+  Ok(if render { NoteDto::rendered(&note) } else { note.into() })
 }
 
-/// Controller for creating a new note.
-pub async fn create(params: CreateNoteParams, storage: &mut Storage) -> Result<NoteDto> {
-  let (title, content, ttl) = params.validate()?;
-  if let Ok(note_id) = storage.create_note(&title, &content, &ttl).await {
-    Ok(NoteDto { note_id, ..NoteDto::default() })
-  } else {
-    Err(err_creating_note_failed())
+/// Controller for creating a new note owned by the user.
+pub async fn create(params: CreateNoteParams, principal: Principal, storage: &mut Storage) -> Result<NoteDto> {
+  let note = params.validate(&principal.user_id)?;
+  if let Some(notebook_id) = &note.notebook_id {
+    services::notebooks::find(notebook_id, &principal, storage).await?;
   }
+  let note = storage.create_note(note).await?;
+  services::events::publish(EventKind::Created, &note, storage).await?;
+  Ok(NoteDto {
+    expires_at: note.expires_at.map(to_rfc3339),
+    remaining_ttl: note.remaining_ttl(),
+    version: Some(note.version),
+    note_id: note.note_id,
+    ..NoteDto::default()
+  })
+}
+
+/// Controller for replacing the title, content and time to live of a note,
+/// `if_match` is the value of `If-Match` header containing the expected version of the note.
+pub async fn replace(note_id: String, params: UpdateNoteParams, if_match: Option<String>, principal: Principal, storage: &mut Storage) -> Result<NoteDto> {
+  let expected_version = parse_if_match(if_match.as_deref())?;
+  let changes = params.validate_replace()?;
+  let note = services::notes::update(&note_id, changes, expected_version, &principal, storage).await?;
+  services::events::publish(EventKind::Updated, &note, storage).await?;
+  Ok(note.into())
+}
+
+/// Controller for updating selected attributes of a note,
+/// `if_match` is the value of `If-Match` header containing the expected version of the note.
+pub async fn update(note_id: String, params: UpdateNoteParams, if_match: Option<String>, principal: Principal, storage: &mut Storage) -> Result<NoteDto> {
+  let expected_version = parse_if_match(if_match.as_deref())?;
+  let changes = params.validate_update()?;
+  let note = services::notes::update(&note_id, changes, expected_version, &principal, storage).await?;
+  services::events::publish(EventKind::Updated, &note, storage).await?;
+  Ok(note.into())
+}
+
+/// Controller for moving a single note to trash.
+pub async fn delete(note_id: String, principal: Principal, storage: &mut Storage) -> Result<String> {
+  let note = services::notes::delete(&note_id, &principal, storage).await?;
+  services::events::publish(EventKind::Deleted, &note, storage).await?;
+  Ok("note moved to trash".to_string())
+}
+
+/// Controller for retrieving the list of shares of a note accessible to the user.
+pub async fn list_shares(note_id: String, principal: Principal, storage: &Storage) -> Result<Vec<ShareDto>> {
+  services::notes::find(&note_id, &principal, Access::Read, storage).await?;
+  Ok(services::shares::list(&note_id, storage).await?.iter().map(Into::into).collect())
+}
+
+/// Controller for sharing a note managed by the user.
+pub async fn create_share(note_id: String, params: ShareParams, principal: Principal, storage: &Storage) -> Result<ShareDto> {
+  let (grantee_type, grantee_id, access) = params.validate()?;
+  let note = services::notes::find_managed(&note_id, &principal, storage).await?;
+  let share = services::shares::grant(&note, grantee_type, &grantee_id, access, storage).await?;
+  services::events::refresh(&note, storage).await?;
+  Ok(share.into())
+}
+
+/// Controller for revoking a share of a note managed by the user.
+pub async fn delete_share(note_id: String, grantee_type: String, grantee_id: String, principal: Principal, storage: &Storage) -> Result<String> {
+  let grantee_type: GranteeType = grantee_type.parse()?;
+  let note = services::notes::find_managed(&note_id, &principal, storage).await?;
+  services::shares::revoke(&note, grantee_type, &grantee_id, storage).await?;
+  services::events::refresh(&note, storage).await?;
+  Ok("share revoked".to_string())
 }
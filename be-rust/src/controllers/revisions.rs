@@ -0,0 +1,68 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for note revisions.
+
+use crate::diff::diff_lines;
+use crate::errors::*;
+use crate::events::EventKind;
+use crate::handlers::notes::{parse_if_match, NoteDto};
+use crate::handlers::revisions::{parse_revision, DiffDto, DiffParams, RevisionDto};
+use crate::handlers::PageParams;
+use crate::repositories::Page;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Controller for retrieving a page of revisions of a note accessible to the user, newest first unless specified otherwise.
+pub async fn list(note_id: String, params: PageParams, principal: Principal, storage: &Storage) -> Result<Page<RevisionDto>> {
+  let page = params.page_request()?;
+  let order = params.sort_order()?;
+  Ok(
+    services::revisions::list(&note_id, &principal, order, &page, storage)
+      .await?
+      .map(RevisionDto::summary),
+  )
+}
+
+/// Controller for retrieving a single revision of a note accessible to the user.
+pub async fn find(note_id: String, revision: String, principal: Principal, storage: &Storage) -> Result<RevisionDto> {
+  let revision = parse_revision(&revision)?;
+  Ok(services::revisions::find(&note_id, revision, &principal, storage).await?.into())
+}
+
+/// Controller for comparing two revisions of a note accessible to the user.
+pub async fn diff(note_id: String, params: DiffParams, principal: Principal, storage: &Storage) -> Result<DiffDto> {
+  let (from, to) = params.validate()?;
+  let (from, to) = services::revisions::compare(&note_id, from, to, &principal, storage).await?;
+  Ok(DiffDto {
+    note_id,
+    from: from.revision,
+    to: to.revision,
+    title: diff_lines(&from.title, &to.title).into_iter().map(Into::into).collect(),
+    content: diff_lines(&from.content, &to.content).into_iter().map(Into::into).collect(),
+  })
+}
+
+/// Controller for restoring the title, content and content format of a note to the state recorded in a revision,
+/// `if_match` is the value of `If-Match` header containing the expected version of the note.
+pub async fn restore(note_id: String, revision: String, if_match: Option<String>, principal: Principal, storage: &mut Storage) -> Result<NoteDto> {
+  let expected_version = parse_if_match(if_match.as_deref())?;
+  let revision = parse_revision(&revision)?;
+  let note = services::revisions::restore(&note_id, revision, expected_version, &principal, storage).await?;
+  services::events::publish(EventKind::Updated, &note, storage).await?;
+  Ok(note.into())
+}
@@ -19,32 +19,37 @@
 use crate::entities::role::RoleEntity;
 use crate::errors::*;
 use crate::handlers::roles::{CreateRoleParams, RoleDto};
+use crate::handlers::PageParams;
+use crate::repositories::Page;
 use crate::services;
 use crate::storage::Storage;
 
 /// Controller for creating a new role.
 pub async fn create(params: CreateRoleParams, storage: &mut Storage) -> Result<RoleDto> {
-  let name = params.validate()?;
-  let role = RoleEntity::new(&name);
-  if let Ok(role_id) = storage.roles_repository.create(role).await {
-    Ok(RoleDto { role_id, ..RoleDto::default() })
-  } else {
-    Err(err_creating_role_failed())
-  }
+  let (name, permissions) = params.validate()?;
+  let role = RoleEntity::new(&name, permissions);
+  let role_id = storage.roles_repository.create(role).await?;
+  Ok(RoleDto { role_id, ..RoleDto::default() })
 }
 
-/// Controller for retrieving a list of roles.
-pub async fn list(storage: &Storage) -> Result<Vec<RoleDto>> {
-  Ok(storage.roles_repository.list().await?.iter().map(|role| role.into()).collect())
+/// Controller for retrieving a page of roles, roles are listed in unspecified order.
+pub async fn list(params: PageParams, storage: &Storage) -> Result<Page<RoleDto>> {
+  Ok(
+    storage
+      .roles_repository
+      .list_page(&params.unordered_page_request()?)
+      .await?
+      .map(|role| (&role).into()),
+  )
 }
 
 /// Controller for retrieving a single role by its identifier.
 pub async fn find_by_id(id: String, storage: &Storage) -> Result<RoleDto> {
-  Ok(services::roles::find_by_id(id, &storage).await?.into())
+  Ok(services::roles::find_by_id(id, storage).await?.into())
 }
 
-/// Controller for deleting all roles.
+/// Controller for deleting all roles except the built-in ones.
 pub async fn delete_all(storage: &mut Storage) -> Result<String> {
-  storage.roles_repository.delete_all().await?;
-  Ok("all roles deleted".to_string())
+  let deleted = services::roles::delete_all(storage).await?;
+  Ok(format!("all roles except built-in roles deleted, roles = {}", deleted))
 }
@@ -0,0 +1,42 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for tags.
+
+use crate::errors::*;
+use crate::handlers::notes::{parse_tags, NoteDto};
+use crate::handlers::tags::TagDto;
+use crate::handlers::PageParams;
+use crate::repositories::Page;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Controller for retrieving tags of notes owned by the user, with the number of notes having each tag.
+pub async fn list(principal: Principal, storage: &Storage) -> Result<Vec<TagDto>> {
+  let counts = services::tags::count(&principal, storage).await?;
+  Ok(counts.into_iter().map(|(tag, count)| TagDto { tag, count }).collect())
+}
+
+/// Controller for retrieving a page of notes owned by the user having specified tag, ordered by creation time.
+pub async fn list_notes(tag: String, params: PageParams, principal: Principal, storage: &Storage) -> Result<Page<NoteDto>> {
+  let page = params.page_request()?;
+  let order = params.sort_order()?;
+  // tags are normalized the same way as when notes are tagged
+  let tag = parse_tags(vec![tag])?.into_iter().next().unwrap_or_default();
+  let notes = services::tags::list_notes(&tag, &principal, order, &page, storage).await?;
+  Ok(notes.map(|note| NoteDto::summary(&note)))
+}
@@ -0,0 +1,50 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of controllers for trash.
+
+use crate::errors::*;
+use crate::events::EventKind;
+use crate::handlers::notes::NoteDto;
+use crate::handlers::PageParams;
+use crate::repositories::Page;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Controller for retrieving a page of notes in trash of the user, ordered by creation time.
+pub async fn list(params: PageParams, principal: Principal, storage: &Storage) -> Result<Page<NoteDto>> {
+  let page = params.page_request()?;
+  let order = params.sort_order()?;
+  Ok(
+    services::trash::list(&principal, order, &page, storage)
+      .await?
+      .map(|note| NoteDto::summary(&note)),
+  )
+}
+
+/// Controller for restoring a note from trash, the restored note is published as created.
+pub async fn restore(note_id: String, principal: Principal, storage: &mut Storage) -> Result<NoteDto> {
+  let note = services::trash::restore(&note_id, &principal, storage).await?;
+  services::events::publish(EventKind::Created, &note, storage).await?;
+  Ok(note.into())
+}
+
+/// Controller for deleting a note in trash permanently.
+pub async fn purge(note_id: String, principal: Principal, storage: &mut Storage) -> Result<String> {
+  services::trash::purge(&note_id, &principal, storage).await?;
+  Ok("note purged".to_string())
+}
@@ -16,4 +16,54 @@
 
 //! Implementation of controllers for users.
 
-// TBD
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::users::{CreateUserParams, UpdateUserParams, UserDto};
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Checks if the principal may access the user with specified identifier,
+/// users may always access their own accounts.
+fn check_access(user_id: &str, principal: &Principal) -> Result<()> {
+  if principal.user_id == user_id {
+    Ok(())
+  } else {
+    principal.check_permission(Permission::UsersAdmin)
+  }
+}
+
+/// Controller for creating a new user.
+pub async fn create(params: CreateUserParams, storage: &mut Storage) -> Result<UserDto> {
+  let (login, password, roles) = params.validate()?;
+  let user_id = services::users::create(&login, &password, roles, storage).await?;
+  Ok(UserDto { user_id, ..UserDto::default() })
+}
+
+/// Controller for retrieving a list of users.
+pub async fn list(storage: &Storage) -> Result<Vec<UserDto>> {
+  Ok(storage.users_repository.list().await?.iter().map(|user| user.into()).collect())
+}
+
+/// Controller for retrieving a single user by its identifier.
+pub async fn find_by_id(id: String, principal: &Principal, storage: &Storage) -> Result<UserDto> {
+  check_access(&id, principal)?;
+  Ok(storage.users_repository.find_by_id(&id).await?.into())
+}
+
+/// Controller for updating the login, password or roles of a user.
+/// Only users administrators may change roles.
+pub async fn update(id: String, params: UpdateUserParams, principal: &Principal, storage: &mut Storage) -> Result<UserDto> {
+  check_access(&id, principal)?;
+  let changes = params.validate()?;
+  if changes.roles.is_some() {
+    principal.check_permission(Permission::UsersAdmin)?;
+  }
+  Ok(services::users::update(&id, changes, storage).await?.into())
+}
+
+/// Controller for deleting a user.
+pub async fn delete(id: String, storage: &mut Storage) -> Result<String> {
+  services::users::delete(&id, storage).await?;
+  Ok("user deleted".to_string())
+}
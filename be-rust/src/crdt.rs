@@ -0,0 +1,266 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! # Replicated Growable Array
+//!
+//! Implementation of the RGA sequence CRDT used for collaborative editing of the content of notes.
+//!
+//! The text is a sequence of elements, each holding a single character and identified by
+//! a unique [Id]: a Lamport timestamp (counter) and the identifier of the site (participant)
+//! that inserted the element. An element is inserted after an existing element (its origin),
+//! or at the beginning of the text. Elements inserted concurrently after the same origin
+//! are ordered by their identifiers, the greatest first, so all sites applying the same
+//! operations in any causal order end up with the same text. Deleted elements are kept
+//! as tombstones, because operations of other sites may still refer to them.
+//!
+//! Identifiers of inserted elements must have counters greater than counters of their origins,
+//! sites generate them by incrementing the greatest counter they have seen.
+
+use crate::errors::*;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Site identifier of elements holding the content of the note when the collaboration started.
+pub const INITIAL_SITE: &str = "";
+
+/// Unique identifier of an element, ordered by counter and then by site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id {
+  /// Lamport timestamp of the insertion.
+  pub counter: u64,
+  /// Identifier of the site that inserted the element.
+  pub site: String,
+}
+
+impl Id {
+  /// Creates an identifier.
+  pub fn new(counter: u64, site: &str) -> Self {
+    Self {
+      counter,
+      site: site.to_string(),
+    }
+  }
+}
+
+impl Display for Id {
+  /// Implementation of [Display] trait for [Id].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}@{}", self.counter, self.site)
+  }
+}
+
+/// Element of the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+  /// Identifier of the element.
+  pub id: Id,
+  /// Character held by the element.
+  pub value: char,
+  /// Set when the element was deleted.
+  pub deleted: bool,
+}
+
+/// Operation modifying the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+  /// Inserts an element after the element identified by `after`, at the beginning when `None`.
+  Insert {
+    /// Identifier of the inserted element.
+    id: Id,
+    /// Identifier of the origin of the inserted element.
+    after: Option<Id>,
+    /// Inserted character.
+    value: char,
+  },
+  /// Deletes the element with specified identifier.
+  Delete {
+    /// Identifier of the deleted element.
+    id: Id,
+  },
+}
+
+/// Text replicated with RGA.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+  /// Elements in the order of the text, including deleted elements.
+  elements: Vec<Element>,
+  /// Identifiers of all elements.
+  ids: HashSet<Id>,
+}
+
+impl Document {
+  /// Creates a document holding specified text, inserted character by character by the [INITIAL_SITE].
+  pub fn new(text: &str) -> Self {
+    let elements: Vec<Element> = text
+      .chars()
+      .enumerate()
+      .map(|(index, value)| Element {
+        id: Id::new(index as u64 + 1, INITIAL_SITE),
+        value,
+        deleted: false,
+      })
+      .collect();
+    let ids = elements.iter().map(|element| element.id.clone()).collect();
+    Self { elements, ids }
+  }
+  /// Creates a document from elements in the order of the text, as returned by [Document::elements].
+  pub fn from_elements(elements: Vec<Element>) -> Self {
+    let ids = elements.iter().map(|element| element.id.clone()).collect();
+    Self { elements, ids }
+  }
+  /// Returns elements in the order of the text, including deleted elements.
+  pub fn elements(&self) -> &[Element] {
+    &self.elements
+  }
+  /// Returns the text, without deleted elements.
+  pub fn text(&self) -> String {
+    self.elements.iter().filter(|element| !element.deleted).map(|element| element.value).collect()
+  }
+  /// Returns the greatest counter of identifiers of elements.
+  pub fn max_counter(&self) -> u64 {
+    self.elements.iter().map(|element| element.id.counter).max().unwrap_or_default()
+  }
+  /// Returns the position of the element with specified identifier.
+  fn position(&self, id: &Id) -> Result<usize> {
+    self
+      .elements
+      .iter()
+      .position(|element| &element.id == id)
+      .ok_or_else(|| err_invalid_operation(&format!("element not found, id = {}", id)))
+  }
+  /// Checks if the operation may be applied, returns `false` when it was already applied.
+  pub fn check(&self, operation: &Operation) -> Result<bool> {
+    match operation {
+      Operation::Insert { id, after, .. } => {
+        if let Some(after) = after {
+          if !self.ids.contains(after) {
+            return Err(err_invalid_operation(&format!("element not found, id = {}", after)));
+          }
+          if id.counter <= after.counter {
+            return Err(err_invalid_operation(&format!("counter not greater than counter of origin, id = {}", id)));
+          }
+        }
+        Ok(!self.ids.contains(id))
+      }
+      Operation::Delete { id } => {
+        let position = self.position(id)?;
+        Ok(!self.elements[position].deleted)
+      }
+    }
+  }
+  /// Applies the operation, returns `false` when it was already applied.
+  pub fn apply(&mut self, operation: &Operation) -> Result<bool> {
+    if !self.check(operation)? {
+      return Ok(false);
+    }
+    match operation {
+      Operation::Insert { id, after, value } => {
+        let mut position = match after {
+          Some(after) => self.position(after)? + 1,
+          None => 0,
+        };
+        // skip elements inserted concurrently after the same origin with greater identifiers,
+        // together with elements inserted after them
+        while position < self.elements.len() && self.elements[position].id > *id {
+          position += 1;
+        }
+        self.elements.insert(
+          position,
+          Element {
+            id: id.clone(),
+            value: *value,
+            deleted: false,
+          },
+        );
+        self.ids.insert(id.clone());
+      }
+      Operation::Delete { id } => {
+        let position = self.position(id)?;
+        self.elements[position].deleted = true;
+      }
+    }
+    Ok(true)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn insert(counter: u64, site: &str, after: Option<(u64, &str)>, value: char) -> Operation {
+    Operation::Insert {
+      id: Id::new(counter, site),
+      after: after.map(|(counter, site)| Id::new(counter, site)),
+      value,
+    }
+  }
+
+  fn delete(counter: u64, site: &str) -> Operation {
+    Operation::Delete { id: Id::new(counter, site) }
+  }
+
+  #[test]
+  fn test_initial_text() {
+    let document = Document::new("fika");
+    assert_eq!("fika", document.text());
+    assert_eq!(4, document.max_counter());
+    assert_eq!(Id::new(1, INITIAL_SITE), document.elements()[0].id);
+    assert_eq!("", Document::default().text());
+  }
+
+  #[test]
+  fn test_concurrent_operations_converge() {
+    // "ab" edited concurrently by two sites: alice inserts "X" after "a" and deletes "b",
+    // bob inserts "Y" after "a" and "Z" after "Y"
+    let alice = [insert(3, "alice", Some((1, "")), 'X'), delete(2, "")];
+    let bob = [insert(3, "bob", Some((1, "")), 'Y'), insert(4, "bob", Some((3, "bob")), 'Z')];
+    let mut first = Document::new("ab");
+    let mut second = Document::new("ab");
+    for operation in alice.iter().chain(bob.iter()) {
+      assert!(first.apply(operation).unwrap());
+    }
+    for operation in bob.iter().chain(alice.iter()) {
+      assert!(second.apply(operation).unwrap());
+    }
+    // bob's insertion has greater identifier, so it goes first
+    assert_eq!("aYZX", first.text());
+    assert_eq!(first.text(), second.text());
+    assert_eq!(first.elements(), second.elements());
+    // operations are idempotent
+    assert!(!first.apply(&alice[0]).unwrap());
+    assert!(!first.apply(&alice[1]).unwrap());
+    assert_eq!("aYZX", first.text());
+    assert_eq!("aYZX", Document::from_elements(first.elements().to_vec()).text());
+  }
+
+  #[test]
+  fn test_invalid_operations() {
+    let mut document = Document::new("ab");
+    assert_eq!(
+      "invalid operation: element not found, id = 7@alice",
+      document.apply(&insert(8, "alice", Some((7, "alice")), 'x')).unwrap_err().to_string()
+    );
+    assert_eq!(
+      "invalid operation: counter not greater than counter of origin, id = 2@alice",
+      document.apply(&insert(2, "alice", Some((2, "")), 'x')).unwrap_err().to_string()
+    );
+    assert_eq!(
+      "invalid operation: element not found, id = 3@",
+      document.apply(&delete(3, "")).unwrap_err().to_string()
+    );
+    assert_eq!("ab", document.text());
+  }
+}
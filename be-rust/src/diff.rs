@@ -0,0 +1,338 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of text comparison.
+//!
+//! Sequences are compared item by item, the result is the shortest edit script found
+//! with the Myers algorithm. Items common to the beginning and the end of both sequences
+//! are matched before the script is searched, so comparing slightly modified versions
+//! of long texts stays cheap. The search is limited to [MAX_EDIT_DISTANCE] changed items,
+//! sequences differing more are reported as the old items deleted and the new items inserted,
+//! so the time and memory needed to compare any texts stay bounded.
+//!
+//! Texts are compared line by line with [diff_lines] and merged line by line with [merge_lines].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Maximum number of inserted and deleted items searched for the shortest edit script.
+pub const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// Step of the edit script transforming the old sequence into the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+  /// The next item is present in both sequences.
+  Equal,
+  /// The next item of the new sequence is inserted.
+  Insert,
+  /// The next item of the old sequence is deleted.
+  Delete,
+}
+
+/// Single line of the comparison result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a> {
+  /// Line present in both texts.
+  Equal(&'a str),
+  /// Line present only in the new text.
+  Insert(&'a str),
+  /// Line present only in the old text.
+  Delete(&'a str),
+}
+
+impl Change<'_> {
+  /// Returns the name of the operation.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Change::Equal(_) => "equal",
+      Change::Insert(_) => "insert",
+      Change::Delete(_) => "delete",
+    }
+  }
+  /// Returns the compared line.
+  pub fn text(&self) -> &str {
+    match self {
+      Change::Equal(text) | Change::Insert(text) | Change::Delete(text) => text,
+    }
+  }
+}
+
+/// Compares two sequences, returns steps transforming the old sequence into the new one.
+/// Deleted items precede inserted items replacing them.
+pub fn diff<T: Eq + Hash>(old: &[T], new: &[T]) -> Vec<Step> {
+  let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+  let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+  let old_middle = &old[prefix..old.len() - suffix];
+  let new_middle = &new[prefix..new.len() - suffix];
+  let mut steps = vec![Step::Equal; prefix];
+  let middle = edit_script(old_middle, new_middle).unwrap_or_else(|| {
+    let mut replaced = vec![Step::Delete; old_middle.len()];
+    replaced.resize(old_middle.len() + new_middle.len(), Step::Insert);
+    replaced
+  });
+  steps.extend(deletions_first(middle));
+  steps.resize(steps.len() + suffix, Step::Equal);
+  steps
+}
+
+/// Compares two texts line by line, returns changes transforming the old text into the new one.
+/// Deleted lines precede inserted lines replacing them.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+  let old: Vec<&str> = old.lines().collect();
+  let new: Vec<&str> = new.lines().collect();
+  let (mut old_lines, mut new_lines) = (old.iter(), new.iter());
+  diff(&old, &new)
+    .into_iter()
+    .filter_map(|step| match step {
+      Step::Equal => {
+        new_lines.next();
+        old_lines.next().map(|line| Change::Equal(line))
+      }
+      Step::Insert => new_lines.next().map(|line| Change::Insert(line)),
+      Step::Delete => old_lines.next().map(|line| Change::Delete(line)),
+    })
+    .collect()
+}
+
+/// Merges changes of two texts derived from the same base text line by line, `None` when the changes conflict.
+///
+/// Changes conflict when they modify the same or adjacent lines of the base text, unless they are the same.
+pub fn merge_lines(base: &str, ours: &str, theirs: &str) -> Option<String> {
+  let base: Vec<&str> = base.split_inclusive('\n').collect();
+  let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+  let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+  let mut changes = hunks(&base, &ours);
+  changes.extend(hunks(&base, &theirs));
+  changes.sort_by_key(|hunk| (hunk.start, hunk.end));
+  let mut merged = String::new();
+  let mut position = 0;
+  let mut previous: Option<Hunk> = None;
+  for hunk in changes {
+    if let Some(previous) = &previous {
+      if *previous == hunk {
+        continue;
+      }
+      if hunk.start <= previous.end {
+        return None;
+      }
+    }
+    merged.extend(base[position..hunk.start].iter().copied());
+    merged.extend(hunk.lines.iter().copied());
+    position = hunk.end;
+    previous = Some(hunk);
+  }
+  merged.extend(base[position..].iter().copied());
+  Some(merged)
+}
+
+/// Lines of the base text replaced with other lines.
+#[derive(Debug, PartialEq, Eq)]
+struct Hunk<'a> {
+  /// Index of the first replaced line.
+  start: usize,
+  /// Index of the line following the last replaced line.
+  end: usize,
+  /// Replacing lines.
+  lines: Vec<&'a str>,
+}
+
+/// Returns hunks transforming base lines into new lines.
+fn hunks<'a>(base: &[&str], new: &[&'a str]) -> Vec<Hunk<'a>> {
+  let mut hunks: Vec<Hunk> = vec![];
+  let (mut old_index, mut new_index) = (0, 0);
+  let mut changed = false;
+  for step in diff(base, new) {
+    if step != Step::Equal && !changed {
+      hunks.push(Hunk {
+        start: old_index,
+        end: old_index,
+        lines: vec![],
+      });
+    }
+    changed = step != Step::Equal;
+    let hunk = hunks.last_mut();
+    match (step, hunk) {
+      (Step::Equal, _) => {
+        old_index += 1;
+        new_index += 1;
+      }
+      (Step::Insert, Some(hunk)) => {
+        hunk.lines.push(new[new_index]);
+        new_index += 1;
+      }
+      (Step::Delete, Some(hunk)) => {
+        old_index += 1;
+        hunk.end = old_index;
+      }
+      _ => {}
+    }
+  }
+  hunks
+}
+
+/// Searches the shortest edit script transforming old items into new items (Myers algorithm),
+/// returns `None` when more than [MAX_EDIT_DISTANCE] items must be inserted or deleted.
+fn edit_script<T: Eq + Hash>(old: &[T], new: &[T]) -> Option<Vec<Step>> {
+  // items are compared by numbers assigned to distinct items
+  let mut numbers: HashMap<&T, usize> = HashMap::new();
+  let mut a = Vec::with_capacity(old.len());
+  let mut b = Vec::with_capacity(new.len());
+  for (items, numbered) in [(old, &mut a), (new, &mut b)] {
+    for item in items {
+      let next = numbers.len();
+      numbered.push(*numbers.entry(item).or_insert(next));
+    }
+  }
+  let (n, m) = (a.len() as isize, b.len() as isize);
+  let max = ((n + m) as usize).min(MAX_EDIT_DISTANCE) as isize;
+  // furthest reaching x on every diagonal k = x - y, diagonals are offset to be valid indices
+  let offset = max + 1;
+  let mut v = vec![0_isize; 2 * max as usize + 3];
+  // values of diagonals -d..=d before every step d, used to trace the script back
+  let mut trace: Vec<Vec<isize>> = vec![];
+  let mut finished = None;
+  'search: for d in 0..=max {
+    trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+    for k in (-d..=d).step_by(2) {
+      let index = (offset + k) as usize;
+      let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+        v[index + 1]
+      } else {
+        v[index - 1] + 1
+      };
+      let mut y = x - k;
+      while x < n && y < m && a[x as usize] == b[y as usize] {
+        x += 1;
+        y += 1;
+      }
+      v[index] = x;
+      if x >= n && y >= m {
+        finished = Some(d);
+        break 'search;
+      }
+    }
+  }
+  let mut steps = vec![];
+  let (mut x, mut y) = (n, m);
+  for d in (0..=finished?).rev() {
+    let previous = &trace[d as usize];
+    let value = |k: isize| previous[(k + d) as usize];
+    let k = x - y;
+    let (previous_x, previous_y) = if d == 0 {
+      (0, 0)
+    } else {
+      let previous_k = if k == -d || (k != d && value(k - 1) < value(k + 1)) { k + 1 } else { k - 1 };
+      (value(previous_k), value(previous_k) - previous_k)
+    };
+    while x > previous_x.max(0) && y > previous_y.max(0) {
+      x -= 1;
+      y -= 1;
+      steps.push(Step::Equal);
+    }
+    if d > 0 {
+      steps.push(if x == previous_x { Step::Insert } else { Step::Delete });
+    }
+    (x, y) = (previous_x, previous_y);
+  }
+  steps.reverse();
+  Some(steps)
+}
+
+/// Reorders every run of changed items, so deleted items precede inserted items.
+fn deletions_first(steps: Vec<Step>) -> Vec<Step> {
+  let mut ordered = Vec::with_capacity(steps.len());
+  let mut inserted = vec![];
+  for step in steps {
+    match step {
+      Step::Insert => inserted.push(step),
+      Step::Delete => ordered.push(step),
+      Step::Equal => {
+        ordered.append(&mut inserted);
+        ordered.push(step);
+      }
+    }
+  }
+  ordered.append(&mut inserted);
+  ordered
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use Change::*;
+
+  #[test]
+  fn test_diff_lines() {
+    assert_eq!(vec![Equal("a"), Equal("b")], diff_lines("a\nb", "a\nb\n"));
+    assert_eq!(vec![Insert("a")], diff_lines("", "a"));
+    assert_eq!(vec![Delete("a")], diff_lines("a", ""));
+    assert!(diff_lines("", "").is_empty());
+    assert_eq!(vec![Delete("Kanelbulle"), Insert("Kanelbullar")], diff_lines("Kanelbulle", "Kanelbullar"));
+    assert_eq!(
+      vec![Equal("a"), Delete("b"), Insert("x"), Equal("c"), Insert("d"), Equal("e"), Delete("f")],
+      diff_lines("a\nb\nc\ne\nf", "a\nx\nc\nd\ne")
+    );
+  }
+
+  #[test]
+  fn test_diff_distant_texts() {
+    let old: Vec<String> = (0..3000).map(|i| format!("old {}", i)).collect();
+    let new: Vec<String> = (0..3000).map(|i| format!("new {}", i)).collect();
+    let (old_text, new_text) = (old.join("\n"), new.join("\n"));
+    let changes = diff_lines(&old_text, &new_text);
+    assert_eq!(6000, changes.len());
+    assert_eq!(Delete("old 0"), changes[0]);
+    assert_eq!(Insert("new 0"), changes[3000]);
+    // the shortest script is found within the limit of changed lines
+    let mut modified = old.clone();
+    for i in (0..3000).step_by(10) {
+      modified[i] = format!("new {}", i);
+    }
+    let modified_text = modified.join("\n");
+    let changes = diff_lines(&old_text, &modified_text);
+    assert_eq!(3300, changes.len());
+    assert_eq!(vec![Delete("old 0"), Insert("new 0"), Equal("old 1")], changes[..3]);
+  }
+
+  #[test]
+  fn test_diff() {
+    let steps = diff(&['a', 'b', 'c'], &['a', 'x', 'c', 'd']);
+    assert_eq!(vec![Step::Equal, Step::Delete, Step::Insert, Step::Equal, Step::Insert], steps);
+  }
+
+  #[test]
+  fn test_merge_lines() {
+    let base = "a\nb\nc\nd\n";
+    assert_eq!(Some("A\nb\nc\nD\n".to_string()), merge_lines(base, "A\nb\nc\nd\n", "a\nb\nc\nD\n"));
+    assert_eq!(Some("a\nx\nb\nc\n".to_string()), merge_lines(base, "a\nx\nb\nc\nd\n", "a\nb\nc\n"));
+    // the same changes do not conflict
+    assert_eq!(Some("a\nB\nc\nd\n".to_string()), merge_lines(base, "a\nB\nc\nd\n", "a\nB\nc\nd\n"));
+    // changes of the same or adjacent lines conflict
+    assert_eq!(None, merge_lines(base, "a\nB\nc\nd\n", "a\nX\nc\nd\n"));
+    assert_eq!(None, merge_lines(base, "a\nB\nc\nd\n", "a\nb\nC\nd\n"));
+    assert_eq!(None, merge_lines(base, "a\nx\nb\nc\nd\n", "a\ny\nb\nc\nd\n"));
+    assert_eq!(Some("x".to_string()), merge_lines("", "x", ""));
+  }
+
+  #[test]
+  fn test_change() {
+    let change = Insert("line");
+    assert_eq!("insert", change.name());
+    assert_eq!("line", change.text());
+    assert_eq!("equal", Equal("").name());
+    assert_eq!("delete", Delete("").name());
+  }
+}
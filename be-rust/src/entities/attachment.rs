@@ -0,0 +1,96 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note attachment entity.
+
+use crate::entities::note::NoteEntity;
+use crate::utils::{now, uuid};
+use time::OffsetDateTime;
+
+/// Maximum number of attachments of a note.
+pub const MAX_ATTACHMENTS: usize = 100;
+
+/// Maximum length of the name of an attached file, in characters.
+pub const MAX_FILE_NAME_LENGTH: usize = 255;
+
+/// Name given to attached files uploaded without a usable name.
+const DEFAULT_FILE_NAME: &str = "attachment";
+
+/// Attachment entity, describes a file attached to a note; the content of the file is stored separately as a blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentEntity {
+  /// Unique attachment identifier.
+  pub attachment_id: String,
+  /// Identifier of the note the file is attached to.
+  pub note_id: String,
+  /// Name of the attached file.
+  pub file_name: String,
+  /// Media type of the content, detected from the content of the file.
+  pub content_type: String,
+  /// Size of the content, in bytes.
+  pub size: i64,
+  /// Date and time (UTC) when the file was attached.
+  pub created_at: OffsetDateTime,
+  /// Date and time (UTC) when the attachment expires together with the note, `None` when the note never expires.
+  pub expires_at: Option<OffsetDateTime>,
+}
+
+impl AttachmentEntity {
+  /// Creates a new attachment of the note, the name of the file is sanitized, see [sanitize_file_name].
+  pub fn new(note: &NoteEntity, file_name: &str, content_type: &str, size: i64) -> Self {
+    Self {
+      attachment_id: uuid(),
+      note_id: note.note_id.clone(),
+      file_name: sanitize_file_name(file_name),
+      content_type: content_type.to_string(),
+      size,
+      created_at: now(),
+      expires_at: note.expires_at,
+    }
+  }
+  /// Returns `true` when the attachment has expired.
+  pub fn has_expired(&self) -> bool {
+    self.expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+  }
+}
+
+/// Sanitizes the name of an uploaded file: directories are removed (browsers may send full paths),
+/// control characters are dropped and the name is truncated to [MAX_FILE_NAME_LENGTH] characters.
+/// Names that end up empty are replaced with a default name.
+pub fn sanitize_file_name(file_name: &str) -> String {
+  let base_name = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+  let sanitized: String = base_name.chars().filter(|ch| !ch.is_control()).take(MAX_FILE_NAME_LENGTH).collect();
+  match sanitized.trim() {
+    "" | "." | ".." => DEFAULT_FILE_NAME.to_string(),
+    trimmed => trimmed.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sanitize_file_name() {
+    assert_eq!("report.pdf", sanitize_file_name("report.pdf"));
+    assert_eq!("photo.jpg", sanitize_file_name("C:\\Users\\alice\\photo.jpg"));
+    assert_eq!("passwd", sanitize_file_name("../../etc/passwd"));
+    assert_eq!("a b.txt", sanitize_file_name(" a\u{0}\n b.txt "));
+    assert_eq!("attachment", sanitize_file_name(".."));
+    assert_eq!("attachment", sanitize_file_name("dir/"));
+    assert_eq!(MAX_FILE_NAME_LENGTH, sanitize_file_name(&"ą".repeat(300)).chars().count());
+  }
+}
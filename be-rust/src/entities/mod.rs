@@ -19,8 +19,14 @@
 //! Entity responsibilities:
 //! - mapping types between Rust code and ScyllaDB database.
 
+pub mod attachment;
 pub mod note;
+pub mod notebook;
+pub mod refresh_token;
+pub mod revision;
 pub mod role;
+pub mod session;
+pub mod share;
 pub mod user;
 
 /// Common interface for all entities.
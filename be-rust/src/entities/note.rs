@@ -16,48 +16,188 @@
 
 //! Implementation of note entity.
 
-use crate::utils::{create_and_expiration_date_time, uuid};
-use scylla::macros::FromRow;
-use time::macros::format_description;
-use time::{OffsetDateTime, PrimitiveDateTime};
+use crate::errors::*;
+use crate::utils::{now, uuid};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use time::OffsetDateTime;
+
+/// Maximum time to live supported by ScyllaDB (20 years), in seconds.
+pub const MAX_TTL_SECONDS: i64 = 630_720_000;
+
+/// Maximum number of tags of a note.
+pub const MAX_TAGS: usize = 32;
+
+/// Maximum length of a tag, in characters.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Format of the content of a note.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ContentFormat {
+  /// Plain text.
+  #[default]
+  Plain,
+  /// Markdown (CommonMark with GitHub Flavored Markdown extensions).
+  Markdown,
+  /// HTML fragment.
+  Html,
+}
+
+impl ContentFormat {
+  /// All defined content formats.
+  pub const ALL: [ContentFormat; 3] = [ContentFormat::Plain, ContentFormat::Markdown, ContentFormat::Html];
+  /// Returns the name of the content format.
+  pub fn name(&self) -> &'static str {
+    match self {
+      ContentFormat::Plain => "plain",
+      ContentFormat::Markdown => "markdown",
+      ContentFormat::Html => "html",
+    }
+  }
+}
+
+impl Display for ContentFormat {
+  /// Implementation of [Display] trait for [ContentFormat].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for ContentFormat {
+  type Err = NordNotesError;
+  /// Parses the content format from its name.
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    ContentFormat::ALL
+      .iter()
+      .find(|format| format.name() == name)
+      .copied()
+      .ok_or_else(|| err_invalid_content_format(name))
+  }
+}
 
 /// Note entity.
-#[derive(Debug, Clone, FromRow)]
+#[derive(Debug, Clone)]
 pub struct NoteEntity {
   /// Unique note identifier.
   pub note_id: String,
+  /// Identifier of the user who created the note,
+  /// empty for notes created before ownership was recorded.
+  pub owner_id: String,
   /// Title of the note.
   pub title: String,
   /// Content of the note.
   pub content: String,
-  /// Date and time when the note was created,
-  /// in format `YYYY-MM-DD hh:mm:ss`.
-  pub created_at: String,
-  /// Date and time when the note expires,
-  /// in format `YYYY-MM-DD hh:mm:ss`.
-  pub expires_at: Option<String>,
+  /// Format of the content of the note.
+  pub content_format: ContentFormat,
+  /// Date and time (UTC) when the note was created.
+  pub created_at: OffsetDateTime,
+  /// Date and time (UTC) when the note was modified for the last time.
+  pub updated_at: OffsetDateTime,
+  /// Version of the note, incremented with every modification,
+  /// notes created before versioning was introduced have version 0.
+  pub version: i64,
+  /// Date and time (UTC) when the note expires, `None` when the note never expires.
+  pub expires_at: Option<OffsetDateTime>,
+  /// Tags of the note.
+  pub tags: BTreeSet<String>,
+  /// Identifier of the notebook the note belongs to, `None` when the note does not belong to any notebook.
+  pub notebook_id: Option<String>,
+  /// Date and time (UTC) when the note was moved to trash, `None` when the note is not in trash.
+  pub deleted_at: Option<OffsetDateTime>,
 }
 
 impl NoteEntity {
-  /// Creates a new note entity with title, content and expiration time.
-  pub fn new(title: &str, content: &str, ttl: &str) -> Self {
-    let (created_at, expires_at) = create_and_expiration_date_time(ttl);
+  /// Creates a new note entity owned by specified user, with title, plain text content and optional expiration time,
+  /// without tags and not belonging to any notebook.
+  pub fn new(owner_id: &str, title: &str, content: &str, expires_at: Option<OffsetDateTime>) -> Self {
+    let created_at = now();
     Self {
       note_id: uuid(),
+      owner_id: owner_id.to_string(),
       title: title.to_string(),
       content: content.to_string(),
+      content_format: ContentFormat::Plain,
       created_at,
+      updated_at: created_at,
+      version: 1,
       expires_at,
+      tags: BTreeSet::new(),
+      notebook_id: None,
+      deleted_at: None,
     }
   }
+  /// Records the modification of the note.
+  pub fn touch(&mut self) {
+    self.updated_at = now();
+  }
+  /// Returns `true` when the note is owned by specified user.
+  pub fn is_owned_by(&self, user_id: &str) -> bool {
+    !self.owner_id.is_empty() && self.owner_id == user_id
+  }
+  /// Returns `true` when the note was moved to trash.
+  pub fn is_trashed(&self) -> bool {
+    self.deleted_at.is_some()
+  }
   /// Returns `true` when the note has expired.
   pub fn has_expired(&self) -> bool {
-    let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
-    if let Some(date_time_str) = &self.expires_at {
-      if let Ok(expires_at) = PrimitiveDateTime::parse(date_time_str, format) {
-        return expires_at.assume_utc() < OffsetDateTime::now_utc();
-      }
-    }
-    false
+    self.expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+  }
+  /// Returns the number of seconds remaining until the note expires (zero when already expired),
+  /// `None` when the note never expires.
+  pub fn remaining_ttl(&self) -> Option<i64> {
+    self
+      .expires_at
+      .map(|expires_at| (expires_at - OffsetDateTime::now_utc()).whole_seconds().max(0))
+  }
+  /// Returns the time to live in seconds, used when the note is stored in the database.
+  ///
+  /// Zero means that the note never expires, otherwise the time to live is at least one second
+  /// and at most [MAX_TTL_SECONDS].
+  pub fn ttl_seconds(&self) -> i32 {
+    ttl_seconds(self.expires_at)
+  }
+}
+
+/// Returns the time to live in seconds for specified expiration time, used when entries expiring
+/// together with a note are stored in the database. Zero means that the entry never expires,
+/// otherwise the time to live is at least one second and at most [MAX_TTL_SECONDS].
+pub fn ttl_seconds(expires_at: Option<OffsetDateTime>) -> i32 {
+  match expires_at {
+    Some(expires_at) => (expires_at - OffsetDateTime::now_utc()).whole_seconds().clamp(1, MAX_TTL_SECONDS) as i32,
+    None => 0,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use time::macros::datetime;
+  use time::Duration;
+
+  #[test]
+  fn test_ttl() {
+    let note = NoteEntity::new("alice", "title", "content", Some(now() + Duration::hours(2)));
+    let remaining = note.remaining_ttl().unwrap();
+    assert!((7140..=7200).contains(&remaining), "{}", remaining);
+    assert!(!note.has_expired());
+    let note = NoteEntity::new("alice", "title", "content", None);
+    assert_eq!(None, note.remaining_ttl());
+    assert_eq!(0, note.ttl_seconds());
+    let mut note = NoteEntity::new("alice", "title", "content", Some(now() + Duration::hours(1)));
+    note.expires_at = Some(datetime!(2000-01-01 00:00 UTC));
+    assert!(note.has_expired());
+    assert_eq!(Some(0), note.remaining_ttl());
+    assert_eq!(1, note.ttl_seconds());
+    let note = NoteEntity::new("alice", "title", "content", Some(now() + Duration::weeks(9999)));
+    assert_eq!(MAX_TTL_SECONDS as i32, note.ttl_seconds());
+  }
+
+  #[test]
+  fn test_content_format() {
+    assert_eq!(ContentFormat::Markdown, "markdown".parse().unwrap());
+    assert_eq!("html", ContentFormat::Html.to_string());
+    assert_eq!(ContentFormat::Plain, ContentFormat::default());
+    assert_eq!("invalid content format, name = rtf", "rtf".parse::<ContentFormat>().unwrap_err().to_string());
   }
 }
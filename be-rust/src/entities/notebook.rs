@@ -0,0 +1,53 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of notebook entity.
+
+use super::Entity;
+use crate::utils::{now, uuid};
+use time::OffsetDateTime;
+
+/// Notebook entity, groups notes of its owner.
+#[derive(Debug, Clone)]
+pub struct NotebookEntity {
+  /// Unique notebook identifier.
+  pub notebook_id: String,
+  /// Identifier of the user who created the notebook.
+  pub owner_id: String,
+  /// Name of the notebook.
+  pub name: String,
+  /// Date and time (UTC) when the notebook was created.
+  pub created_at: OffsetDateTime,
+}
+
+impl Entity for NotebookEntity {
+  /// Returns unique identifier of the notebook.
+  fn id(&self) -> String {
+    self.notebook_id.clone()
+  }
+}
+
+impl NotebookEntity {
+  /// Creates a new notebook owned by specified user.
+  pub fn new(owner_id: &str, name: &str) -> Self {
+    Self {
+      notebook_id: uuid(),
+      owner_id: owner_id.to_string(),
+      name: name.to_string(),
+      created_at: now(),
+    }
+  }
+}
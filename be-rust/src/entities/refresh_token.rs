@@ -0,0 +1,55 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of refresh token entity.
+
+use super::Entity;
+use crate::utils::uuid;
+
+/// Refresh token entity, issued together with JWT access token.
+///
+/// Every refresh token may be used only once, using it issues a new refresh token
+/// in the same session. All refresh tokens issued in a session form a single family.
+#[derive(Debug, Clone)]
+pub struct RefreshTokenEntity {
+  /// Unique identifier of the refresh token, used as a token by clients.
+  pub token_id: String,
+  /// Identifier of the session in which the token was issued.
+  pub session_id: String,
+  /// Identifier of the user owning the session.
+  pub user_id: String,
+  /// Flag indicating if the token was already used.
+  pub used: bool,
+}
+
+impl Entity for RefreshTokenEntity {
+  /// Returns unique identifier of the refresh token.
+  fn id(&self) -> String {
+    self.token_id.clone()
+  }
+}
+
+impl RefreshTokenEntity {
+  /// Creates a new unused refresh token in specified session.
+  pub fn new(session_id: &str, user_id: &str) -> Self {
+    Self {
+      token_id: uuid(),
+      session_id: session_id.to_string(),
+      user_id: user_id.to_string(),
+      used: false,
+    }
+  }
+}
@@ -0,0 +1,61 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note revision entity.
+
+use crate::entities::note::{ContentFormat, NoteEntity};
+use time::OffsetDateTime;
+
+/// Revision of a note, records the title, content and content format of a single version of the note.
+#[derive(Debug, Clone)]
+pub struct RevisionEntity {
+  /// Identifier of the note.
+  pub note_id: String,
+  /// Number of the revision, equal to the version of the note.
+  pub revision: i64,
+  /// Identifier of the user who created or modified the note.
+  pub author_id: String,
+  /// Date and time (UTC) when the version of the note was stored.
+  pub created_at: OffsetDateTime,
+  /// Title of the note.
+  pub title: String,
+  /// Content of the note.
+  pub content: String,
+  /// Format of the content of the note.
+  pub content_format: ContentFormat,
+  /// Date and time (UTC) when the revision expires together with the note, `None` when the note never expires.
+  pub expires_at: Option<OffsetDateTime>,
+}
+
+impl RevisionEntity {
+  /// Creates a revision recording the current version of the note, stored by specified user.
+  pub fn new(note: &NoteEntity, author_id: &str) -> Self {
+    Self {
+      note_id: note.note_id.clone(),
+      revision: note.version,
+      author_id: author_id.to_string(),
+      created_at: note.updated_at,
+      title: note.title.clone(),
+      content: note.content.clone(),
+      content_format: note.content_format,
+      expires_at: note.expires_at,
+    }
+  }
+  /// Returns `true` when the revision has expired.
+  pub fn has_expired(&self) -> bool {
+    self.expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+  }
+}
@@ -17,16 +17,80 @@
 //! Implementation of role entity.
 
 use super::Entity;
+use crate::errors::*;
 use crate::utils::uuid;
-use scylla::macros::FromRow;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Permission granted to users through their roles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Permission {
+  /// Reading notes.
+  NotesRead,
+  /// Creating and modifying notes.
+  NotesWrite,
+  /// Administering all notes, regardless of their owners.
+  NotesAdmin,
+  /// Reading roles.
+  RolesRead,
+  /// Creating, modifying and deleting roles.
+  RolesAdmin,
+  /// Creating, modifying and deleting users.
+  UsersAdmin,
+}
+
+impl Permission {
+  /// All defined permissions.
+  pub const ALL: [Permission; 6] = [
+    Permission::NotesRead,
+    Permission::NotesWrite,
+    Permission::NotesAdmin,
+    Permission::RolesRead,
+    Permission::RolesAdmin,
+    Permission::UsersAdmin,
+  ];
+  /// Returns the name of the permission.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Permission::NotesRead => "notes:read",
+      Permission::NotesWrite => "notes:write",
+      Permission::NotesAdmin => "notes:admin",
+      Permission::RolesRead => "roles:read",
+      Permission::RolesAdmin => "roles:admin",
+      Permission::UsersAdmin => "users:admin",
+    }
+  }
+}
+
+impl Display for Permission {
+  /// Implementation of [Display] trait for [Permission].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Permission {
+  type Err = NordNotesError;
+  /// Parses the permission from its name.
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Permission::ALL
+      .iter()
+      .find(|permission| permission.name() == name)
+      .copied()
+      .ok_or_else(|| err_invalid_permission(name))
+  }
+}
 
 /// Role entity.
-#[derive(FromRow)]
+#[derive(Debug, Clone)]
 pub struct RoleEntity {
   /// Unique role identifier.
   role_id: String,
   /// Role name.
   name: String,
+  /// Permissions granted by the role.
+  permissions: BTreeSet<Permission>,
 }
 
 impl Entity for RoleEntity {
@@ -37,18 +101,31 @@ impl Entity for RoleEntity {
 }
 
 impl RoleEntity {
-  /// Creates a new role with specified name.
+  /// Creates a new role with specified name and permissions.
   /// Role names are always converted to uppercase.
-  pub fn new(name: &str) -> Self {
+  pub fn new(name: &str, permissions: BTreeSet<Permission>) -> Self {
     Self {
       role_id: uuid(),
       name: name.to_uppercase(),
+      permissions,
     }
   }
+  /// Creates a role with all attributes specified, used when loading roles from storage.
+  pub fn load(role_id: String, name: String, permissions: BTreeSet<Permission>) -> Self {
+    Self { role_id, name, permissions }
+  }
   /// Returns the name of the role.
   pub fn name(&self) -> String {
     self.name.clone()
   }
+  /// Returns permissions granted by the role.
+  pub fn permissions(&self) -> &BTreeSet<Permission> {
+    &self.permissions
+  }
+  /// Replaces permissions granted by the role.
+  pub fn set_permissions(&mut self, permissions: BTreeSet<Permission>) {
+    self.permissions = permissions;
+  }
 }
 
 #[cfg(test)]
@@ -57,8 +134,18 @@ mod tests {
 
   #[test]
   fn test_create() {
-    let role = RoleEntity::new("admin");
+    let role = RoleEntity::new("admin", BTreeSet::from([Permission::NotesAdmin]));
     assert_eq!(36, role.id().len());
     assert_eq!("ADMIN", role.name());
+    assert!(role.permissions().contains(&Permission::NotesAdmin));
+  }
+
+  #[test]
+  fn test_permission_names() {
+    for permission in Permission::ALL {
+      assert_eq!(permission, permission.name().parse().unwrap());
+    }
+    assert_eq!("notes:write", Permission::NotesWrite.to_string());
+    assert!("notes:delete".parse::<Permission>().is_err());
   }
 }
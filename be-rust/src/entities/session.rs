@@ -0,0 +1,86 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of session entity.
+
+use super::Entity;
+use crate::utils::uuid;
+use time::{Duration, OffsetDateTime};
+
+/// Session entity, created for a user after successful login.
+#[derive(Debug, Clone)]
+pub struct SessionEntity {
+  /// Unique session identifier, used as a token by clients.
+  pub session_id: String,
+  /// Identifier of the user owning the session.
+  pub user_id: String,
+  /// Date and time when the session was created.
+  pub created_at: OffsetDateTime,
+  /// Date and time when the session was used for the last time.
+  pub last_used_at: OffsetDateTime,
+  /// Date and time when the session expires, regardless of its usage.
+  pub expires_at: OffsetDateTime,
+}
+
+impl Entity for SessionEntity {
+  /// Returns unique identifier of the session.
+  fn id(&self) -> String {
+    self.session_id.clone()
+  }
+}
+
+impl SessionEntity {
+  /// Creates a new session for the user, expiring after specified timeout.
+  pub fn new(user_id: &str, timeout: Duration) -> Self {
+    let now = OffsetDateTime::now_utc();
+    Self {
+      session_id: uuid(),
+      user_id: user_id.to_string(),
+      created_at: now,
+      last_used_at: now,
+      expires_at: now + timeout,
+    }
+  }
+  /// Returns `true` when the session has expired, either absolutely or after idle timeout.
+  pub fn has_expired(&self, idle_timeout: Duration) -> bool {
+    let now = OffsetDateTime::now_utc();
+    now >= self.expires_at || now >= self.last_used_at + idle_timeout
+  }
+  /// Returns the number of seconds the session remains valid when not used, at least one second.
+  pub fn ttl(&self, idle_timeout: Duration) -> i32 {
+    let deadline = self.expires_at.min(self.last_used_at + idle_timeout);
+    (deadline - OffsetDateTime::now_utc()).whole_seconds().clamp(1, i32::MAX as i64) as i32
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_expiration() {
+    let mut session = SessionEntity::new("user", Duration::hours(2));
+    assert!(!session.has_expired(Duration::minutes(30)));
+    assert!((1799..=1800).contains(&session.ttl(Duration::minutes(30))));
+    assert!((7199..=7200).contains(&session.ttl(Duration::hours(3))));
+    session.last_used_at -= Duration::minutes(31);
+    assert!(session.has_expired(Duration::minutes(30)));
+    assert_eq!(1, session.ttl(Duration::minutes(30)));
+    session.last_used_at = OffsetDateTime::now_utc();
+    session.expires_at = session.last_used_at;
+    assert!(session.has_expired(Duration::minutes(30)));
+  }
+}
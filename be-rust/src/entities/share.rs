@@ -0,0 +1,140 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note share entity.
+
+use crate::errors::*;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Access to a note granted by a share.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+  /// Reading the note.
+  Read,
+  /// Reading and modifying the note.
+  Edit,
+}
+
+impl Access {
+  /// All defined kinds of access.
+  pub const ALL: [Access; 2] = [Access::Read, Access::Edit];
+  /// Returns the name of the access.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Access::Read => "read",
+      Access::Edit => "edit",
+    }
+  }
+}
+
+impl Display for Access {
+  /// Implementation of [Display] trait for [Access].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Access {
+  type Err = NordNotesError;
+  /// Parses the access from its name.
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Access::ALL
+      .iter()
+      .find(|access| access.name() == name)
+      .copied()
+      .ok_or_else(|| err_invalid_access(name))
+  }
+}
+
+/// Kind of the grantee the note is shared with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GranteeType {
+  /// The note is shared with a single user.
+  User,
+  /// The note is shared with all users having the role.
+  Role,
+}
+
+impl GranteeType {
+  /// All defined kinds of grantees.
+  pub const ALL: [GranteeType; 2] = [GranteeType::User, GranteeType::Role];
+  /// Returns the name of the grantee type.
+  pub fn name(&self) -> &'static str {
+    match self {
+      GranteeType::User => "user",
+      GranteeType::Role => "role",
+    }
+  }
+}
+
+impl Display for GranteeType {
+  /// Implementation of [Display] trait for [GranteeType].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for GranteeType {
+  type Err = NordNotesError;
+  /// Parses the grantee type from its name.
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    GranteeType::ALL
+      .iter()
+      .find(|grantee_type| grantee_type.name() == name)
+      .copied()
+      .ok_or_else(|| err_invalid_grantee_type(name))
+  }
+}
+
+/// Share entity, grants access to a note for a user or for all users having a role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareEntity {
+  /// Identifier of the shared note.
+  pub note_id: String,
+  /// Kind of the grantee.
+  pub grantee_type: GranteeType,
+  /// Identifier of the user or role the note is shared with.
+  pub grantee_id: String,
+  /// Granted access.
+  pub access: Access,
+}
+
+impl ShareEntity {
+  /// Creates a new share of the note.
+  pub fn new(note_id: &str, grantee_type: GranteeType, grantee_id: &str, access: Access) -> Self {
+    Self {
+      note_id: note_id.to_string(),
+      grantee_type,
+      grantee_id: grantee_id.to_string(),
+      access,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_names() {
+    assert_eq!(Access::Edit, "edit".parse().unwrap());
+    assert_eq!(GranteeType::Role, "role".parse().unwrap());
+    assert_eq!("invalid access, name = write", "write".parse::<Access>().unwrap_err().to_string());
+    assert_eq!("invalid grantee type, name = group", "group".parse::<GranteeType>().unwrap_err().to_string());
+    assert!(Access::Read < Access::Edit);
+  }
+}
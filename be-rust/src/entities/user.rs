@@ -16,30 +16,43 @@
 
 //! Implementation of user entity.
 
+use super::Entity;
 use crate::utils::uuid;
-use scylla::macros::FromRow;
+use std::collections::BTreeSet;
 
 /// User entity.
-#[derive(Debug, Clone, FromRow)]
+#[derive(Debug, Clone)]
 pub struct UserEntity {
   /// Unique user identifier.
   pub user_id: String,
   /// User login.
   pub login: String,
-  /// User password.
+  /// Password hash in PHC string format.
+  /// Users imported from legacy file hold a plaintext password until their first successful login.
   pub password: String,
-  /// Token generated for the user.
-  pub token: Option<String>,
+  /// Identifiers of roles granted to the user.
+  pub roles: BTreeSet<String>,
+}
+
+impl Entity for UserEntity {
+  /// Returns unique identifier of the user.
+  fn id(&self) -> String {
+    self.user_id.clone()
+  }
 }
 
 impl UserEntity {
-  /// Creates a new user entity.
+  /// Creates a new user entity without any roles.
   pub fn new(login: &str, password: &str) -> Self {
     Self {
       user_id: uuid(),
       login: login.to_string(),
       password: password.to_string(),
-      token: None,
+      roles: BTreeSet::new(),
     }
   }
+  /// Returns `true` when the user was granted the role with specified identifier.
+  pub fn has_role(&self, role_id: &str) -> bool {
+    self.roles.contains(role_id)
+  }
 }
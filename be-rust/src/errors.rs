@@ -15,33 +15,145 @@
  */
 
 //! Definition of common error type used across `nordnotes` application.
+//!
+//! Every error has a stable machine-readable code, see [ErrorCode], which determines the HTTP status
+//! of the response reporting the error. Details of internal errors are only logged, clients receive
+//! a generic description instead.
 
+use crate::collaboration::MAX_OPERATIONS;
+use crate::entities::attachment::MAX_ATTACHMENTS;
+use crate::entities::note::{MAX_TAGS, MAX_TAG_LENGTH};
+use crate::repositories::MAX_PAGE_SIZE;
+use actix_web::http::StatusCode;
+use argon2::password_hash;
 use scylla::cql_to_rust::FromRowError;
 use scylla::transport::errors::{NewSessionError, QueryError};
 use std::fmt::Display;
 use std::io::Error;
 
+/// Description of internal errors reported to clients.
+const INTERNAL_ERROR_DETAILS: &str = "internal server error";
+
 /// Common result type used across `nordnotes` application.
 pub type Result<T, E = NordNotesError> = std::result::Result<T, E>;
 
+/// Codes of errors, names of codes are stable and may be interpreted by clients.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+  /// The request is malformed or has invalid parameters.
+  InvalidRequest,
+  /// The request was not authenticated or the session has ended.
+  NotAuthorized,
+  /// Invalid login and password, or invalid refresh token.
+  InvalidCredentials,
+  /// The user lacks the required permission or access.
+  Forbidden,
+  /// The requested resource or endpoint does not exist.
+  NotFound,
+  /// The created resource already exists.
+  AlreadyExists,
+  /// The note was modified concurrently.
+  VersionConflict,
+  /// The number of items would exceed the limit.
+  LimitExceeded,
+  /// The uploaded content is too large.
+  PayloadTooLarge,
+  /// The requested range of content is not satisfiable.
+  RangeNotSatisfiable,
+  /// The endpoint requires the upgrade of the connection to WebSocket.
+  UpgradeRequired,
+  /// The expected version of the note was not specified.
+  PreconditionRequired,
+  /// The service is temporarily unavailable.
+  Unavailable,
+  /// Internal server error.
+  Internal,
+}
+
+impl ErrorCode {
+  /// Returns the name of the error code.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::InvalidRequest => "invalid-request",
+      Self::NotAuthorized => "not-authorized",
+      Self::InvalidCredentials => "invalid-credentials",
+      Self::Forbidden => "forbidden",
+      Self::NotFound => "not-found",
+      Self::AlreadyExists => "already-exists",
+      Self::VersionConflict => "version-conflict",
+      Self::LimitExceeded => "limit-exceeded",
+      Self::PayloadTooLarge => "payload-too-large",
+      Self::RangeNotSatisfiable => "range-not-satisfiable",
+      Self::UpgradeRequired => "upgrade-required",
+      Self::PreconditionRequired => "precondition-required",
+      Self::Unavailable => "unavailable",
+      Self::Internal => "internal-error",
+    }
+  }
+  /// Returns the HTTP status of responses reporting errors with this code.
+  pub fn status(&self) -> StatusCode {
+    match self {
+      Self::InvalidRequest => StatusCode::BAD_REQUEST,
+      Self::NotAuthorized | Self::InvalidCredentials => StatusCode::UNAUTHORIZED,
+      Self::Forbidden => StatusCode::FORBIDDEN,
+      Self::NotFound => StatusCode::NOT_FOUND,
+      Self::AlreadyExists | Self::VersionConflict | Self::LimitExceeded => StatusCode::CONFLICT,
+      Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+      Self::RangeNotSatisfiable => StatusCode::RANGE_NOT_SATISFIABLE,
+      Self::UpgradeRequired => StatusCode::UPGRADE_REQUIRED,
+      Self::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+      Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+      Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+  }
+}
+
 /// Common error used across `nordnotes` application.
 #[derive(Debug)]
-pub struct NordNotesError(String);
+pub struct NordNotesError {
+  /// Error code.
+  code: ErrorCode,
+  /// Error details, reported to clients unless the error is internal.
+  details: String,
+}
+
+impl NordNotesError {
+  /// Creates an error with specified code and details.
+  fn new(code: ErrorCode, details: impl Into<String>) -> Self {
+    Self { code, details: details.into() }
+  }
+  /// Returns the error code.
+  pub fn code(&self) -> ErrorCode {
+    self.code
+  }
+  /// Returns the error details that may be reported to clients, details of internal errors are replaced with a generic description.
+  pub fn details(&self) -> &str {
+    match self.code {
+      ErrorCode::Internal => INTERNAL_ERROR_DETAILS,
+      _ => &self.details,
+    }
+  }
+}
 
 impl Display for NordNotesError {
-  /// Implementation of [Display] trait for [NordNotesError].
+  /// Implementation of [Display] trait for [NordNotesError], displays full details, also of internal errors.
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{}", self.0)
+    write!(f, "{}", self.details)
   }
 }
 
 impl From<Error> for NordNotesError {
-  /// Converts [Error] into [NordNotesError].
+  /// Converts [Error] into internal [NordNotesError].
   fn from(e: Error) -> Self {
-    Self(e.to_string())
+    Self::new(ErrorCode::Internal, e.to_string())
   }
 }
 
+/// Creates an invalid request error, reported when the request can not be parsed.
+pub fn err_invalid_request(reason: impl Display) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid request: {}", reason))
+}
+
 /// Creates an internal web server error description.
 pub fn err_server_internal(e: Error) -> NordNotesError {
   e.into()
@@ -49,55 +161,323 @@ pub fn err_server_internal(e: Error) -> NordNotesError {
 
 /// Creates a non-existing endpoint error.
 pub fn err_endpoint_not_found(message: &str) -> NordNotesError {
-  NordNotesError(format!("endpoint not found: {}", message))
+  NordNotesError::new(ErrorCode::NotFound, format!("endpoint not found: {}", message))
 }
 
 /// Creates a non-existing note error.
 pub fn err_note_not_found(note_id: &str) -> NordNotesError {
-  NordNotesError(format!("note not found, id = {}", note_id))
+  NordNotesError::new(ErrorCode::NotFound, format!("note not found, id = {}", note_id))
 }
 
 /// Creates a non-existing entity error.
 pub fn err_entity_not_found(entity: &str, description: &str) -> NordNotesError {
-  NordNotesError(format!("{} not found: {}", entity, description))
+  NordNotesError::new(ErrorCode::NotFound, format!("{} not found: {}", entity, description))
 }
 
 /// Creates a missing required attribute error.
 pub fn err_required_attribute_not_specified(attribute_name: &str) -> NordNotesError {
-  NordNotesError(format!("required attribute not specified, name = {}", attribute_name))
+  NordNotesError::new(
+    ErrorCode::InvalidRequest,
+    format!("required attribute not specified, name = {}", attribute_name),
+  )
 }
 
-/// Creates a failed login error.
-pub fn err_invalid_login_or_password() -> NordNotesError {
-  NordNotesError("invalid login or password".to_string())
+/// Creates an unknown permission error.
+pub fn err_invalid_permission(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid permission, name = {}", name))
+}
+
+/// Creates an error for authorized user lacking the required permission.
+pub fn err_forbidden(permission: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Forbidden, format!("forbidden, required permission = {}", permission))
 }
 
-/// Creates a failed note creation error.
-pub fn err_creating_note_failed() -> NordNotesError {
-  NordNotesError("creating a new note failed".to_string())
+/// Creates a duplicated user login error.
+pub fn err_user_already_exists(login: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::AlreadyExists, format!("user already exists, login = {}", login))
 }
 
-/// Creates a failed role creation error.
-pub fn err_creating_role_failed() -> NordNotesError {
-  NordNotesError("creating a new role failed".to_string())
+/// Creates an error for superuser whose stored password differs from the configured one.
+pub fn err_superuser_password_differs(login: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("stored password of superuser differs, login = {}", login))
+}
+
+/// Creates a failed login error.
+pub fn err_invalid_login_or_password() -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidCredentials, "invalid login or password".to_string())
 }
 
 /// Creates a not authorized user error.
 pub fn err_not_authorized() -> NordNotesError {
-  NordNotesError("not authorized".to_string())
+  NordNotesError::new(ErrorCode::NotAuthorized, "not authorized".to_string())
+}
+
+/// Creates an unsupported storage backend error.
+pub fn err_invalid_storage(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("invalid storage, name = {}", name))
+}
+
+/// Creates an invalid timestamp error.
+pub fn err_invalid_timestamp(e: time::error::ComponentRange) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("invalid timestamp: {}", e))
+}
+
+/// Creates an invalid setting value error.
+pub fn err_invalid_setting(name: &str, value: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("invalid setting {} = {}", name, value))
+}
+
+/// Creates an error reported when the note was modified concurrently.
+pub fn err_version_conflict(note_id: &str, current_version: i64) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::VersionConflict,
+    format!("version conflict, note id = {}, current version = {}", note_id, current_version),
+  )
+}
+
+/// Creates an error reported when the expected version was not specified.
+pub fn err_precondition_required() -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::PreconditionRequired,
+    "precondition required, specify the expected version in If-Match header".to_string(),
+  )
+}
+
+/// Creates an invalid If-Match header error.
+pub fn err_invalid_if_match(value: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid If-Match header, value = {}", value))
+}
+
+/// Creates an invalid time to live error.
+pub fn err_invalid_ttl(ttl: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid time to live, ttl = {}", ttl))
+}
+
+/// Creates an invalid expiration time error.
+pub fn err_invalid_expires_at(expires_at: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid expiration time, expiresAt = {}", expires_at))
+}
+
+/// Creates an error reported when the expiration time is in the past or too far in the future.
+pub fn err_expiration_out_of_range(expires_at: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("expiration time out of range, expires at = {}", expires_at))
+}
+
+/// Creates an error reported when mutually exclusive attributes are specified together.
+pub fn err_conflicting_attributes(first: &str, second: &str) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::InvalidRequest,
+    format!("conflicting attributes specified, names = {}, {}", first, second),
+  )
+}
+
+/// Creates an invalid sort order error.
+pub fn err_invalid_sort_order(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid sort order, name = {}", name))
+}
+
+/// Creates an error reporting the sort order requested for a listing in unspecified order.
+pub fn err_sort_order_not_supported(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("sort order not supported, name = {}", name))
+}
+
+/// Creates an invalid page size error.
+pub fn err_invalid_page_size(size: &str) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::InvalidRequest,
+    format!("invalid page size, size = {}, maximum = {}", size, MAX_PAGE_SIZE),
+  )
+}
+
+/// Creates an invalid page token error.
+pub fn err_invalid_page_token() -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, "invalid page token".to_string())
+}
+
+/// Creates an invalid search query error.
+pub fn err_invalid_search_query(query: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid search query, q = {}", query))
+}
+
+/// Creates an error for searching before the search index was built.
+pub fn err_search_index_not_ready() -> NordNotesError {
+  NordNotesError::new(ErrorCode::Unavailable, "search index is being built, try again later")
+}
+
+/// Creates a non-existing notebook error.
+pub fn err_notebook_not_found(notebook_id: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::NotFound, format!("notebook not found, id = {}", notebook_id))
+}
+
+/// Creates an invalid tag error.
+pub fn err_invalid_tag(tag: &str) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::InvalidRequest,
+    format!("invalid tag, tag = {}, maximum length = {}", tag, MAX_TAG_LENGTH),
+  )
+}
+
+/// Creates an error reported when a note has too many tags.
+pub fn err_too_many_tags(count: usize) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("too many tags, count = {}, maximum = {}", count, MAX_TAGS))
+}
+
+/// Creates a non-existing note revision error.
+pub fn err_revision_not_found(note_id: &str, revision: i64) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::NotFound,
+    format!("revision not found, note id = {}, revision = {}", note_id, revision),
+  )
+}
+
+/// Creates an invalid revision number error.
+pub fn err_invalid_revision(value: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid revision, value = {}", value))
+}
+
+/// Creates an insufficient note access error.
+pub fn err_note_access_forbidden(note_id: &str, access: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Forbidden, format!("forbidden, required access = {}, note id = {}", access, note_id))
+}
+
+/// Creates an unknown note access error.
+pub fn err_invalid_access(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid access, name = {}", name))
+}
+
+/// Creates an unknown content format error.
+pub fn err_invalid_content_format(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid content format, name = {}", name))
+}
+
+/// Creates an unsupported rendering format error.
+pub fn err_invalid_render(value: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid render format, value = {}", value))
+}
+
+/// Creates an unknown grantee type error.
+pub fn err_invalid_grantee_type(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid grantee type, name = {}", name))
+}
+
+/// Creates a non-existing note share error.
+pub fn err_share_not_found(note_id: &str, grantee_type: &str, grantee_id: &str) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::NotFound,
+    format!("share not found, note id = {}, {} id = {}", note_id, grantee_type, grantee_id),
+  )
+}
+
+/// Creates a missing required setting error.
+pub fn err_required_setting(name: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("required setting {} is not set", name))
+}
+
+/// Creates a file reading error.
+pub fn err_read_file(path: &str, e: Error) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("reading file {} failed: {}", path, e))
+}
+
+/// Creates a file writing error.
+pub fn err_write_file(path: &str, e: Error) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("writing file {} failed: {}", path, e))
+}
+
+/// Creates a non-existing attachment error.
+pub fn err_attachment_not_found(note_id: &str, attachment_id: &str) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::NotFound,
+    format!("attachment not found, note id = {}, attachment id = {}", note_id, attachment_id),
+  )
+}
+
+/// Creates an error reported when the uploaded file exceeds the maximum size of an attachment.
+pub fn err_attachment_too_large(max_size: u64) -> NordNotesError {
+  NordNotesError::new(ErrorCode::PayloadTooLarge, format!("attachment too large, maximum size = {} bytes", max_size))
+}
+
+/// Creates an error reported when the request uploading files exceeds the maximum size.
+pub fn err_upload_too_large(max_size: u64) -> NordNotesError {
+  NordNotesError::new(ErrorCode::PayloadTooLarge, format!("upload too large, maximum size = {} bytes", max_size))
+}
+
+/// Creates an error reported when the number of attachments of a note would exceed the limit.
+pub fn err_too_many_attachments(count: usize) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::LimitExceeded,
+    format!("too many attachments, count = {}, maximum = {}", count, MAX_ATTACHMENTS),
+  )
+}
+
+/// Creates an invalid multipart request error.
+pub fn err_invalid_multipart(reason: impl Display) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid multipart request: {}", reason))
+}
+
+/// Creates an unsatisfiable range error.
+pub fn err_range_not_satisfiable(size: i64) -> NordNotesError {
+  NordNotesError::new(ErrorCode::RangeNotSatisfiable, format!("range not satisfiable, size = {}", size))
+}
+
+/// Creates an invalid collaborative editing operation error.
+pub fn err_invalid_operation(reason: &str) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid operation: {}", reason))
+}
+
+/// Creates an invalid collaborative editing message error.
+pub fn err_invalid_message(reason: impl Display) -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, format!("invalid message: {}", reason))
+}
+
+/// Creates an error reported when a WebSocket endpoint is requested without the upgrade of the connection.
+pub fn err_websocket_required() -> NordNotesError {
+  NordNotesError::new(ErrorCode::UpgradeRequired, "WebSocket connection required".to_string())
+}
+
+/// Creates an error reported when a collaborative editing message has too many operations.
+pub fn err_too_many_operations(count: usize) -> NordNotesError {
+  NordNotesError::new(
+    ErrorCode::LimitExceeded,
+    format!("too many operations, count = {}, maximum = {}", count, MAX_OPERATIONS),
+  )
+}
+
+/// Creates a JWT processing error.
+pub fn err_jwt(e: jsonwebtoken::errors::Error) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("JWT error: {}", e))
+}
+
+/// Creates an error reported when JWT access tokens are not enabled.
+pub fn err_jwt_disabled() -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidRequest, "JWT access tokens are disabled".to_string())
+}
+
+/// Creates an invalid refresh token error.
+pub fn err_invalid_refresh_token() -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidCredentials, "invalid refresh token".to_string())
+}
+
+/// Creates an error reported when already used refresh token is presented again.
+pub fn err_refresh_token_reused() -> NordNotesError {
+  NordNotesError::new(ErrorCode::InvalidCredentials, "refresh token reused, session revoked".to_string())
 }
 
 /// Creates a new session initialization error.
 pub fn err_new_session(e: NewSessionError) -> NordNotesError {
-  NordNotesError(format!("{:?}", e))
+  NordNotesError::new(ErrorCode::Internal, format!("connecting to database failed: {}", e))
 }
 
 /// Creates an error for failed query.
 pub fn err_query(e: QueryError) -> NordNotesError {
-  NordNotesError(format!("{:?}", e))
+  NordNotesError::new(ErrorCode::Internal, format!("query failed: {}", e))
 }
 
 /// Creates an error for failure during row conversion.
 pub fn err_from_row(e: FromRowError) -> NordNotesError {
-  NordNotesError(format!("{:?}", e))
+  NordNotesError::new(ErrorCode::Internal, format!("row conversion failed: {}", e))
+}
+
+/// Creates an error for failure during password hashing.
+pub fn err_password_hash(e: password_hash::Error) -> NordNotesError {
+  NordNotesError::new(ErrorCode::Internal, format!("password hashing failed: {}", e))
 }
@@ -0,0 +1,261 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of the internal event bus publishing changes of notes.
+//!
+//! Events are published by controllers after notes are created, updated or moved to trash,
+//! and by the expiration watcher of the server when notes expire. Every event carries
+//! the audience of the note: names of timelines of the owner and of users and roles the note
+//! is shared with (see [timeline]), so subscribers receive only events of notes they may read.
+//!
+//! Expiration times of notes are scheduled when events of created and updated notes are published,
+//! expiration events are published with the audience recorded when the note was last published or shared.
+//! The bus is held in memory, events published while nobody subscribes are lost.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::role::Permission;
+use crate::entities::share::GranteeType;
+use crate::repositories::timelines::timeline;
+use crate::services::auth::Principal;
+use crate::utils::now;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+
+/// Maximum number of events buffered for a subscriber, slower subscribers miss the oldest events.
+const CAPACITY: usize = 1024;
+
+/// Kind of change of a note.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventKind {
+  /// The note was created or restored from trash.
+  Created,
+  /// The note was modified.
+  Updated,
+  /// The note was moved to trash.
+  Deleted,
+  /// The note has expired.
+  Expired,
+}
+
+impl EventKind {
+  /// Returns the name of the kind of event.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Created => "created",
+      Self::Updated => "updated",
+      Self::Deleted => "deleted",
+      Self::Expired => "expired",
+    }
+  }
+}
+
+impl Display for EventKind {
+  /// Implementation of [Display] trait for [EventKind].
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+/// Event describing a change of a note.
+#[derive(Debug, Clone)]
+pub struct NoteEvent {
+  /// Kind of change.
+  pub kind: EventKind,
+  /// Identifier of the changed note.
+  pub note_id: String,
+  /// Changed note, present in events of created and updated notes.
+  pub note: Option<NoteEntity>,
+  /// Names of timelines of users and roles allowed to read the note.
+  pub audience: Vec<String>,
+  /// Date and time (UTC) when the change occurred.
+  pub occurred_at: OffsetDateTime,
+}
+
+impl NoteEvent {
+  /// Creates an event of the change of the note, readable by specified audience.
+  pub fn new(kind: EventKind, note: &NoteEntity, audience: Vec<String>) -> Self {
+    Self {
+      kind,
+      note_id: note.note_id.clone(),
+      note: matches!(kind, EventKind::Created | EventKind::Updated).then(|| note.clone()),
+      audience,
+      occurred_at: now(),
+    }
+  }
+  /// Returns `true` when the user may receive the event.
+  pub fn is_visible_to(&self, principal: &Principal) -> bool {
+    principal.has_permission(Permission::NotesAdmin)
+      || self.audience.contains(&timeline(GranteeType::User, &principal.user_id))
+      || principal
+        .roles
+        .iter()
+        .any(|role_id| self.audience.contains(&timeline(GranteeType::Role, role_id)))
+  }
+}
+
+/// Scheduled expiration times of notes.
+#[derive(Default)]
+struct Schedule {
+  /// Audiences of notes ordered by expiration time.
+  queue: BTreeMap<(OffsetDateTime, String), Vec<String>>,
+  /// Expiration times of scheduled notes.
+  expirations: HashMap<String, OffsetDateTime>,
+}
+
+impl Schedule {
+  /// Schedules the expiration of the note, replacing the previously scheduled expiration.
+  fn add(&mut self, note_id: &str, expires_at: OffsetDateTime, audience: Vec<String>) {
+    self.remove(note_id);
+    self.expirations.insert(note_id.to_string(), expires_at);
+    self.queue.insert((expires_at, note_id.to_string()), audience);
+  }
+  /// Cancels the scheduled expiration of the note.
+  fn remove(&mut self, note_id: &str) {
+    if let Some(expires_at) = self.expirations.remove(note_id) {
+      self.queue.remove(&(expires_at, note_id.to_string()));
+    }
+  }
+}
+
+/// Event bus delivering events of changed notes to subscribers.
+pub struct EventBus {
+  /// Sender of events to all subscribers.
+  sender: broadcast::Sender<Arc<NoteEvent>>,
+  /// Scheduled expiration times of notes.
+  schedule: Mutex<Schedule>,
+}
+
+impl Default for EventBus {
+  /// Creates an event bus without subscribers.
+  fn default() -> Self {
+    Self {
+      sender: broadcast::channel(CAPACITY).0,
+      schedule: Mutex::new(Schedule::default()),
+    }
+  }
+}
+
+impl EventBus {
+  /// Publishes the event to all subscribers and updates the scheduled expiration of the note.
+  pub fn publish(&self, event: NoteEvent) {
+    match &event.note {
+      Some(note) => self.schedule(note, event.audience.clone()),
+      None => self.schedule.lock().unwrap().remove(&event.note_id),
+    }
+    // sending fails only when there are no subscribers
+    let _ = self.sender.send(Arc::new(event));
+  }
+  /// Schedules the expiration event of the note readable by specified audience,
+  /// cancels the scheduled expiration when the note never expires.
+  pub fn schedule(&self, note: &NoteEntity, audience: Vec<String>) {
+    let mut schedule = self.schedule.lock().unwrap();
+    match note.expires_at {
+      Some(expires_at) => schedule.add(&note.note_id, expires_at, audience),
+      None => schedule.remove(&note.note_id),
+    }
+  }
+  /// Publishes expiration events of notes that expired before specified time,
+  /// returns the number of published events.
+  pub fn publish_expired(&self, time: OffsetDateTime) -> usize {
+    let mut expired = vec![];
+    {
+      let mut schedule = self.schedule.lock().unwrap();
+      while let Some(entry) = schedule.queue.first_entry() {
+        if entry.key().0 > time {
+          break;
+        }
+        let ((occurred_at, note_id), audience) = entry.remove_entry();
+        schedule.expirations.remove(&note_id);
+        expired.push(NoteEvent {
+          kind: EventKind::Expired,
+          note_id,
+          note: None,
+          audience,
+          occurred_at,
+        });
+      }
+    }
+    let count = expired.len();
+    for event in expired {
+      let _ = self.sender.send(Arc::new(event));
+    }
+    count
+  }
+  /// Subscribes to events published from now on.
+  pub fn subscribe(&self) -> broadcast::Receiver<Arc<NoteEvent>> {
+    self.sender.subscribe()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::BTreeSet;
+  use time::Duration;
+
+  fn principal(user_id: &str, roles: &[&str]) -> Principal {
+    Principal {
+      session_id: "session".to_string(),
+      user_id: user_id.to_string(),
+      login: user_id.to_string(),
+      roles: roles.iter().map(|role_id| role_id.to_string()).collect(),
+      permissions: BTreeSet::new(),
+    }
+  }
+
+  #[test]
+  fn test_visibility() {
+    let note = NoteEntity::new("alice", "title", "content", None);
+    let audience = vec![timeline(GranteeType::User, "alice"), timeline(GranteeType::Role, "EDITOR")];
+    let event = NoteEvent::new(EventKind::Deleted, &note, audience);
+    assert!(event.note.is_none());
+    assert!(event.is_visible_to(&principal("alice", &[])));
+    assert!(event.is_visible_to(&principal("bob", &["EDITOR"])));
+    assert!(!event.is_visible_to(&principal("bob", &["USER"])));
+    let mut admin = principal("eve", &[]);
+    admin.permissions.insert(Permission::NotesAdmin);
+    assert!(event.is_visible_to(&admin));
+  }
+
+  #[tokio::test]
+  async fn test_expiration_events() {
+    let bus = EventBus::default();
+    let mut receiver = bus.subscribe();
+    let mut note = NoteEntity::new("alice", "title", "content", None);
+    note.expires_at = Some(now() + Duration::minutes(5));
+    let audience = vec![timeline(GranteeType::User, "alice")];
+    bus.publish(NoteEvent::new(EventKind::Created, &note, audience.clone()));
+    assert_eq!(EventKind::Created, receiver.recv().await.unwrap().kind);
+    assert_eq!(0, bus.publish_expired(now()));
+    // updated expiration time replaces the scheduled one
+    note.expires_at = Some(now() + Duration::minutes(10));
+    bus.publish(NoteEvent::new(EventKind::Updated, &note, audience.clone()));
+    receiver.recv().await.unwrap();
+    assert_eq!(0, bus.publish_expired(now() + Duration::minutes(6)));
+    assert_eq!(1, bus.publish_expired(now() + Duration::minutes(11)));
+    let event = receiver.recv().await.unwrap();
+    assert_eq!(EventKind::Expired, event.kind);
+    assert_eq!(note.note_id, event.note_id);
+    assert_eq!(note.expires_at, Some(event.occurred_at));
+    // notes moved to trash do not expire
+    bus.publish(NoteEvent::new(EventKind::Updated, &note, audience.clone()));
+    bus.publish(NoteEvent::new(EventKind::Deleted, &note, audience));
+    assert_eq!(0, bus.publish_expired(now() + Duration::minutes(11)));
+  }
+}
@@ -0,0 +1,291 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of request handlers for note attachments.
+//!
+//! Files are uploaded as `multipart/form-data`, every part having a file name is attached to the note.
+//! Access to the note is checked before the request body is read. Contents of uploaded files are written
+//! in chunks as they are received and the storage is locked only while a chunk is written,
+//! so large uploads neither fill the memory nor block other requests.
+
+use crate::controllers::attachments;
+use crate::entities::attachment::{AttachmentEntity, MAX_ATTACHMENTS};
+use crate::entities::note::NoteEntity;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::authorized;
+use crate::server::{ApplicationData, ResultDto};
+use crate::services::attachments::{discard, Upload};
+use crate::utils::to_rfc3339;
+use actix_multipart::{Field, Multipart};
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::{
+  Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue, Header, Range as RangeHeader, ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE,
+  X_CONTENT_TYPE_OPTIONS,
+};
+use actix_web::web::{Json, Path};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, ResponseError};
+use futures_util::StreamExt;
+use serde_derive::Serialize;
+use std::ops::Range;
+
+/// Media types of attached files displayed by browsers instead of being saved.
+const INLINE_CONTENT_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp", "application/pdf"];
+
+/// Data transfer object for an attached file.
+#[derive(Serialize)]
+pub struct AttachmentDto {
+  /// Identifier of the attachment.
+  #[serde(rename = "attachmentId")]
+  pub attachment_id: String,
+  /// Identifier of the note the file is attached to.
+  #[serde(rename = "noteId")]
+  pub note_id: String,
+  /// Name of the attached file.
+  #[serde(rename = "fileName")]
+  pub file_name: String,
+  /// Media type of the content, detected from the content of the file.
+  #[serde(rename = "contentType")]
+  pub content_type: String,
+  /// Size of the content, in bytes.
+  #[serde(rename = "size")]
+  pub size: i64,
+  /// Date and time when the file was attached, in RFC 3339 format.
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+impl From<AttachmentEntity> for AttachmentDto {
+  /// Converts an [AttachmentEntity] into [AttachmentDto].
+  fn from(attachment: AttachmentEntity) -> Self {
+    Self {
+      attachment_id: attachment.attachment_id,
+      note_id: attachment.note_id,
+      file_name: attachment.file_name,
+      content_type: attachment.content_type,
+      size: attachment.size,
+      created_at: to_rfc3339(attachment.created_at),
+    }
+  }
+}
+
+/// Range of the content of an attached file returned in the response.
+pub enum ContentRange {
+  /// The whole content.
+  Full,
+  /// Part of the content, requested in `Range` header.
+  Partial(Range<u64>),
+  /// The requested range lies outside the content.
+  NotSatisfiable,
+}
+
+/// Content of an attached file returned in the response.
+pub struct AttachmentContent {
+  /// Attachment the content belongs to.
+  pub attachment: AttachmentEntity,
+  /// Returned range of the content.
+  pub range: ContentRange,
+  /// Returned bytes of the content.
+  pub content: Vec<u8>,
+}
+
+/// Parses the value of `Range` header and returns the requested range of the content having specified size.
+///
+/// Only a single range of bytes is supported; missing, malformed and multiple ranges
+/// are ignored, so the whole content is returned.
+pub fn parse_range(value: Option<&str>, size: u64) -> ContentRange {
+  let specs = match value.map(str::parse) {
+    Some(Ok(RangeHeader::Bytes(specs))) if specs.len() == 1 => specs,
+    _ => return ContentRange::Full,
+  };
+  match specs[0].to_satisfiable_range(size) {
+    Some((first, last)) => ContentRange::Partial(first..last + 1),
+    None => ContentRange::NotSatisfiable,
+  }
+}
+
+/// Returns the name of the file uploaded in the part of the multipart request, `None` when the part is not a file.
+fn uploaded_file_name(field: &Field) -> Option<String> {
+  let content_disposition = field.content_disposition()?;
+  match content_disposition.get_filename_ext() {
+    Some(extended) => Some(String::from_utf8_lossy(&extended.value).to_string()),
+    None => content_disposition.get_filename().map(str::to_string),
+  }
+}
+
+/// Limits of files uploaded in a single request.
+struct UploadLimits {
+  /// Maximum size of a single file, in bytes.
+  file_size: u64,
+  /// Maximum size of the whole request body, in bytes.
+  request_size: u64,
+  /// Maximum number of files.
+  files: usize,
+}
+
+/// Receives files uploaded in the multipart request to be attached to the note,
+/// returns attachments of uploaded files, not added to the note yet.
+///
+/// Contents of files already written are deleted when the upload fails.
+async fn upload_files(payload: Multipart, note: &NoteEntity, limits: UploadLimits, data: &ApplicationData) -> Result<Vec<AttachmentEntity>> {
+  let mut started = vec![];
+  let result = receive_files(payload, note, limits, data, &mut started).await;
+  if result.is_err() {
+    discard(&started, &*data.storage.read().await).await;
+  }
+  result
+}
+
+/// Receives files uploaded in the multipart request and writes their contents in chunks,
+/// attachments of files are collected in `started` before their contents are written.
+async fn receive_files(
+  mut payload: Multipart,
+  note: &NoteEntity,
+  limits: UploadLimits,
+  data: &ApplicationData,
+  started: &mut Vec<AttachmentEntity>,
+) -> Result<Vec<AttachmentEntity>> {
+  let mut uploaded = vec![];
+  let mut received = 0;
+  while let Some(field) = payload.next().await {
+    let mut field = field.map_err(err_invalid_multipart)?;
+    let mut upload = match uploaded_file_name(&field) {
+      Some(_) if uploaded.len() == limits.files => return Err(err_too_many_attachments(MAX_ATTACHMENTS + 1)),
+      Some(file_name) => {
+        let upload = Upload::new(note, &file_name);
+        started.push(upload.attachment().clone());
+        Some(upload)
+      }
+      None => None,
+    };
+    while let Some(chunk) = field.next().await {
+      let chunk = chunk.map_err(err_invalid_multipart)?;
+      received += chunk.len() as u64;
+      if received > limits.request_size {
+        return Err(err_upload_too_large(limits.request_size));
+      }
+      if let Some(upload) = &mut upload {
+        if upload.size() + chunk.len() as u64 > limits.file_size {
+          return Err(err_attachment_too_large(limits.file_size));
+        }
+        upload.receive(&chunk);
+        if upload.has_whole_chunk() {
+          upload.write_chunks(&*data.storage.read().await).await?;
+        }
+      }
+    }
+    if let Some(upload) = upload {
+      uploaded.push(upload.complete(&*data.storage.read().await).await?);
+    }
+  }
+  if uploaded.is_empty() {
+    return Err(err_required_attribute_not_specified("file"));
+  }
+  Ok(uploaded)
+}
+
+/// Creates the response with the content of an attached file.
+///
+/// The content type detected when the file was attached is enforced with `X-Content-Type-Options: nosniff`,
+/// images and PDF documents are displayed inline, other files are downloaded.
+fn content_response(content: AttachmentContent) -> HttpResponse {
+  let attachment = content.attachment;
+  let size = attachment.size;
+  let disposition = ContentDisposition {
+    disposition: if INLINE_CONTENT_TYPES.contains(&attachment.content_type.as_str()) {
+      DispositionType::Inline
+    } else {
+      DispositionType::Attachment
+    },
+    parameters: if attachment.file_name.is_ascii() {
+      vec![DispositionParam::Filename(attachment.file_name)]
+    } else {
+      vec![DispositionParam::FilenameExt(ExtendedValue {
+        charset: Charset::Ext("UTF-8".to_string()),
+        language_tag: None,
+        value: attachment.file_name.into_bytes(),
+      })]
+    },
+  };
+  let mut response = match &content.range {
+    ContentRange::Full => HttpResponse::Ok(),
+    ContentRange::Partial(range) => {
+      let mut response = HttpResponse::PartialContent();
+      response.insert_header((CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end - 1, size)));
+      response
+    }
+    ContentRange::NotSatisfiable => {
+      let mut response = err_range_not_satisfiable(size).error_response();
+      if let Ok(value) = HeaderValue::try_from(format!("bytes */{}", size)) {
+        response.headers_mut().insert(CONTENT_RANGE, value);
+      }
+      return response;
+    }
+  };
+  response
+    .insert_header((CONTENT_TYPE, attachment.content_type))
+    .insert_header((ContentDisposition::name(), disposition))
+    .insert_header((ACCEPT_RANGES, "bytes"))
+    .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+    .body(content.content)
+}
+
+/// Handler for attaching files to a note.
+#[post("/api/v1/notes/{id}/attachments")]
+pub async fn create(req: HttpRequest, id: Path<String>, payload: Multipart, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<AttachmentDto>>>> {
+  let note_id = id.into_inner();
+  let (principal, note, limits) = {
+    let storage = data.storage.read().await;
+    let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+    let (note, count) = attachments::prepare(&note_id, &principal, &storage).await?;
+    let limits = UploadLimits {
+      file_size: storage.settings.attachment_max_size,
+      request_size: storage.settings.upload_max_size,
+      files: MAX_ATTACHMENTS - count,
+    };
+    (principal, note, limits)
+  };
+  let uploaded = upload_files(payload, &note, limits, &data).await?;
+  let storage = data.storage.read().await;
+  Ok(Json(ResultDto::data(attachments::create(note_id, uploaded, principal, &storage).await?)))
+}
+
+/// Handler for retrieving attachments of a note.
+#[get("/api/v1/notes/{id}/attachments")]
+pub async fn list(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<AttachmentDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(attachments::list(id.into_inner(), principal, &storage).await?)))
+}
+
+/// Handler for downloading the content of an attached file, a single range of bytes may be requested in `Range` header.
+#[get("/api/v1/notes/{id}/attachments/{attachment_id}")]
+pub async fn download(req: HttpRequest, path: Path<(String, String)>, data: web::Data<ApplicationData>) -> Result<HttpResponse> {
+  let storage = data.storage.read().await;
+  let (id, attachment_id) = path.into_inner();
+  let range = req.headers().get(RangeHeader::name()).and_then(|value| value.to_str().ok()).map(str::to_string);
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(content_response(attachments::download(id, attachment_id, range, principal, &storage).await?))
+}
+
+/// Handler for deleting an attachment of a note.
+#[delete("/api/v1/notes/{id}/attachments/{attachment_id}")]
+pub async fn delete(req: HttpRequest, path: Path<(String, String)>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let storage = data.storage.read().await;
+  let (id, attachment_id) = path.into_inner();
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(attachments::delete(id, attachment_id, principal, &storage).await?)))
+}
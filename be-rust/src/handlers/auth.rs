@@ -16,9 +16,11 @@
 
 use crate::controllers::auth;
 use crate::errors::*;
+use crate::handlers::authenticated;
 use crate::server::{ApplicationData, ResultDto};
+use crate::services::tokens::Tokens;
 use actix_web::web::Json;
-use actix_web::{post, web};
+use actix_web::{post, web, HttpRequest};
 use serde_derive::{Deserialize, Serialize};
 
 /// Data transfer object for login result.
@@ -26,6 +28,31 @@ use serde_derive::{Deserialize, Serialize};
 pub struct LoginDto {
   #[serde(rename = "token")]
   pub token: String,
+  /// Access and refresh tokens, present only when JWT access tokens are enabled.
+  #[serde(flatten, skip_serializing_if = "Option::is_none")]
+  pub tokens: Option<TokensDto>,
+}
+
+/// Data transfer object for JWT access token and refresh token.
+#[derive(Serialize)]
+pub struct TokensDto {
+  #[serde(rename = "accessToken")]
+  pub access_token: String,
+  #[serde(rename = "refreshToken")]
+  pub refresh_token: String,
+  #[serde(rename = "expiresIn")]
+  pub expires_in: i64,
+}
+
+impl From<Tokens> for TokensDto {
+  /// Converts [Tokens] into [TokensDto].
+  fn from(tokens: Tokens) -> Self {
+    Self {
+      access_token: tokens.access_token,
+      refresh_token: tokens.refresh_token,
+      expires_in: tokens.expires_in,
+    }
+  }
 }
 
 /// Parameters needed when processing user's login.
@@ -52,12 +79,46 @@ impl LoginParams {
   }
 }
 
+/// Parameters needed when refreshing the access token.
+#[derive(Deserialize)]
+pub struct RefreshParams {
+  #[serde(rename = "refreshToken")]
+  pub refresh_token: Option<String>,
+}
+
+impl RefreshParams {
+  /// Validates required parameters for refreshing the access token.
+  pub fn validate(self) -> Result<String> {
+    self.refresh_token.ok_or_else(|| err_required_attribute_not_specified("refreshToken"))
+  }
+}
+
 /// Handler for logging a user.
 #[post("/api/v1/login")]
-pub async fn login(params: Json<LoginParams>, data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<LoginDto>>> {
-  let mut storage = data.storage.write().await;
-  match auth::login(params.into_inner(), &mut storage).await {
-    Ok(result) => Ok(Json(ResultDto::data(result))),
-    Err(reason) => Ok(Json(ResultDto::error(reason))),
-  }
+pub async fn login(params: Json<LoginParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<LoginDto>>> {
+  let storage = data.storage.read().await;
+  Ok(Json(ResultDto::data(auth::login(params.into_inner(), &storage).await?)))
+}
+
+/// Handler for exchanging the refresh token for new access token and refresh token.
+#[post("/api/v1/refresh")]
+pub async fn refresh(params: Json<RefreshParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<TokensDto>>> {
+  let storage = data.storage.read().await;
+  Ok(Json(ResultDto::data(auth::refresh(params.into_inner(), &storage).await?)))
+}
+
+/// Handler for logging out the current session of the user.
+#[post("/api/v1/logout")]
+pub async fn logout(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let storage = data.storage.read().await;
+  let principal = authenticated(&req, &storage).await?;
+  Ok(Json(ResultDto::data(auth::logout(principal, &storage).await?)))
+}
+
+/// Handler for logging out all sessions of the user.
+#[post("/api/v1/logout/all")]
+pub async fn logout_all(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let storage = data.storage.read().await;
+  let principal = authenticated(&req, &storage).await?;
+  Ok(Json(ResultDto::data(auth::logout_all(principal, &storage).await?)))
 }
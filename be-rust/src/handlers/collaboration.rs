@@ -0,0 +1,321 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of request handlers for collaborative editing of notes.
+//!
+//! Participants exchange JSON text messages over WebSocket. Identifiers of elements are pairs
+//! `[counter, siteId]`, values of elements are single characters (Unicode scalar values).
+//!
+//! Messages sent by the server:
+//! - `{"type": "sync", "siteId": "2", "version": 4, "readOnly": false, "snapshot": [{"id": [1, ""], "value": "a", "deleted": false}], "updates": [...]}`
+//!   the state of the document: elements at the last save (in the order of the text) and operations applied since then,
+//!   sent when the participant joins and when the document was replaced with the content of the note modified outside the session,
+//! - `{"type": "update", "siteId": "1", "operations": [...]}` - operations applied by another participant,
+//! - `{"type": "saved", "version": 5}` - the document was saved into the note,
+//! - `{"type": "error", "code": "invalid-request", "details": "..."}` - the message sent by the participant was rejected.
+//!
+//! Messages sent by participants:
+//! - `{"type": "update", "operations": [{"op": "insert", "id": [5, "2"], "after": [1, ""], "value": "b"}, {"op": "delete", "id": [1, ""]}]}`,
+//!   inserted elements must have the site identifier of the participant; `after` is `null` when inserting at the beginning.
+
+use crate::collaboration::Notification;
+use crate::controllers::collaboration;
+use crate::crdt::{Element, Id, Operation};
+use crate::errors::*;
+use crate::handlers::websocket::{is_upgrade, upgrade, Received, WebSocket};
+use crate::handlers::TokenParams;
+use crate::server::ApplicationData;
+use crate::services::collaboration::Collaborator;
+use actix_http::ws::{CloseCode, Message};
+use actix_web::web::{Bytes, Path, Query};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures_util::stream;
+use serde_derive::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::time::{interval_at, Instant, Interval};
+
+/// Interval between keep-alive pings, the user is authenticated again before every ping.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Data transfer object for the identifier of an element, a pair of the counter and the site identifier.
+pub type IdDto = (u64, String);
+
+/// Data transfer object for an element of the document.
+#[derive(Serialize)]
+pub struct ElementDto {
+  /// Identifier of the element.
+  #[serde(rename = "id")]
+  pub id: IdDto,
+  /// Character held by the element.
+  #[serde(rename = "value")]
+  pub value: String,
+  /// Set when the element was deleted.
+  #[serde(rename = "deleted")]
+  pub deleted: bool,
+}
+
+impl From<&Element> for ElementDto {
+  /// Converts an [Element] into [ElementDto].
+  fn from(element: &Element) -> Self {
+    Self {
+      id: (element.id.counter, element.id.site.clone()),
+      value: element.value.to_string(),
+      deleted: element.deleted,
+    }
+  }
+}
+
+/// Data transfer object for an operation modifying the document.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum OperationDto {
+  /// Insertion of an element.
+  #[serde(rename = "insert")]
+  Insert {
+    /// Identifier of the inserted element.
+    #[serde(rename = "id")]
+    id: IdDto,
+    /// Identifier of the element the element is inserted after, `None` when inserted at the beginning.
+    #[serde(rename = "after")]
+    after: Option<IdDto>,
+    /// Inserted character.
+    #[serde(rename = "value")]
+    value: String,
+  },
+  /// Deletion of an element.
+  #[serde(rename = "delete")]
+  Delete {
+    /// Identifier of the deleted element.
+    #[serde(rename = "id")]
+    id: IdDto,
+  },
+}
+
+impl From<&Operation> for OperationDto {
+  /// Converts an [Operation] into [OperationDto].
+  fn from(operation: &Operation) -> Self {
+    match operation {
+      Operation::Insert { id, after, value } => Self::Insert {
+        id: (id.counter, id.site.clone()),
+        after: after.as_ref().map(|after| (after.counter, after.site.clone())),
+        value: value.to_string(),
+      },
+      Operation::Delete { id } => Self::Delete {
+        id: (id.counter, id.site.clone()),
+      },
+    }
+  }
+}
+
+impl TryFrom<OperationDto> for Operation {
+  type Error = NordNotesError;
+  /// Converts an [OperationDto] into [Operation], inserted values must be single characters.
+  fn try_from(operation: OperationDto) -> Result<Self> {
+    Ok(match operation {
+      OperationDto::Insert { id, after, value } => {
+        let mut chars = value.chars();
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+          return Err(err_invalid_operation(&format!("value is not a single character, value = {}", value)));
+        };
+        Self::Insert {
+          id: Id::new(id.0, &id.1),
+          after: after.map(|after| Id::new(after.0, &after.1)),
+          value: ch,
+        }
+      }
+      OperationDto::Delete { id } => Self::Delete { id: Id::new(id.0, &id.1) },
+    })
+  }
+}
+
+/// Data transfer object for a message sent to a participant.
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum NotificationDto {
+  /// The state of the document.
+  #[serde(rename = "sync")]
+  Sync {
+    /// Site identifier of the participant.
+    #[serde(rename = "siteId")]
+    site_id: String,
+    /// Version of the note the snapshot was saved as.
+    #[serde(rename = "version")]
+    version: i64,
+    /// Set when the participant may not modify the document.
+    #[serde(rename = "readOnly")]
+    read_only: bool,
+    /// Elements of the document at the last save.
+    #[serde(rename = "snapshot")]
+    snapshot: Vec<ElementDto>,
+    /// Operations applied since the last save.
+    #[serde(rename = "updates")]
+    updates: Vec<OperationDto>,
+  },
+  /// Operations applied by another participant.
+  #[serde(rename = "update")]
+  Update {
+    /// Site identifier of the participant who sent the operations.
+    #[serde(rename = "siteId")]
+    site_id: String,
+    /// Applied operations.
+    #[serde(rename = "operations")]
+    operations: Vec<OperationDto>,
+  },
+  /// The document was saved into the note.
+  #[serde(rename = "saved")]
+  Saved {
+    /// New version of the note.
+    #[serde(rename = "version")]
+    version: i64,
+  },
+  /// The message sent by the participant was rejected.
+  #[serde(rename = "error")]
+  Error {
+    /// Error code.
+    #[serde(rename = "code")]
+    code: String,
+    /// Error details.
+    #[serde(rename = "details")]
+    details: String,
+  },
+}
+
+impl NotificationDto {
+  /// Converts a [Notification] sent to the participant into [NotificationDto].
+  fn new(notification: Notification, read_only: bool) -> Self {
+    match notification {
+      Notification::Sync {
+        site_id,
+        version,
+        snapshot,
+        updates,
+      } => Self::Sync {
+        site_id,
+        version,
+        read_only,
+        snapshot: snapshot.iter().map(Into::into).collect(),
+        updates: updates.iter().map(Into::into).collect(),
+      },
+      Notification::Update { site_id, operations } => Self::Update {
+        site_id,
+        operations: operations.iter().map(Into::into).collect(),
+      },
+      Notification::Saved { version } => Self::Saved { version },
+    }
+  }
+}
+
+/// Data transfer object for a message sent by a participant.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum MessageDto {
+  /// Operations modifying the document.
+  #[serde(rename = "update")]
+  Update {
+    /// Operations in the order they were applied by the participant.
+    #[serde(rename = "operations")]
+    operations: Vec<OperationDto>,
+  },
+}
+
+/// WebSocket connection of a participant of a collaborative editing session.
+struct CollaborationWebSocket {
+  /// Server side of the connection.
+  socket: WebSocket,
+  /// User participating in the session.
+  collaborator: Collaborator,
+  /// Timer of keep-alive pings.
+  keep_alive: Interval,
+  /// Shared application data, used to authenticate the user again.
+  data: web::Data<ApplicationData>,
+}
+
+impl CollaborationWebSocket {
+  /// Applies operations in the message sent by the participant.
+  fn receive(&self, text: &str) -> Result<()> {
+    let MessageDto::Update { operations } = serde_json::from_str(text).map_err(err_invalid_message)?;
+    let operations = operations.into_iter().map(TryInto::try_into).collect::<Result<Vec<Operation>>>()?;
+    self.collaborator.apply(operations)
+  }
+  /// Encodes the message sent to the participant.
+  fn send(&mut self, notification: NotificationDto) -> Bytes {
+    self.socket.text(serde_json::to_string(&notification).unwrap_or_default())
+  }
+  /// Waits for the next frame sent to the participant, `None` when the connection is closed.
+  async fn next(&mut self) -> Option<Bytes> {
+    while !self.socket.is_closed() {
+      tokio::select! {
+        received = self.socket.receive() => match received {
+          Received::Text(text) => {
+            if let Err(reason) = self.receive(&text) {
+              let code = reason.code().name().to_string();
+              let details = reason.details().to_string();
+              return Some(self.send(NotificationDto::Error { code, details }));
+            }
+          }
+          Received::Reply(reply) => return Some(reply),
+          Received::Closed => return None,
+        },
+        notification = self.collaborator.next() => {
+          return Some(match notification {
+            Some(notification) => {
+              let read_only = !self.collaborator.can_edit();
+              self.send(NotificationDto::new(notification, read_only))
+            }
+            // the note was deleted or has expired
+            None => self.socket.close(CloseCode::Away),
+          });
+        }
+        _ = self.keep_alive.tick() => {
+          let storage = self.data.storage.read().await;
+          return Some(match self.collaborator.reauthenticate(&storage).await {
+            Ok(()) => self.socket.encode(Message::Ping(Bytes::new())),
+            Err(_) => self.socket.close(CloseCode::Policy),
+          });
+        }
+      }
+    }
+    None
+  }
+}
+
+/// Handler for joining the collaborative editing session of a note over WebSocket.
+#[get("/api/v1/notes/{id}/collaboration")]
+pub async fn join(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Query<TokenParams>,
+  payload: web::Payload,
+  data: web::Data<ApplicationData>,
+) -> Result<HttpResponse> {
+  if !is_upgrade(&req) {
+    return Err(err_websocket_required());
+  }
+  let token = params.into_inner().token(&req);
+  let collaborator = collaboration::join(id.into_inner(), token, &*data.storage.read().await).await?;
+  let connection = CollaborationWebSocket {
+    socket: WebSocket::new(payload),
+    collaborator,
+    keep_alive: interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL),
+    data: data.clone(),
+  };
+  let frames = stream::unfold(connection, |mut connection| async move {
+    let frame = connection.next().await?;
+    Some((Ok::<_, Infallible>(frame), connection))
+  });
+  Ok(upgrade(&req, frames))
+}
@@ -0,0 +1,207 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of request handlers for note events.
+//!
+//! Events are streamed as Server-Sent Events, or as JSON text messages over WebSocket
+//! when the request asks for the upgrade of the connection. Browsers can not set headers
+//! of `EventSource` and WebSocket requests, so the token may be passed in `token` parameter.
+//!
+//! The user is authenticated again with every keep-alive message,
+//! the stream ends when the session of the user ends.
+
+use crate::controllers::events;
+use crate::errors::*;
+use crate::events::NoteEvent;
+use crate::handlers::notes::NoteDto;
+use crate::handlers::websocket::{is_upgrade, upgrade, Received, WebSocket};
+use crate::handlers::TokenParams;
+use crate::server::ApplicationData;
+use crate::services::events::Subscriber;
+use crate::utils::to_rfc3339;
+use actix_http::ws::{CloseCode, Message};
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::web::{Bytes, Query};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures_util::stream;
+use serde_derive::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval_at, Instant, Interval};
+
+/// Interval between keep-alive messages, the user is authenticated again before every keep-alive message.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Data transfer object for an event of a note.
+#[derive(Serialize)]
+pub struct EventDto {
+  /// Kind of change, `created`, `updated`, `deleted` or `expired`.
+  #[serde(rename = "type")]
+  pub kind: String,
+  /// Identifier of the note.
+  #[serde(rename = "noteId")]
+  pub note_id: String,
+  /// Date and time of the change, in RFC 3339 format.
+  #[serde(rename = "occurredAt")]
+  pub occurred_at: String,
+  /// The note without content, present in events of created and updated notes.
+  #[serde(rename = "note", skip_serializing_if = "Option::is_none")]
+  pub note: Option<NoteDto>,
+}
+
+impl From<&NoteEvent> for EventDto {
+  /// Converts a [NoteEvent] into [EventDto].
+  fn from(event: &NoteEvent) -> Self {
+    Self {
+      kind: event.kind.to_string(),
+      note_id: event.note_id.clone(),
+      occurred_at: to_rfc3339(event.occurred_at),
+      note: event.note.as_ref().map(NoteDto::summary),
+    }
+  }
+}
+
+/// Signal delivered to the stream of events.
+enum Signal {
+  /// Event of a note readable by the user.
+  Event(Arc<NoteEvent>),
+  /// Time to send a keep-alive message, the user is still authenticated.
+  KeepAlive,
+  /// The stream has to end.
+  Close,
+}
+
+/// Stream of events delivered to a single subscriber.
+struct EventStream {
+  /// Subscriber receiving events.
+  subscriber: Subscriber,
+  /// Timer of keep-alive messages.
+  keep_alive: Interval,
+  /// Shared application data, used to authenticate the user again.
+  data: web::Data<ApplicationData>,
+}
+
+impl EventStream {
+  /// Creates a stream of events for the subscriber.
+  fn new(subscriber: Subscriber, data: web::Data<ApplicationData>) -> Self {
+    Self {
+      subscriber,
+      keep_alive: interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL),
+      data,
+    }
+  }
+  /// Waits for the next event or keep-alive message.
+  async fn next(&mut self) -> Signal {
+    tokio::select! {
+      event = self.subscriber.next() => match event {
+        Some(event) => Signal::Event(event),
+        None => Signal::Close,
+      },
+      _ = self.keep_alive.tick() => {
+        let storage = self.data.storage.read().await;
+        match self.subscriber.reauthenticate(&storage).await {
+          Ok(()) => Signal::KeepAlive,
+          Err(_) => Signal::Close,
+        }
+      }
+    }
+  }
+}
+
+/// Serializes the event into JSON.
+fn to_json(event: &NoteEvent) -> String {
+  serde_json::to_string(&EventDto::from(event)).unwrap_or_default()
+}
+
+/// Encodes the event as Server-Sent Event.
+fn encode_server_sent_event(event: &NoteEvent) -> Bytes {
+  Bytes::from(format!("event: {}\ndata: {}\n\n", event.kind, to_json(event)))
+}
+
+/// Creates the response streaming events as Server-Sent Events.
+fn server_sent_events(events: EventStream) -> HttpResponse {
+  let stream = stream::unfold(events, |mut events| async move {
+    let chunk = match events.next().await {
+      Signal::Event(event) => encode_server_sent_event(&event),
+      Signal::KeepAlive => Bytes::from_static(b": keep-alive\n\n"),
+      Signal::Close => return None,
+    };
+    Some((Ok::<_, Infallible>(chunk), events))
+  });
+  HttpResponse::Ok()
+    .content_type("text/event-stream")
+    .insert_header((CACHE_CONTROL, "no-cache"))
+    .streaming(stream)
+}
+
+/// WebSocket connection streaming events as JSON text messages.
+struct EventsWebSocket {
+  /// Server side of the connection.
+  socket: WebSocket,
+  /// Stream of events.
+  events: EventStream,
+}
+
+impl EventsWebSocket {
+  /// Waits for the next frame sent to the client, `None` when the connection is closed.
+  /// Messages sent by the client are ignored.
+  async fn next(&mut self) -> Option<Bytes> {
+    while !self.socket.is_closed() {
+      tokio::select! {
+        received = self.socket.receive() => match received {
+          Received::Reply(reply) => return Some(reply),
+          Received::Text(_) => {}
+          Received::Closed => return None,
+        },
+        signal = self.events.next() => {
+          return Some(match signal {
+            Signal::Event(event) => self.socket.text(to_json(&event)),
+            Signal::KeepAlive => self.socket.encode(Message::Ping(Bytes::new())),
+            Signal::Close => self.socket.close(CloseCode::Policy),
+          });
+        }
+      }
+    }
+    None
+  }
+}
+
+/// Creates the response upgrading the connection to WebSocket and streaming events as JSON text messages.
+fn web_socket(req: &HttpRequest, payload: web::Payload, events: EventStream) -> HttpResponse {
+  let connection = EventsWebSocket {
+    socket: WebSocket::new(payload),
+    events,
+  };
+  let frames = stream::unfold(connection, |mut connection| async move {
+    let frame = connection.next().await?;
+    Some((Ok::<_, Infallible>(frame), connection))
+  });
+  upgrade(req, frames)
+}
+
+/// Handler for subscribing to events of notes readable by the user.
+#[get("/api/v1/events")]
+pub async fn subscribe(req: HttpRequest, params: Query<TokenParams>, payload: web::Payload, data: web::Data<ApplicationData>) -> Result<HttpResponse> {
+  let token = params.into_inner().token(&req);
+  let subscriber = events::subscribe(token, &*data.storage.read().await).await?;
+  let events = EventStream::new(subscriber, data.clone());
+  if is_upgrade(&req) {
+    Ok(web_socket(&req, payload, events))
+  } else {
+    Ok(server_sent_events(events))
+  }
+}
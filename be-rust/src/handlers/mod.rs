@@ -28,24 +28,102 @@
 //! - gather result DTOs and return response result to caller,
 //! - when errors occur, return result describing the details.
 
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::repositories::{PageRequest, SortOrder, MAX_PAGE_SIZE};
+use crate::services::auth::{authenticate, Principal};
 use crate::storage::Storage;
 use actix_web::HttpRequest;
+use serde_derive::Deserialize;
 
+pub mod attachments;
 pub mod auth;
+pub mod collaboration;
+pub mod events;
+pub mod notebooks;
 pub mod notes;
+pub mod revisions;
 pub mod roles;
 pub mod system;
+pub mod tags;
+pub mod trash;
+pub mod users;
+pub mod websocket;
 
-/// Checks if the request contains valid authorization header.
-fn is_authorized(req: &HttpRequest, storage: &Storage) -> bool {
-  if let Some(value) = req.headers().get("Authorization") {
-    if !value.is_empty() {
-      if let Ok(str_value) = value.to_str() {
-        if let Some(token) = str_value.trim().strip_prefix("Bearer ") {
-          return storage.check_token(token.trim());
-        }
-      }
+/// Parameters of listings returned page by page.
+#[derive(Deserialize)]
+pub struct PageParams {
+  /// Maximum number of items in the page, from 1 to [MAX_PAGE_SIZE].
+  #[serde(rename = "pageSize")]
+  pub page_size: Option<String>,
+  /// Token of the requested page, returned as `nextPage` with the previous page.
+  #[serde(rename = "page")]
+  pub page: Option<String>,
+  /// Order of listed items, `asc` or `desc` (default).
+  #[serde(rename = "order")]
+  pub order: Option<String>,
+}
+
+impl PageParams {
+  /// Validates the page size and returns the request for the page.
+  pub fn page_request(&self) -> Result<PageRequest> {
+    let mut request = PageRequest {
+      token: self.page.clone().filter(|token| !token.is_empty()),
+      ..Default::default()
+    };
+    if let Some(value) = &self.page_size {
+      request.size = value
+        .parse()
+        .ok()
+        .filter(|size| (1..=MAX_PAGE_SIZE).contains(size))
+        .ok_or_else(|| err_invalid_page_size(value))?;
+    }
+    Ok(request)
+  }
+  /// Validates the page size of a listing in unspecified order and returns the request for the page,
+  /// the order of listed items may not be requested.
+  pub fn unordered_page_request(&self) -> Result<PageRequest> {
+    if let Some(order) = &self.order {
+      return Err(err_sort_order_not_supported(order));
     }
+    self.page_request()
+  }
+  /// Validates and returns the order of listed items.
+  pub fn sort_order(&self) -> Result<SortOrder> {
+    self.order.as_deref().map_or_else(|| Ok(SortOrder::default()), str::parse)
+  }
+}
+
+/// Parameters of requests authenticated with the token passed in the query string,
+/// used by browsers that can not set headers of `EventSource` and WebSocket requests.
+#[derive(Deserialize)]
+pub struct TokenParams {
+  /// Authorization token, used when the request has no authorization header.
+  #[serde(rename = "token")]
+  pub token: Option<String>,
+}
+
+impl TokenParams {
+  /// Returns the token from the authorization header of the request, or the token passed in the query string.
+  pub fn token(self, req: &HttpRequest) -> Option<String> {
+    bearer_token(req).map(str::to_string).or(self.token)
   }
-  false
+}
+
+/// Returns the token from the authorization header of the request.
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+  let value = req.headers().get("Authorization")?;
+  value.to_str().ok()?.trim().strip_prefix("Bearer ").map(str::trim)
+}
+
+/// Authenticates the user issuing the request.
+async fn authenticated(req: &HttpRequest, storage: &Storage) -> Result<Principal> {
+  authenticate(bearer_token(req).ok_or_else(err_not_authorized)?, storage).await
+}
+
+/// Authenticates the user issuing the request and checks if the user was granted specified permission.
+async fn authorized(req: &HttpRequest, storage: &Storage, permission: Permission) -> Result<Principal> {
+  let principal = authenticated(req, storage).await?;
+  principal.check_permission(permission)?;
+  Ok(principal)
 }
@@ -0,0 +1,140 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::controllers::notebooks;
+use crate::entities::notebook::NotebookEntity;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::notes::NoteDto;
+use crate::handlers::{authorized, PageParams};
+use crate::server::{ApplicationData, ResultDto};
+use crate::utils::to_rfc3339;
+use actix_web::web::{Json, Path, Query};
+use actix_web::{delete, get, post, put, web, HttpRequest};
+use serde_derive::{Deserialize, Serialize};
+
+/// Data transfer object for a notebook.
+#[derive(Serialize)]
+pub struct NotebookDto {
+  /// Unique notebook identifier.
+  #[serde(rename = "notebookId")]
+  pub notebook_id: String,
+  /// Name of the notebook.
+  #[serde(rename = "name")]
+  pub name: String,
+  /// Date and time when the notebook was created, in RFC 3339 format.
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+impl From<NotebookEntity> for NotebookDto {
+  /// Converts a [NotebookEntity] into [NotebookDto].
+  fn from(notebook: NotebookEntity) -> Self {
+    Self::from(&notebook)
+  }
+}
+
+impl From<&NotebookEntity> for NotebookDto {
+  /// Converts a reference to [NotebookEntity] into [NotebookDto].
+  fn from(notebook: &NotebookEntity) -> Self {
+    Self {
+      notebook_id: notebook.notebook_id.clone(),
+      name: notebook.name.clone(),
+      created_at: to_rfc3339(notebook.created_at),
+    }
+  }
+}
+
+/// Parameters needed when a notebook is created or renamed.
+#[derive(Deserialize)]
+pub struct NotebookParams {
+  /// Name of the notebook.
+  #[serde(rename = "name")]
+  pub name: Option<String>,
+}
+
+impl NotebookParams {
+  /// Validates the name of the notebook, surrounding whitespace is removed.
+  pub fn validate(self) -> Result<String> {
+    self
+      .name
+      .map(|name| name.trim().to_string())
+      .filter(|name| !name.is_empty())
+      .ok_or_else(|| err_required_attribute_not_specified("name"))
+  }
+}
+
+/// Handler for creating a new notebook.
+#[post("/api/v1/notebooks")]
+pub async fn create(req: HttpRequest, params: Json<NotebookParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<NotebookDto>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(notebooks::create(params.into_inner(), principal, &mut storage).await?)))
+}
+
+/// Handler for retrieving all notebooks of the user.
+#[get("/api/v1/notebooks")]
+pub async fn list(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<NotebookDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(notebooks::list(principal, &storage).await?)))
+}
+
+/// Handler for retrieving a single notebook identified by unique identifier.
+#[get("/api/v1/notebooks/{id}")]
+pub async fn find(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<NotebookDto>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(notebooks::find(id.into_inner(), principal, &storage).await?)))
+}
+
+/// Handler for renaming a notebook.
+#[put("/api/v1/notebooks/{id}")]
+pub async fn rename(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Json<NotebookParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<Json<ResultDto<NotebookDto>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(
+    notebooks::rename(id.into_inner(), params.into_inner(), principal, &mut storage).await?,
+  )))
+}
+
+/// Handler for deleting a notebook, notes belonging to the notebook are not deleted.
+#[delete("/api/v1/notebooks/{id}")]
+pub async fn delete(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(notebooks::delete(id.into_inner(), principal, &mut storage).await?)))
+}
+
+/// Handler for retrieving a page of notes belonging to a notebook.
+#[get("/api/v1/notebooks/{id}/notes")]
+pub async fn list_notes(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Query<PageParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<Json<ResultDto<Vec<NoteDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(
+    notebooks::list_notes(id.into_inner(), params.into_inner(), principal, &storage).await?,
+  )))
+}
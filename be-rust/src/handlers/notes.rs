@@ -15,13 +15,24 @@
  */
 
 use crate::controllers::notes;
-use crate::entities::note::NoteEntity;
+use crate::entities::note::{ContentFormat, NoteEntity, MAX_TAGS, MAX_TAG_LENGTH, MAX_TTL_SECONDS};
+use crate::entities::role::Permission;
+use crate::entities::share::{Access, GranteeType, ShareEntity};
 use crate::errors::*;
-use crate::handlers::is_authorized;
+use crate::handlers::{authorized, PageParams};
+use crate::render::render_html;
+use crate::repositories::PageRequest;
+use crate::search::SearchQuery;
 use crate::server::{ApplicationData, ResultDto};
-use actix_web::web::{Json, Path};
-use actix_web::{delete, get, post, web, HttpRequest};
+use crate::services::notes::NoteChanges;
+use crate::utils::{now, parse_ttl, to_rfc3339};
+use actix_web::http::header::{ETAG, IF_MATCH};
+use actix_web::web::{Json, Path, Query};
+use actix_web::{delete, get, patch, post, put, web, CustomizeResponder, HttpRequest, Responder};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime, UtcOffset};
 
 /// Data transfer object for a note.
 #[derive(Default, Serialize)]
@@ -29,12 +40,48 @@ pub struct NoteDto {
   /// Unique note identifier.
   #[serde(rename = "noteId")]
   pub note_id: String,
+  /// Identifier of the user who created the note.
+  #[serde(rename = "ownerId", skip_serializing_if = "Option::is_none")]
+  pub owner_id: Option<String>,
   /// Title of the note.
   #[serde(rename = "title", skip_serializing_if = "Option::is_none")]
   pub title: Option<String>,
   /// Content of the note (optional).
   #[serde(rename = "content", skip_serializing_if = "Option::is_none")]
   pub content: Option<String>,
+  /// Format of the content of the note, `plain`, `markdown` or `html`.
+  #[serde(rename = "contentFormat", skip_serializing_if = "Option::is_none")]
+  pub content_format: Option<String>,
+  /// Content of the note rendered into sanitized HTML, present only when rendering was requested.
+  #[serde(rename = "renderedContent", skip_serializing_if = "Option::is_none")]
+  pub rendered_content: Option<String>,
+  /// Date and time when the note was created, in RFC 3339 format.
+  #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<String>,
+  /// Date and time when the note was modified for the last time, in RFC 3339 format.
+  #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<String>,
+  /// Version of the note, incremented with every modification.
+  #[serde(rename = "version", skip_serializing_if = "Option::is_none")]
+  pub version: Option<i64>,
+  /// Date and time when the note expires, in RFC 3339 format, not present when the note never expires.
+  #[serde(rename = "expiresAt", skip_serializing_if = "Option::is_none")]
+  pub expires_at: Option<String>,
+  /// Number of seconds remaining until the note expires, not present when the note never expires.
+  #[serde(rename = "remainingTtl", skip_serializing_if = "Option::is_none")]
+  pub remaining_ttl: Option<i64>,
+  /// Tags of the note.
+  #[serde(rename = "tags", skip_serializing_if = "Option::is_none")]
+  pub tags: Option<Vec<String>>,
+  /// Identifier of the notebook the note belongs to, not present when the note does not belong to any notebook.
+  #[serde(rename = "notebookId", skip_serializing_if = "Option::is_none")]
+  pub notebook_id: Option<String>,
+  /// Date and time when the note was moved to trash, in RFC 3339 format, present only for notes in trash.
+  #[serde(rename = "deletedAt", skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<String>,
+  /// Relevance of the note, present only in search results.
+  #[serde(rename = "score", skip_serializing_if = "Option::is_none")]
+  pub score: Option<f64>,
 }
 
 impl From<NoteEntity> for NoteDto {
@@ -49,12 +96,144 @@ impl From<&NoteEntity> for NoteDto {
   fn from(note: &NoteEntity) -> Self {
     Self {
       note_id: note.note_id.clone(),
+      owner_id: Some(note.owner_id.clone()),
       title: Some(note.title.clone()),
       content: Some(note.content.clone()),
+      content_format: Some(note.content_format.to_string()),
+      rendered_content: None,
+      created_at: Some(to_rfc3339(note.created_at)),
+      updated_at: Some(to_rfc3339(note.updated_at)),
+      version: Some(note.version),
+      expires_at: note.expires_at.map(to_rfc3339),
+      remaining_ttl: note.remaining_ttl(),
+      tags: Some(note.tags.iter().cloned().collect()),
+      notebook_id: note.notebook_id.clone(),
+      deleted_at: note.deleted_at.map(to_rfc3339),
+      score: None,
     }
   }
 }
 
+impl NoteDto {
+  /// Creates [NoteDto] with all attributes of the note except its content, used in listings.
+  pub fn summary(note: &NoteEntity) -> Self {
+    Self {
+      content: None,
+      ..Self::from(note)
+    }
+  }
+  /// Creates [NoteDto] with all attributes of the note and its content rendered into sanitized HTML.
+  pub fn rendered(note: &NoteEntity) -> Self {
+    Self {
+      rendered_content: Some(render_html(&note.content, note.content_format)),
+      ..Self::from(note)
+    }
+  }
+}
+
+/// Parameters of retrieving a single note.
+#[derive(Deserialize)]
+pub struct NoteParams {
+  /// Format the content of the note is rendered into, only `html` is supported.
+  #[serde(rename = "render")]
+  pub render: Option<String>,
+}
+
+impl NoteParams {
+  /// Validates the rendering format, returns `true` when the content should be rendered into HTML.
+  pub fn validate(self) -> Result<bool> {
+    match self.render.as_deref() {
+      None => Ok(false),
+      Some("html") => Ok(true),
+      Some(other) => Err(err_invalid_render(other)),
+    }
+  }
+}
+
+/// Data transfer object for a note share.
+#[derive(Serialize)]
+pub struct ShareDto {
+  /// Kind of the grantee, `user` or `role`.
+  #[serde(rename = "granteeType")]
+  pub grantee_type: String,
+  /// Identifier of the user or role the note is shared with.
+  #[serde(rename = "granteeId")]
+  pub grantee_id: String,
+  /// Granted access, `read` or `edit`.
+  #[serde(rename = "access")]
+  pub access: String,
+}
+
+impl From<ShareEntity> for ShareDto {
+  /// Converts a [ShareEntity] into [ShareDto].
+  fn from(share: ShareEntity) -> Self {
+    Self::from(&share)
+  }
+}
+
+impl From<&ShareEntity> for ShareDto {
+  /// Converts a reference to [ShareEntity] into [ShareDto].
+  fn from(share: &ShareEntity) -> Self {
+    Self {
+      grantee_type: share.grantee_type.to_string(),
+      grantee_id: share.grantee_id.clone(),
+      access: share.access.to_string(),
+    }
+  }
+}
+
+/// Parameters needed when a note is shared.
+#[derive(Deserialize)]
+pub struct ShareParams {
+  /// Kind of the grantee, `user` or `role`.
+  #[serde(rename = "granteeType")]
+  pub grantee_type: Option<String>,
+  /// Identifier of the user or role the note is shared with.
+  #[serde(rename = "granteeId")]
+  pub grantee_id: Option<String>,
+  /// Granted access, `read` or `edit`.
+  #[serde(rename = "access")]
+  pub access: Option<String>,
+}
+
+impl ShareParams {
+  /// Validates required parameters for sharing a note.
+  pub fn validate(self) -> Result<(GranteeType, String, Access)> {
+    let grantee_type = self.grantee_type.ok_or_else(|| err_required_attribute_not_specified("granteeType"))?;
+    let grantee_id = self.grantee_id.ok_or_else(|| err_required_attribute_not_specified("granteeId"))?;
+    let access = self.access.ok_or_else(|| err_required_attribute_not_specified("access"))?;
+    Ok((grantee_type.parse()?, grantee_id, access.parse()?))
+  }
+}
+
+/// Parameters of full-text search of notes.
+#[derive(Deserialize)]
+pub struct SearchParams {
+  /// Search query, see [search](crate::search) for the syntax.
+  #[serde(rename = "q")]
+  pub q: Option<String>,
+  /// Maximum number of notes in the page.
+  #[serde(rename = "pageSize")]
+  pub page_size: Option<String>,
+  /// Token of the requested page, returned as `nextPage` with the previous page.
+  #[serde(rename = "page")]
+  pub page: Option<String>,
+}
+
+impl SearchParams {
+  /// Validates and parses the search query, returns the query and the request for the page.
+  pub fn validate(self) -> Result<(SearchQuery, PageRequest)> {
+    let query = self.q.ok_or_else(|| err_required_attribute_not_specified("q"))?.parse()?;
+    let page = PageParams {
+      page_size: self.page_size,
+      page: self.page,
+      order: None,
+    }
+    .page_request()?;
+    Ok((query, page))
+  }
+}
+
 /// Parameters needed when a new note is created.
 #[derive(Deserialize)]
 pub struct CreateNoteParams {
@@ -64,20 +243,36 @@ pub struct CreateNoteParams {
   /// The content of a note.
   #[serde(rename = "content")]
   pub content: Option<String>,
+  /// The format of the content, `plain` (default), `markdown` or `html`.
+  #[serde(rename = "contentFormat")]
+  pub content_format: Option<String>,
   /// Time to live, defines how long the content of a note will be available.
-  /// `ttl` has the format: `Nw`, `Nd`, `Nh` or `Nm`, where `N` is an integer
-  /// and letters have following meaning: `w` - weeks, `d` - days, `h` - hours, `m` - minutes.
+  /// `ttl` is a compound duration like `1d12h30m` or an ISO 8601 duration like `P1DT2H`, see [parse_ttl].
   /// For example `ttl` == "10d" means that the note will expire after 10 days from creation.
   #[serde(rename = "ttl")]
   pub ttl: Option<String>,
+  /// Date and time when the note expires, in RFC 3339 format, alternative to `ttl`.
+  #[serde(rename = "expiresAt")]
+  pub expires_at: Option<String>,
+  /// Tags of the note (optional).
+  #[serde(rename = "tags")]
+  pub tags: Option<Vec<String>>,
+  /// Identifier of the notebook the note belongs to (optional).
+  #[serde(rename = "notebookId")]
+  pub notebook_id: Option<String>,
 }
 
 impl CreateNoteParams {
-  /// Validates required parameters for creating a new note.
-  pub fn validate(self) -> Result<(String, String, String)> {
+  /// Validates required parameters for creating a new note, returns the note owned by specified user.
+  /// The existence of the notebook is not checked.
+  pub fn validate(self, owner_id: &str) -> Result<NoteEntity> {
     if let Some(title) = self.title {
       if let Some(content) = self.content {
-        Ok((title, content, self.ttl.unwrap_or_else(|| "".to_string())))
+        let mut note = NoteEntity::new(owner_id, &title, &content, parse_expiration(self.ttl.as_deref(), self.expires_at.as_deref())?);
+        note.content_format = parse_content_format(self.content_format.as_deref())?.unwrap_or_default();
+        note.tags = parse_tags(self.tags.unwrap_or_default())?;
+        note.notebook_id = self.notebook_id.filter(|notebook_id| !notebook_id.is_empty());
+        Ok(note)
       } else {
         Err(err_required_attribute_not_specified("content"))
       }
@@ -87,50 +282,269 @@ impl CreateNoteParams {
   }
 }
 
+/// Parameters needed when a note is updated.
+#[derive(Deserialize)]
+pub struct UpdateNoteParams {
+  /// New title of the note.
+  #[serde(rename = "title")]
+  pub title: Option<String>,
+  /// New content of the note.
+  #[serde(rename = "content")]
+  pub content: Option<String>,
+  /// New format of the content of the note.
+  #[serde(rename = "contentFormat")]
+  pub content_format: Option<String>,
+  /// New time to live of the note, counted from the moment of the update, empty means no expiration.
+  #[serde(rename = "ttl")]
+  pub ttl: Option<String>,
+  /// New date and time when the note expires, in RFC 3339 format, alternative to `ttl`.
+  #[serde(rename = "expiresAt")]
+  pub expires_at: Option<String>,
+  /// New tags of the note.
+  #[serde(rename = "tags")]
+  pub tags: Option<Vec<String>>,
+  /// Identifier of the new notebook of the note, empty means the note does not belong to any notebook.
+  #[serde(rename = "notebookId")]
+  pub notebook_id: Option<String>,
+}
+
+impl UpdateNoteParams {
+  /// Validates attributes when replacing a note, title and content are required,
+  /// unspecified content format means plain text, unspecified time to live means the note never expires,
+  /// unspecified tags and notebook mean the note has no tags and does not belong to any notebook.
+  pub fn validate_replace(self) -> Result<NoteChanges> {
+    let title = self.title.ok_or_else(|| err_required_attribute_not_specified("title"))?;
+    let content = self.content.ok_or_else(|| err_required_attribute_not_specified("content"))?;
+    Ok(NoteChanges {
+      title: Some(title),
+      content: Some(content),
+      content_format: Some(parse_content_format(self.content_format.as_deref())?.unwrap_or_default()),
+      expires_at: Some(parse_expiration(self.ttl.as_deref(), self.expires_at.as_deref())?),
+      tags: Some(parse_tags(self.tags.unwrap_or_default())?),
+      notebook_id: Some(self.notebook_id.filter(|notebook_id| !notebook_id.is_empty())),
+    })
+  }
+  /// Validates attributes when updating a note, at least one attribute must be specified.
+  pub fn validate_update(self) -> Result<NoteChanges> {
+    if self.title.is_none()
+      && self.content.is_none()
+      && self.content_format.is_none()
+      && self.ttl.is_none()
+      && self.expires_at.is_none()
+      && self.tags.is_none()
+      && self.notebook_id.is_none()
+    {
+      return Err(err_required_attribute_not_specified(
+        "title, content, contentFormat, ttl, expiresAt, tags or notebookId",
+      ));
+    }
+    let expires_at = if self.ttl.is_some() || self.expires_at.is_some() {
+      Some(parse_expiration(self.ttl.as_deref(), self.expires_at.as_deref())?)
+    } else {
+      None
+    };
+    Ok(NoteChanges {
+      title: self.title,
+      content: self.content,
+      content_format: parse_content_format(self.content_format.as_deref())?,
+      expires_at,
+      tags: self.tags.map(parse_tags).transpose()?,
+      notebook_id: self
+        .notebook_id
+        .map(|notebook_id| Some(notebook_id).filter(|notebook_id| !notebook_id.is_empty())),
+    })
+  }
+}
+
+/// Parses the name of the content format, `None` when the format is not specified.
+pub fn parse_content_format(name: Option<&str>) -> Result<Option<ContentFormat>> {
+  name.map(str::parse).transpose()
+}
+
+/// Validates and normalizes tags: surrounding whitespace is removed and tags are lowercased,
+/// so tags differing only in case are the same tag. Empty and too long tags are invalid.
+pub fn parse_tags(tags: Vec<String>) -> Result<BTreeSet<String>> {
+  let mut parsed = BTreeSet::new();
+  for tag in tags {
+    let normalized = tag.trim().to_lowercase();
+    if normalized.is_empty() || normalized.chars().count() > MAX_TAG_LENGTH {
+      return Err(err_invalid_tag(&tag));
+    }
+    parsed.insert(normalized);
+  }
+  if parsed.len() > MAX_TAGS {
+    return Err(err_too_many_tags(parsed.len()));
+  }
+  Ok(parsed)
+}
+
+/// Resolves the expiration time of a note from time to live (counted from now)
+/// or from the date and time in RFC 3339 format, which are mutually exclusive.
+/// Returns `None` when neither is specified or time to live is empty, meaning the note never expires.
+pub fn parse_expiration(ttl: Option<&str>, expires_at: Option<&str>) -> Result<Option<OffsetDateTime>> {
+  let now = now();
+  let expiration = match (ttl.filter(|ttl| !ttl.is_empty()), expires_at) {
+    (Some(_), Some(_)) => return Err(err_conflicting_attributes("ttl", "expiresAt")),
+    (Some(ttl), None) => now.checked_add(parse_ttl(ttl)?).ok_or_else(|| err_invalid_ttl(ttl))?,
+    (None, Some(expires_at)) => OffsetDateTime::parse(expires_at, &Rfc3339)
+      .map_err(|_| err_invalid_expires_at(expires_at))?
+      .to_offset(UtcOffset::UTC),
+    (None, None) => return Ok(None),
+  };
+  if expiration <= now || expiration - now > Duration::seconds(MAX_TTL_SECONDS) {
+    return Err(err_expiration_out_of_range(&to_rfc3339(expiration)));
+  }
+  Ok(Some(expiration))
+}
+
+/// Parses the value of `If-Match` header, returns the expected version of the note,
+/// or `None` when any version is accepted (`*`). The header is required.
+pub fn parse_if_match(value: Option<&str>) -> Result<Option<i64>> {
+  let value = value.ok_or_else(err_precondition_required)?.trim();
+  if value == "*" {
+    return Ok(None);
+  }
+  let version = value.strip_prefix("W/").unwrap_or(value).trim_matches('"');
+  version.parse().map(Some).map_err(|_| err_invalid_if_match(value))
+}
+
+/// Returns the value of `If-Match` header of the request.
+pub fn if_match(req: &HttpRequest) -> Option<String> {
+  req.headers().get(IF_MATCH).and_then(|value| value.to_str().ok()).map(str::to_string)
+}
+
+/// Creates a response with a note, the version of the note is returned in `ETag` header.
+pub fn note_response(note: NoteDto) -> CustomizeResponder<Json<ResultDto<NoteDto>>> {
+  match note.version {
+    Some(version) => Json(ResultDto::data(note)).customize().insert_header((ETAG, format!("\"{}\"", version))),
+    None => Json(ResultDto::data(note)).customize(),
+  }
+}
+
 /// Handler for creating a new note.
 #[post("/api/v1/notes")]
-pub async fn create(req: HttpRequest, params: Json<CreateNoteParams>, data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<NoteDto>>> {
+pub async fn create(req: HttpRequest, params: Json<CreateNoteParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<NoteDto>>> {
   let mut storage = data.storage.write().await;
-  if is_authorized(&req, &storage) {
-    match notes::create(params.into_inner(), &mut storage).await {
-      Ok(result) => Ok(Json(ResultDto::data(result))),
-      Err(reason) => Ok(Json(ResultDto::error(reason))),
-    }
-  } else {
-    Ok(Json(ResultDto::error(err_not_authorized())))
-  }
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(notes::create(params.into_inner(), principal, &mut storage).await?)))
 }
 
-/// Handler for retrieving a list of notes.
+/// Handler for retrieving a page of notes visible to the user.
 #[get("/api/v1/notes")]
-pub async fn list(data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<Vec<NoteDto>>>> {
+pub async fn list(req: HttpRequest, params: Query<PageParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<NoteDto>>>> {
   let storage = data.storage.read().await;
-  match notes::list(&storage).await {
-    Ok(result) => Ok(Json(ResultDto::data(result))),
-    Err(reason) => Ok(Json(ResultDto::error(reason))),
-  }
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(notes::list(params.into_inner(), principal, &storage).await?)))
 }
 
-/// Handler for retrieving the details of a single note identified by unique identifier.
+/// Handler for full-text searching of notes visible to the user.
+#[get("/api/v1/notes/search")]
+pub async fn search(req: HttpRequest, params: Query<SearchParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<NoteDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(notes::search(params.into_inner(), principal, &storage).await?)))
+}
+
+/// Handler for rebuilding the full-text search index of notes.
+#[post("/api/v1/notes/search/rebuild")]
+pub async fn rebuild_search_index(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let storage = data.storage.read().await;
+  authorized(&req, &storage, Permission::NotesAdmin).await?;
+  Ok(Json(ResultDto::data(notes::rebuild_search_index(&storage).await?)))
+}
+
+/// Handler for retrieving the details of a single note identified by unique identifier,
+/// with the content optionally rendered into sanitized HTML.
 #[get("/api/v1/notes/{id}")]
-pub async fn get_by_id(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<NoteDto>>> {
+pub async fn get_by_id(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Query<NoteParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<CustomizeResponder<Json<ResultDto<NoteDto>>>> {
   let storage = data.storage.read().await;
-  if is_authorized(&req, &storage) {
-    match notes::get_by_id(id.into_inner(), &storage).await {
-      Ok(result) => Ok(Json(ResultDto::data(result))),
-      Err(reason) => Ok(Json(ResultDto::error(reason))),
-    }
-  } else {
-    Ok(Json(ResultDto::error(err_not_authorized())))
-  }
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(note_response(
+    notes::get_by_id(id.into_inner(), params.into_inner(), principal, &storage).await?,
+  ))
 }
 
-/// Handler for deleting all notes.
+/// Handler for moving all notes to trash.
 #[delete("/api/v1/notes")]
-pub async fn delete_all(data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<String>>> {
+pub async fn delete_all(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
   let mut storage = data.storage.write().await;
-  match notes::delete_all(&mut storage).await {
-    Ok(result) => Ok(Json(ResultDto::data(result))),
-    Err(reason) => Ok(Json(ResultDto::error(reason))),
-  }
+  authorized(&req, &storage, Permission::NotesAdmin).await?;
+  Ok(Json(ResultDto::data(notes::delete_all(&mut storage).await?)))
+}
+
+/// Handler for replacing the title, content and time to live of a note.
+#[put("/api/v1/notes/{id}")]
+pub async fn replace(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Json<UpdateNoteParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<CustomizeResponder<Json<ResultDto<NoteDto>>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(note_response(
+    notes::replace(id.into_inner(), params.into_inner(), if_match(&req), principal, &mut storage).await?,
+  ))
+}
+
+/// Handler for updating selected attributes of a note.
+#[patch("/api/v1/notes/{id}")]
+pub async fn update(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Json<UpdateNoteParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<CustomizeResponder<Json<ResultDto<NoteDto>>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(note_response(
+    notes::update(id.into_inner(), params.into_inner(), if_match(&req), principal, &mut storage).await?,
+  ))
+}
+
+/// Handler for moving a single note to trash.
+#[delete("/api/v1/notes/{id}")]
+pub async fn delete(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(notes::delete(id.into_inner(), principal, &mut storage).await?)))
+}
+
+/// Handler for retrieving the list of shares of a note.
+#[get("/api/v1/notes/{id}/shares")]
+pub async fn list_shares(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<ShareDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(notes::list_shares(id.into_inner(), principal, &storage).await?)))
+}
+
+/// Handler for sharing a note with a user or role.
+#[post("/api/v1/notes/{id}/shares")]
+pub async fn create_share(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Json<ShareParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<Json<ResultDto<ShareDto>>> {
+  let storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(
+    notes::create_share(id.into_inner(), params.into_inner(), principal, &storage).await?,
+  )))
+}
+
+/// Handler for revoking a share of a note.
+#[delete("/api/v1/notes/{id}/shares/{grantee_type}/{grantee_id}")]
+pub async fn delete_share(req: HttpRequest, path: Path<(String, String, String)>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let storage = data.storage.write().await;
+  let (id, grantee_type, grantee_id) = path.into_inner();
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(
+    notes::delete_share(id, grantee_type, grantee_id, principal, &storage).await?,
+  )))
 }
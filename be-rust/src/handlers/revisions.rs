@@ -0,0 +1,195 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of request handlers for note revisions.
+
+use crate::controllers::revisions;
+use crate::diff::Change;
+use crate::entities::revision::RevisionEntity;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::notes::{if_match, note_response, NoteDto};
+use crate::handlers::{authorized, PageParams};
+use crate::server::{ApplicationData, ResultDto};
+use crate::utils::to_rfc3339;
+use actix_web::web::{Json, Path, Query};
+use actix_web::{get, post, web, CustomizeResponder, HttpRequest};
+use serde_derive::{Deserialize, Serialize};
+
+/// Data transfer object for a note revision.
+#[derive(Serialize)]
+pub struct RevisionDto {
+  /// Identifier of the note.
+  #[serde(rename = "noteId")]
+  pub note_id: String,
+  /// Number of the revision, equal to the version of the note.
+  #[serde(rename = "revision")]
+  pub revision: i64,
+  /// Identifier of the user who created or modified the note.
+  #[serde(rename = "authorId")]
+  pub author_id: String,
+  /// Date and time when the version of the note was stored, in RFC 3339 format.
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+  /// Title of the note.
+  #[serde(rename = "title")]
+  pub title: String,
+  /// Content of the note, not present in listings.
+  #[serde(rename = "content", skip_serializing_if = "Option::is_none")]
+  pub content: Option<String>,
+  /// Format of the content of the note.
+  #[serde(rename = "contentFormat")]
+  pub content_format: String,
+}
+
+impl From<RevisionEntity> for RevisionDto {
+  /// Converts a [RevisionEntity] into [RevisionDto].
+  fn from(revision: RevisionEntity) -> Self {
+    Self {
+      note_id: revision.note_id,
+      revision: revision.revision,
+      author_id: revision.author_id,
+      created_at: to_rfc3339(revision.created_at),
+      title: revision.title,
+      content: Some(revision.content),
+      content_format: revision.content_format.to_string(),
+    }
+  }
+}
+
+impl RevisionDto {
+  /// Creates [RevisionDto] with all attributes of the revision except the content, used in listings.
+  pub fn summary(revision: RevisionEntity) -> Self {
+    Self {
+      content: None,
+      ..Self::from(revision)
+    }
+  }
+}
+
+/// Data transfer object for a single line of compared texts.
+#[derive(Serialize)]
+pub struct ChangeDto {
+  /// Operation, `equal`, `insert` or `delete`.
+  #[serde(rename = "op")]
+  pub op: String,
+  /// Compared line.
+  #[serde(rename = "text")]
+  pub text: String,
+}
+
+impl From<Change<'_>> for ChangeDto {
+  /// Converts a [Change] into [ChangeDto].
+  fn from(change: Change) -> Self {
+    Self {
+      op: change.name().to_string(),
+      text: change.text().to_string(),
+    }
+  }
+}
+
+/// Data transfer object for the comparison of two revisions of a note.
+#[derive(Serialize)]
+pub struct DiffDto {
+  /// Identifier of the note.
+  #[serde(rename = "noteId")]
+  pub note_id: String,
+  /// Number of the older compared revision.
+  #[serde(rename = "from")]
+  pub from: i64,
+  /// Number of the newer compared revision.
+  #[serde(rename = "to")]
+  pub to: i64,
+  /// Line by line changes of the title.
+  #[serde(rename = "title")]
+  pub title: Vec<ChangeDto>,
+  /// Line by line changes of the content.
+  #[serde(rename = "content")]
+  pub content: Vec<ChangeDto>,
+}
+
+/// Parameters of the comparison of two revisions.
+#[derive(Deserialize)]
+pub struct DiffParams {
+  /// Number of the older compared revision.
+  #[serde(rename = "from")]
+  pub from: Option<String>,
+  /// Number of the newer compared revision, the current version of the note when not specified.
+  #[serde(rename = "to")]
+  pub to: Option<String>,
+}
+
+impl DiffParams {
+  /// Validates and returns numbers of compared revisions.
+  pub fn validate(self) -> Result<(i64, Option<i64>)> {
+    let from = parse_revision(&self.from.ok_or_else(|| err_required_attribute_not_specified("from"))?)?;
+    let to = self.to.as_deref().map(parse_revision).transpose()?;
+    Ok((from, to))
+  }
+}
+
+/// Parses the number of a revision, revision numbers are not negative.
+pub fn parse_revision(value: &str) -> Result<i64> {
+  value
+    .trim()
+    .parse()
+    .ok()
+    .filter(|revision| *revision >= 0)
+    .ok_or_else(|| err_invalid_revision(value))
+}
+
+/// Handler for retrieving a page of revisions of a note.
+#[get("/api/v1/notes/{id}/revisions")]
+pub async fn list(
+  req: HttpRequest,
+  id: Path<String>,
+  params: Query<PageParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<Json<ResultDto<Vec<RevisionDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(
+    revisions::list(id.into_inner(), params.into_inner(), principal, &storage).await?,
+  )))
+}
+
+/// Handler for comparing two revisions of a note.
+#[get("/api/v1/notes/{id}/revisions/diff")]
+pub async fn diff(req: HttpRequest, id: Path<String>, params: Query<DiffParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<DiffDto>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(
+    revisions::diff(id.into_inner(), params.into_inner(), principal, &storage).await?,
+  )))
+}
+
+/// Handler for retrieving a single revision of a note.
+#[get("/api/v1/notes/{id}/revisions/{revision}")]
+pub async fn find(req: HttpRequest, path: Path<(String, String)>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<RevisionDto>>> {
+  let storage = data.storage.read().await;
+  let (id, revision) = path.into_inner();
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(revisions::find(id, revision, principal, &storage).await?)))
+}
+
+/// Handler for restoring a note to the state recorded in a revision.
+#[post("/api/v1/notes/{id}/revisions/{revision}/restore")]
+pub async fn restore(req: HttpRequest, path: Path<(String, String)>, data: web::Data<ApplicationData>) -> Result<CustomizeResponder<Json<ResultDto<NoteDto>>>> {
+  let mut storage = data.storage.write().await;
+  let (id, revision) = path.into_inner();
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(note_response(revisions::restore(id, revision, if_match(&req), principal, &mut storage).await?))
+}
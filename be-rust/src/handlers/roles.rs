@@ -15,14 +15,15 @@
  */
 
 use crate::controllers::roles;
-use crate::entities::role::RoleEntity;
+use crate::entities::role::{Permission, RoleEntity};
 use crate::entities::Entity;
 use crate::errors::*;
-use crate::handlers::is_authorized;
+use crate::handlers::{authorized, PageParams};
 use crate::server::{ApplicationData, ResultDto};
-use actix_web::web::{Json, Path};
+use actix_web::web::{Json, Path, Query};
 use actix_web::{delete, get, post, web, HttpRequest};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 /// Data transfer object for a role.
 #[derive(Default, Serialize)]
@@ -33,6 +34,9 @@ pub struct RoleDto {
   /// Name of the role.
   #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
   pub name: Option<String>,
+  /// Names of permissions granted by the role.
+  #[serde(rename = "permissions", skip_serializing_if = "Option::is_none")]
+  pub permissions: Option<Vec<String>>,
 }
 
 impl From<RoleEntity> for RoleDto {
@@ -48,23 +52,28 @@ impl From<&RoleEntity> for RoleDto {
     Self {
       role_id: role.id(),
       name: Some(role.name()),
+      permissions: Some(role.permissions().iter().map(Permission::to_string).collect()),
     }
   }
 }
 
-/// Parameters needed when a new note is created.
+/// Parameters needed when a new role is created.
 #[derive(Deserialize)]
 pub struct CreateRoleParams {
   /// Name of a role.
   #[serde(rename = "name")]
   pub name: Option<String>,
+  /// Names of permissions granted by a role (optional).
+  #[serde(rename = "permissions")]
+  pub permissions: Option<Vec<String>>,
 }
 
 impl CreateRoleParams {
   /// Validates attributes required when creating a new role.
-  pub fn validate(self) -> Result<String> {
+  pub fn validate(self) -> Result<(String, BTreeSet<Permission>)> {
     if let Some(name) = self.name {
-      Ok(name)
+      let permissions = self.permissions.unwrap_or_default().iter().map(|name| name.parse()).collect::<Result<_>>()?;
+      Ok((name, permissions))
     } else {
       Err(err_required_attribute_not_specified("name"))
     }
@@ -73,48 +82,32 @@ impl CreateRoleParams {
 
 /// Handler for creating a new role.
 #[post("/api/v1/roles")]
-pub async fn create(req: HttpRequest, params: Json<CreateRoleParams>, data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<RoleDto>>> {
+pub async fn create(req: HttpRequest, params: Json<CreateRoleParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<RoleDto>>> {
   let mut storage = data.storage.write().await;
-  if is_authorized(&req, &storage) {
-    match roles::create(params.into_inner(), &mut storage).await {
-      Ok(result) => Ok(Json(ResultDto::data(result))),
-      Err(reason) => Ok(Json(ResultDto::error(reason))),
-    }
-  } else {
-    Ok(Json(ResultDto::error(err_not_authorized())))
-  }
+  authorized(&req, &storage, Permission::RolesAdmin).await?;
+  Ok(Json(ResultDto::data(roles::create(params.into_inner(), &mut storage).await?)))
 }
 
-/// Handler for retrieving a list of roles.
+/// Handler for retrieving a page of roles.
 #[get("/api/v1/roles")]
-pub async fn list(data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<Vec<RoleDto>>>> {
+pub async fn list(req: HttpRequest, params: Query<PageParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<RoleDto>>>> {
   let storage = data.storage.read().await;
-  match roles::list(&storage).await {
-    Ok(result) => Ok(Json(ResultDto::data(result))),
-    Err(reason) => Ok(Json(ResultDto::error(reason))),
-  }
+  authorized(&req, &storage, Permission::RolesRead).await?;
+  Ok(Json(ResultDto::page(roles::list(params.into_inner(), &storage).await?)))
 }
 
 /// Handler for retrieving a single role searched by identifier.
 #[get("/api/v1/roles/{id}")]
-pub async fn find(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<RoleDto>>> {
+pub async fn find(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<RoleDto>>> {
   let storage = data.storage.read().await;
-  if is_authorized(&req, &storage) {
-    match roles::find_by_id(id.into_inner(), &storage).await {
-      Ok(result) => Ok(Json(ResultDto::data(result))),
-      Err(reason) => Ok(Json(ResultDto::error(reason))),
-    }
-  } else {
-    Ok(Json(ResultDto::error(err_not_authorized())))
-  }
+  authorized(&req, &storage, Permission::RolesRead).await?;
+  Ok(Json(ResultDto::data(roles::find_by_id(id.into_inner(), &storage).await?)))
 }
 
-/// Handler for deleting all roles.
+/// Handler for deleting all roles except the built-in `ADMIN` and `USER` roles.
 #[delete("/api/v1/roles")]
-pub async fn delete_all(data: web::Data<ApplicationData>) -> std::io::Result<Json<ResultDto<String>>> {
+pub async fn delete_all(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
   let mut storage = data.storage.write().await;
-  match roles::delete_all(&mut storage).await {
-    Ok(result) => Ok(Json(ResultDto::data(result))),
-    Err(reason) => Ok(Json(ResultDto::error(reason))),
-  }
+  authorized(&req, &storage, Permission::RolesAdmin).await?;
+  Ok(Json(ResultDto::data(roles::delete_all(&mut storage).await?)))
 }
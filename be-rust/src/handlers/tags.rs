@@ -0,0 +1,59 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::controllers::tags;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::notes::NoteDto;
+use crate::handlers::{authorized, PageParams};
+use crate::server::{ApplicationData, ResultDto};
+use actix_web::web::{Json, Path, Query};
+use actix_web::{get, web, HttpRequest};
+use serde_derive::Serialize;
+
+/// Data transfer object for a tag.
+#[derive(Serialize)]
+pub struct TagDto {
+  /// Name of the tag.
+  #[serde(rename = "tag")]
+  pub tag: String,
+  /// Number of notes having the tag.
+  #[serde(rename = "count")]
+  pub count: usize,
+}
+
+/// Handler for retrieving tags of notes owned by the user, with the number of notes having each tag.
+#[get("/api/v1/tags")]
+pub async fn list(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<TagDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::data(tags::list(principal, &storage).await?)))
+}
+
+/// Handler for retrieving a page of notes owned by the user having specified tag.
+#[get("/api/v1/tags/{tag}/notes")]
+pub async fn list_notes(
+  req: HttpRequest,
+  tag: Path<String>,
+  params: Query<PageParams>,
+  data: web::Data<ApplicationData>,
+) -> Result<Json<ResultDto<Vec<NoteDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(
+    tags::list_notes(tag.into_inner(), params.into_inner(), principal, &storage).await?,
+  )))
+}
@@ -0,0 +1,50 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of request handlers for trash.
+
+use crate::controllers::trash;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::handlers::notes::{note_response, NoteDto};
+use crate::handlers::{authorized, PageParams};
+use crate::server::{ApplicationData, ResultDto};
+use actix_web::web::{Json, Path, Query};
+use actix_web::{delete, get, post, web, CustomizeResponder, HttpRequest};
+
+/// Handler for retrieving a page of notes in trash of the user.
+#[get("/api/v1/trash")]
+pub async fn list(req: HttpRequest, params: Query<PageParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<NoteDto>>>> {
+  let storage = data.storage.read().await;
+  let principal = authorized(&req, &storage, Permission::NotesRead).await?;
+  Ok(Json(ResultDto::page(trash::list(params.into_inner(), principal, &storage).await?)))
+}
+
+/// Handler for restoring a note from trash.
+#[post("/api/v1/trash/{id}/restore")]
+pub async fn restore(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<CustomizeResponder<Json<ResultDto<NoteDto>>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(note_response(trash::restore(id.into_inner(), principal, &mut storage).await?))
+}
+
+/// Handler for deleting a note in trash permanently.
+#[delete("/api/v1/trash/{id}")]
+pub async fn purge(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authorized(&req, &storage, Permission::NotesWrite).await?;
+  Ok(Json(ResultDto::data(trash::purge(id.into_inner(), principal, &mut storage).await?)))
+}
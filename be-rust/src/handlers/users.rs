@@ -0,0 +1,167 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use crate::controllers::users;
+use crate::entities::role::Permission;
+use crate::entities::user::UserEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::handlers::{authenticated, authorized};
+use crate::server::{ApplicationData, ResultDto};
+use crate::services::users::UserChanges;
+use actix_web::web::{Json, Path};
+use actix_web::{delete, get, post, put, web, HttpRequest};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Data transfer object for a user.
+#[derive(Default, Serialize)]
+pub struct UserDto {
+  /// Unique user identifier.
+  #[serde(rename = "userId")]
+  pub user_id: String,
+  /// User login.
+  #[serde(rename = "login", skip_serializing_if = "Option::is_none")]
+  pub login: Option<String>,
+  /// Identifiers of roles granted to the user.
+  #[serde(rename = "roles", skip_serializing_if = "Option::is_none")]
+  pub roles: Option<Vec<String>>,
+}
+
+impl From<UserEntity> for UserDto {
+  /// Converts a [UserEntity] into [UserDto].
+  fn from(user: UserEntity) -> Self {
+    Self::from(&user)
+  }
+}
+
+impl From<&UserEntity> for UserDto {
+  /// Converts a reference to [UserEntity] into [UserDto].
+  fn from(user: &UserEntity) -> Self {
+    Self {
+      user_id: user.id(),
+      login: Some(user.login.clone()),
+      roles: Some(user.roles.iter().cloned().collect()),
+    }
+  }
+}
+
+/// Parameters needed when a new user is created.
+#[derive(Deserialize)]
+pub struct CreateUserParams {
+  /// User login.
+  #[serde(rename = "login")]
+  pub login: Option<String>,
+  /// User password.
+  #[serde(rename = "password")]
+  pub password: Option<String>,
+  /// Identifiers of roles granted to the user (optional),
+  /// when not specified, the default `USER` role is granted.
+  #[serde(rename = "roles")]
+  pub roles: Option<Vec<String>>,
+}
+
+impl CreateUserParams {
+  /// Validates attributes required when creating a new user.
+  pub fn validate(self) -> Result<(String, String, Option<BTreeSet<String>>)> {
+    if let Some(login) = self.login.filter(|value| !value.trim().is_empty()) {
+      if let Some(password) = self.password.filter(|value| !value.is_empty()) {
+        Ok((login.trim().to_string(), password, self.roles.map(|roles| roles.into_iter().collect())))
+      } else {
+        Err(err_required_attribute_not_specified("password"))
+      }
+    } else {
+      Err(err_required_attribute_not_specified("login"))
+    }
+  }
+}
+
+/// Parameters needed when a user is updated.
+#[derive(Deserialize)]
+pub struct UpdateUserParams {
+  /// New user login (optional).
+  #[serde(rename = "login")]
+  pub login: Option<String>,
+  /// New user password (optional).
+  #[serde(rename = "password")]
+  pub password: Option<String>,
+  /// New identifiers of roles granted to the user (optional).
+  #[serde(rename = "roles")]
+  pub roles: Option<Vec<String>>,
+}
+
+impl UpdateUserParams {
+  /// Validates attributes when updating a user, at least one attribute must be specified.
+  pub fn validate(self) -> Result<UserChanges> {
+    let login = self.login.map(|value| value.trim().to_string());
+    if login.as_ref().is_some_and(|value| value.is_empty()) {
+      return Err(err_required_attribute_not_specified("login"));
+    }
+    if self.password.as_ref().is_some_and(|value| value.is_empty()) {
+      return Err(err_required_attribute_not_specified("password"));
+    }
+    if login.is_none() && self.password.is_none() && self.roles.is_none() {
+      return Err(err_required_attribute_not_specified("login, password or roles"));
+    }
+    Ok(UserChanges {
+      login,
+      password: self.password,
+      roles: self.roles.map(|roles| roles.into_iter().collect()),
+    })
+  }
+}
+
+/// Handler for creating a new user.
+#[post("/api/v1/users")]
+pub async fn create(req: HttpRequest, params: Json<CreateUserParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<UserDto>>> {
+  let mut storage = data.storage.write().await;
+  authorized(&req, &storage, Permission::UsersAdmin).await?;
+  Ok(Json(ResultDto::data(users::create(params.into_inner(), &mut storage).await?)))
+}
+
+/// Handler for retrieving a list of users.
+#[get("/api/v1/users")]
+pub async fn list(req: HttpRequest, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<Vec<UserDto>>>> {
+  let storage = data.storage.read().await;
+  authorized(&req, &storage, Permission::UsersAdmin).await?;
+  Ok(Json(ResultDto::data(users::list(&storage).await?)))
+}
+
+/// Handler for retrieving a single user searched by identifier.
+#[get("/api/v1/users/{id}")]
+pub async fn find(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<UserDto>>> {
+  let storage = data.storage.read().await;
+  let principal = authenticated(&req, &storage).await?;
+  Ok(Json(ResultDto::data(users::find_by_id(id.into_inner(), &principal, &storage).await?)))
+}
+
+/// Handler for updating the login, password or roles of a user identified by unique identifier.
+#[put("/api/v1/users/{id}")]
+pub async fn update(req: HttpRequest, id: Path<String>, params: Json<UpdateUserParams>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<UserDto>>> {
+  let mut storage = data.storage.write().await;
+  let principal = authenticated(&req, &storage).await?;
+  Ok(Json(ResultDto::data(
+    users::update(id.into_inner(), params.into_inner(), &principal, &mut storage).await?,
+  )))
+}
+
+/// Handler for deleting a user identified by unique identifier.
+#[delete("/api/v1/users/{id}")]
+pub async fn delete(req: HttpRequest, id: Path<String>, data: web::Data<ApplicationData>) -> Result<Json<ResultDto<String>>> {
+  let mut storage = data.storage.write().await;
+  authorized(&req, &storage, Permission::UsersAdmin).await?;
+  Ok(Json(ResultDto::data(users::delete(id.into_inner(), &mut storage).await?)))
+}
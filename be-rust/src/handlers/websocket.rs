@@ -0,0 +1,130 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of WebSocket connections used by request handlers pushing messages to clients.
+//!
+//! Handlers stream encoded frames in the body of the response upgrading the connection,
+//! frames received from the client are decoded from the payload of the request.
+//! Pings are answered with pongs and close frames with close frames, text messages
+//! are returned to the handler, other frames are ignored.
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{hash_key, verify_handshake, CloseCode, Codec, Frame, Message};
+use actix_web::http::header::{HeaderValue, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use futures_util::{Stream, StreamExt};
+use std::convert::Infallible;
+
+/// Result of receiving from the client.
+pub enum Received {
+  /// Text message sent by the client.
+  Text(String),
+  /// Frame to be sent back to the client, a pong or a close frame.
+  Reply(Bytes),
+  /// The connection was closed.
+  Closed,
+}
+
+/// Server side of a WebSocket connection.
+pub struct WebSocket {
+  /// Frames received from the client.
+  payload: web::Payload,
+  /// WebSocket protocol codec.
+  codec: Codec,
+  /// Received bytes not decoded yet.
+  received: BytesMut,
+  /// Set when the connection was closed.
+  closed: bool,
+}
+
+impl WebSocket {
+  /// Creates the server side of the connection receiving frames from the payload of the request.
+  pub fn new(payload: web::Payload) -> Self {
+    Self {
+      payload,
+      codec: Codec::new(),
+      received: BytesMut::new(),
+      closed: false,
+    }
+  }
+  /// Returns `true` when the connection was closed.
+  pub fn is_closed(&self) -> bool {
+    self.closed
+  }
+  /// Encodes the message sent to the client.
+  pub fn encode(&mut self, message: Message) -> Bytes {
+    let mut encoded = BytesMut::new();
+    if self.codec.encode(message, &mut encoded).is_err() {
+      self.closed = true;
+    }
+    encoded.freeze()
+  }
+  /// Encodes the text message sent to the client.
+  pub fn text(&mut self, text: String) -> Bytes {
+    self.encode(Message::Text(text.into()))
+  }
+  /// Closes the connection with specified code, returns the encoded close frame.
+  pub fn close(&mut self, code: CloseCode) -> Bytes {
+    self.closed = true;
+    self.encode(Message::Close(Some(code.into())))
+  }
+  /// Waits for the next text message or frame to be sent back to the client.
+  ///
+  /// Received bytes are buffered in the connection, so the returned future may be dropped safely.
+  pub async fn receive(&mut self) -> Received {
+    loop {
+      match self.codec.decode(&mut self.received) {
+        Ok(Some(Frame::Text(text))) => match String::from_utf8(text.to_vec()) {
+          Ok(text) => return Received::Text(text),
+          Err(_) => return Received::Reply(self.close(CloseCode::Invalid)),
+        },
+        Ok(Some(Frame::Ping(message))) => return Received::Reply(self.encode(Message::Pong(message))),
+        Ok(Some(Frame::Close(reason))) => {
+          self.closed = true;
+          return Received::Reply(self.encode(Message::Close(reason)));
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => match self.payload.next().await {
+          Some(Ok(bytes)) => self.received.extend_from_slice(&bytes),
+          _ => {
+            self.closed = true;
+            return Received::Closed;
+          }
+        },
+        Err(_) => return Received::Reply(self.close(CloseCode::Protocol)),
+      }
+    }
+  }
+}
+
+/// Returns `true` when the request asks for the upgrade of the connection.
+pub fn is_upgrade(req: &HttpRequest) -> bool {
+  req.headers().contains_key(UPGRADE)
+}
+
+/// Creates the response upgrading the connection to WebSocket and streaming encoded frames.
+pub fn upgrade(req: &HttpRequest, frames: impl Stream<Item = Result<Bytes, Infallible>> + 'static) -> HttpResponse {
+  if let Err(reason) = verify_handshake(req.head()) {
+    return reason.error_response();
+  }
+  // the key was verified with the handshake
+  let key = hash_key(req.headers().get(SEC_WEBSOCKET_KEY).map(HeaderValue::as_bytes).unwrap_or_default());
+  HttpResponse::SwitchingProtocols()
+    .upgrade("websocket")
+    .insert_header((SEC_WEBSOCKET_ACCEPT, HeaderValue::from_bytes(&key).unwrap()))
+    .streaming(frames)
+}
@@ -0,0 +1,58 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! The `nordnotes` application library.
+//!
+//! All application components are defined in this library,
+//! the executable only starts the server.
+
+extern crate actix_codec;
+extern crate actix_cors;
+extern crate actix_http;
+extern crate actix_multipart;
+extern crate actix_web;
+extern crate ammonia;
+extern crate async_trait;
+extern crate chrono;
+extern crate futures_util;
+extern crate jsonwebtoken;
+extern crate lazy_static;
+extern crate pulldown_cmark;
+extern crate scylla;
+extern crate serde;
+extern crate serde_derive;
+extern crate serde_json;
+extern crate time;
+extern crate tokio;
+extern crate uuid;
+
+pub mod collaboration;
+pub mod controllers;
+pub mod crdt;
+pub mod diff;
+pub mod entities;
+pub mod errors;
+pub mod events;
+pub mod handlers;
+pub mod render;
+pub mod repositories;
+pub mod search;
+pub mod server;
+pub mod services;
+pub mod settings;
+pub mod sniff;
+pub mod storage;
+pub mod utils;
@@ -16,32 +16,13 @@
 
 //! The `nordnotes` application.
 
-extern crate actix_cors;
-extern crate actix_web;
-extern crate lazy_static;
-extern crate scylla;
-extern crate serde;
-extern crate serde_derive;
-extern crate serde_json;
-extern crate time;
-extern crate tokio;
-extern crate uuid;
+use env_logger::Env;
+use nordnotes::errors::Result;
+use nordnotes::server::start_server;
 
-use crate::errors::Result;
-use crate::server::start_server;
-
-mod controllers;
-mod entities;
-mod errors;
-mod handlers;
-mod repositories;
-mod server;
-mod services;
-mod storage;
-mod utils;
-
-/// Main entrypoint of the `nordnotes` application.
+/// Main entrypoint of the `nordnotes` application, messages are logged with `info` level unless `RUST_LOG` is set.
 #[tokio::main]
 async fn main() -> Result<()> {
+  env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
   start_server().await
 }
@@ -0,0 +1,178 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! # Rendering of notes
+//!
+//! The content of a note is rendered into an HTML fragment according to its format:
+//! - plain text is escaped, paragraphs are separated by blank lines and single line breaks are preserved,
+//! - Markdown is rendered following CommonMark with GitHub Flavored Markdown extensions
+//!   (tables, strikethrough, task lists and footnotes),
+//! - HTML is used as it is.
+//!
+//! Rendered HTML is always sanitized, so it is safe to be displayed in a browser: scripts, styles,
+//! event handlers and `javascript:` links are removed, links get `rel="noopener noreferrer"`.
+
+use crate::entities::note::ContentFormat;
+use ammonia::Builder;
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Options, Parser};
+
+lazy_static! {
+  /// Sanitizer of rendered HTML, allows disabled checkboxes rendered for task list items.
+  /// Inputs of other types lose their `type` attribute here and are dropped by [drop_inputs].
+  static ref SANITIZER: Builder<'static> = {
+    let mut builder = Builder::default();
+    builder
+      .add_tags(["input"])
+      .add_tag_attributes("input", ["type", "checked"])
+      .set_tag_attribute_value("input", "disabled", "")
+      .attribute_filter(|element, attribute, value| match (element, attribute) {
+        ("input", "type") if value != "checkbox" => None,
+        ("input", "checked") => Some("".into()),
+        _ => Some(value.into()),
+      });
+    builder
+  };
+}
+
+/// Prefix of an `input` tag in sanitized HTML.
+const INPUT_TAG: &str = "<input ";
+
+/// Attribute of an `input` tag in sanitized HTML marking a checkbox.
+const CHECKBOX_TYPE: &str = " type=\"checkbox\"";
+
+/// Renders the content of a note in specified format into a sanitized HTML fragment.
+pub fn render_html(content: &str, format: ContentFormat) -> String {
+  let html = match format {
+    ContentFormat::Plain => plain_to_html(content),
+    ContentFormat::Markdown => markdown_to_html(content),
+    ContentFormat::Html => content.to_string(),
+  };
+  drop_inputs(&SANITIZER.clean(&html).to_string())
+}
+
+/// Removes `input` tags other than checkboxes from sanitized HTML.
+///
+/// Sanitized HTML has escaped text and double-quoted attribute values,
+/// so tags are found by tracking quotes between `<` and `>`.
+fn drop_inputs(html: &str) -> String {
+  let mut output = String::with_capacity(html.len());
+  let mut rest = html;
+  while let Some(start) = rest.find('<') {
+    output.push_str(&rest[..start]);
+    let mut quoted = false;
+    let end = rest[start..]
+      .char_indices()
+      .find(|(_, ch)| {
+        if *ch == '"' {
+          quoted = !quoted;
+        }
+        *ch == '>' && !quoted
+      })
+      .map_or(rest.len(), |(offset, _)| start + offset + 1);
+    let tag = &rest[start..end];
+    if !tag.starts_with(INPUT_TAG) || tag.contains(CHECKBOX_TYPE) {
+      output.push_str(tag);
+    }
+    rest = &rest[end..];
+  }
+  output.push_str(rest);
+  output
+}
+
+/// Renders Markdown into HTML.
+fn markdown_to_html(content: &str) -> String {
+  let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+  let mut output = String::with_capacity(content.len() * 3 / 2);
+  html::push_html(&mut output, Parser::new_ext(content, options));
+  output
+}
+
+/// Renders plain text into HTML paragraphs.
+fn plain_to_html(content: &str) -> String {
+  let normalized = content.replace("\r\n", "\n");
+  normalized
+    .split("\n\n")
+    .map(str::trim)
+    .filter(|paragraph| !paragraph.is_empty())
+    .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph).replace('\n', "<br>\n")))
+    .collect()
+}
+
+/// Escapes characters having special meaning in HTML.
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    match ch {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _ => escaped.push(ch),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_render_plain() {
+    assert_eq!(
+      "<p>Kanelbullar &lt;3<br>\nfika</p>\n<p>&lt;script&gt;alert(1)&lt;/script&gt;</p>\n",
+      render_html("Kanelbullar <3\nfika\n\n\n<script>alert(1)</script>", ContentFormat::Plain)
+    );
+    assert_eq!("", render_html(" \n\n ", ContentFormat::Plain));
+  }
+
+  #[test]
+  fn test_render_markdown() {
+    assert_eq!(
+      "<h1>Fika</h1>\n<p><strong>strong</strong> <del>coffee</del></p>\n",
+      render_html("# Fika\n\n**strong** ~~coffee~~", ContentFormat::Markdown)
+    );
+    assert_eq!(
+      "<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody>\n<tr><td>1</td><td>2</td></tr>\n</tbody></table>\n",
+      render_html("| a | b |\n|---|---|\n| 1 | 2 |", ContentFormat::Markdown)
+    );
+    assert_eq!(
+      "<ul>\n<li><input type=\"checkbox\" checked=\"\" disabled=\"\">\nmilk</li>\n<li><input type=\"checkbox\" disabled=\"\">\nsugar</li>\n</ul>\n",
+      render_html("- [x] milk\n- [ ] sugar", ContentFormat::Markdown)
+    );
+  }
+
+  #[test]
+  fn test_sanitize() {
+    assert_eq!(
+      "<p>hello</p>\n",
+      render_html("<p onclick=\"steal()\">hello</p><script>alert(1)</script>\n", ContentFormat::Markdown)
+    );
+    assert_eq!(
+      "<p><a rel=\"noopener noreferrer\">link</a></p>\n",
+      render_html("[link](javascript:alert(1))", ContentFormat::Markdown)
+    );
+    assert_eq!(
+      "<b>bold</b><input type=\"checkbox\" disabled=\"\"><abbr title=\"<input \">x</abbr>",
+      render_html(
+        "<b>bold</b><input type=\"checkbox\"><input type=\"password\" checked><input><style>p {}</style><abbr title=\"<input \">x</abbr>",
+        ContentFormat::Html
+      )
+    );
+  }
+}
@@ -0,0 +1,39 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for metadata of note attachments.
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for metadata of note attachments, contents of attached files are stored in [BlobsRepository](super::blobs::BlobsRepository).
+#[async_trait]
+pub trait AttachmentsRepository: Send + Sync {
+  /// Adds a new attachment.
+  async fn add(&self, attachment: AttachmentEntity) -> Result<()>;
+  /// Lists all attachments of the note with specified identifier.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<AttachmentEntity>>;
+  /// Searches for an attachment of the note with specified identifier.
+  async fn find(&self, note_id: &str, attachment_id: &str) -> Result<AttachmentEntity>;
+  /// Changes the expiration time of all attachments of the note to the expiration time of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()>;
+  /// Deletes an attachment of the note with specified identifier.
+  async fn delete(&self, note_id: &str, attachment_id: &str) -> Result<()>;
+  /// Deletes all attachments of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()>;
+}
@@ -0,0 +1,78 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for contents of attached files (blobs).
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+use std::ops::{Range, RangeInclusive};
+
+/// Size of chunks the content of a file is split into when stored in the database, in bytes.
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Repository for contents of attached files.
+#[async_trait]
+pub trait BlobsRepository: Send + Sync {
+  /// Writes a chunk of the content of the attached file, the content expires together with the attachment.
+  ///
+  /// Chunks are written in order starting from chunk number 0, all chunks but the last one have [CHUNK_SIZE] bytes,
+  /// the only chunk of an empty content is empty. The content may be read after it is [completed](Self::complete).
+  async fn write_chunk(&self, attachment: &AttachmentEntity, chunk: i32, data: &[u8]) -> Result<()>;
+  /// Completes writing the content of the attached file.
+  async fn complete(&self, attachment: &AttachmentEntity) -> Result<()>;
+  /// Writes the whole content of the attached file in chunks.
+  async fn write(&self, attachment: &AttachmentEntity, content: &[u8]) -> Result<()> {
+    if content.is_empty() {
+      self.write_chunk(attachment, 0, content).await?;
+    }
+    for (chunk, data) in content.chunks(CHUNK_SIZE).enumerate() {
+      self.write_chunk(attachment, chunk as i32, data).await?;
+    }
+    self.complete(attachment).await
+  }
+  /// Reads specified range of bytes of the content of the attached file, the range must lie within the content.
+  async fn read(&self, attachment: &AttachmentEntity, range: Range<u64>) -> Result<Vec<u8>>;
+  /// Changes the expiration time of the content to the expiration time of the attachment.
+  async fn refresh_expiration(&self, attachment: &AttachmentEntity) -> Result<()>;
+  /// Deletes the content of the attached file.
+  async fn delete(&self, attachment: &AttachmentEntity) -> Result<()>;
+  /// Deletes contents that have expired and are not removed by the storage itself,
+  /// returns the number of deleted contents.
+  async fn purge_expired(&self) -> Result<usize>;
+}
+
+/// Returns the numbers of chunks holding specified non-empty range of bytes.
+pub fn chunks(range: &Range<u64>) -> RangeInclusive<i32> {
+  let first = range.start / CHUNK_SIZE as u64;
+  let last = range.end.saturating_sub(1) / CHUNK_SIZE as u64;
+  first as i32..=last as i32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_chunks() {
+    let size = CHUNK_SIZE as u64;
+    assert_eq!(0..=0, chunks(&(0..1)));
+    assert_eq!(0..=0, chunks(&(0..size)));
+    assert_eq!(0..=1, chunks(&(0..size + 1)));
+    assert_eq!(1..=1, chunks(&(size..size + 10)));
+    assert_eq!(0..=2, chunks(&(size - 1..2 * size + 1)));
+  }
+}
@@ -0,0 +1,241 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of local file system repository for contents of attached files.
+//!
+//! The content of an attached file is stored in `<root>/<note id>/<attachment id>`.
+//! The file system does not expire files, so the expiration time of the content is stored next to it
+//! in `<attachment id>.expires` (seconds since unix epoch) and expired contents are deleted
+//! by [purge_expired](BlobsRepository::purge_expired), called periodically by the server.
+//! Contents are written to `<attachment id>.partial` until complete, partial files left by interrupted uploads
+//! are deleted by [purge_expired](BlobsRepository::purge_expired) when they are not written for a day.
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::errors::*;
+use crate::repositories::blobs::BlobsRepository;
+use async_trait::async_trait;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use time::OffsetDateTime;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Extension of files holding expiration times of contents.
+const EXPIRES_EXTENSION: &str = "expires";
+
+/// Extension of files being written, renamed when the content is complete.
+const PARTIAL_EXTENSION: &str = "partial";
+
+/// Time after the last write, when a partial file is considered abandoned.
+const PARTIAL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Local file system repository for contents of attached files.
+pub struct FilesystemBlobsRepository {
+  /// Directory containing contents of attached files.
+  root: PathBuf,
+  /// Time after the last write, when a partial file is deleted.
+  partial_max_age: Duration,
+}
+
+impl FilesystemBlobsRepository {
+  /// Creates a new blobs repository storing contents in specified directory.
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    Self {
+      root: root.into(),
+      partial_max_age: PARTIAL_MAX_AGE,
+    }
+  }
+  /// Returns the path of the file with the content of the attachment.
+  fn content_path(&self, attachment: &AttachmentEntity) -> PathBuf {
+    // identifiers are generated by the application, so they are safe to be used as file names
+    self.root.join(&attachment.note_id).join(&attachment.attachment_id)
+  }
+  /// Writes the expiration time of the content, removes it when the content never expires.
+  async fn write_expiration(&self, attachment: &AttachmentEntity) -> Result<()> {
+    let path = self.content_path(attachment).with_extension(EXPIRES_EXTENSION);
+    match attachment.expires_at {
+      Some(expires_at) => fs::write(&path, expires_at.unix_timestamp().to_string())
+        .await
+        .map_err(|e| err_write_file(&path.to_string_lossy(), e)),
+      None => remove_file(&path).await,
+    }
+  }
+}
+
+/// Removes the file, a file that does not exist is not an error.
+async fn remove_file(path: &Path) -> Result<()> {
+  match fs::remove_file(path).await {
+    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(err_write_file(&path.to_string_lossy(), e)),
+    _ => Ok(()),
+  }
+}
+
+/// Returns `true` when the file was not modified for specified time, `false` also when the file is missing.
+async fn is_stale(path: &Path, max_age: Duration) -> bool {
+  let Ok(modified) = fs::metadata(path).await.and_then(|metadata| metadata.modified()) else {
+    return false;
+  };
+  SystemTime::now().duration_since(modified).is_ok_and(|age| age >= max_age)
+}
+
+/// Reads the expiration time stored in specified file, `None` when the file is missing or invalid.
+async fn read_expiration(path: &Path) -> Option<OffsetDateTime> {
+  let seconds = fs::read_to_string(path).await.ok()?.trim().parse().ok()?;
+  OffsetDateTime::from_unix_timestamp(seconds).ok()
+}
+
+#[async_trait]
+impl BlobsRepository for FilesystemBlobsRepository {
+  /// Appends the chunk to a temporary file, renamed when complete, so partially written contents are never read.
+  async fn write_chunk(&self, attachment: &AttachmentEntity, chunk: i32, data: &[u8]) -> Result<()> {
+    let path = self.content_path(attachment);
+    let partial = path.with_extension(PARTIAL_EXTENSION);
+    let err_write = |e| err_write_file(&path.to_string_lossy(), e);
+    if chunk == 0 {
+      fs::create_dir_all(self.root.join(&attachment.note_id)).await.map_err(err_write)?;
+      self.write_expiration(attachment).await?;
+    }
+    let mut file = fs::OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(chunk == 0)
+      .append(chunk > 0)
+      .open(&partial)
+      .await
+      .map_err(err_write)?;
+    file.write_all(data).await.map_err(err_write)?;
+    file.flush().await.map_err(err_write)
+  }
+  /// Renames the temporary file holding the written content.
+  async fn complete(&self, attachment: &AttachmentEntity) -> Result<()> {
+    let path = self.content_path(attachment);
+    fs::rename(path.with_extension(PARTIAL_EXTENSION), &path)
+      .await
+      .map_err(|e| err_write_file(&path.to_string_lossy(), e))
+  }
+  /// Reads specified range of bytes of the content, expired contents are not found.
+  async fn read(&self, attachment: &AttachmentEntity, range: Range<u64>) -> Result<Vec<u8>> {
+    let path = self.content_path(attachment);
+    let not_found = |_| err_attachment_not_found(&attachment.note_id, &attachment.attachment_id);
+    let expires_at = read_expiration(&path.with_extension(EXPIRES_EXTENSION)).await;
+    if expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc()) {
+      return Err(err_attachment_not_found(&attachment.note_id, &attachment.attachment_id));
+    }
+    let mut file = fs::File::open(&path).await.map_err(not_found)?;
+    let mut content = vec![0; (range.end - range.start) as usize];
+    file.seek(SeekFrom::Start(range.start)).await.map_err(not_found)?;
+    file.read_exact(&mut content).await.map_err(not_found)?;
+    Ok(content)
+  }
+  /// Rewrites the expiration time of the content.
+  async fn refresh_expiration(&self, attachment: &AttachmentEntity) -> Result<()> {
+    self.write_expiration(attachment).await
+  }
+  /// Deletes the content (also when it was not completed) and its expiration time,
+  /// the directory of the note is deleted when it becomes empty.
+  async fn delete(&self, attachment: &AttachmentEntity) -> Result<()> {
+    let path = self.content_path(attachment);
+    remove_file(&path).await?;
+    remove_file(&path.with_extension(PARTIAL_EXTENSION)).await?;
+    remove_file(&path.with_extension(EXPIRES_EXTENSION)).await?;
+    // fails when other attachments of the note remain
+    let _ = fs::remove_dir(self.root.join(&attachment.note_id)).await;
+    Ok(())
+  }
+  /// Deletes contents which expiration time has passed and partial files not written for a while,
+  /// returns the number of deleted contents.
+  async fn purge_expired(&self) -> Result<usize> {
+    let now = OffsetDateTime::now_utc();
+    let mut purged = 0;
+    let Ok(mut note_dirs) = fs::read_dir(&self.root).await else {
+      // nothing was stored yet
+      return Ok(0);
+    };
+    let err_read = |e| err_read_file(&self.root.to_string_lossy(), e);
+    while let Some(note_dir) = note_dirs.next_entry().await.map_err(err_read)? {
+      let Ok(mut entries) = fs::read_dir(note_dir.path()).await else {
+        continue;
+      };
+      while let Some(entry) = entries.next_entry().await.map_err(err_read)? {
+        let path = entry.path();
+        let Some(extension) = path.extension() else {
+          continue;
+        };
+        if extension == EXPIRES_EXTENSION && read_expiration(&path).await.is_some_and(|expires_at| expires_at <= now) {
+          remove_file(&path.with_extension("")).await?;
+          remove_file(&path.with_extension(PARTIAL_EXTENSION)).await?;
+          remove_file(&path).await?;
+          purged += 1;
+        } else if extension == PARTIAL_EXTENSION && is_stale(&path, self.partial_max_age).await {
+          remove_file(&path).await?;
+          remove_file(&path.with_extension(EXPIRES_EXTENSION)).await?;
+          purged += 1;
+        }
+      }
+      let _ = fs::remove_dir(note_dir.path()).await;
+    }
+    Ok(purged)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::note::NoteEntity;
+  use crate::utils::{now, uuid};
+  use time::Duration;
+
+  #[tokio::test]
+  async fn test_blobs() {
+    let root = std::env::temp_dir().join(format!("nordnotes-{}", uuid()));
+    let repository = FilesystemBlobsRepository::new(&root);
+    assert_eq!(0, repository.purge_expired().await.unwrap());
+    let note = NoteEntity::new("alice", "title", "content", Some(now() + Duration::hours(1)));
+    let attachment = AttachmentEntity::new(&note, "a.txt", "text/plain", 10);
+    repository.write(&attachment, b"0123456789").await.unwrap();
+    assert_eq!(b"2345".to_vec(), repository.read(&attachment, 2..6).await.unwrap());
+    assert!(repository.read(&attachment, 5..11).await.is_err());
+    let mut expired = AttachmentEntity::new(&note, "b.txt", "text/plain", 1);
+    repository.write(&expired, b"x").await.unwrap();
+    expired.expires_at = Some(now() - Duration::seconds(1));
+    repository.refresh_expiration(&expired).await.unwrap();
+    assert!(repository.read(&expired, 0..1).await.is_err());
+    assert_eq!(1, repository.purge_expired().await.unwrap());
+    assert!(!repository.content_path(&expired).exists());
+    // contents not completed are not read, but are deleted
+    let partial = AttachmentEntity::new(&note, "c.txt", "text/plain", 1);
+    repository.write_chunk(&partial, 0, b"c").await.unwrap();
+    assert!(repository.read(&partial, 0..1).await.is_err());
+    repository.delete(&partial).await.unwrap();
+    // partial files are purged when they are not written for a while
+    let abandoned = AttachmentEntity::new(&note, "d.txt", "text/plain", 2);
+    repository.write_chunk(&abandoned, 0, b"d").await.unwrap();
+    assert_eq!(0, repository.purge_expired().await.unwrap());
+    let impatient = FilesystemBlobsRepository {
+      root: root.clone(),
+      partial_max_age: std::time::Duration::ZERO,
+    };
+    assert_eq!(1, impatient.purge_expired().await.unwrap());
+    assert!(!repository.content_path(&abandoned).with_extension(PARTIAL_EXTENSION).exists());
+    assert!(repository.read(&attachment, 0..1).await.is_ok());
+    repository.delete(&attachment).await.unwrap();
+    assert!(repository.read(&attachment, 0..1).await.is_err());
+    assert!(!root.join(&note.note_id).exists());
+    fs::remove_dir_all(&root).await.unwrap();
+  }
+}
@@ -0,0 +1,89 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for metadata of note attachments.
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::attachments::AttachmentsRepository;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// In-memory repository for metadata of note attachments.
+#[derive(Default)]
+pub struct InMemoryAttachmentsRepository {
+  /// Attachments indexed by note identifier and attachment identifier.
+  attachments: RwLock<BTreeMap<(String, String), AttachmentEntity>>,
+}
+
+#[async_trait]
+impl AttachmentsRepository for InMemoryAttachmentsRepository {
+  /// Adds a new attachment.
+  async fn add(&self, attachment: AttachmentEntity) -> Result<()> {
+    let key = (attachment.note_id.clone(), attachment.attachment_id.clone());
+    self.attachments.write().unwrap().insert(key, attachment);
+    Ok(())
+  }
+  /// Lists all attachments of the note with specified identifier, skips expired attachments.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<AttachmentEntity>> {
+    Ok(
+      self
+        .attachments
+        .read()
+        .unwrap()
+        .values()
+        .filter(|attachment| attachment.note_id == note_id && !attachment.has_expired())
+        .cloned()
+        .collect(),
+    )
+  }
+  /// Searches for an attachment of the note with specified identifier, expired attachments are not found.
+  async fn find(&self, note_id: &str, attachment_id: &str) -> Result<AttachmentEntity> {
+    self
+      .attachments
+      .read()
+      .unwrap()
+      .get(&(note_id.to_string(), attachment_id.to_string()))
+      .filter(|attachment| !attachment.has_expired())
+      .cloned()
+      .ok_or_else(|| err_attachment_not_found(note_id, attachment_id))
+  }
+  /// Changes the expiration time of all attachments of the note to the expiration time of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()> {
+    for attachment in self.attachments.write().unwrap().values_mut() {
+      if attachment.note_id == note.note_id {
+        attachment.expires_at = note.expires_at;
+      }
+    }
+    Ok(())
+  }
+  /// Deletes an attachment of the note with specified identifier.
+  async fn delete(&self, note_id: &str, attachment_id: &str) -> Result<()> {
+    self.attachments.write().unwrap().remove(&(note_id.to_string(), attachment_id.to_string()));
+    Ok(())
+  }
+  /// Deletes all attachments of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    self
+      .attachments
+      .write()
+      .unwrap()
+      .retain(|(attachment_note_id, _), _| attachment_note_id != note_id);
+    Ok(())
+  }
+}
@@ -0,0 +1,125 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for contents of attached files.
+
+use crate::entities::attachment::AttachmentEntity;
+use crate::errors::*;
+use crate::repositories::blobs::BlobsRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::RwLock;
+use time::OffsetDateTime;
+
+/// Content of an attached file held in memory.
+struct Blob {
+  /// Bytes of the content.
+  content: Vec<u8>,
+  /// Date and time (UTC) when the content expires, `None` when the content never expires.
+  expires_at: Option<OffsetDateTime>,
+}
+
+impl Blob {
+  /// Returns `true` when the content has expired.
+  fn has_expired(&self) -> bool {
+    self.expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+  }
+}
+
+/// In-memory repository for contents of attached files.
+#[derive(Default)]
+pub struct InMemoryBlobsRepository {
+  /// Contents indexed by attachment identifier.
+  blobs: RwLock<HashMap<String, Blob>>,
+}
+
+#[async_trait]
+impl BlobsRepository for InMemoryBlobsRepository {
+  /// Writes a chunk of the content of the attached file, the first chunk replaces the previous content.
+  async fn write_chunk(&self, attachment: &AttachmentEntity, chunk: i32, data: &[u8]) -> Result<()> {
+    let mut blobs = self.blobs.write().unwrap();
+    let blob = blobs.entry(attachment.attachment_id.clone()).or_insert_with(|| Blob {
+      content: vec![],
+      expires_at: attachment.expires_at,
+    });
+    if chunk == 0 {
+      blob.content.clear();
+    }
+    blob.content.extend_from_slice(data);
+    Ok(())
+  }
+  /// Contents are held in memory, nothing needs to be completed.
+  async fn complete(&self, _attachment: &AttachmentEntity) -> Result<()> {
+    Ok(())
+  }
+  /// Reads specified range of bytes of the content, expired contents are not found.
+  async fn read(&self, attachment: &AttachmentEntity, range: Range<u64>) -> Result<Vec<u8>> {
+    let blobs = self.blobs.read().unwrap();
+    blobs
+      .get(&attachment.attachment_id)
+      .filter(|blob| !blob.has_expired())
+      .and_then(|blob| blob.content.get(range.start as usize..range.end as usize))
+      .map(<[u8]>::to_vec)
+      .ok_or_else(|| err_attachment_not_found(&attachment.note_id, &attachment.attachment_id))
+  }
+  /// Changes the expiration time of the content to the expiration time of the attachment.
+  async fn refresh_expiration(&self, attachment: &AttachmentEntity) -> Result<()> {
+    if let Some(blob) = self.blobs.write().unwrap().get_mut(&attachment.attachment_id) {
+      blob.expires_at = attachment.expires_at;
+    }
+    Ok(())
+  }
+  /// Deletes the content of the attached file.
+  async fn delete(&self, attachment: &AttachmentEntity) -> Result<()> {
+    self.blobs.write().unwrap().remove(&attachment.attachment_id);
+    Ok(())
+  }
+  /// Deletes expired contents, returns the number of deleted contents.
+  async fn purge_expired(&self) -> Result<usize> {
+    let mut blobs = self.blobs.write().unwrap();
+    let count = blobs.len();
+    blobs.retain(|_, blob| !blob.has_expired());
+    Ok(count - blobs.len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::note::NoteEntity;
+  use crate::utils::now;
+  use time::Duration;
+
+  #[tokio::test]
+  async fn test_blobs() {
+    let repository = InMemoryBlobsRepository::default();
+    let note = NoteEntity::new("alice", "title", "content", None);
+    let attachment = AttachmentEntity::new(&note, "a.txt", "text/plain", 10);
+    repository.write(&attachment, b"0123456789").await.unwrap();
+    assert_eq!(b"2345".to_vec(), repository.read(&attachment, 2..6).await.unwrap());
+    assert!(repository.read(&attachment, 5..11).await.is_err());
+    let mut expired = AttachmentEntity::new(&note, "b.txt", "text/plain", 1);
+    repository.write(&expired, b"x").await.unwrap();
+    expired.expires_at = Some(now() - Duration::seconds(1));
+    repository.refresh_expiration(&expired).await.unwrap();
+    assert!(repository.read(&expired, 0..1).await.is_err());
+    assert_eq!(1, repository.purge_expired().await.unwrap());
+    assert_eq!(0, repository.purge_expired().await.unwrap());
+    repository.delete(&attachment).await.unwrap();
+    assert!(repository.read(&attachment, 0..1).await.is_err());
+  }
+}
@@ -0,0 +1,35 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of repositories held in memory.
+//!
+//! In-memory repositories behave like their ScyllaDB counterparts
+//! (including expiration of entities with limited time to live),
+//! but all data is lost when the application stops.
+//! They are used mainly to run the application and its tests without a database.
+
+pub mod attachments;
+pub mod blobs;
+pub mod notebooks;
+pub mod notes;
+pub mod refresh_tokens;
+pub mod revisions;
+pub mod roles;
+pub mod sessions;
+pub mod shares;
+pub mod tags;
+pub mod timelines;
+pub mod users;
@@ -0,0 +1,63 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for notebooks.
+
+use crate::entities::notebook::NotebookEntity;
+use crate::errors::*;
+use crate::repositories::notebooks::NotebooksRepository;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// In-memory repository for notebooks.
+#[derive(Default)]
+pub struct InMemoryNotebooksRepository {
+  /// Notebooks indexed by owner and notebook identifier.
+  notebooks: RwLock<BTreeMap<(String, String), NotebookEntity>>,
+}
+
+#[async_trait]
+impl NotebooksRepository for InMemoryNotebooksRepository {
+  /// Saves a new or modified notebook.
+  async fn save(&self, notebook: NotebookEntity) -> Result<()> {
+    let key = (notebook.owner_id.clone(), notebook.notebook_id.clone());
+    self.notebooks.write().unwrap().insert(key, notebook);
+    Ok(())
+  }
+  /// Lists all notebooks owned by specified user, ordered by identifier.
+  async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<NotebookEntity>> {
+    let notebooks = self.notebooks.read().unwrap();
+    Ok(notebooks.values().filter(|notebook| notebook.owner_id == owner_id).cloned().collect())
+  }
+  /// Searches for a notebook with specified identifier owned by specified user.
+  async fn find(&self, owner_id: &str, notebook_id: &str) -> Result<NotebookEntity> {
+    let key = (owner_id.to_string(), notebook_id.to_string());
+    self
+      .notebooks
+      .read()
+      .unwrap()
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| err_notebook_not_found(notebook_id))
+  }
+  /// Deletes a notebook with specified identifier owned by specified user.
+  async fn delete(&self, owner_id: &str, notebook_id: &str) -> Result<()> {
+    let key = (owner_id.to_string(), notebook_id.to_string());
+    self.notebooks.write().unwrap().remove(&key);
+    Ok(())
+  }
+}
@@ -0,0 +1,172 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for notes.
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::notes::NotesRepository;
+use crate::repositories::{Page, PageRequest};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// In-memory repository for notes.
+#[derive(Default)]
+pub struct InMemoryNotesRepository {
+  /// Notes indexed by identifier.
+  notes: RwLock<HashMap<String, NoteEntity>>,
+}
+
+impl InMemoryNotesRepository {
+  /// Removes all notes that have expired.
+  fn purge_expired(&self) {
+    self.notes.write().unwrap().retain(|_, note| !note.has_expired());
+  }
+}
+
+#[async_trait]
+impl NotesRepository for InMemoryNotesRepository {
+  /// Adds a new note.
+  async fn add(&self, note: NoteEntity) -> Result<String> {
+    let note_id = note.note_id.clone();
+    self.notes.write().unwrap().insert(note_id.clone(), note);
+    Ok(note_id)
+  }
+  /// Lists a single page of notes that have not expired yet ordered by identifier,
+  /// the token of the next page contains the identifier of the last listed note.
+  async fn list_page(&self, page: &PageRequest) -> Result<Page<NoteEntity>> {
+    let last = match &page.token {
+      Some(token) => Some(
+        from_hex(token)
+          .and_then(|bytes| String::from_utf8(bytes).ok())
+          .ok_or_else(err_invalid_page_token)?,
+      ),
+      None => None,
+    };
+    self.purge_expired();
+    let notes = self.notes.read().unwrap();
+    let mut note_ids: Vec<&String> = notes.keys().filter(|note_id| last.as_ref().is_none_or(|last| *note_id > last)).collect();
+    note_ids.sort();
+    let next_page = (note_ids.len() > page.size).then(|| to_hex(note_ids[page.size - 1].as_bytes()));
+    Ok(Page {
+      items: note_ids.into_iter().take(page.size).map(|note_id| notes[note_id].clone()).collect(),
+      next_page,
+    })
+  }
+  /// Lists all notes owned by specified user that have not expired yet.
+  async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<NoteEntity>> {
+    self.purge_expired();
+    Ok(self.notes.read().unwrap().values().filter(|note| note.is_owned_by(owner_id)).cloned().collect())
+  }
+  /// Searches for a note with specified identifier.
+  async fn find(&self, note_id: &str) -> Result<NoteEntity> {
+    self.purge_expired();
+    self.notes.read().unwrap().get(note_id).cloned().ok_or_else(|| err_note_not_found(note_id))
+  }
+  /// Updates an existing note when its stored version equals expected version.
+  async fn update(&self, note: NoteEntity, expected_version: i64) -> Result<()> {
+    let mut notes = self.notes.write().unwrap();
+    match notes.get_mut(&note.note_id) {
+      Some(stored) if stored.version == expected_version => {
+        *stored = note;
+        Ok(())
+      }
+      Some(stored) => Err(err_version_conflict(&note.note_id, stored.version)),
+      None => Err(err_note_not_found(&note.note_id)),
+    }
+  }
+  /// Deletes a note with specified identifier.
+  async fn delete(&self, note_id: &str) -> Result<()> {
+    self.notes.write().unwrap().remove(note_id);
+    Ok(())
+  }
+  /// Deletes all notes.
+  async fn delete_all(&self) -> Result<()> {
+    self.notes.write().unwrap().clear();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::now;
+  use time::macros::datetime;
+  use time::Duration;
+
+  #[tokio::test]
+  async fn test_expired_notes_are_removed() {
+    let repository = InMemoryNotesRepository::default();
+    let active_id = repository
+      .add(NoteEntity::new("alice", "active", "content", Some(now() + Duration::hours(1))))
+      .await
+      .unwrap();
+    let mut expired = NoteEntity::new("alice", "expired", "content", Some(now() + Duration::hours(1)));
+    expired.expires_at = Some(datetime!(2000-01-01 00:00 UTC));
+    let expired_id = repository.add(expired).await.unwrap();
+    let notes = repository.list_page(&PageRequest::default()).await.unwrap().items;
+    assert_eq!(1, notes.len());
+    assert_eq!(active_id, notes[0].note_id);
+    assert!(repository.find(&expired_id).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_update_checks_version() {
+    let repository = InMemoryNotesRepository::default();
+    let mut note = NoteEntity::new("alice", "title", "content", None);
+    repository.add(note.clone()).await.unwrap();
+    note.version = 2;
+    repository.update(note.clone(), 1).await.unwrap();
+    note.version = 3;
+    let error = repository.update(note.clone(), 1).await.unwrap_err();
+    assert_eq!(format!("version conflict, note id = {}, current version = 2", note.note_id), error.to_string());
+  }
+
+  #[tokio::test]
+  async fn test_list_page() {
+    let repository = InMemoryNotesRepository::default();
+    let mut note_ids = vec![];
+    for i in 0..3 {
+      note_ids.push(repository.add(NoteEntity::new("alice", &format!("note {}", i), "content", None)).await.unwrap());
+    }
+    note_ids.sort();
+    let first = repository.list_page(&PageRequest { size: 2, token: None }).await.unwrap();
+    assert_eq!(note_ids[..2], first.items.iter().map(|note| note.note_id.clone()).collect::<Vec<String>>());
+    let second = repository
+      .list_page(&PageRequest {
+        size: 2,
+        token: first.next_page,
+      })
+      .await
+      .unwrap();
+    assert_eq!(note_ids[2..], second.items.iter().map(|note| note.note_id.clone()).collect::<Vec<String>>());
+    assert!(second.next_page.is_none());
+  }
+
+  #[tokio::test]
+  async fn test_list_by_owner() {
+    let repository = InMemoryNotesRepository::default();
+    let alice_id = repository.add(NoteEntity::new("alice", "first", "content", None)).await.unwrap();
+    repository.add(NoteEntity::new("bob", "second", "content", None)).await.unwrap();
+    repository.add(NoteEntity::new("", "legacy", "content", None)).await.unwrap();
+    let notes = repository.list_by_owner("alice").await.unwrap();
+    assert_eq!(1, notes.len());
+    assert_eq!(alice_id, notes[0].note_id);
+    assert!(repository.list_by_owner("").await.unwrap().is_empty());
+  }
+}
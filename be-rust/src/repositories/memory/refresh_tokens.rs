@@ -0,0 +1,86 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for refresh tokens.
+
+use crate::entities::refresh_token::RefreshTokenEntity;
+use crate::errors::*;
+use crate::repositories::refresh_tokens::RefreshTokensRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use time::{Duration, OffsetDateTime};
+
+/// In-memory repository for refresh tokens.
+#[derive(Default)]
+pub struct InMemoryRefreshTokensRepository {
+  /// Refresh tokens indexed by identifier, together with the date and time of their removal.
+  tokens: RwLock<HashMap<String, (RefreshTokenEntity, OffsetDateTime)>>,
+}
+
+impl InMemoryRefreshTokensRepository {
+  /// Removes all refresh tokens which time to live has elapsed.
+  fn purge_expired(&self) {
+    let now = OffsetDateTime::now_utc();
+    self.tokens.write().unwrap().retain(|_, (_, removed_at)| *removed_at > now);
+  }
+}
+
+#[async_trait]
+impl RefreshTokensRepository for InMemoryRefreshTokensRepository {
+  /// Saves a new refresh token with time to live.
+  async fn save(&self, token: RefreshTokenEntity, ttl: i32) -> Result<()> {
+    let removed_at = OffsetDateTime::now_utc() + Duration::seconds(ttl as i64);
+    self.tokens.write().unwrap().insert(token.token_id.clone(), (token, removed_at));
+    Ok(())
+  }
+  /// Searches for a refresh token with specified identifier.
+  async fn find(&self, token_id: &str) -> Result<RefreshTokenEntity> {
+    self.purge_expired();
+    let tokens = self.tokens.read().unwrap();
+    tokens
+      .get(token_id)
+      .map(|(token, _)| token.clone())
+      .ok_or_else(|| err_entity_not_found("refresh token", token_id))
+  }
+  /// Atomically marks the refresh token as used.
+  async fn mark_used(&self, token: &RefreshTokenEntity, ttl: i32) -> Result<bool> {
+    let mut tokens = self.tokens.write().unwrap();
+    match tokens.get_mut(&token.token_id) {
+      Some((stored, removed_at)) if !stored.used => {
+        stored.used = true;
+        *removed_at = OffsetDateTime::now_utc() + Duration::seconds(ttl as i64);
+        Ok(true)
+      }
+      _ => Ok(false),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_token_is_used_once() {
+    let repository = InMemoryRefreshTokensRepository::default();
+    let token = RefreshTokenEntity::new("session", "alice");
+    repository.save(token.clone(), 60).await.unwrap();
+    assert!(repository.mark_used(&token, 60).await.unwrap());
+    assert!(!repository.mark_used(&token, 60).await.unwrap());
+    assert!(repository.find(&token.token_id).await.unwrap().used);
+  }
+}
@@ -0,0 +1,158 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for note revisions.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::revision::RevisionEntity;
+use crate::errors::*;
+use crate::repositories::revisions::{decode_token, encode_token, RevisionsRepository};
+use crate::repositories::{Page, PageRequest, SortOrder};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// In-memory repository for note revisions.
+#[derive(Default)]
+pub struct InMemoryRevisionsRepository {
+  /// Revisions indexed by note identifier and revision number.
+  revisions: RwLock<BTreeMap<(String, i64), RevisionEntity>>,
+}
+
+#[async_trait]
+impl RevisionsRepository for InMemoryRevisionsRepository {
+  /// Saves a revision of the note.
+  async fn save(&self, revision: RevisionEntity) -> Result<()> {
+    let key = (revision.note_id.clone(), revision.revision);
+    self.revisions.write().unwrap().insert(key, revision);
+    Ok(())
+  }
+  /// Lists a page of revisions of the note, skips expired revisions.
+  async fn list(&self, note_id: &str, order: SortOrder, page: &PageRequest) -> Result<Page<RevisionEntity>> {
+    let (order, last) = match page.token.as_deref().map(decode_token).transpose()? {
+      Some((order, last)) => (order, Some(last)),
+      None => (order, None),
+    };
+    let all = self.revisions.read().unwrap();
+    let revisions = all
+      .range((note_id.to_string(), i64::MIN)..=(note_id.to_string(), i64::MAX))
+      .map(|(_, revision)| revision)
+      .filter(|revision| !revision.has_expired());
+    let ordered: Box<dyn Iterator<Item = &RevisionEntity>> = match (order, last) {
+      (SortOrder::Ascending, Some(last)) => Box::new(revisions.filter(move |revision| revision.revision > last)),
+      (SortOrder::Ascending, None) => Box::new(revisions),
+      (SortOrder::Descending, Some(last)) => Box::new(revisions.rev().filter(move |revision| revision.revision < last)),
+      (SortOrder::Descending, None) => Box::new(revisions.rev()),
+    };
+    let mut items: Vec<RevisionEntity> = ordered.take(page.size + 1).cloned().collect();
+    let has_more = items.len() > page.size;
+    items.truncate(page.size);
+    Ok(Page {
+      next_page: if has_more {
+        items.last().map(|revision| encode_token(order, revision.revision))
+      } else {
+        None
+      },
+      items,
+    })
+  }
+  /// Searches for a revision of the note with specified number, expired revisions are not found.
+  async fn find(&self, note_id: &str, revision: i64) -> Result<RevisionEntity> {
+    self
+      .revisions
+      .read()
+      .unwrap()
+      .get(&(note_id.to_string(), revision))
+      .filter(|found| !found.has_expired())
+      .cloned()
+      .ok_or_else(|| err_revision_not_found(note_id, revision))
+  }
+  /// Returns `true` when the revision of the note with specified number was saved and has not expired.
+  async fn exists(&self, note_id: &str, revision: i64) -> Result<bool> {
+    let revisions = self.revisions.read().unwrap();
+    Ok(revisions.get(&(note_id.to_string(), revision)).is_some_and(|found| !found.has_expired()))
+  }
+  /// Changes the expiration time of all revisions of the note to the expiration time of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()> {
+    let mut revisions = self.revisions.write().unwrap();
+    for (_, revision) in revisions.range_mut((note.note_id.clone(), i64::MIN)..=(note.note_id.clone(), i64::MAX)) {
+      revision.expires_at = note.expires_at;
+    }
+    Ok(())
+  }
+  /// Deletes all revisions of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    self.revisions.write().unwrap().retain(|(revision_note_id, _), _| revision_note_id != note_id);
+    Ok(())
+  }
+  /// Deletes all revisions of all notes.
+  async fn delete_all(&self) -> Result<()> {
+    self.revisions.write().unwrap().clear();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::now;
+  use time::Duration;
+
+  #[tokio::test]
+  async fn test_list() {
+    let repository = InMemoryRevisionsRepository::default();
+    let mut note = NoteEntity::new("alice", "title", "content", None);
+    for version in 1..=5 {
+      note.version = version;
+      repository.save(RevisionEntity::new(&note, "alice")).await.unwrap();
+    }
+    repository
+      .save(RevisionEntity::new(&NoteEntity::new("bob", "other", "", None), "bob"))
+      .await
+      .unwrap();
+    let page = repository
+      .list(&note.note_id, SortOrder::Descending, &PageRequest { size: 2, token: None })
+      .await
+      .unwrap();
+    assert_eq!(vec![5, 4], page.items.iter().map(|revision| revision.revision).collect::<Vec<i64>>());
+    let page = PageRequest {
+      size: 10,
+      token: page.next_page,
+    };
+    let page = repository.list(&note.note_id, SortOrder::Ascending, &page).await.unwrap();
+    assert_eq!(vec![3, 2, 1], page.items.iter().map(|revision| revision.revision).collect::<Vec<i64>>());
+    assert!(page.next_page.is_none());
+    assert!(repository.exists(&note.note_id, 3).await.unwrap());
+    assert_eq!(
+      format!("revision not found, note id = {}, revision = 6", note.note_id),
+      repository.find(&note.note_id, 6).await.unwrap_err().to_string()
+    );
+    // revisions expire together with the note
+    note.expires_at = Some(now() - Duration::seconds(1));
+    repository.refresh_expiration(&note).await.unwrap();
+    assert!(!repository.exists(&note.note_id, 3).await.unwrap());
+    assert!(repository
+      .list(&note.note_id, SortOrder::Ascending, &PageRequest::default())
+      .await
+      .unwrap()
+      .items
+      .is_empty());
+    repository.delete_by_note(&note.note_id).await.unwrap();
+    assert_eq!(1, repository.revisions.read().unwrap().len());
+    repository.delete_all().await.unwrap();
+    assert!(repository.revisions.read().unwrap().is_empty());
+  }
+}
@@ -0,0 +1,102 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for roles.
+
+use crate::entities::role::RoleEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::repositories::roles::RolesRepository;
+use crate::repositories::{Page, PageRequest};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// In-memory repository for roles.
+#[derive(Default)]
+pub struct InMemoryRolesRepository {
+  /// Roles indexed by identifier.
+  roles: RwLock<HashMap<String, RoleEntity>>,
+}
+
+#[async_trait]
+impl RolesRepository for InMemoryRolesRepository {
+  /// Creates a new role.
+  async fn create(&self, role: RoleEntity) -> Result<String> {
+    let role_id = role.id();
+    self.roles.write().unwrap().insert(role_id.clone(), role);
+    Ok(role_id)
+  }
+  /// Lists all roles.
+  async fn list(&self) -> Result<Vec<RoleEntity>> {
+    Ok(self.roles.read().unwrap().values().cloned().collect())
+  }
+  /// Lists a single page of roles ordered by identifier,
+  /// the token of the next page contains the identifier of the last listed role.
+  async fn list_page(&self, page: &PageRequest) -> Result<Page<RoleEntity>> {
+    let last = match &page.token {
+      Some(token) => Some(
+        from_hex(token)
+          .and_then(|bytes| String::from_utf8(bytes).ok())
+          .ok_or_else(err_invalid_page_token)?,
+      ),
+      None => None,
+    };
+    let roles = self.roles.read().unwrap();
+    let mut role_ids: Vec<&String> = roles.keys().filter(|role_id| last.as_ref().is_none_or(|last| *role_id > last)).collect();
+    role_ids.sort();
+    let next_page = (role_ids.len() > page.size).then(|| to_hex(role_ids[page.size - 1].as_bytes()));
+    Ok(Page {
+      items: role_ids.into_iter().take(page.size).map(|role_id| roles[role_id].clone()).collect(),
+      next_page,
+    })
+  }
+  /// Searches for a role with specified identifier.
+  async fn find_by_id(&self, role_id: &str) -> Result<RoleEntity> {
+    self
+      .roles
+      .read()
+      .unwrap()
+      .get(role_id)
+      .cloned()
+      .ok_or_else(|| err_entity_not_found("role", role_id))
+  }
+  /// Searches for a role with specified name.
+  async fn find_by_name(&self, role_name: &str) -> Result<RoleEntity> {
+    let roles = self.roles.read().unwrap();
+    roles
+      .values()
+      .find(|role| role.name() == role_name)
+      .cloned()
+      .ok_or_else(|| err_entity_not_found("role", role_name))
+  }
+  /// Updates an existing role.
+  async fn update(&self, role: RoleEntity) -> Result<()> {
+    let mut roles = self.roles.write().unwrap();
+    if let Some(existing) = roles.get_mut(&role.id()) {
+      *existing = role;
+      Ok(())
+    } else {
+      Err(err_entity_not_found("role", &role.id()))
+    }
+  }
+  /// Deletes a role with specified identifier.
+  async fn delete(&self, role_id: &str) -> Result<()> {
+    self.roles.write().unwrap().remove(role_id);
+    Ok(())
+  }
+}
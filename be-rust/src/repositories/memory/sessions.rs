@@ -0,0 +1,112 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for sessions.
+
+use crate::entities::session::SessionEntity;
+use crate::errors::*;
+use crate::repositories::sessions::SessionsRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use time::{Duration, OffsetDateTime};
+
+/// In-memory repository for sessions.
+#[derive(Default)]
+pub struct InMemorySessionsRepository {
+  /// Sessions indexed by identifier, together with the date and time of their removal.
+  sessions: RwLock<HashMap<String, (SessionEntity, OffsetDateTime)>>,
+}
+
+impl InMemorySessionsRepository {
+  /// Removes all sessions which time to live has elapsed.
+  fn purge_expired(&self) {
+    let now = OffsetDateTime::now_utc();
+    self.sessions.write().unwrap().retain(|_, (_, removed_at)| *removed_at > now);
+  }
+}
+
+#[async_trait]
+impl SessionsRepository for InMemorySessionsRepository {
+  /// Saves the session with time to live.
+  async fn save(&self, session: SessionEntity, ttl: i32) -> Result<()> {
+    let removed_at = OffsetDateTime::now_utc() + Duration::seconds(ttl as i64);
+    self.sessions.write().unwrap().insert(session.session_id.clone(), (session, removed_at));
+    Ok(())
+  }
+  /// Searches for a session with specified identifier.
+  async fn find(&self, session_id: &str) -> Result<SessionEntity> {
+    self.purge_expired();
+    let sessions = self.sessions.read().unwrap();
+    sessions
+      .get(session_id)
+      .map(|(session, _)| session.clone())
+      .ok_or_else(|| err_entity_not_found("session", session_id))
+  }
+  /// Lists all sessions of the user.
+  async fn list_by_user(&self, user_id: &str) -> Result<Vec<SessionEntity>> {
+    self.purge_expired();
+    let sessions = self.sessions.read().unwrap();
+    Ok(
+      sessions
+        .values()
+        .filter(|(session, _)| session.user_id == user_id)
+        .map(|(session, _)| session.clone())
+        .collect(),
+    )
+  }
+  /// Deletes a session.
+  async fn delete(&self, session: &SessionEntity) -> Result<()> {
+    self.sessions.write().unwrap().remove(&session.session_id);
+    Ok(())
+  }
+  /// Deletes all sessions of the user.
+  async fn delete_by_user(&self, user_id: &str) -> Result<()> {
+    self.sessions.write().unwrap().retain(|_, (session, _)| session.user_id != user_id);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_sessions() {
+    let repository = InMemorySessionsRepository::default();
+    let first = SessionEntity::new("alice", Duration::hours(1));
+    let second = SessionEntity::new("alice", Duration::hours(1));
+    let other = SessionEntity::new("bob", Duration::hours(1));
+    repository.save(first.clone(), 3600).await.unwrap();
+    repository.save(second.clone(), 3600).await.unwrap();
+    repository.save(other.clone(), 3600).await.unwrap();
+    assert_eq!(2, repository.list_by_user("alice").await.unwrap().len());
+    repository.delete(&first).await.unwrap();
+    assert!(repository.find(&first.session_id).await.is_err());
+    assert!(repository.find(&second.session_id).await.is_ok());
+    repository.delete_by_user("alice").await.unwrap();
+    assert!(repository.list_by_user("alice").await.unwrap().is_empty());
+    assert_eq!(other.user_id, repository.find(&other.session_id).await.unwrap().user_id);
+  }
+
+  #[tokio::test]
+  async fn test_expired_sessions_are_removed() {
+    let repository = InMemorySessionsRepository::default();
+    let session = SessionEntity::new("alice", Duration::hours(1));
+    repository.save(session.clone(), 0).await.unwrap();
+    assert!(repository.find(&session.session_id).await.is_err());
+  }
+}
@@ -0,0 +1,75 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for note shares.
+
+use crate::entities::share::{GranteeType, ShareEntity};
+use crate::errors::*;
+use crate::repositories::shares::SharesRepository;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Key of the share: note identifier, grantee type and grantee identifier.
+type ShareKey = (String, GranteeType, String);
+
+/// In-memory repository for note shares.
+#[derive(Default)]
+pub struct InMemorySharesRepository {
+  /// Shares indexed by note and grantee.
+  shares: RwLock<BTreeMap<ShareKey, ShareEntity>>,
+}
+
+#[async_trait]
+impl SharesRepository for InMemorySharesRepository {
+  /// Saves the share.
+  async fn save(&self, share: ShareEntity) -> Result<()> {
+    let key = (share.note_id.clone(), share.grantee_type, share.grantee_id.clone());
+    self.shares.write().unwrap().insert(key, share);
+    Ok(())
+  }
+  /// Lists all shares of the note.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<ShareEntity>> {
+    Ok(self.shares.read().unwrap().values().filter(|share| share.note_id == note_id).cloned().collect())
+  }
+  /// Lists all shares granted to specified grantee.
+  async fn list_by_grantee(&self, grantee_type: GranteeType, grantee_id: &str) -> Result<Vec<ShareEntity>> {
+    let shares = self.shares.read().unwrap();
+    Ok(
+      shares
+        .values()
+        .filter(|share| share.grantee_type == grantee_type && share.grantee_id == grantee_id)
+        .cloned()
+        .collect(),
+    )
+  }
+  /// Deletes the share of the note granted to specified grantee.
+  async fn delete(&self, note_id: &str, grantee_type: GranteeType, grantee_id: &str) -> Result<()> {
+    let key = (note_id.to_string(), grantee_type, grantee_id.to_string());
+    self.shares.write().unwrap().remove(&key);
+    Ok(())
+  }
+  /// Deletes all shares of the note.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    self.shares.write().unwrap().retain(|_, share| share.note_id != note_id);
+    Ok(())
+  }
+  /// Deletes all shares.
+  async fn delete_all(&self) -> Result<()> {
+    self.shares.write().unwrap().clear();
+    Ok(())
+  }
+}
@@ -0,0 +1,103 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for note tags.
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::tags::TagsRepository;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use time::OffsetDateTime;
+
+/// Key of the tagged note: owner identifier, tag and note identifier.
+type TagKey = (String, String, String);
+
+/// In-memory repository for note tags.
+#[derive(Default)]
+pub struct InMemoryTagsRepository {
+  /// Expiration times of tagged notes, indexed by owner, tag and note.
+  tags: RwLock<BTreeMap<TagKey, Option<OffsetDateTime>>>,
+}
+
+#[async_trait]
+impl TagsRepository for InMemoryTagsRepository {
+  /// Indexes all tags of the note.
+  async fn save(&self, note: &NoteEntity) -> Result<()> {
+    let mut tags = self.tags.write().unwrap();
+    for tag in &note.tags {
+      tags.insert((note.owner_id.clone(), tag.clone(), note.note_id.clone()), note.expires_at);
+    }
+    Ok(())
+  }
+  /// Returns the number of notes having each tag, skips expired notes.
+  async fn count(&self, owner_id: &str) -> Result<BTreeMap<String, usize>> {
+    let now = OffsetDateTime::now_utc();
+    let mut counts = BTreeMap::new();
+    for ((owner, tag, _), expires_at) in self.tags.read().unwrap().iter() {
+      if owner == owner_id && expires_at.is_none_or(|expires_at| expires_at > now) {
+        *counts.entry(tag.clone()).or_default() += 1;
+      }
+    }
+    Ok(counts)
+  }
+  /// Removes all tags of the note from the index.
+  async fn delete(&self, note: &NoteEntity) -> Result<()> {
+    let mut tags = self.tags.write().unwrap();
+    for tag in &note.tags {
+      tags.remove(&(note.owner_id.clone(), tag.clone(), note.note_id.clone()));
+    }
+    Ok(())
+  }
+  /// Removes all tags from the index.
+  async fn delete_all(&self) -> Result<()> {
+    self.tags.write().unwrap().clear();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::now;
+  use time::Duration;
+
+  fn note(owner_id: &str, tags: &[&str]) -> NoteEntity {
+    let mut note = NoteEntity::new(owner_id, "title", "content", None);
+    note.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    note
+  }
+
+  #[tokio::test]
+  async fn test_count() {
+    let repository = InMemoryTagsRepository::default();
+    let first = note("alice", &["home", "work"]);
+    repository.save(&first).await.unwrap();
+    repository.save(&note("alice", &["work"])).await.unwrap();
+    repository.save(&note("bob", &["home"])).await.unwrap();
+    let mut expired = note("alice", &["home"]);
+    expired.expires_at = Some(now() - Duration::seconds(1));
+    repository.save(&expired).await.unwrap();
+    let counts = repository.count("alice").await.unwrap();
+    assert_eq!(BTreeMap::from([("home".to_string(), 1), ("work".to_string(), 2)]), counts);
+    repository.delete(&first).await.unwrap();
+    let counts = repository.count("alice").await.unwrap();
+    assert_eq!(BTreeMap::from([("work".to_string(), 1)]), counts);
+    repository.delete_all().await.unwrap();
+    assert!(repository.count("bob").await.unwrap().is_empty());
+  }
+}
@@ -0,0 +1,188 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for note timelines.
+//!
+//! The token of the next page contains the order and the key of the last listed note.
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::timelines::TimelinesRepository;
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::RwLock;
+use time::OffsetDateTime;
+
+/// Key of the note in a timeline: creation time and note identifier.
+type TimelineKey = (OffsetDateTime, String);
+
+/// In-memory repository for note timelines.
+#[derive(Default)]
+pub struct InMemoryTimelinesRepository {
+  /// Expiration times of notes, indexed by timeline and key of the note.
+  timelines: RwLock<HashMap<String, BTreeMap<TimelineKey, Option<OffsetDateTime>>>>,
+}
+
+/// Encodes the token of the page following the note with specified key.
+fn encode_token(order: SortOrder, key: &TimelineKey) -> String {
+  to_hex(format!("{}~{}~{}", order.name(), key.0.unix_timestamp_nanos(), key.1).as_bytes())
+}
+
+/// Decodes the token of the page, returns the order and the key of the last listed note.
+fn decode_token(token: &str) -> Result<(SortOrder, TimelineKey)> {
+  let decoded = from_hex(token)
+    .and_then(|bytes| String::from_utf8(bytes).ok())
+    .ok_or_else(err_invalid_page_token)?;
+  let mut parts = decoded.splitn(3, '~');
+  match (parts.next(), parts.next(), parts.next()) {
+    (Some(order), Some(nanos), Some(note_id)) => {
+      let order = order.parse().map_err(|_| err_invalid_page_token())?;
+      let nanos = nanos.parse().map_err(|_| err_invalid_page_token())?;
+      let created_at = OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|_| err_invalid_page_token())?;
+      Ok((order, (created_at, note_id.to_string())))
+    }
+    _ => Err(err_invalid_page_token()),
+  }
+}
+
+#[async_trait]
+impl TimelinesRepository for InMemoryTimelinesRepository {
+  /// Adds the note to the timeline ordered by specified time.
+  async fn save_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()> {
+    let key = (at, note.note_id.clone());
+    self
+      .timelines
+      .write()
+      .unwrap()
+      .entry(timeline.to_string())
+      .or_default()
+      .insert(key, note.expires_at);
+    Ok(())
+  }
+  /// Lists identifiers of notes from specified timelines, skips expired notes.
+  async fn list(&self, timelines: &[String], order: SortOrder, page: &PageRequest) -> Result<Page<String>> {
+    let (order, last) = match page.token.as_deref().map(decode_token).transpose()? {
+      Some((order, last)) => (order, Some(last)),
+      None => (order, None),
+    };
+    let now = OffsetDateTime::now_utc();
+    let all = self.timelines.read().unwrap();
+    let keys: BTreeSet<&TimelineKey> = timelines
+      .iter()
+      .filter_map(|timeline| all.get(timeline))
+      .flat_map(|entries| entries.iter())
+      .filter(|(_, expires_at)| expires_at.is_none_or(|expires_at| expires_at > now))
+      .map(|(key, _)| key)
+      .collect();
+    let ordered: Box<dyn Iterator<Item = &&TimelineKey>> = match (order, &last) {
+      (SortOrder::Ascending, Some(last)) => Box::new(keys.iter().filter(move |key| **key > last)),
+      (SortOrder::Ascending, None) => Box::new(keys.iter()),
+      (SortOrder::Descending, Some(last)) => Box::new(keys.iter().rev().filter(move |key| **key < last)),
+      (SortOrder::Descending, None) => Box::new(keys.iter().rev()),
+    };
+    let mut keys: Vec<&TimelineKey> = ordered.take(page.size + 1).copied().collect();
+    let has_more = keys.len() > page.size;
+    keys.truncate(page.size);
+    Ok(Page {
+      next_page: if has_more { keys.last().map(|key| encode_token(order, key)) } else { None },
+      items: keys.into_iter().map(|(_, note_id)| note_id.clone()).collect(),
+    })
+  }
+  /// Returns `true` when no notes were added to any timeline.
+  async fn is_empty(&self) -> Result<bool> {
+    Ok(self.timelines.read().unwrap().values().all(BTreeMap::is_empty))
+  }
+  /// Removes the note added with specified time from the timeline.
+  async fn delete_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()> {
+    if let Some(entries) = self.timelines.write().unwrap().get_mut(timeline) {
+      entries.remove(&(at, note.note_id.clone()));
+    }
+    Ok(())
+  }
+  /// Removes all notes from all timelines.
+  async fn delete_all(&self) -> Result<()> {
+    self.timelines.write().unwrap().clear();
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::now;
+  use time::Duration;
+
+  /// Lists all pages of the timelines, returns identifiers of listed notes.
+  async fn list_all(repository: &InMemoryTimelinesRepository, timelines: &[String], order: SortOrder, size: usize) -> Vec<String> {
+    let mut page = PageRequest { size, token: None };
+    let mut note_ids = vec![];
+    loop {
+      let result = repository.list(timelines, order, &page).await.unwrap();
+      assert!(result.items.len() <= size);
+      note_ids.extend(result.items);
+      match result.next_page {
+        Some(token) => page.token = Some(token),
+        None => return note_ids,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_list_merged_timelines() {
+    let repository = InMemoryTimelinesRepository::default();
+    let mut notes = vec![];
+    for i in 0..5 {
+      let mut note = NoteEntity::new("alice", &format!("note {}", i), "content", None);
+      note.created_at = now() + Duration::seconds(i);
+      notes.push(note);
+    }
+    repository.save("user:alice", &notes[0]).await.unwrap();
+    repository.save("user:alice", &notes[2]).await.unwrap();
+    repository.save("user:alice", &notes[3]).await.unwrap();
+    repository.save("role:user", &notes[1]).await.unwrap();
+    repository.save("role:user", &notes[3]).await.unwrap();
+    repository.save("user:bob", &notes[4]).await.unwrap();
+    let mut expired = notes[4].clone();
+    expired.expires_at = Some(now() - Duration::seconds(1));
+    repository.save("role:user", &expired).await.unwrap();
+    let timelines = ["user:alice".to_string(), "role:user".to_string()];
+    let ids = |indexes: &[usize]| indexes.iter().map(|i| notes[*i].note_id.clone()).collect::<Vec<String>>();
+    assert_eq!(ids(&[3, 2, 1, 0]), list_all(&repository, &timelines, SortOrder::Descending, 3).await);
+    assert_eq!(ids(&[0, 1, 2, 3]), list_all(&repository, &timelines, SortOrder::Ascending, 1).await);
+    repository.delete("user:alice", &notes[3]).await.unwrap();
+    assert_eq!(ids(&[3, 2, 1, 0]), list_all(&repository, &timelines, SortOrder::Descending, 2).await);
+    repository.delete("role:user", &notes[3]).await.unwrap();
+    assert_eq!(ids(&[2, 1, 0]), list_all(&repository, &timelines, SortOrder::Descending, 10).await);
+    assert!(!repository.is_empty().await.unwrap());
+    repository.delete_all().await.unwrap();
+    assert!(repository.is_empty().await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_invalid_token() {
+    let repository = InMemoryTimelinesRepository::default();
+    let page = PageRequest {
+      size: 10,
+      token: Some("invalid".to_string()),
+    };
+    assert_eq!(
+      "invalid page token",
+      repository.list(&[], SortOrder::Ascending, &page).await.unwrap_err().to_string()
+    );
+  }
+}
@@ -0,0 +1,114 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of in-memory repository for users.
+
+use crate::entities::user::UserEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::repositories::users::UsersRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// In-memory repository for users.
+#[derive(Default)]
+pub struct InMemoryUsersRepository {
+  /// Users indexed by identifier.
+  users: RwLock<HashMap<String, UserEntity>>,
+}
+
+#[async_trait]
+impl UsersRepository for InMemoryUsersRepository {
+  /// Creates a new user, fails when another user has the same login.
+  async fn create(&self, user: UserEntity) -> Result<String> {
+    let user_id = user.id();
+    let mut users = self.users.write().unwrap();
+    if users.values().any(|other| other.login == user.login) {
+      return Err(err_user_already_exists(&user.login));
+    }
+    users.insert(user_id.clone(), user);
+    Ok(user_id)
+  }
+  /// Lists all users.
+  async fn list(&self) -> Result<Vec<UserEntity>> {
+    Ok(self.users.read().unwrap().values().cloned().collect())
+  }
+  /// Searches for a user with specified identifier.
+  async fn find_by_id(&self, user_id: &str) -> Result<UserEntity> {
+    self
+      .users
+      .read()
+      .unwrap()
+      .get(user_id)
+      .cloned()
+      .ok_or_else(|| err_entity_not_found("user", user_id))
+  }
+  /// Searches for a user with specified login.
+  async fn find_by_login(&self, login: &str) -> Result<UserEntity> {
+    let users = self.users.read().unwrap();
+    users
+      .values()
+      .find(|user| user.login == login)
+      .cloned()
+      .ok_or_else(|| err_entity_not_found("user", login))
+  }
+  /// Updates an existing user, fails when another user has the same login.
+  async fn update(&self, user: UserEntity) -> Result<()> {
+    let mut users = self.users.write().unwrap();
+    if users.values().any(|other| other.login == user.login && other.user_id != user.user_id) {
+      return Err(err_user_already_exists(&user.login));
+    }
+    if let Some(existing) = users.get_mut(&user.user_id) {
+      *existing = user;
+      Ok(())
+    } else {
+      Err(err_entity_not_found("user", &user.user_id))
+    }
+  }
+  /// Deletes a user with specified identifier.
+  async fn delete(&self, user_id: &str) -> Result<()> {
+    self.users.write().unwrap().remove(user_id);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_crud() {
+    let repository = InMemoryUsersRepository::default();
+    let user_id = repository.create(UserEntity::new("alice", "secret")).await.unwrap();
+    let mut user = repository.find_by_login("alice").await.unwrap();
+    assert_eq!(user_id, user.user_id);
+    user.login = "alicja".to_string();
+    repository.update(user).await.unwrap();
+    assert!(repository.find_by_login("alice").await.is_err());
+    // logins are unique
+    let bob_id = repository.create(UserEntity::new("bob", "secret")).await.unwrap();
+    assert!(repository.create(UserEntity::new("alicja", "secret")).await.is_err());
+    let mut bob = repository.find_by_id(&bob_id).await.unwrap();
+    bob.login = "alicja".to_string();
+    assert!(repository.update(bob).await.is_err());
+    repository.delete(&bob_id).await.unwrap();
+    assert_eq!("alicja", repository.find_by_id(&user_id).await.unwrap().login);
+    repository.delete(&user_id).await.unwrap();
+    assert!(repository.list().await.unwrap().is_empty());
+    assert!(repository.update(UserEntity::new("bob", "secret")).await.is_err());
+  }
+}
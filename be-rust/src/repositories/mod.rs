@@ -19,7 +19,117 @@
 //! Repository responsibilities are:
 //! - separate database operations from Rust code,
 //! - provide common database operations for entities.
+//!
+//! Each repository is defined as a trait, implemented by every supported storage backend:
+//! - [scylla] - repositories persisted in ScyllaDB,
+//! - [memory] - repositories held in memory, used mainly for testing.
+//!
+//! Contents of attached files may be stored also in the local file system, see [filesystem].
 
+use crate::errors::*;
+use std::str::FromStr;
+
+pub mod attachments;
+pub mod blobs;
+pub mod filesystem;
+pub mod memory;
+pub mod notebooks;
 pub mod notes;
+pub mod refresh_tokens;
+pub mod revisions;
 pub mod roles;
+pub mod scylla;
+pub mod sessions;
+pub mod shares;
+pub mod tags;
+pub mod timelines;
 pub mod users;
+
+/// Default number of items in a page.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Maximum number of items in a page.
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Request for a single page of a listing.
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+  /// Maximum number of items in the page.
+  pub size: usize,
+  /// Opaque token of the requested page, returned with the previous page, `None` for the first page.
+  pub token: Option<String>,
+}
+
+impl Default for PageRequest {
+  /// Creates a request for the first page of default size.
+  fn default() -> Self {
+    Self {
+      size: DEFAULT_PAGE_SIZE,
+      token: None,
+    }
+  }
+}
+
+/// Single page of a listing.
+#[derive(Debug)]
+pub struct Page<T> {
+  /// Items of the page.
+  pub items: Vec<T>,
+  /// Opaque token of the next page, `None` when this is the last page.
+  pub next_page: Option<String>,
+}
+
+impl<T> Page<T> {
+  /// Converts items of the page, the token of the next page is retained.
+  pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+    Page {
+      items: self.items.into_iter().map(f).collect(),
+      next_page: self.next_page,
+    }
+  }
+}
+
+/// Order of items in a listing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SortOrder {
+  /// From the oldest to the newest.
+  Ascending,
+  /// From the newest to the oldest.
+  #[default]
+  Descending,
+}
+
+impl SortOrder {
+  /// Returns the name of the order.
+  pub fn name(&self) -> &'static str {
+    match self {
+      SortOrder::Ascending => "asc",
+      SortOrder::Descending => "desc",
+    }
+  }
+}
+
+impl FromStr for SortOrder {
+  type Err = NordNotesError;
+  /// Parses the order from its name.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "asc" => Ok(SortOrder::Ascending),
+      "desc" => Ok(SortOrder::Descending),
+      other => Err(err_invalid_sort_order(other)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sort_order() {
+    assert_eq!(SortOrder::Descending, SortOrder::default());
+    assert_eq!(SortOrder::Ascending, "asc".parse().unwrap());
+    assert_eq!("desc", "desc".parse::<SortOrder>().unwrap().name());
+    assert_eq!("invalid sort order, name = newest", "newest".parse::<SortOrder>().unwrap_err().to_string());
+  }
+}
@@ -0,0 +1,34 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for notebooks.
+
+use crate::entities::notebook::NotebookEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for notebooks.
+#[async_trait]
+pub trait NotebooksRepository: Send + Sync {
+  /// Saves a new or modified notebook.
+  async fn save(&self, notebook: NotebookEntity) -> Result<()>;
+  /// Lists all notebooks owned by specified user, ordered by identifier.
+  async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<NotebookEntity>>;
+  /// Searches for a notebook with specified identifier owned by specified user.
+  async fn find(&self, owner_id: &str, notebook_id: &str) -> Result<NotebookEntity>;
+  /// Deletes a notebook with specified identifier owned by specified user.
+  async fn delete(&self, owner_id: &str, notebook_id: &str) -> Result<()>;
+}
@@ -0,0 +1,34 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for refresh tokens.
+
+use crate::entities::refresh_token::RefreshTokenEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for refresh tokens.
+///
+/// Refresh tokens are saved with time to live, after which they are removed automatically.
+#[async_trait]
+pub trait RefreshTokensRepository: Send + Sync {
+  /// Saves a new refresh token, removed after `ttl` seconds.
+  async fn save(&self, token: RefreshTokenEntity, ttl: i32) -> Result<()>;
+  /// Searches for a refresh token with specified identifier.
+  async fn find(&self, token_id: &str) -> Result<RefreshTokenEntity>;
+  /// Atomically marks the refresh token as used, returns `false` when the token was already used.
+  async fn mark_used(&self, token: &RefreshTokenEntity, ttl: i32) -> Result<bool>;
+}
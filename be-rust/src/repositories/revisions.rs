@@ -0,0 +1,72 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for note revisions.
+
+use super::{Page, PageRequest, SortOrder};
+use crate::entities::note::NoteEntity;
+use crate::entities::revision::RevisionEntity;
+use crate::errors::*;
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+
+/// Repository for note revisions.
+#[async_trait]
+pub trait RevisionsRepository: Send + Sync {
+  /// Saves a revision of the note.
+  async fn save(&self, revision: RevisionEntity) -> Result<()>;
+  /// Lists a page of revisions of the note, ordered by revision number.
+  async fn list(&self, note_id: &str, order: SortOrder, page: &PageRequest) -> Result<Page<RevisionEntity>>;
+  /// Searches for a revision of the note with specified number.
+  async fn find(&self, note_id: &str, revision: i64) -> Result<RevisionEntity>;
+  /// Returns `true` when the revision of the note with specified number was saved.
+  async fn exists(&self, note_id: &str, revision: i64) -> Result<bool>;
+  /// Changes the expiration time of all revisions of the note to the expiration time of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()>;
+  /// Deletes all revisions of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()>;
+  /// Deletes all revisions of all notes.
+  async fn delete_all(&self) -> Result<()>;
+}
+
+/// Encodes the token of the page following the revision with specified number.
+pub fn encode_token(order: SortOrder, last: i64) -> String {
+  to_hex(format!("{}~{}", order.name(), last).as_bytes())
+}
+
+/// Decodes the token of the page, returns the order and the number of the last listed revision.
+pub fn decode_token(token: &str) -> Result<(SortOrder, i64)> {
+  let decoded = from_hex(token)
+    .and_then(|bytes| String::from_utf8(bytes).ok())
+    .ok_or_else(err_invalid_page_token)?;
+  let (order, last) = decoded.split_once('~').ok_or_else(err_invalid_page_token)?;
+  let order = order.parse().map_err(|_| err_invalid_page_token())?;
+  let last = last.parse().map_err(|_| err_invalid_page_token())?;
+  Ok((order, last))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_token() {
+    let token = encode_token(SortOrder::Ascending, 17);
+    assert_eq!((SortOrder::Ascending, 17), decode_token(&token).unwrap());
+    assert_eq!("invalid page token", decode_token("invalid").unwrap_err().to_string());
+    assert!(decode_token(&to_hex(b"asc~x")).is_err());
+  }
+}
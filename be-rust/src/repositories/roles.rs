@@ -14,86 +14,28 @@
  * SOFTWARE.
  */
 
-//! Implementation of database repository for notes.
+//! Definition of the repository for roles.
 
 use crate::entities::role::RoleEntity;
-use crate::entities::Entity;
 use crate::errors::*;
-use crate::storage::{KEYSPACE, TABLE_ROLES};
-use lazy_static::lazy_static;
-use scylla::{IntoTypedRows, Session, ValueList};
-use std::sync::Arc;
-
-lazy_static! {
-  static ref QUERY_CREATE: String = format!("INSERT INTO {}.{} (role_id, name) VALUES (?, ?)", KEYSPACE, TABLE_ROLES);
-  static ref QUERY_LIST: String = format!("SELECT role_id, name FROM {}.{}", KEYSPACE, TABLE_ROLES);
-  static ref QUERY_FIND_BY_ID: String = format!("SELECT role_id, name FROM {}.{} WHERE role_id = ?", KEYSPACE, TABLE_ROLES);
-  static ref QUERY_FIND_BY_NAME: String = format!("SELECT role_id, name FROM {}.{} WHERE name = ?", KEYSPACE, TABLE_ROLES);
-  static ref QUERY_DELETE_ALL: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_ROLES);
-}
+use crate::repositories::{Page, PageRequest};
+use async_trait::async_trait;
 
 /// Repository for roles.
-pub struct RolesRepository {
-  session: Arc<Session>,
-}
-
-/// Value list containing the role's identifier.
-#[derive(ValueList)]
-struct RoleId {
-  role_id: String,
-}
-
-/// Value list containing the role's name.
-#[derive(ValueList)]
-struct RoleName {
-  name: String,
-}
-
-impl RolesRepository {
-  /// Creates a new roles repository.
-  pub fn new(session: Arc<Session>) -> Self {
-    Self { session }
-  }
-  /// Creates a new note.
-  pub async fn create(&self, role: RoleEntity) -> Result<String> {
-    let values = (role.id(), role.name());
-    self.session.query(QUERY_CREATE.as_str(), values).await.map_err(err_query)?;
-    Ok(role.id())
-  }
-  /// Lists all notes.
-  pub async fn list(&self) -> Result<Vec<RoleEntity>> {
-    let mut roles = vec![];
-    if let Some(rows) = self.session.query(QUERY_LIST.as_str(), &[]).await.map_err(err_query)?.rows {
-      for row in rows.into_typed::<RoleEntity>() {
-        let role = row.map_err(err_from_row)?;
-        roles.push(role);
-      }
-    }
-    Ok(roles)
-  }
+#[async_trait]
+pub trait RolesRepository: Send + Sync {
+  /// Creates a new role, returns the identifier of the created role.
+  async fn create(&self, role: RoleEntity) -> Result<String>;
+  /// Lists all roles.
+  async fn list(&self) -> Result<Vec<RoleEntity>>;
+  /// Lists a single page of roles, in unspecified but stable order.
+  async fn list_page(&self, page: &PageRequest) -> Result<Page<RoleEntity>>;
   /// Searches for a role with specified identifier.
-  pub async fn find_by_id(&self, role_id: &str) -> Result<RoleEntity> {
-    let id = RoleId { role_id: role_id.to_string() };
-    if let Some(rows) = self.session.query(QUERY_FIND_BY_ID.as_str(), id).await.map_err(err_query)?.rows {
-      if let Some(row) = rows.into_typed::<RoleEntity>().take(1).next() {
-        return row.map_err(err_from_row);
-      }
-    }
-    Err(err_entity_not_found("role", role_id))
-  }
+  async fn find_by_id(&self, role_id: &str) -> Result<RoleEntity>;
   /// Searches for a role with specified name.
-  pub async fn find_by_name(&self, role_name: &str) -> Result<RoleEntity> {
-    let params = RoleName { name: role_name.to_string() };
-    if let Some(rows) = self.session.query(QUERY_FIND_BY_NAME.as_str(), params).await.map_err(err_query)?.rows {
-      if let Some(row) = rows.into_typed::<RoleEntity>().take(1).next() {
-        return row.map_err(err_from_row);
-      }
-    }
-    Err(err_entity_not_found("role", role_name))
-  }
-  /// Deletes all roles.
-  pub async fn delete_all(&self) -> Result<()> {
-    self.session.query(QUERY_DELETE_ALL.as_str(), &[]).await.map_err(err_query)?;
-    Ok(())
-  }
+  async fn find_by_name(&self, role_name: &str) -> Result<RoleEntity>;
+  /// Updates an existing role.
+  async fn update(&self, role: RoleEntity) -> Result<()>;
+  /// Deletes a role with specified identifier.
+  async fn delete(&self, role_id: &str) -> Result<()>;
 }
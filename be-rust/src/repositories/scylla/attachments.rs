@@ -0,0 +1,146 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for metadata of note attachments.
+//!
+//! Attachments are stored in the `note_attachments` table partitioned by note,
+//! written with the time to live of the note, so they expire together with the note.
+
+use super::{from_timestamp, to_timestamp, KEYSPACE, TABLE_NOTE_ATTACHMENTS};
+use crate::entities::attachment::AttachmentEntity;
+use crate::entities::note::{ttl_seconds, NoteEntity};
+use crate::errors::*;
+use crate::repositories::attachments::AttachmentsRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session};
+use std::sync::Arc;
+
+/// Columns of the attachments table.
+const COLUMNS: &str = "note_id, attachment_id, file_name, content_type, size, created_at, expires_at";
+
+lazy_static! {
+  static ref QUERY_INSERT: String = format!(
+    "INSERT INTO {}.{} ({}) VALUES (?, ?, ?, ?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTE_ATTACHMENTS, COLUMNS
+  );
+  static ref QUERY_LIST_BY_NOTE: String = format!("SELECT {} FROM {}.{} WHERE note_id = ?", COLUMNS, KEYSPACE, TABLE_NOTE_ATTACHMENTS);
+  static ref QUERY_FIND: String = format!(
+    "SELECT {} FROM {}.{} WHERE note_id = ? AND attachment_id = ?",
+    COLUMNS, KEYSPACE, TABLE_NOTE_ATTACHMENTS
+  );
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE note_id = ? AND attachment_id = ?", KEYSPACE, TABLE_NOTE_ATTACHMENTS);
+  static ref QUERY_DELETE_BY_NOTE: String = format!("DELETE FROM {}.{} WHERE note_id = ?", KEYSPACE, TABLE_NOTE_ATTACHMENTS);
+}
+
+/// ScyllaDB repository for metadata of note attachments.
+pub struct ScyllaAttachmentsRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the attachments table.
+#[derive(FromRow)]
+struct AttachmentRow {
+  note_id: String,
+  attachment_id: String,
+  file_name: String,
+  content_type: String,
+  size: i64,
+  created_at: Timestamp,
+  expires_at: Option<Timestamp>,
+}
+
+impl TryFrom<AttachmentRow> for AttachmentEntity {
+  type Error = NordNotesError;
+  /// Converts an [AttachmentRow] into [AttachmentEntity].
+  fn try_from(row: AttachmentRow) -> Result<Self> {
+    Ok(Self {
+      attachment_id: row.attachment_id,
+      note_id: row.note_id,
+      file_name: row.file_name,
+      content_type: row.content_type,
+      size: row.size,
+      created_at: from_timestamp(row.created_at)?,
+      expires_at: row.expires_at.map(from_timestamp).transpose()?,
+    })
+  }
+}
+
+impl ScyllaAttachmentsRepository {
+  /// Creates a new attachments repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Executes the query returning attachments.
+  async fn query_attachments(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<AttachmentEntity>> {
+    let mut attachments = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<AttachmentRow>() {
+        attachments.push(row.map_err(err_from_row)?.try_into()?);
+      }
+    }
+    Ok(attachments)
+  }
+}
+
+#[async_trait]
+impl AttachmentsRepository for ScyllaAttachmentsRepository {
+  /// Adds a new attachment, written with the time to live of the note.
+  async fn add(&self, attachment: AttachmentEntity) -> Result<()> {
+    let ttl = ttl_seconds(attachment.expires_at);
+    let values = (
+      attachment.note_id,
+      attachment.attachment_id,
+      attachment.file_name,
+      attachment.content_type,
+      attachment.size,
+      to_timestamp(attachment.created_at),
+      attachment.expires_at.map(to_timestamp),
+      ttl,
+    );
+    self.session.query(QUERY_INSERT.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Lists all attachments of the note with specified identifier.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<AttachmentEntity>> {
+    self.query_attachments(&QUERY_LIST_BY_NOTE, (note_id,)).await
+  }
+  /// Searches for an attachment of the note with specified identifier.
+  async fn find(&self, note_id: &str, attachment_id: &str) -> Result<AttachmentEntity> {
+    let attachments = self.query_attachments(&QUERY_FIND, (note_id, attachment_id)).await?;
+    attachments.into_iter().next().ok_or_else(|| err_attachment_not_found(note_id, attachment_id))
+  }
+  /// Rewrites all attachments of the note with the time to live of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()> {
+    for mut attachment in self.list_by_note(&note.note_id).await? {
+      attachment.expires_at = note.expires_at;
+      self.add(attachment).await?;
+    }
+    Ok(())
+  }
+  /// Deletes an attachment of the note with specified identifier.
+  async fn delete(&self, note_id: &str, attachment_id: &str) -> Result<()> {
+    self.session.query(QUERY_DELETE.as_str(), (note_id, attachment_id)).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes all attachments of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    self.session.query(QUERY_DELETE_BY_NOTE.as_str(), (note_id,)).await.map_err(err_query)?;
+    Ok(())
+  }
+}
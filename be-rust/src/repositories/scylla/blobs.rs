@@ -0,0 +1,126 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for contents of attached files.
+//!
+//! The content of a file is split into chunks of [CHUNK_SIZE] bytes stored in the `attachment_chunks` table,
+//! partitioned by attachment and clustered by chunk number, so ranges of the content are read
+//! without loading the whole file. Chunks are written with the time to live of the attachment,
+//! so they expire together with the note.
+
+use super::{KEYSPACE, TABLE_ATTACHMENT_CHUNKS};
+use crate::entities::attachment::AttachmentEntity;
+use crate::entities::note::ttl_seconds;
+use crate::errors::*;
+use crate::repositories::blobs::{chunks, BlobsRepository, CHUNK_SIZE};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::{IntoTypedRows, Session};
+use std::ops::Range;
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_INSERT_CHUNK: String = format!(
+    "INSERT INTO {}.{} (attachment_id, chunk, data) VALUES (?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_ATTACHMENT_CHUNKS
+  );
+  static ref QUERY_LIST_CHUNKS: String = format!("SELECT chunk, data FROM {}.{} WHERE attachment_id = ?", KEYSPACE, TABLE_ATTACHMENT_CHUNKS);
+  static ref QUERY_LIST_CHUNKS_RANGE: String = format!(
+    "SELECT chunk, data FROM {}.{} WHERE attachment_id = ? AND chunk >= ? AND chunk <= ?",
+    KEYSPACE, TABLE_ATTACHMENT_CHUNKS
+  );
+  static ref QUERY_DELETE_CHUNKS: String = format!("DELETE FROM {}.{} WHERE attachment_id = ?", KEYSPACE, TABLE_ATTACHMENT_CHUNKS);
+}
+
+/// ScyllaDB repository for contents of attached files.
+pub struct ScyllaBlobsRepository {
+  session: Arc<Session>,
+}
+
+impl ScyllaBlobsRepository {
+  /// Creates a new blobs repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Executes the query returning chunks ordered by chunk number.
+  async fn query_chunks(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<(i32, Vec<u8>)>> {
+    let mut chunks = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<(i32, Vec<u8>)>() {
+        chunks.push(row.map_err(err_from_row)?);
+      }
+    }
+    Ok(chunks)
+  }
+  /// Writes a single chunk with specified time to live.
+  async fn insert_chunk(&self, attachment_id: &str, chunk: i32, data: Vec<u8>, ttl: i32) -> Result<()> {
+    let values = (attachment_id, chunk, data, ttl);
+    self.session.query(QUERY_INSERT_CHUNK.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl BlobsRepository for ScyllaBlobsRepository {
+  /// Writes a single chunk with the time to live of the attachment.
+  async fn write_chunk(&self, attachment: &AttachmentEntity, chunk: i32, data: &[u8]) -> Result<()> {
+    self
+      .insert_chunk(&attachment.attachment_id, chunk, data.to_vec(), ttl_seconds(attachment.expires_at))
+      .await
+  }
+  /// Chunks are read only after the attachment is added, nothing needs to be completed.
+  async fn complete(&self, _attachment: &AttachmentEntity) -> Result<()> {
+    Ok(())
+  }
+  /// Reads specified range of bytes from chunks holding the range.
+  async fn read(&self, attachment: &AttachmentEntity, range: Range<u64>) -> Result<Vec<u8>> {
+    if range.is_empty() {
+      return Ok(vec![]);
+    }
+    let chunk_numbers = chunks(&range);
+    let first = *chunk_numbers.start();
+    let values = (&attachment.attachment_id, first, *chunk_numbers.end());
+    let mut content = Vec::with_capacity(chunk_numbers.count() * CHUNK_SIZE);
+    for (_, data) in self.query_chunks(&QUERY_LIST_CHUNKS_RANGE, values).await? {
+      content.extend(data);
+    }
+    // offsets of the range within the content of the first read chunk
+    let start = (range.start - first as u64 * CHUNK_SIZE as u64) as usize;
+    let end = start + (range.end - range.start) as usize;
+    content
+      .get(start..end)
+      .map(<[u8]>::to_vec)
+      .ok_or_else(|| err_attachment_not_found(&attachment.note_id, &attachment.attachment_id))
+  }
+  /// Rewrites all chunks with the time to live of the attachment.
+  async fn refresh_expiration(&self, attachment: &AttachmentEntity) -> Result<()> {
+    let ttl = ttl_seconds(attachment.expires_at);
+    for (chunk, data) in self.query_chunks(&QUERY_LIST_CHUNKS, (&attachment.attachment_id,)).await? {
+      self.insert_chunk(&attachment.attachment_id, chunk, data, ttl).await?;
+    }
+    Ok(())
+  }
+  /// Deletes all chunks of the content.
+  async fn delete(&self, attachment: &AttachmentEntity) -> Result<()> {
+    let values = (&attachment.attachment_id,);
+    self.session.query(QUERY_DELETE_CHUNKS.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Expired chunks are removed by the database, nothing is purged.
+  async fn purge_expired(&self) -> Result<usize> {
+    Ok(0)
+  }
+}
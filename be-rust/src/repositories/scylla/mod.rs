@@ -0,0 +1,303 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of repositories persisted in ScyllaDB.
+
+use crate::errors::*;
+use lazy_static::lazy_static;
+use log::info;
+use scylla::frame::value::Timestamp;
+use scylla::transport::session::PoolSize;
+use scylla::{IntoTypedRows, QueryResult, Session, SessionBuilder};
+use std::env;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+pub mod attachments;
+pub mod blobs;
+pub mod notebooks;
+pub mod notes;
+pub mod refresh_tokens;
+pub mod revisions;
+pub mod roles;
+pub mod sessions;
+pub mod shares;
+pub mod tags;
+pub mod timelines;
+pub mod users;
+
+/// Name of the keyspace.
+pub const KEYSPACE: &str = "nordnotes";
+
+/// Name of the table with notes.
+pub const TABLE_NOTES: &str = "notes";
+
+/// Name of the temporary table used while migrating notes stored by previous versions of the application.
+pub const TABLE_NOTES_MIGRATION: &str = "notes_migration";
+
+/// Name of the table with roles.
+pub const TABLE_ROLES: &str = "roles";
+
+/// Name of the table with users.
+pub const TABLE_USERS: &str = "users";
+
+/// Name of the table with logins claimed by users.
+pub const TABLE_USERS_BY_LOGIN: &str = "users_by_login";
+
+/// Name of the table with sessions.
+pub const TABLE_SESSIONS: &str = "sessions";
+
+/// Name of the table with session identifiers of users.
+pub const TABLE_SESSIONS_BY_USER: &str = "sessions_by_user";
+
+/// Name of the table with note shares.
+pub const TABLE_SHARES: &str = "shares";
+
+/// Name of the table with note shares indexed by grantee.
+pub const TABLE_SHARES_BY_GRANTEE: &str = "shares_by_grantee";
+
+/// Name of the table with note timelines, partitioned by timeline and month of creation of notes.
+pub const TABLE_NOTE_TIMELINES: &str = "note_timelines_by_month";
+
+/// Name of the table with months in which notes were added to timelines.
+pub const TABLE_NOTE_TIMELINE_MONTHS: &str = "note_timeline_months";
+
+/// Name of the table with notebooks.
+pub const TABLE_NOTEBOOKS: &str = "notebooks";
+
+/// Name of the table with tags of notes indexed by owner.
+pub const TABLE_NOTE_TAGS: &str = "note_tags";
+
+/// Name of the table with note revisions.
+pub const TABLE_NOTE_REVISIONS: &str = "note_revisions";
+
+/// Name of the table with metadata of note attachments.
+pub const TABLE_NOTE_ATTACHMENTS: &str = "note_attachments";
+
+/// Name of the table with chunks of contents of attached files.
+pub const TABLE_ATTACHMENT_CHUNKS: &str = "attachment_chunks";
+
+/// Name of the table with refresh tokens.
+pub const TABLE_REFRESH_TOKENS: &str = "refresh_tokens";
+
+/// Name of the table with names of completed data migrations.
+pub const TABLE_MIGRATIONS: &str = "migrations";
+
+/// Name of the migration claiming logins of users stored before logins were claimed.
+const MIGRATION_CLAIM_LOGINS: &str = "claim_logins";
+
+lazy_static! {
+  static ref QUERY_CREATE_KEYSPACE: String = format!(
+    "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}",
+    KEYSPACE
+  );
+  static ref QUERY_CREATE_TABLE_NOTES: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, owner_id text, title text, content text, content_format text, created_at timestamp, updated_at timestamp, version bigint, expires_at timestamp, tags set<text>, notebook_id text, deleted_at timestamp, primary key (note_id))",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_CREATE_TABLE_NOTES_MIGRATION: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, owner_id text, title text, content text, content_format text, created_at timestamp, updated_at timestamp, version bigint, expires_at timestamp, tags set<text>, notebook_id text, deleted_at timestamp, primary key (note_id))",
+    KEYSPACE, TABLE_NOTES_MIGRATION
+  );
+  static ref QUERY_CREATE_INDEX_NOTES_OWNER: String = format!("CREATE INDEX IF NOT EXISTS ON {}.{} (owner_id)", KEYSPACE, TABLE_NOTES);
+  static ref QUERY_FIND_COLUMN: String = "SELECT type FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ? AND column_name = ?".to_string();
+  static ref QUERY_CREATE_TABLE_ROLES: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (role_id text, name text, permissions set<text>, primary key (role_id))",
+    KEYSPACE, TABLE_ROLES
+  );
+  static ref QUERY_CREATE_INDEX_ROLES_NAME: String = format!("CREATE INDEX IF NOT EXISTS ON {}.{} (name)", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_CREATE_TABLE_USERS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (user_id text, login text, password text, roles set<text>, primary key (user_id))",
+    KEYSPACE, TABLE_USERS
+  );
+  static ref QUERY_CREATE_TABLE_USERS_BY_LOGIN: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (login text, user_id text, primary key (login))",
+    KEYSPACE, TABLE_USERS_BY_LOGIN
+  );
+  static ref QUERY_CREATE_TABLE_SESSIONS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (session_id text, user_id text, created_at timestamp, last_used_at timestamp, expires_at timestamp, primary key (session_id))",
+    KEYSPACE, TABLE_SESSIONS
+  );
+  static ref QUERY_CREATE_TABLE_SESSIONS_BY_USER: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (user_id text, session_id text, primary key (user_id, session_id))",
+    KEYSPACE, TABLE_SESSIONS_BY_USER
+  );
+  static ref QUERY_CREATE_TABLE_SHARES: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, grantee_type text, grantee_id text, access text, primary key (note_id, grantee_type, grantee_id))",
+    KEYSPACE, TABLE_SHARES
+  );
+  static ref QUERY_CREATE_TABLE_SHARES_BY_GRANTEE: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (grantee_type text, grantee_id text, note_id text, access text, primary key ((grantee_type, grantee_id), note_id))",
+    KEYSPACE, TABLE_SHARES_BY_GRANTEE
+  );
+  static ref QUERY_CREATE_TABLE_NOTE_TIMELINES: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (timeline text, month int, created_at timestamp, note_id text, primary key ((timeline, month), created_at, note_id)) WITH CLUSTERING ORDER BY (created_at DESC, note_id DESC)",
+    KEYSPACE, TABLE_NOTE_TIMELINES
+  );
+  static ref QUERY_CREATE_TABLE_NOTE_TIMELINE_MONTHS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (timeline text, month int, primary key (timeline, month))",
+    KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS
+  );
+  static ref QUERY_CREATE_TABLE_NOTEBOOKS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (owner_id text, notebook_id text, name text, created_at timestamp, primary key (owner_id, notebook_id))",
+    KEYSPACE, TABLE_NOTEBOOKS
+  );
+  static ref QUERY_CREATE_TABLE_NOTE_TAGS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (owner_id text, tag text, note_id text, primary key (owner_id, tag, note_id))",
+    KEYSPACE, TABLE_NOTE_TAGS
+  );
+  static ref QUERY_CREATE_TABLE_NOTE_REVISIONS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, revision bigint, author_id text, created_at timestamp, title text, content text, content_format text, expires_at timestamp, primary key (note_id, revision)) WITH CLUSTERING ORDER BY (revision DESC)",
+    KEYSPACE, TABLE_NOTE_REVISIONS
+  );
+  static ref QUERY_CREATE_TABLE_NOTE_ATTACHMENTS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, attachment_id text, file_name text, content_type text, size bigint, created_at timestamp, expires_at timestamp, primary key (note_id, attachment_id))",
+    KEYSPACE, TABLE_NOTE_ATTACHMENTS
+  );
+  static ref QUERY_CREATE_TABLE_ATTACHMENT_CHUNKS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (attachment_id text, chunk int, data blob, primary key (attachment_id, chunk))",
+    KEYSPACE, TABLE_ATTACHMENT_CHUNKS
+  );
+  static ref QUERY_CREATE_TABLE_REFRESH_TOKENS: String = format!(
+    "CREATE TABLE IF NOT EXISTS {}.{} (token_id text, session_id text, user_id text, used boolean, primary key (token_id))",
+    KEYSPACE, TABLE_REFRESH_TOKENS
+  );
+  static ref QUERY_CREATE_TABLE_MIGRATIONS: String = format!("CREATE TABLE IF NOT EXISTS {}.{} (name text, primary key (name))", KEYSPACE, TABLE_MIGRATIONS);
+  static ref QUERY_FIND_MIGRATION: String = format!("SELECT name FROM {}.{} WHERE name = ?", KEYSPACE, TABLE_MIGRATIONS);
+  static ref QUERY_INSERT_MIGRATION: String = format!("INSERT INTO {}.{} (name) VALUES (?)", KEYSPACE, TABLE_MIGRATIONS);
+}
+
+/// Connects to ScyllaDB and initializes the database structure.
+///
+/// The address of the database node is read from `SCYLLA_URI` environment variable,
+/// when not set, the default address `127.0.0.1:9042` is used.
+pub async fn connect() -> Result<Arc<Session>> {
+  let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+  // connect to database
+  let session = Arc::new(
+    SessionBuilder::new()
+      .known_node(&uri)
+      .pool_size(PoolSize::PerHost(NonZeroUsize::new(4).unwrap()))
+      .build()
+      .await
+      .map_err(err_new_session)?,
+  );
+  // initialize database structure
+  session.query(QUERY_CREATE_KEYSPACE.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_MIGRATIONS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTES.as_str(), &[]).await.map_err(err_query)?;
+  notes::migrate(&session).await?;
+  session.query(QUERY_CREATE_INDEX_NOTES_OWNER.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTE_TIMELINES.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTE_TIMELINE_MONTHS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTEBOOKS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTE_TAGS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_NOTE_REVISIONS.as_str(), &[]).await.map_err(err_query)?;
+  add_column(&session, TABLE_NOTE_REVISIONS, "content_format", "text").await?;
+  session.query(QUERY_CREATE_TABLE_NOTE_ATTACHMENTS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_ATTACHMENT_CHUNKS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_SHARES.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_SHARES_BY_GRANTEE.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_ROLES.as_str(), &[]).await.map_err(err_query)?;
+  add_column(&session, TABLE_ROLES, "permissions", "set<text>").await?;
+  session.query(QUERY_CREATE_INDEX_ROLES_NAME.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_USERS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_USERS_BY_LOGIN.as_str(), &[]).await.map_err(err_query)?;
+  // the migration is recorded only when all logins were claimed, so an interrupted migration is run again
+  if !is_migrated(&session, MIGRATION_CLAIM_LOGINS).await? {
+    users::claim_existing_logins(&session).await?;
+    session
+      .query(QUERY_INSERT_MIGRATION.as_str(), (MIGRATION_CLAIM_LOGINS,))
+      .await
+      .map_err(err_query)?;
+  }
+  session.query(QUERY_CREATE_TABLE_SESSIONS.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_SESSIONS_BY_USER.as_str(), &[]).await.map_err(err_query)?;
+  session.query(QUERY_CREATE_TABLE_REFRESH_TOKENS.as_str(), &[]).await.map_err(err_query)?;
+  info!("database initialized");
+  Ok(session)
+}
+
+/// Adds a column to the table created by a previous version of the application,
+/// does nothing when the column already exists.
+async fn add_column(session: &Session, table: &str, column: &str, column_type: &str) -> Result<()> {
+  if find_column_type(session, table, column).await?.is_none() {
+    let query = format!("ALTER TABLE {}.{} ADD {} {}", KEYSPACE, table, column, column_type);
+    session.query(query, &[]).await.map_err(err_query)?;
+  }
+  Ok(())
+}
+
+/// Returns the CQL type of the column, `None` when the table or the column does not exist.
+async fn find_column_type(session: &Session, table: &str, column: &str) -> Result<Option<String>> {
+  let values = (KEYSPACE, table, column);
+  if let Some(rows) = session.query(QUERY_FIND_COLUMN.as_str(), values).await.map_err(err_query)?.rows {
+    if let Some(row) = rows.into_typed::<(String,)>().next() {
+      return Ok(Some(row.map_err(err_from_row)?.0));
+    }
+  }
+  Ok(None)
+}
+
+/// Returns `true` when the data migration with specified name was completed.
+async fn is_migrated(session: &Session, name: &str) -> Result<bool> {
+  let result = session.query(QUERY_FIND_MIGRATION.as_str(), (name,)).await.map_err(err_query)?;
+  Ok(result.rows.is_some_and(|rows| !rows.is_empty()))
+}
+
+/// Returns `true` when the lightweight transaction was applied.
+fn is_applied(result: &QueryResult) -> bool {
+  // the first column of the lightweight transaction result is the `[applied]` flag
+  result
+    .rows
+    .as_ref()
+    .and_then(|rows| rows.first())
+    .and_then(|row| row.columns.first())
+    .and_then(|column| column.as_ref())
+    .and_then(|value| value.as_boolean())
+    .unwrap_or(false)
+}
+
+/// Converts date and time into CQL timestamp (milliseconds since unix epoch).
+pub fn to_timestamp(date_time: OffsetDateTime) -> Timestamp {
+  Timestamp(chrono::Duration::milliseconds((date_time.unix_timestamp_nanos() / 1_000_000) as i64))
+}
+
+/// Converts CQL timestamp into date and time.
+pub fn from_timestamp(timestamp: Timestamp) -> Result<OffsetDateTime> {
+  OffsetDateTime::from_unix_timestamp_nanos(timestamp.0.num_milliseconds() as i128 * 1_000_000).map_err(err_invalid_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_queries() {
+    assert_eq!(
+      "CREATE KEYSPACE IF NOT EXISTS nordnotes WITH REPLICATION = {'class' : 'SimpleStrategy', 'replication_factor' : 1}",
+      QUERY_CREATE_KEYSPACE.as_str()
+    )
+  }
+
+  #[test]
+  fn test_timestamps() {
+    let date_time = time::macros::datetime!(2022-04-01 12:30:15.123 UTC);
+    assert_eq!(1648816215123, to_timestamp(date_time).0.num_milliseconds());
+    assert_eq!(date_time, from_timestamp(to_timestamp(date_time)).unwrap());
+  }
+}
@@ -0,0 +1,114 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for notebooks.
+//!
+//! Notebooks are partitioned by owner, so all notebooks of a user are read from a single partition.
+
+use super::{from_timestamp, to_timestamp, KEYSPACE, TABLE_NOTEBOOKS};
+use crate::entities::notebook::NotebookEntity;
+use crate::errors::*;
+use crate::repositories::notebooks::NotebooksRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session};
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_SAVE: String = format!(
+    "INSERT INTO {}.{} (owner_id, notebook_id, name, created_at) VALUES (?, ?, ?, ?)",
+    KEYSPACE, TABLE_NOTEBOOKS
+  );
+  static ref QUERY_LIST_BY_OWNER: String = format!(
+    "SELECT owner_id, notebook_id, name, created_at FROM {}.{} WHERE owner_id = ?",
+    KEYSPACE, TABLE_NOTEBOOKS
+  );
+  static ref QUERY_FIND: String = format!(
+    "SELECT owner_id, notebook_id, name, created_at FROM {}.{} WHERE owner_id = ? AND notebook_id = ?",
+    KEYSPACE, TABLE_NOTEBOOKS
+  );
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE owner_id = ? AND notebook_id = ?", KEYSPACE, TABLE_NOTEBOOKS);
+}
+
+/// ScyllaDB repository for notebooks.
+pub struct ScyllaNotebooksRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the notebooks table.
+#[derive(FromRow)]
+struct NotebookRow {
+  owner_id: String,
+  notebook_id: String,
+  name: String,
+  created_at: Timestamp,
+}
+
+impl TryFrom<NotebookRow> for NotebookEntity {
+  type Error = NordNotesError;
+  /// Converts a [NotebookRow] into [NotebookEntity].
+  fn try_from(row: NotebookRow) -> Result<Self> {
+    Ok(Self {
+      notebook_id: row.notebook_id,
+      owner_id: row.owner_id,
+      name: row.name,
+      created_at: from_timestamp(row.created_at)?,
+    })
+  }
+}
+
+impl ScyllaNotebooksRepository {
+  /// Creates a new notebooks repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Executes the query returning notebooks.
+  async fn query_notebooks(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<NotebookEntity>> {
+    let mut notebooks = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<NotebookRow>() {
+        notebooks.push(row.map_err(err_from_row)?.try_into()?);
+      }
+    }
+    Ok(notebooks)
+  }
+}
+
+#[async_trait]
+impl NotebooksRepository for ScyllaNotebooksRepository {
+  /// Saves a new or modified notebook.
+  async fn save(&self, notebook: NotebookEntity) -> Result<()> {
+    let values = (notebook.owner_id, notebook.notebook_id, notebook.name, to_timestamp(notebook.created_at));
+    self.session.query(QUERY_SAVE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Lists all notebooks owned by specified user, ordered by identifier.
+  async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<NotebookEntity>> {
+    self.query_notebooks(QUERY_LIST_BY_OWNER.as_str(), (owner_id,)).await
+  }
+  /// Searches for a notebook with specified identifier owned by specified user.
+  async fn find(&self, owner_id: &str, notebook_id: &str) -> Result<NotebookEntity> {
+    let notebooks = self.query_notebooks(QUERY_FIND.as_str(), (owner_id, notebook_id)).await?;
+    notebooks.into_iter().next().ok_or_else(|| err_notebook_not_found(notebook_id))
+  }
+  /// Deletes a notebook with specified identifier owned by specified user.
+  async fn delete(&self, owner_id: &str, notebook_id: &str) -> Result<()> {
+    self.session.query(QUERY_DELETE.as_str(), (owner_id, notebook_id)).await.map_err(err_query)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,400 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for notes.
+//!
+//! Notes with limited time to live are written using native CQL TTL,
+//! so expired notes are removed by the database.
+//!
+//! Previous versions of the application stored dates and times of notes as text,
+//! such notes are migrated into timestamp columns when the application starts, see [migrate].
+
+use super::{
+  add_column, find_column_type, from_timestamp, is_applied, to_timestamp, KEYSPACE, QUERY_CREATE_TABLE_NOTES, QUERY_CREATE_TABLE_NOTES_MIGRATION, TABLE_NOTES,
+  TABLE_NOTES_MIGRATION,
+};
+use crate::entities::note::{ContentFormat, NoteEntity};
+use crate::errors::*;
+use crate::repositories::notes::NotesRepository;
+use crate::repositories::{Page, PageRequest};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use log::info;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::query::Query;
+use scylla::{Bytes, IntoTypedRows, Session, ValueList};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+lazy_static! {
+  static ref QUERY_INSERT_NOTE: String = format!(
+    "INSERT INTO {}.{} (note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_UPDATE_NOTE: String = format!(
+    "UPDATE {}.{} USING TTL ? SET owner_id = ?, title = ?, content = ?, content_format = ?, created_at = ?, updated_at = ?, version = ?, expires_at = ?, tags = ?, notebook_id = ?, deleted_at = ? WHERE note_id = ? IF created_at != null AND version = ?",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_DELETE_NOTE: String = format!("DELETE FROM {}.{} WHERE note_id = ?", KEYSPACE, TABLE_NOTES);
+  static ref QUERY_DELETE_ALL_NOTES: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_NOTES);
+  static ref QUERY_LIST_NOTES: String = format!(
+    "SELECT note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at FROM {}.{}",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_LIST_LEGACY_NOTES: String = format!(
+    "SELECT note_id, owner_id, title, content, created_at, updated_at, version, expires_at FROM {}.{}",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_LIST_NOTES_BY_OWNER: String = format!(
+    "SELECT note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at FROM {}.{} WHERE owner_id = ?",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_FIND_NOTE: String = format!(
+    "SELECT note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at FROM {}.{} WHERE note_id = ?",
+    KEYSPACE, TABLE_NOTES
+  );
+  static ref QUERY_FIND_NOTE_VERSION: String = format!("SELECT version FROM {}.{} WHERE note_id = ?", KEYSPACE, TABLE_NOTES);
+  static ref QUERY_INSERT_MIGRATED_NOTE: String = format!(
+    "INSERT INTO {}.{} (note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTES_MIGRATION
+  );
+  static ref QUERY_LIST_MIGRATED_NOTES: String = format!(
+    "SELECT note_id, owner_id, title, content, content_format, created_at, updated_at, version, expires_at, tags, notebook_id, deleted_at FROM {}.{}",
+    KEYSPACE, TABLE_NOTES_MIGRATION
+  );
+  static ref QUERY_DROP_TABLE_NOTES: String = format!("DROP TABLE IF EXISTS {}.{}", KEYSPACE, TABLE_NOTES);
+  static ref QUERY_DROP_TABLE_NOTES_MIGRATION: String = format!("DROP TABLE IF EXISTS {}.{}", KEYSPACE, TABLE_NOTES_MIGRATION);
+}
+
+/// Migrates notes stored by previous versions of the application, where dates and times were kept
+/// in text columns, into the table with timestamp columns.
+///
+/// The type of a column can not be altered, so legacy notes are copied into a temporary table,
+/// the legacy table is dropped and recreated, then notes are copied back. Every step may be repeated,
+/// so a migration interrupted by a failure is completed when the application starts again.
+/// Notes that have already expired are not migrated, the remaining notes keep their time to live.
+pub async fn migrate(session: &Session) -> Result<()> {
+  if find_column_type(session, TABLE_NOTES, "created_at").await?.as_deref() == Some("text") {
+    // legacy table may lack columns added by previous versions of the application
+    add_column(session, TABLE_NOTES, "owner_id", "text").await?;
+    add_column(session, TABLE_NOTES, "updated_at", "text").await?;
+    add_column(session, TABLE_NOTES, "version", "bigint").await?;
+    session.query(QUERY_CREATE_TABLE_NOTES_MIGRATION.as_str(), &[]).await.map_err(err_query)?;
+    if let Some(rows) = session.query(QUERY_LIST_LEGACY_NOTES.as_str(), &[]).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<LegacyNoteRow>() {
+        if let Some(note) = row.map_err(err_from_row)?.into_entity() {
+          insert_note(session, QUERY_INSERT_MIGRATED_NOTE.as_str(), note).await?;
+        }
+      }
+    }
+    session.query(QUERY_DROP_TABLE_NOTES.as_str(), &[]).await.map_err(err_query)?;
+    session.query(QUERY_CREATE_TABLE_NOTES.as_str(), &[]).await.map_err(err_query)?;
+  }
+  add_column(session, TABLE_NOTES, "tags", "set<text>").await?;
+  add_column(session, TABLE_NOTES, "notebook_id", "text").await?;
+  add_column(session, TABLE_NOTES, "deleted_at", "timestamp").await?;
+  add_column(session, TABLE_NOTES, "content_format", "text").await?;
+  if find_column_type(session, TABLE_NOTES_MIGRATION, "note_id").await?.is_some() {
+    // migration table may have been created by a previous version of the application
+    add_column(session, TABLE_NOTES_MIGRATION, "tags", "set<text>").await?;
+    add_column(session, TABLE_NOTES_MIGRATION, "notebook_id", "text").await?;
+    add_column(session, TABLE_NOTES_MIGRATION, "deleted_at", "timestamp").await?;
+    add_column(session, TABLE_NOTES_MIGRATION, "content_format", "text").await?;
+    if let Some(rows) = session.query(QUERY_LIST_MIGRATED_NOTES.as_str(), &[]).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<NoteRow>() {
+        if let Some(note) = row.map_err(err_from_row)?.into_entity()? {
+          insert_note(session, QUERY_INSERT_NOTE.as_str(), note).await?;
+        }
+      }
+    }
+    session.query(QUERY_DROP_TABLE_NOTES_MIGRATION.as_str(), &[]).await.map_err(err_query)?;
+    info!("notes migrated");
+  }
+  Ok(())
+}
+
+/// Inserts the note using specified query, the note is written with time to live derived from its expiration time.
+async fn insert_note(session: &Session, query: &str, note: NoteEntity) -> Result<()> {
+  let ttl = note.ttl_seconds();
+  let values = (
+    note.note_id,
+    note.owner_id,
+    note.title,
+    note.content,
+    note.content_format.name(),
+    to_timestamp(note.created_at),
+    to_timestamp(note.updated_at),
+    note.version,
+    note.expires_at.map(to_timestamp),
+    note.tags,
+    note.notebook_id,
+    note.deleted_at.map(to_timestamp),
+    ttl,
+  );
+  session.query(query, values).await.map_err(err_query)?;
+  Ok(())
+}
+
+/// ScyllaDB repository for notes.
+pub struct ScyllaNotesRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the notes table.
+#[derive(FromRow)]
+struct NoteRow {
+  note_id: String,
+  /// Notes created before ownership was recorded have no owner.
+  owner_id: Option<String>,
+  /// Columns are null when the row outlived its cells, which expired after the time to live was shortened.
+  title: Option<String>,
+  content: Option<String>,
+  /// Notes created before content formats were introduced have no format, their content is plain text.
+  content_format: Option<String>,
+  created_at: Option<Timestamp>,
+  /// Notes created before modifications were recorded have no modification time.
+  updated_at: Option<Timestamp>,
+  /// Notes created before versioning was introduced have no version.
+  version: Option<i64>,
+  expires_at: Option<Timestamp>,
+  /// Empty sets are stored as null.
+  tags: Option<BTreeSet<String>>,
+  notebook_id: Option<String>,
+  deleted_at: Option<Timestamp>,
+}
+
+impl NoteRow {
+  /// Converts the row into [NoteEntity], returns `None` when the note has expired.
+  fn into_entity(self) -> Result<Option<NoteEntity>> {
+    let Some(created_at) = self.created_at else {
+      return Ok(None);
+    };
+    let created_at = from_timestamp(created_at)?;
+    let note = NoteEntity {
+      note_id: self.note_id,
+      owner_id: self.owner_id.unwrap_or_default(),
+      title: self.title.unwrap_or_default(),
+      content: self.content.unwrap_or_default(),
+      content_format: self.content_format.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+      created_at,
+      updated_at: self.updated_at.map(from_timestamp).transpose()?.unwrap_or(created_at),
+      version: self.version.unwrap_or_default(),
+      expires_at: self.expires_at.map(from_timestamp).transpose()?,
+      tags: self.tags.unwrap_or_default(),
+      notebook_id: self.notebook_id,
+      deleted_at: self.deleted_at.map(from_timestamp).transpose()?,
+    };
+    // notes written before native time to live was used are expired only by date
+    Ok((!note.has_expired()).then_some(note))
+  }
+}
+
+/// Row of the notes table created by previous versions of the application,
+/// where dates and times were stored as text in format `YYYY-MM-DDThh:mm:ss` (UTC).
+#[derive(FromRow)]
+struct LegacyNoteRow {
+  note_id: String,
+  owner_id: Option<String>,
+  title: Option<String>,
+  content: Option<String>,
+  created_at: Option<String>,
+  updated_at: Option<String>,
+  version: Option<i64>,
+  expires_at: Option<String>,
+}
+
+impl LegacyNoteRow {
+  /// Converts the row into [NoteEntity], returns `None` when the note has expired
+  /// or its creation time is missing or invalid.
+  fn into_entity(self) -> Option<NoteEntity> {
+    let created_at = parse_legacy_date_time(&self.created_at?)?;
+    let note = NoteEntity {
+      note_id: self.note_id,
+      owner_id: self.owner_id.unwrap_or_default(),
+      title: self.title.unwrap_or_default(),
+      content: self.content.unwrap_or_default(),
+      content_format: ContentFormat::Plain,
+      created_at,
+      updated_at: self.updated_at.as_deref().and_then(parse_legacy_date_time).unwrap_or(created_at),
+      version: self.version.unwrap_or_default(),
+      // invalid expiration time was never recognized, so such notes did not expire
+      expires_at: self.expires_at.as_deref().and_then(parse_legacy_date_time),
+      tags: BTreeSet::new(),
+      notebook_id: None,
+      deleted_at: None,
+    };
+    (!note.has_expired()).then_some(note)
+  }
+}
+
+/// Parses the date and time stored as text by previous versions of the application.
+fn parse_legacy_date_time(date_time: &str) -> Option<OffsetDateTime> {
+  let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+  PrimitiveDateTime::parse(date_time, format).ok().map(PrimitiveDateTime::assume_utc)
+}
+
+/// Value list containing the note's identifier.
+#[derive(ValueList)]
+struct NoteId {
+  note_id: String,
+}
+
+/// Value list containing the owner's identifier.
+#[derive(ValueList)]
+struct OwnerId {
+  owner_id: String,
+}
+
+impl ScyllaNotesRepository {
+  /// Creates a new notes repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Returns the version stored in the row of the note, `None` when no version is stored.
+  async fn find_stored_version(&self, note_id: &str) -> Result<Option<i64>> {
+    let id = NoteId { note_id: note_id.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_NOTE_VERSION.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<(Option<i64>,)>().take(1).next() {
+        return Ok(row.map_err(err_from_row)?.0);
+      }
+    }
+    Err(err_note_not_found(note_id))
+  }
+  /// Executes the query returning notes, skips notes that have expired.
+  async fn query_notes(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<NoteEntity>> {
+    let mut notes = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<NoteRow>() {
+        notes.extend(row.map_err(err_from_row)?.into_entity()?);
+      }
+    }
+    Ok(notes)
+  }
+}
+
+#[async_trait]
+impl NotesRepository for ScyllaNotesRepository {
+  /// Adds a new note, written with time to live derived from its expiration time.
+  async fn add(&self, note: NoteEntity) -> Result<String> {
+    let note_id = note.note_id.clone();
+    insert_note(&self.session, QUERY_INSERT_NOTE.as_str(), note).await?;
+    Ok(note_id)
+  }
+  /// Lists a single page of notes, the token of the next page is the paging state of the database.
+  ///
+  /// Expired notes are skipped, so the page may be shorter than requested.
+  async fn list_page(&self, page: &PageRequest) -> Result<Page<NoteEntity>> {
+    let paging_state = match &page.token {
+      Some(token) => Some(Bytes::from(from_hex(token).ok_or_else(err_invalid_page_token)?)),
+      None => None,
+    };
+    let query = Query::new(QUERY_LIST_NOTES.as_str()).with_page_size(page.size as i32);
+    let result = self.session.query_paged(query, &[], paging_state).await.map_err(err_query)?;
+    let mut notes = vec![];
+    for row in result.rows.unwrap_or_default().into_typed::<NoteRow>() {
+      notes.extend(row.map_err(err_from_row)?.into_entity()?);
+    }
+    Ok(Page {
+      items: notes,
+      next_page: result.paging_state.as_deref().map(to_hex),
+    })
+  }
+  /// Lists all notes owned by specified user.
+  async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<NoteEntity>> {
+    let id = OwnerId {
+      owner_id: owner_id.to_string(),
+    };
+    self.query_notes(QUERY_LIST_NOTES_BY_OWNER.as_str(), id).await
+  }
+  /// Searches for a note with specified identified, expired notes are not found.
+  async fn find(&self, note_id: &str) -> Result<NoteEntity> {
+    let id = NoteId { note_id: note_id.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_NOTE.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<NoteRow>().take(1).next() {
+        if let Some(note) = row.map_err(err_from_row)?.into_entity()? {
+          return Ok(note);
+        }
+      }
+    }
+    Err(err_note_not_found(note_id))
+  }
+  /// Updates an existing note using lightweight transaction conditioned on the stored version.
+  ///
+  /// All columns are rewritten, so the whole note gets the time to live derived from its expiration time.
+  async fn update(&self, note: NoteEntity, expected_version: i64) -> Result<()> {
+    let note_id = note.note_id.clone();
+    let ttl = note.ttl_seconds();
+    // notes created before versioning was introduced have no version stored (null),
+    // while notes migrated from text columns have version 0 stored
+    let stored_version = if expected_version == 0 {
+      self.find_stored_version(&note_id).await?.filter(|version| *version == 0)
+    } else {
+      Some(expected_version)
+    };
+    let values = (
+      ttl,
+      note.owner_id,
+      note.title,
+      note.content,
+      note.content_format.name(),
+      to_timestamp(note.created_at),
+      to_timestamp(note.updated_at),
+      note.version,
+      note.expires_at.map(to_timestamp),
+      note.tags,
+      note.notebook_id,
+      note.deleted_at.map(to_timestamp),
+      note.note_id,
+      stored_version,
+    );
+    // the condition on `created_at` makes sure the note exists, otherwise the update would create a new row
+    let result = self.session.query(QUERY_UPDATE_NOTE.as_str(), values).await.map_err(err_query)?;
+    if is_applied(&result) {
+      return Ok(());
+    }
+    // report the currently stored version, or that the note does not exist
+    let current = self.find(&note_id).await?;
+    Err(err_version_conflict(&note_id, current.version))
+  }
+  /// Deletes a note with specified identifier.
+  async fn delete(&self, note_id: &str) -> Result<()> {
+    let id = NoteId { note_id: note_id.to_string() };
+    self.session.query(QUERY_DELETE_NOTE.as_str(), id).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes all notes.
+  async fn delete_all(&self) -> Result<()> {
+    self.session.query(QUERY_DELETE_ALL_NOTES.as_str(), &[]).await.map_err(err_query)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use time::macros::datetime;
+
+  #[test]
+  fn test_parse_legacy_date_time() {
+    assert_eq!(Some(datetime!(2022-04-01 12:30:00 UTC)), parse_legacy_date_time("2022-04-01T12:30:00"));
+    assert_eq!(None, parse_legacy_date_time("2022-04-01 12:30:00"));
+    assert_eq!(None, parse_legacy_date_time(""));
+  }
+}
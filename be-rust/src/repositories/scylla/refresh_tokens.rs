@@ -0,0 +1,112 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for refresh tokens.
+//!
+//! Refresh tokens are written with native CQL time to live, marking a token as used
+//! is a lightweight transaction, so a token can not be used twice by concurrent requests.
+
+use super::{is_applied, KEYSPACE, TABLE_REFRESH_TOKENS};
+use crate::entities::refresh_token::RefreshTokenEntity;
+use crate::errors::*;
+use crate::repositories::refresh_tokens::RefreshTokensRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session, ValueList};
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_SAVE: String = format!(
+    "INSERT INTO {}.{} (token_id, session_id, user_id, used) VALUES (?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_REFRESH_TOKENS
+  );
+  static ref QUERY_FIND: String = format!(
+    "SELECT token_id, session_id, user_id, used FROM {}.{} WHERE token_id = ?",
+    KEYSPACE, TABLE_REFRESH_TOKENS
+  );
+  static ref QUERY_MARK_USED: String = format!(
+    "UPDATE {}.{} USING TTL ? SET used = true WHERE token_id = ? IF used = false",
+    KEYSPACE, TABLE_REFRESH_TOKENS
+  );
+}
+
+/// ScyllaDB repository for refresh tokens.
+pub struct ScyllaRefreshTokensRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the refresh tokens table.
+#[derive(FromRow)]
+struct RefreshTokenRow {
+  token_id: String,
+  session_id: String,
+  user_id: String,
+  used: bool,
+}
+
+impl From<RefreshTokenRow> for RefreshTokenEntity {
+  /// Converts a [RefreshTokenRow] into [RefreshTokenEntity].
+  fn from(row: RefreshTokenRow) -> Self {
+    Self {
+      token_id: row.token_id,
+      session_id: row.session_id,
+      user_id: row.user_id,
+      used: row.used,
+    }
+  }
+}
+
+/// Value list containing the refresh token's identifier.
+#[derive(ValueList)]
+struct TokenId {
+  token_id: String,
+}
+
+impl ScyllaRefreshTokensRepository {
+  /// Creates a new refresh tokens repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+}
+
+#[async_trait]
+impl RefreshTokensRepository for ScyllaRefreshTokensRepository {
+  /// Saves a new refresh token with time to live.
+  async fn save(&self, token: RefreshTokenEntity, ttl: i32) -> Result<()> {
+    let values = (token.token_id, token.session_id, token.user_id, token.used, ttl);
+    self.session.query(QUERY_SAVE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Searches for a refresh token with specified identifier.
+  async fn find(&self, token_id: &str) -> Result<RefreshTokenEntity> {
+    let id = TokenId {
+      token_id: token_id.to_string(),
+    };
+    if let Some(rows) = self.session.query(QUERY_FIND.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<RefreshTokenRow>().take(1).next() {
+        return Ok(row.map_err(err_from_row)?.into());
+      }
+    }
+    Err(err_entity_not_found("refresh token", token_id))
+  }
+  /// Atomically marks the refresh token as used.
+  async fn mark_used(&self, token: &RefreshTokenEntity, ttl: i32) -> Result<bool> {
+    let values = (ttl, token.token_id.clone());
+    let result = self.session.query(QUERY_MARK_USED.as_str(), values).await.map_err(err_query)?;
+    Ok(is_applied(&result))
+  }
+}
@@ -0,0 +1,200 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for note revisions.
+//!
+//! Revisions of a note are stored in a single partition clustered by revision number,
+//! so pages of revisions are read by ranges of revision numbers. Revisions are written
+//! with the time to live of the note and rewritten when the expiration time of the note changes,
+//! so they expire together with the note.
+
+use super::{from_timestamp, to_timestamp, KEYSPACE, TABLE_NOTE_REVISIONS};
+use crate::entities::note::{ttl_seconds, NoteEntity};
+use crate::entities::revision::RevisionEntity;
+use crate::errors::*;
+use crate::repositories::revisions::{decode_token, encode_token, RevisionsRepository};
+use crate::repositories::{Page, PageRequest, SortOrder};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session};
+use std::sync::Arc;
+
+/// Columns of the revisions table.
+const COLUMNS: &str = "note_id, revision, author_id, created_at, title, content, content_format, expires_at";
+
+lazy_static! {
+  static ref QUERY_INSERT: String = format!(
+    "INSERT INTO {}.{} ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTE_REVISIONS, COLUMNS
+  );
+  static ref QUERY_LIST: String = format!("SELECT {} FROM {}.{} WHERE note_id = ?", COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS);
+  static ref QUERY_LIST_DESCENDING: String = format!("SELECT {} FROM {}.{} WHERE note_id = ? LIMIT ?", COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS);
+  static ref QUERY_LIST_DESCENDING_BEFORE: String = format!(
+    "SELECT {} FROM {}.{} WHERE note_id = ? AND revision < ? LIMIT ?",
+    COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS
+  );
+  static ref QUERY_LIST_ASCENDING: String = format!(
+    "SELECT {} FROM {}.{} WHERE note_id = ? ORDER BY revision ASC LIMIT ?",
+    COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS
+  );
+  static ref QUERY_LIST_ASCENDING_AFTER: String = format!(
+    "SELECT {} FROM {}.{} WHERE note_id = ? AND revision > ? ORDER BY revision ASC LIMIT ?",
+    COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS
+  );
+  static ref QUERY_FIND: String = format!(
+    "SELECT {} FROM {}.{} WHERE note_id = ? AND revision = ?",
+    COLUMNS, KEYSPACE, TABLE_NOTE_REVISIONS
+  );
+  static ref QUERY_DELETE_BY_NOTE: String = format!("DELETE FROM {}.{} WHERE note_id = ?", KEYSPACE, TABLE_NOTE_REVISIONS);
+  static ref QUERY_DELETE_ALL: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_NOTE_REVISIONS);
+}
+
+/// ScyllaDB repository for note revisions.
+pub struct ScyllaRevisionsRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the revisions table.
+#[derive(FromRow)]
+struct RevisionRow {
+  note_id: String,
+  revision: i64,
+  author_id: String,
+  created_at: Timestamp,
+  title: String,
+  content: String,
+  /// Revisions stored before content formats were introduced have no format, their content is plain text.
+  content_format: Option<String>,
+  expires_at: Option<Timestamp>,
+}
+
+impl TryFrom<RevisionRow> for RevisionEntity {
+  type Error = NordNotesError;
+  /// Converts a [RevisionRow] into [RevisionEntity].
+  fn try_from(row: RevisionRow) -> Result<Self> {
+    Ok(Self {
+      note_id: row.note_id,
+      revision: row.revision,
+      author_id: row.author_id,
+      created_at: from_timestamp(row.created_at)?,
+      title: row.title,
+      content: row.content,
+      content_format: row.content_format.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+      expires_at: row.expires_at.map(from_timestamp).transpose()?,
+    })
+  }
+}
+
+impl ScyllaRevisionsRepository {
+  /// Creates a new revisions repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Executes the query returning revisions.
+  async fn query_revisions(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<RevisionEntity>> {
+    let mut revisions = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<RevisionRow>() {
+        revisions.push(row.map_err(err_from_row)?.try_into()?);
+      }
+    }
+    Ok(revisions)
+  }
+  /// Writes the revision with specified time to live, zero means the revision never expires.
+  async fn insert(&self, revision: RevisionEntity, ttl: i32) -> Result<()> {
+    let values = (
+      revision.note_id,
+      revision.revision,
+      revision.author_id,
+      to_timestamp(revision.created_at),
+      revision.title,
+      revision.content,
+      revision.content_format.name(),
+      revision.expires_at.map(to_timestamp),
+      ttl,
+    );
+    self.session.query(QUERY_INSERT.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl RevisionsRepository for ScyllaRevisionsRepository {
+  /// Saves a revision of the note, written with the time to live of the note.
+  async fn save(&self, revision: RevisionEntity) -> Result<()> {
+    let ttl = ttl_seconds(revision.expires_at);
+    self.insert(revision, ttl).await
+  }
+  /// Lists a page of revisions of the note, the page following the last listed revision
+  /// is read using the range of revision numbers.
+  async fn list(&self, note_id: &str, order: SortOrder, page: &PageRequest) -> Result<Page<RevisionEntity>> {
+    let limit = page.size as i32 + 1;
+    let (order, mut items) = match page.token.as_deref().map(decode_token).transpose()? {
+      Some((SortOrder::Ascending, last)) => (
+        SortOrder::Ascending,
+        self.query_revisions(&QUERY_LIST_ASCENDING_AFTER, (note_id, last, limit)).await?,
+      ),
+      Some((SortOrder::Descending, last)) => (
+        SortOrder::Descending,
+        self.query_revisions(&QUERY_LIST_DESCENDING_BEFORE, (note_id, last, limit)).await?,
+      ),
+      None => match order {
+        SortOrder::Ascending => (order, self.query_revisions(&QUERY_LIST_ASCENDING, (note_id, limit)).await?),
+        SortOrder::Descending => (order, self.query_revisions(&QUERY_LIST_DESCENDING, (note_id, limit)).await?),
+      },
+    };
+    let has_more = items.len() > page.size;
+    items.truncate(page.size);
+    Ok(Page {
+      next_page: if has_more {
+        items.last().map(|revision| encode_token(order, revision.revision))
+      } else {
+        None
+      },
+      items,
+    })
+  }
+  /// Searches for a revision of the note with specified number.
+  async fn find(&self, note_id: &str, revision: i64) -> Result<RevisionEntity> {
+    let revisions = self.query_revisions(&QUERY_FIND, (note_id, revision)).await?;
+    revisions.into_iter().next().ok_or_else(|| err_revision_not_found(note_id, revision))
+  }
+  /// Returns `true` when the revision of the note with specified number was saved and has not expired.
+  async fn exists(&self, note_id: &str, revision: i64) -> Result<bool> {
+    Ok(!self.query_revisions(&QUERY_FIND, (note_id, revision)).await?.is_empty())
+  }
+  /// Rewrites all revisions of the note with the time to live of the note.
+  async fn refresh_expiration(&self, note: &NoteEntity) -> Result<()> {
+    let ttl = note.ttl_seconds();
+    for mut revision in self.query_revisions(&QUERY_LIST, (&note.note_id,)).await? {
+      revision.expires_at = note.expires_at;
+      self.insert(revision, ttl).await?;
+    }
+    Ok(())
+  }
+  /// Deletes all revisions of the note with specified identifier.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    self.session.query(QUERY_DELETE_BY_NOTE.as_str(), (note_id,)).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes all revisions of all notes.
+  async fn delete_all(&self) -> Result<()> {
+    self.session.query(QUERY_DELETE_ALL.as_str(), &[]).await.map_err(err_query)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,157 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for roles.
+
+use super::{KEYSPACE, TABLE_ROLES};
+use crate::entities::role::{Permission, RoleEntity};
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::repositories::roles::RolesRepository;
+use crate::repositories::{Page, PageRequest};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::macros::FromRow;
+use scylla::query::Query;
+use scylla::{Bytes, IntoTypedRows, Session, ValueList};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_CREATE: String = format!("INSERT INTO {}.{} (role_id, name, permissions) VALUES (?, ?, ?)", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_LIST: String = format!("SELECT role_id, name, permissions FROM {}.{}", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_FIND_BY_ID: String = format!("SELECT role_id, name, permissions FROM {}.{} WHERE role_id = ?", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_FIND_BY_NAME: String = format!("SELECT role_id, name, permissions FROM {}.{} WHERE name = ?", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_UPDATE: String = format!("UPDATE {}.{} SET name = ?, permissions = ? WHERE role_id = ?", KEYSPACE, TABLE_ROLES);
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE role_id = ?", KEYSPACE, TABLE_ROLES);
+}
+
+/// ScyllaDB repository for roles.
+pub struct ScyllaRolesRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the roles table.
+#[derive(FromRow)]
+struct RoleRow {
+  role_id: String,
+  name: String,
+  /// Empty sets are stored as nulls.
+  permissions: Option<BTreeSet<String>>,
+}
+
+impl From<RoleRow> for RoleEntity {
+  /// Converts a [RoleRow] into [RoleEntity], names of no longer defined permissions are skipped.
+  fn from(row: RoleRow) -> Self {
+    let permissions = row.permissions.unwrap_or_default().iter().filter_map(|name| name.parse().ok()).collect();
+    RoleEntity::load(row.role_id, row.name, permissions)
+  }
+}
+
+/// Returns the names of permissions granted by the role.
+fn permission_names(role: &RoleEntity) -> BTreeSet<String> {
+  role.permissions().iter().map(Permission::to_string).collect()
+}
+
+/// Value list containing the role's identifier.
+#[derive(ValueList)]
+struct RoleId {
+  role_id: String,
+}
+
+/// Value list containing the role's name.
+#[derive(ValueList)]
+struct RoleName {
+  name: String,
+}
+
+impl ScyllaRolesRepository {
+  /// Creates a new roles repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+}
+
+#[async_trait]
+impl RolesRepository for ScyllaRolesRepository {
+  /// Creates a new role.
+  async fn create(&self, role: RoleEntity) -> Result<String> {
+    let values = (role.id(), role.name(), permission_names(&role));
+    self.session.query(QUERY_CREATE.as_str(), values).await.map_err(err_query)?;
+    Ok(role.id())
+  }
+  /// Lists all roles.
+  async fn list(&self) -> Result<Vec<RoleEntity>> {
+    let mut roles = vec![];
+    if let Some(rows) = self.session.query(QUERY_LIST.as_str(), &[]).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<RoleRow>() {
+        roles.push(row.map_err(err_from_row)?.into());
+      }
+    }
+    Ok(roles)
+  }
+  /// Lists a single page of roles, the token of the next page is the paging state of the database.
+  async fn list_page(&self, page: &PageRequest) -> Result<Page<RoleEntity>> {
+    let paging_state = match &page.token {
+      Some(token) => Some(Bytes::from(from_hex(token).ok_or_else(err_invalid_page_token)?)),
+      None => None,
+    };
+    let query = Query::new(QUERY_LIST.as_str()).with_page_size(page.size as i32);
+    let result = self.session.query_paged(query, &[], paging_state).await.map_err(err_query)?;
+    let mut roles = vec![];
+    for row in result.rows.unwrap_or_default().into_typed::<RoleRow>() {
+      roles.push(row.map_err(err_from_row)?.into());
+    }
+    Ok(Page {
+      items: roles,
+      next_page: result.paging_state.as_deref().map(to_hex),
+    })
+  }
+  /// Searches for a role with specified identifier.
+  async fn find_by_id(&self, role_id: &str) -> Result<RoleEntity> {
+    let id = RoleId { role_id: role_id.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_BY_ID.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<RoleRow>().take(1).next() {
+        return Ok(row.map_err(err_from_row)?.into());
+      }
+    }
+    Err(err_entity_not_found("role", role_id))
+  }
+  /// Searches for a role with specified name.
+  async fn find_by_name(&self, role_name: &str) -> Result<RoleEntity> {
+    let params = RoleName { name: role_name.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_BY_NAME.as_str(), params).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<RoleRow>().take(1).next() {
+        return Ok(row.map_err(err_from_row)?.into());
+      }
+    }
+    Err(err_entity_not_found("role", role_name))
+  }
+  /// Updates an existing role.
+  async fn update(&self, role: RoleEntity) -> Result<()> {
+    // make sure the role exists, otherwise the update would create a new row
+    self.find_by_id(&role.id()).await?;
+    let values = (role.name(), permission_names(&role), role.id());
+    self.session.query(QUERY_UPDATE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes a role with specified identifier.
+  async fn delete(&self, role_id: &str) -> Result<()> {
+    self.session.query(QUERY_DELETE.as_str(), (role_id,)).await.map_err(err_query)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,166 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for sessions.
+//!
+//! Sessions are written with native CQL time to live, so expired sessions are removed by the database.
+//! Identifiers of sessions are additionally stored per user, to allow logging out all sessions of the user.
+
+use super::{from_timestamp, to_timestamp, KEYSPACE, TABLE_SESSIONS, TABLE_SESSIONS_BY_USER};
+use crate::entities::session::SessionEntity;
+use crate::errors::*;
+use crate::repositories::sessions::SessionsRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session, ValueList};
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_SAVE: String = format!(
+    "INSERT INTO {}.{} (session_id, user_id, created_at, last_used_at, expires_at) VALUES (?, ?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_SESSIONS
+  );
+  static ref QUERY_SAVE_BY_USER: String = format!(
+    "INSERT INTO {}.{} (user_id, session_id) VALUES (?, ?) USING TTL ?",
+    KEYSPACE, TABLE_SESSIONS_BY_USER
+  );
+  static ref QUERY_FIND: String = format!(
+    "SELECT session_id, user_id, created_at, last_used_at, expires_at FROM {}.{} WHERE session_id = ?",
+    KEYSPACE, TABLE_SESSIONS
+  );
+  static ref QUERY_LIST_BY_USER: String = format!("SELECT session_id FROM {}.{} WHERE user_id = ?", KEYSPACE, TABLE_SESSIONS_BY_USER);
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE session_id = ?", KEYSPACE, TABLE_SESSIONS);
+  static ref QUERY_DELETE_BY_USER: String = format!("DELETE FROM {}.{} WHERE user_id = ? AND session_id = ?", KEYSPACE, TABLE_SESSIONS_BY_USER);
+  static ref QUERY_DELETE_ALL_BY_USER: String = format!("DELETE FROM {}.{} WHERE user_id = ?", KEYSPACE, TABLE_SESSIONS_BY_USER);
+}
+
+/// ScyllaDB repository for sessions.
+pub struct ScyllaSessionsRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the sessions table.
+#[derive(FromRow)]
+struct SessionRow {
+  session_id: String,
+  user_id: String,
+  created_at: Timestamp,
+  last_used_at: Timestamp,
+  expires_at: Timestamp,
+}
+
+impl TryFrom<SessionRow> for SessionEntity {
+  type Error = NordNotesError;
+  /// Converts a [SessionRow] into [SessionEntity].
+  fn try_from(row: SessionRow) -> Result<Self> {
+    Ok(Self {
+      session_id: row.session_id,
+      user_id: row.user_id,
+      created_at: from_timestamp(row.created_at)?,
+      last_used_at: from_timestamp(row.last_used_at)?,
+      expires_at: from_timestamp(row.expires_at)?,
+    })
+  }
+}
+
+/// Value list containing the session's identifier.
+#[derive(ValueList)]
+struct SessionId {
+  session_id: String,
+}
+
+/// Value list containing the user's identifier.
+#[derive(ValueList)]
+struct UserId {
+  user_id: String,
+}
+
+impl ScyllaSessionsRepository {
+  /// Creates a new sessions repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+}
+
+#[async_trait]
+impl SessionsRepository for ScyllaSessionsRepository {
+  /// Saves the session with time to live.
+  async fn save(&self, session: SessionEntity, ttl: i32) -> Result<()> {
+    let values = (session.user_id.clone(), session.session_id.clone(), ttl);
+    self.session.query(QUERY_SAVE_BY_USER.as_str(), values).await.map_err(err_query)?;
+    let values = (
+      session.session_id,
+      session.user_id,
+      to_timestamp(session.created_at),
+      to_timestamp(session.last_used_at),
+      to_timestamp(session.expires_at),
+      ttl,
+    );
+    self.session.query(QUERY_SAVE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Searches for a session with specified identifier.
+  async fn find(&self, session_id: &str) -> Result<SessionEntity> {
+    let id = SessionId {
+      session_id: session_id.to_string(),
+    };
+    if let Some(rows) = self.session.query(QUERY_FIND.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<SessionRow>().take(1).next() {
+        return row.map_err(err_from_row)?.try_into();
+      }
+    }
+    Err(err_entity_not_found("session", session_id))
+  }
+  /// Lists all sessions of the user.
+  async fn list_by_user(&self, user_id: &str) -> Result<Vec<SessionEntity>> {
+    let mut sessions = vec![];
+    let id = UserId { user_id: user_id.to_string() };
+    if let Some(rows) = self.session.query(QUERY_LIST_BY_USER.as_str(), id).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<(String,)>() {
+        let (session_id,) = row.map_err(err_from_row)?;
+        // the session may have expired in the meantime
+        if let Ok(session) = self.find(&session_id).await {
+          sessions.push(session);
+        }
+      }
+    }
+    Ok(sessions)
+  }
+  /// Deletes a session.
+  async fn delete(&self, session: &SessionEntity) -> Result<()> {
+    let values = (session.user_id.clone(), session.session_id.clone());
+    self.session.query(QUERY_DELETE_BY_USER.as_str(), values).await.map_err(err_query)?;
+    let id = SessionId {
+      session_id: session.session_id.clone(),
+    };
+    self.session.query(QUERY_DELETE.as_str(), id).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes all sessions of the user.
+  async fn delete_by_user(&self, user_id: &str) -> Result<()> {
+    for session in self.list_by_user(user_id).await? {
+      let id = SessionId {
+        session_id: session.session_id,
+      };
+      self.session.query(QUERY_DELETE.as_str(), id).await.map_err(err_query)?;
+    }
+    let id = UserId { user_id: user_id.to_string() };
+    self.session.query(QUERY_DELETE_ALL_BY_USER.as_str(), id).await.map_err(err_query)?;
+    Ok(())
+  }
+}
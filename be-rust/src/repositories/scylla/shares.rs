@@ -0,0 +1,151 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for note shares.
+//!
+//! Every share is stored twice: in a table partitioned by note (to list who has access to the note)
+//! and in a table partitioned by grantee (to list notes shared with a user or role).
+
+use super::{KEYSPACE, TABLE_SHARES, TABLE_SHARES_BY_GRANTEE};
+use crate::entities::share::{GranteeType, ShareEntity};
+use crate::errors::*;
+use crate::repositories::shares::SharesRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session, ValueList};
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_SAVE: String = format!(
+    "INSERT INTO {}.{} (note_id, grantee_type, grantee_id, access) VALUES (?, ?, ?, ?)",
+    KEYSPACE, TABLE_SHARES
+  );
+  static ref QUERY_SAVE_BY_GRANTEE: String = format!(
+    "INSERT INTO {}.{} (grantee_type, grantee_id, note_id, access) VALUES (?, ?, ?, ?)",
+    KEYSPACE, TABLE_SHARES_BY_GRANTEE
+  );
+  static ref QUERY_LIST_BY_NOTE: String = format!(
+    "SELECT note_id, grantee_type, grantee_id, access FROM {}.{} WHERE note_id = ?",
+    KEYSPACE, TABLE_SHARES
+  );
+  static ref QUERY_LIST_BY_GRANTEE: String = format!(
+    "SELECT note_id, grantee_type, grantee_id, access FROM {}.{} WHERE grantee_type = ? AND grantee_id = ?",
+    KEYSPACE, TABLE_SHARES_BY_GRANTEE
+  );
+  static ref QUERY_DELETE: String = format!(
+    "DELETE FROM {}.{} WHERE note_id = ? AND grantee_type = ? AND grantee_id = ?",
+    KEYSPACE, TABLE_SHARES
+  );
+  static ref QUERY_DELETE_BY_GRANTEE: String = format!(
+    "DELETE FROM {}.{} WHERE grantee_type = ? AND grantee_id = ? AND note_id = ?",
+    KEYSPACE, TABLE_SHARES_BY_GRANTEE
+  );
+  static ref QUERY_DELETE_ALL: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_SHARES);
+  static ref QUERY_DELETE_ALL_BY_GRANTEE: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_SHARES_BY_GRANTEE);
+}
+
+/// ScyllaDB repository for note shares.
+pub struct ScyllaSharesRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the shares tables.
+#[derive(FromRow)]
+struct ShareRow {
+  note_id: String,
+  grantee_type: String,
+  grantee_id: String,
+  access: String,
+}
+
+impl TryFrom<ShareRow> for ShareEntity {
+  type Error = NordNotesError;
+  /// Converts a [ShareRow] into [ShareEntity].
+  fn try_from(row: ShareRow) -> Result<Self> {
+    Ok(Self {
+      note_id: row.note_id,
+      grantee_type: row.grantee_type.parse()?,
+      grantee_id: row.grantee_id,
+      access: row.access.parse()?,
+    })
+  }
+}
+
+/// Value list containing the note's identifier.
+#[derive(ValueList)]
+struct NoteId {
+  note_id: String,
+}
+
+impl ScyllaSharesRepository {
+  /// Creates a new shares repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Executes the query returning shares.
+  async fn query_shares(&self, query: &str, values: impl scylla::frame::value::ValueList) -> Result<Vec<ShareEntity>> {
+    let mut shares = vec![];
+    if let Some(rows) = self.session.query(query, values).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<ShareRow>() {
+        shares.push(row.map_err(err_from_row)?.try_into()?);
+      }
+    }
+    Ok(shares)
+  }
+}
+
+#[async_trait]
+impl SharesRepository for ScyllaSharesRepository {
+  /// Saves the share.
+  async fn save(&self, share: ShareEntity) -> Result<()> {
+    let values = (share.grantee_type.name(), &share.grantee_id, &share.note_id, share.access.name());
+    self.session.query(QUERY_SAVE_BY_GRANTEE.as_str(), values).await.map_err(err_query)?;
+    let values = (&share.note_id, share.grantee_type.name(), &share.grantee_id, share.access.name());
+    self.session.query(QUERY_SAVE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Lists all shares of the note.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<ShareEntity>> {
+    let id = NoteId { note_id: note_id.to_string() };
+    self.query_shares(QUERY_LIST_BY_NOTE.as_str(), id).await
+  }
+  /// Lists all shares granted to specified grantee.
+  async fn list_by_grantee(&self, grantee_type: GranteeType, grantee_id: &str) -> Result<Vec<ShareEntity>> {
+    self.query_shares(QUERY_LIST_BY_GRANTEE.as_str(), (grantee_type.name(), grantee_id)).await
+  }
+  /// Deletes the share of the note granted to specified grantee.
+  async fn delete(&self, note_id: &str, grantee_type: GranteeType, grantee_id: &str) -> Result<()> {
+    let values = (note_id, grantee_type.name(), grantee_id);
+    self.session.query(QUERY_DELETE.as_str(), values).await.map_err(err_query)?;
+    let values = (grantee_type.name(), grantee_id, note_id);
+    self.session.query(QUERY_DELETE_BY_GRANTEE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Deletes all shares of the note.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()> {
+    for share in self.list_by_note(note_id).await? {
+      self.delete(note_id, share.grantee_type, &share.grantee_id).await?;
+    }
+    Ok(())
+  }
+  /// Deletes all shares.
+  async fn delete_all(&self) -> Result<()> {
+    self.session.query(QUERY_DELETE_ALL.as_str(), &[]).await.map_err(err_query)?;
+    self.session.query(QUERY_DELETE_ALL_BY_GRANTEE.as_str(), &[]).await.map_err(err_query)?;
+    Ok(())
+  }
+}
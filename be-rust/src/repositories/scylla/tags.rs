@@ -0,0 +1,89 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for note tags.
+//!
+//! Tags are partitioned by the owner of notes and clustered by tag, so the tags used by the user
+//! are counted by reading a single partition. Entries are written with the time to live of the note,
+//! so they expire together with the note.
+
+use super::{KEYSPACE, TABLE_NOTE_TAGS};
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::tags::TagsRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::{IntoTypedRows, Session};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_INSERT: String = format!(
+    "INSERT INTO {}.{} (owner_id, tag, note_id) VALUES (?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTE_TAGS
+  );
+  static ref QUERY_LIST_TAGS: String = format!("SELECT tag FROM {}.{} WHERE owner_id = ?", KEYSPACE, TABLE_NOTE_TAGS);
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE owner_id = ? AND tag = ? AND note_id = ?", KEYSPACE, TABLE_NOTE_TAGS);
+  static ref QUERY_DELETE_ALL: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_NOTE_TAGS);
+}
+
+/// ScyllaDB repository for note tags.
+pub struct ScyllaTagsRepository {
+  session: Arc<Session>,
+}
+
+impl ScyllaTagsRepository {
+  /// Creates a new tags repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+}
+
+#[async_trait]
+impl TagsRepository for ScyllaTagsRepository {
+  /// Indexes all tags of the note, written with the time to live of the note.
+  async fn save(&self, note: &NoteEntity) -> Result<()> {
+    let ttl = note.ttl_seconds();
+    for tag in &note.tags {
+      let values = (&note.owner_id, tag, &note.note_id, ttl);
+      self.session.query(QUERY_INSERT.as_str(), values).await.map_err(err_query)?;
+    }
+    Ok(())
+  }
+  /// Returns the number of notes having each tag, for all tags of notes owned by specified user.
+  async fn count(&self, owner_id: &str) -> Result<BTreeMap<String, usize>> {
+    let mut counts = BTreeMap::new();
+    if let Some(rows) = self.session.query(QUERY_LIST_TAGS.as_str(), (owner_id,)).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<(String,)>() {
+        *counts.entry(row.map_err(err_from_row)?.0).or_default() += 1;
+      }
+    }
+    Ok(counts)
+  }
+  /// Removes all tags of the note from the index.
+  async fn delete(&self, note: &NoteEntity) -> Result<()> {
+    for tag in &note.tags {
+      let values = (&note.owner_id, tag, &note.note_id);
+      self.session.query(QUERY_DELETE.as_str(), values).await.map_err(err_query)?;
+    }
+    Ok(())
+  }
+  /// Removes all tags from the index.
+  async fn delete_all(&self) -> Result<()> {
+    self.session.query(QUERY_DELETE_ALL.as_str(), &[]).await.map_err(err_query)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,429 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for note timelines.
+//!
+//! Every timeline is split into partitions by month of creation of notes (or of the time notes were added with),
+//! each partition is clustered by that time, so no partition grows without limit. Entries are written with the time
+//! to live of the note, so they expire together with the note. Months in which notes were added
+//! to the timeline are recorded in a separate table, so listing visits only months having notes.
+//!
+//! Timelines are listed month by month, using paging state of the database within a month.
+//! When more than one timeline is listed, the timelines are merged, and for each of them the token
+//! of the next page records the listed month, the paging state of the fetched page and the number
+//! of rows of that page already listed.
+
+use super::{from_timestamp, to_timestamp, KEYSPACE, TABLE_NOTE_TIMELINES, TABLE_NOTE_TIMELINE_MONTHS};
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::timelines::TimelinesRepository;
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::utils::{from_hex, to_hex};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::frame::value::Timestamp;
+use scylla::macros::FromRow;
+use scylla::query::Query;
+use scylla::{Bytes, IntoTypedRows, Session};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+lazy_static! {
+  static ref QUERY_INSERT: String = format!(
+    "INSERT INTO {}.{} (timeline, month, created_at, note_id) VALUES (?, ?, ?, ?) USING TTL ?",
+    KEYSPACE, TABLE_NOTE_TIMELINES
+  );
+  static ref QUERY_INSERT_MONTH: String = format!("INSERT INTO {}.{} (timeline, month) VALUES (?, ?)", KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS);
+  static ref QUERY_LIST_DESCENDING: String = format!(
+    "SELECT created_at, note_id FROM {}.{} WHERE timeline = ? AND month = ?",
+    KEYSPACE, TABLE_NOTE_TIMELINES
+  );
+  static ref QUERY_LIST_ASCENDING: String = format!(
+    "SELECT created_at, note_id FROM {}.{} WHERE timeline = ? AND month = ? ORDER BY created_at ASC, note_id ASC",
+    KEYSPACE, TABLE_NOTE_TIMELINES
+  );
+  static ref QUERY_FIRST_MONTH_DESCENDING: String = format!(
+    "SELECT month FROM {}.{} WHERE timeline = ? ORDER BY month DESC LIMIT 1",
+    KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS
+  );
+  static ref QUERY_FIRST_MONTH_ASCENDING: String = format!(
+    "SELECT month FROM {}.{} WHERE timeline = ? ORDER BY month ASC LIMIT 1",
+    KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS
+  );
+  static ref QUERY_NEXT_MONTH_DESCENDING: String = format!(
+    "SELECT month FROM {}.{} WHERE timeline = ? AND month < ? ORDER BY month DESC LIMIT 1",
+    KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS
+  );
+  static ref QUERY_NEXT_MONTH_ASCENDING: String = format!(
+    "SELECT month FROM {}.{} WHERE timeline = ? AND month > ? ORDER BY month ASC LIMIT 1",
+    KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS
+  );
+  static ref QUERY_FIND_ANY: String = format!("SELECT note_id FROM {}.{} LIMIT 1", KEYSPACE, TABLE_NOTE_TIMELINES);
+  static ref QUERY_DELETE: String = format!(
+    "DELETE FROM {}.{} WHERE timeline = ? AND month = ? AND created_at = ? AND note_id = ?",
+    KEYSPACE, TABLE_NOTE_TIMELINES
+  );
+  static ref QUERY_DELETE_ALL: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_NOTE_TIMELINES);
+  static ref QUERY_DELETE_ALL_MONTHS: String = format!("TRUNCATE TABLE {}.{}", KEYSPACE, TABLE_NOTE_TIMELINE_MONTHS);
+}
+
+/// Key of the note in a timeline: creation time and note identifier.
+type TimelineKey = (OffsetDateTime, String);
+
+/// Returns the month of specified date and time, counted from the year zero,
+/// used as a part of the partition key of timelines.
+fn month(date_time: OffsetDateTime) -> i32 {
+  date_time.year() * 12 + date_time.month() as i32 - 1
+}
+
+/// Returns the month following or preceding (depending on order) specified month,
+/// in which notes were added to the timeline, or the first such month when no month is specified.
+async fn find_month(session: &Session, timeline: &str, order: SortOrder, after: Option<i32>) -> Result<Option<i32>> {
+  let result = match (order, after) {
+    (SortOrder::Ascending, None) => session.query(QUERY_FIRST_MONTH_ASCENDING.as_str(), (timeline,)).await,
+    (SortOrder::Descending, None) => session.query(QUERY_FIRST_MONTH_DESCENDING.as_str(), (timeline,)).await,
+    (SortOrder::Ascending, Some(month)) => session.query(QUERY_NEXT_MONTH_ASCENDING.as_str(), (timeline, month)).await,
+    (SortOrder::Descending, Some(month)) => session.query(QUERY_NEXT_MONTH_DESCENDING.as_str(), (timeline, month)).await,
+  }
+  .map_err(err_query)?;
+  match result.rows.unwrap_or_default().into_typed::<(i32,)>().next() {
+    Some(row) => Ok(Some(row.map_err(err_from_row)?.0)),
+    None => Ok(None),
+  }
+}
+
+/// Row of the timelines table.
+#[derive(FromRow)]
+struct TimelineRow {
+  created_at: Timestamp,
+  note_id: String,
+}
+
+/// Position of the listing in a single timeline.
+struct Cursor {
+  /// Name of the timeline.
+  timeline: String,
+  /// Listed month of the timeline, `None` until the first month is found.
+  month: Option<i32>,
+  /// Paging state used to fetch the current page, `None` for the first page.
+  paging_state: Option<Bytes>,
+  /// Number of rows of the current page already listed.
+  skip: usize,
+  /// Rows of the current page not listed yet, empty until the page is fetched.
+  rows: VecDeque<TimelineKey>,
+  /// `true` when the current page was fetched.
+  fetched: bool,
+  /// Paging state of the page following the current page.
+  next_paging_state: Option<Bytes>,
+  /// `true` when all rows of the timeline were listed.
+  finished: bool,
+}
+
+impl Cursor {
+  /// Creates a cursor positioned at the beginning of the timeline.
+  fn new(timeline: &str) -> Self {
+    Self {
+      timeline: timeline.to_string(),
+      month: None,
+      paging_state: None,
+      skip: 0,
+      rows: VecDeque::new(),
+      fetched: false,
+      next_paging_state: None,
+      finished: false,
+    }
+  }
+  /// Returns the key of the next row of the timeline, fetching pages and moving to next months when needed.
+  async fn head(&mut self, session: &Session, order: SortOrder, query: &Query) -> Result<Option<&TimelineKey>> {
+    while self.rows.is_empty() && !self.finished {
+      if self.month.is_none() || self.fetched {
+        // all rows of the current page were listed, move to the next page or to the next month
+        if let Some(paging_state) = self.next_paging_state.take() {
+          self.paging_state = Some(paging_state);
+        } else {
+          match find_month(session, &self.timeline, order, self.month).await? {
+            Some(month) => {
+              self.month = Some(month);
+              self.paging_state = None;
+            }
+            None => self.finished = true,
+          }
+        }
+        self.skip = 0;
+        self.fetched = false;
+        continue;
+      }
+      let result = session
+        .query_paged(query.clone(), (&self.timeline, self.month), self.paging_state.clone())
+        .await
+        .map_err(err_query)?;
+      for row in result.rows.unwrap_or_default().into_typed::<TimelineRow>().skip(self.skip) {
+        let row = row.map_err(err_from_row)?;
+        self.rows.push_back((from_timestamp(row.created_at)?, row.note_id));
+      }
+      self.next_paging_state = result.paging_state;
+      self.fetched = true;
+    }
+    Ok(self.rows.front())
+  }
+  /// Removes the next row of the timeline, the row must be fetched with [Cursor::head] before.
+  fn pop(&mut self) -> Option<TimelineKey> {
+    let key = self.rows.pop_front()?;
+    self.skip += 1;
+    Some(key)
+  }
+  /// Encodes the position of the cursor.
+  fn encode(&self) -> String {
+    if self.finished {
+      format!("{}.-", self.timeline)
+    } else {
+      let month = self.month.map(|month| month.to_string()).unwrap_or_default();
+      let paging_state = self.paging_state.as_deref().map(to_hex).unwrap_or_default();
+      format!("{}.{}.{}.{}", self.timeline, month, paging_state, self.skip)
+    }
+  }
+  /// Decodes the position of the cursor in specified timeline.
+  fn decode(timeline: &str, value: &str) -> Option<Self> {
+    let mut cursor = Cursor::new(timeline);
+    let mut parts = value.strip_prefix(timeline)?.strip_prefix('.')?.split('.');
+    match (parts.next()?, parts.next(), parts.next(), parts.next()) {
+      ("-", None, None, None) => cursor.finished = true,
+      (month, Some(paging_state), Some(skip), None) => {
+        if !month.is_empty() {
+          cursor.month = Some(month.parse().ok()?);
+        }
+        if !paging_state.is_empty() {
+          cursor.paging_state = Some(Bytes::from(from_hex(paging_state)?));
+        }
+        cursor.skip = skip.parse().ok()?;
+      }
+      _ => return None,
+    }
+    Some(cursor)
+  }
+}
+
+/// State of the listing of merged timelines, encoded in the token of the next page.
+struct Listing {
+  /// Order of the listed notes.
+  order: SortOrder,
+  /// Number of rows fetched from a timeline at once.
+  fetch_size: usize,
+  /// Key of the last listed note, used to skip notes present in more than one timeline.
+  last: Option<TimelineKey>,
+  /// Positions in listed timelines.
+  cursors: Vec<Cursor>,
+}
+
+impl Listing {
+  /// Creates a listing of the first page of specified timelines.
+  fn new(timelines: &[String], order: SortOrder, fetch_size: usize) -> Self {
+    Self {
+      order,
+      fetch_size,
+      last: None,
+      cursors: timelines.iter().map(|timeline| Cursor::new(timeline)).collect(),
+    }
+  }
+  /// Encodes the listing into the token of the next page.
+  fn encode(&self) -> String {
+    let mut parts = vec![self.order.name().to_string(), self.fetch_size.to_string()];
+    parts.push(match &self.last {
+      Some((created_at, note_id)) => format!("{}.{}", to_timestamp(*created_at).0.num_milliseconds(), note_id),
+      None => String::new(),
+    });
+    parts.extend(self.cursors.iter().map(Cursor::encode));
+    // every part is encoded separately, so timeline names may contain any characters
+    parts.iter().map(|part| to_hex(part.as_bytes())).collect::<Vec<String>>().join(".")
+  }
+  /// Decodes the listing of specified timelines from the token of the page.
+  fn decode(timelines: &[String], token: &str) -> Option<Self> {
+    let decoded = token
+      .split('.')
+      .map(|part| String::from_utf8(from_hex(part)?).ok())
+      .collect::<Option<Vec<String>>>()?;
+    let parts: Vec<&str> = decoded.iter().map(String::as_str).collect();
+    if parts.len() != timelines.len() + 3 {
+      return None;
+    }
+    let order = parts[0].parse().ok()?;
+    let fetch_size = parts[1].parse().ok().filter(|size| *size > 0)?;
+    let last = match parts[2].split_once('.') {
+      Some((millis, note_id)) => Some((
+        from_timestamp(Timestamp(chrono::Duration::milliseconds(millis.parse().ok()?))).ok()?,
+        note_id.to_string(),
+      )),
+      None if parts[2].is_empty() => None,
+      None => return None,
+    };
+    let cursors = timelines
+      .iter()
+      .zip(&parts[3..])
+      .map(|(timeline, value)| Cursor::decode(timeline, value))
+      .collect::<Option<Vec<Cursor>>>()?;
+    Some(Self {
+      order,
+      fetch_size,
+      last,
+      cursors,
+    })
+  }
+}
+
+/// Returns `true` when the key should be listed before the other key in specified order.
+fn precedes(order: SortOrder, key: &TimelineKey, other: &TimelineKey) -> bool {
+  match order {
+    SortOrder::Ascending => key < other,
+    SortOrder::Descending => key > other,
+  }
+}
+
+/// ScyllaDB repository for note timelines.
+pub struct ScyllaTimelinesRepository {
+  session: Arc<Session>,
+}
+
+impl ScyllaTimelinesRepository {
+  /// Creates a new timelines repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+}
+
+#[async_trait]
+impl TimelinesRepository for ScyllaTimelinesRepository {
+  /// Adds the note to the timeline ordered by specified time, the entry gets the time to live derived
+  /// from expiration time of the note.
+  ///
+  /// The month of the entry is recorded without time to live, months left without notes are skipped when listed.
+  async fn save_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()> {
+    let month = month(at);
+    self.session.query(QUERY_INSERT_MONTH.as_str(), (timeline, month)).await.map_err(err_query)?;
+    let values = (timeline, month, to_timestamp(at), &note.note_id, note.ttl_seconds());
+    self.session.query(QUERY_INSERT.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Lists identifiers of notes from specified timelines, merged by creation time.
+  ///
+  /// Notes are read from every timeline in pages of the size of the first requested page,
+  /// the order and the size of fetched pages are retained in the token of the next page.
+  async fn list(&self, timelines: &[String], order: SortOrder, page: &PageRequest) -> Result<Page<String>> {
+    let mut listing = match &page.token {
+      Some(token) => Listing::decode(timelines, token).ok_or_else(err_invalid_page_token)?,
+      None => Listing::new(timelines, order, page.size),
+    };
+    let query_text = match listing.order {
+      SortOrder::Ascending => QUERY_LIST_ASCENDING.as_str(),
+      SortOrder::Descending => QUERY_LIST_DESCENDING.as_str(),
+    };
+    let query = Query::new(query_text).with_page_size(listing.fetch_size as i32);
+    let mut note_ids = vec![];
+    loop {
+      // find the timeline with the note to be listed next
+      let mut next: Option<(usize, TimelineKey)> = None;
+      for (index, cursor) in listing.cursors.iter_mut().enumerate() {
+        if let Some(key) = cursor.head(&self.session, listing.order, &query).await? {
+          if next.as_ref().is_none_or(|(_, next_key)| precedes(listing.order, key, next_key)) {
+            next = Some((index, key.clone()));
+          }
+        }
+      }
+      let Some((index, key)) = next else {
+        // all timelines were listed
+        return Ok(Page {
+          items: note_ids,
+          next_page: None,
+        });
+      };
+      if listing.last.as_ref() == Some(&key) {
+        // the note was already listed from another timeline
+        listing.cursors[index].pop();
+        continue;
+      }
+      if note_ids.len() == page.size {
+        return Ok(Page {
+          items: note_ids,
+          next_page: Some(listing.encode()),
+        });
+      }
+      listing.cursors[index].pop();
+      note_ids.push(key.1.clone());
+      listing.last = Some(key);
+    }
+  }
+  /// Returns `true` when no notes were added to any timeline.
+  async fn is_empty(&self) -> Result<bool> {
+    let result = self.session.query(QUERY_FIND_ANY.as_str(), &[]).await.map_err(err_query)?;
+    Ok(result.rows.unwrap_or_default().is_empty())
+  }
+  /// Removes the note added with specified time from the timeline.
+  async fn delete_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()> {
+    let values = (timeline, month(at), to_timestamp(at), &note.note_id);
+    self.session.query(QUERY_DELETE.as_str(), values).await.map_err(err_query)?;
+    Ok(())
+  }
+  /// Removes all notes from all timelines.
+  async fn delete_all(&self) -> Result<()> {
+    self.session.query(QUERY_DELETE_ALL.as_str(), &[]).await.map_err(err_query)?;
+    self.session.query(QUERY_DELETE_ALL_MONTHS.as_str(), &[]).await.map_err(err_query)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use time::macros::datetime;
+
+  #[test]
+  fn test_token() {
+    let timelines = vec!["user:alice".to_string(), "role:user".to_string(), "tag:alice:a~b.c".to_string()];
+    let mut listing = Listing::new(&timelines, SortOrder::Ascending, 20);
+    listing.last = Some((datetime!(2022-04-01 12:30:15.123 UTC), "note".to_string()));
+    listing.cursors[0].month = Some(month(datetime!(2022-04-01 12:30:15.123 UTC)));
+    listing.cursors[0].paging_state = Some(Bytes::from_static(&[1, 2, 255]));
+    listing.cursors[0].skip = 7;
+    listing.cursors[1].finished = true;
+    listing.cursors[2].month = Some(24267);
+    let token = listing.encode();
+    let decoded = Listing::decode(&timelines, &token).unwrap();
+    assert_eq!(SortOrder::Ascending, decoded.order);
+    assert_eq!(20, decoded.fetch_size);
+    assert_eq!(listing.last, decoded.last);
+    assert_eq!(Some(24267), decoded.cursors[0].month);
+    assert_eq!(None, decoded.cursors[1].month);
+    assert_eq!(Some(Bytes::from_static(&[1, 2, 255])), decoded.cursors[0].paging_state);
+    assert_eq!(7, decoded.cursors[0].skip);
+    assert!(!decoded.cursors[0].finished);
+    assert!(decoded.cursors[1].finished);
+    assert_eq!(Some(24267), decoded.cursors[2].month);
+    assert_eq!(token, decoded.encode());
+    // token is valid only for the same timelines
+    assert!(Listing::decode(&timelines[..1], &token).is_none());
+    let other = ["user:bob".to_string(), "role:user".to_string(), "tag:alice:a~b.c".to_string()];
+    assert!(Listing::decode(&other, &token).is_none());
+    assert!(Listing::decode(&timelines, "invalid").is_none());
+  }
+
+  #[test]
+  fn test_month() {
+    assert_eq!(24267, month(datetime!(2022-04-01 00:00:00 UTC)));
+    assert_eq!(24267, month(datetime!(2022-04-30 23:59:59.999 UTC)));
+    assert_eq!(24268, month(datetime!(2022-05-01 00:00:00 UTC)));
+    assert_eq!(24276, month(datetime!(2023-01-01 00:00:00 UTC)));
+  }
+}
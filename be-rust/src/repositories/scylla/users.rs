@@ -0,0 +1,195 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of ScyllaDB repository for users.
+//!
+//! Logins are unique, each login is claimed by the user in a separate table
+//! using lightweight transaction, so concurrent requests can not register the same login twice.
+
+use super::{is_applied, KEYSPACE, TABLE_USERS, TABLE_USERS_BY_LOGIN};
+use crate::entities::user::UserEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::repositories::users::UsersRepository;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use scylla::macros::FromRow;
+use scylla::{IntoTypedRows, Session, ValueList};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+lazy_static! {
+  static ref QUERY_CREATE: String = format!("INSERT INTO {}.{} (user_id, login, password, roles) VALUES (?, ?, ?, ?)", KEYSPACE, TABLE_USERS);
+  static ref QUERY_LIST: String = format!("SELECT user_id, login, password, roles FROM {}.{}", KEYSPACE, TABLE_USERS);
+  static ref QUERY_FIND_BY_ID: String = format!("SELECT user_id, login, password, roles FROM {}.{} WHERE user_id = ?", KEYSPACE, TABLE_USERS);
+  static ref QUERY_FIND_BY_LOGIN: String = format!("SELECT user_id FROM {}.{} WHERE login = ?", KEYSPACE, TABLE_USERS_BY_LOGIN);
+  static ref QUERY_UPDATE: String = format!("UPDATE {}.{} SET login = ?, password = ?, roles = ? WHERE user_id = ?", KEYSPACE, TABLE_USERS);
+  static ref QUERY_DELETE: String = format!("DELETE FROM {}.{} WHERE user_id = ?", KEYSPACE, TABLE_USERS);
+  static ref QUERY_CLAIM_LOGIN: String = format!("INSERT INTO {}.{} (login, user_id) VALUES (?, ?) IF NOT EXISTS", KEYSPACE, TABLE_USERS_BY_LOGIN);
+  static ref QUERY_RELEASE_LOGIN: String = format!("DELETE FROM {}.{} WHERE login = ? IF user_id = ?", KEYSPACE, TABLE_USERS_BY_LOGIN);
+}
+
+/// Claims logins of users stored by previous versions of the application, which did not claim logins.
+pub async fn claim_existing_logins(session: &Session) -> Result<()> {
+  if let Some(rows) = session.query(QUERY_LIST.as_str(), &[]).await.map_err(err_query)?.rows {
+    for row in rows.into_typed::<UserRow>() {
+      let user = row.map_err(err_from_row)?;
+      session.query(QUERY_CLAIM_LOGIN.as_str(), (user.login, user.user_id)).await.map_err(err_query)?;
+    }
+  }
+  Ok(())
+}
+
+/// ScyllaDB repository for users.
+pub struct ScyllaUsersRepository {
+  session: Arc<Session>,
+}
+
+/// Row of the users table.
+#[derive(FromRow)]
+struct UserRow {
+  user_id: String,
+  login: String,
+  password: String,
+  /// Empty sets are stored as nulls.
+  roles: Option<BTreeSet<String>>,
+}
+
+impl From<UserRow> for UserEntity {
+  /// Converts a [UserRow] into [UserEntity].
+  fn from(row: UserRow) -> Self {
+    Self {
+      user_id: row.user_id,
+      login: row.login,
+      password: row.password,
+      roles: row.roles.unwrap_or_default(),
+    }
+  }
+}
+
+/// Value list containing the user's identifier.
+#[derive(ValueList)]
+struct UserId {
+  user_id: String,
+}
+
+/// Value list containing the user's login.
+#[derive(ValueList)]
+struct UserLogin {
+  login: String,
+}
+
+impl ScyllaUsersRepository {
+  /// Creates a new users repository.
+  pub fn new(session: Arc<Session>) -> Self {
+    Self { session }
+  }
+  /// Claims the login for the user, fails when the login is already claimed by another user.
+  async fn claim_login(&self, login: &str, user_id: &str) -> Result<()> {
+    let result = self.session.query(QUERY_CLAIM_LOGIN.as_str(), (login, user_id)).await.map_err(err_query)?;
+    if is_applied(&result) {
+      return Ok(());
+    }
+    Err(err_user_already_exists(login))
+  }
+  /// Releases the login, when it is still claimed by the user.
+  async fn release_login(&self, login: &str, user_id: &str) -> Result<()> {
+    self.session.query(QUERY_RELEASE_LOGIN.as_str(), (login, user_id)).await.map_err(err_query)?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl UsersRepository for ScyllaUsersRepository {
+  /// Creates a new user, fails when the login is already claimed by another user.
+  async fn create(&self, user: UserEntity) -> Result<String> {
+    let user_id = user.id();
+    self.claim_login(&user.login, &user_id).await?;
+    let login = user.login.clone();
+    let values = (user.user_id, user.login, user.password, user.roles);
+    if let Err(reason) = self.session.query(QUERY_CREATE.as_str(), values).await.map_err(err_query) {
+      self.release_login(&login, &user_id).await?;
+      return Err(reason);
+    }
+    Ok(user_id)
+  }
+  /// Lists all users.
+  async fn list(&self) -> Result<Vec<UserEntity>> {
+    let mut users = vec![];
+    if let Some(rows) = self.session.query(QUERY_LIST.as_str(), &[]).await.map_err(err_query)?.rows {
+      for row in rows.into_typed::<UserRow>() {
+        users.push(row.map_err(err_from_row)?.into());
+      }
+    }
+    Ok(users)
+  }
+  /// Searches for a user with specified identifier.
+  async fn find_by_id(&self, user_id: &str) -> Result<UserEntity> {
+    let id = UserId { user_id: user_id.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_BY_ID.as_str(), id).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<UserRow>().take(1).next() {
+        return Ok(row.map_err(err_from_row)?.into());
+      }
+    }
+    Err(err_entity_not_found("user", user_id))
+  }
+  /// Searches for a user with specified login.
+  async fn find_by_login(&self, login: &str) -> Result<UserEntity> {
+    let params = UserLogin { login: login.to_string() };
+    if let Some(rows) = self.session.query(QUERY_FIND_BY_LOGIN.as_str(), params).await.map_err(err_query)?.rows {
+      if let Some(row) = rows.into_typed::<(String,)>().take(1).next() {
+        let (user_id,) = row.map_err(err_from_row)?;
+        // the claim outlives the user, when the user was not stored after claiming the login
+        if let Ok(user) = self.find_by_id(&user_id).await {
+          if user.login == login {
+            return Ok(user);
+          }
+        }
+      }
+    }
+    Err(err_entity_not_found("user", login))
+  }
+  /// Updates an existing user, the changed login is claimed before the user is updated
+  /// and the previous login is released afterwards.
+  async fn update(&self, user: UserEntity) -> Result<()> {
+    // make sure the user exists, otherwise the update would create a new row
+    let existing = self.find_by_id(&user.user_id).await?;
+    let login_changed = existing.login != user.login;
+    if login_changed {
+      self.claim_login(&user.login, &user.user_id).await?;
+    }
+    let values = (user.login.clone(), user.password, user.roles, user.user_id.clone());
+    if let Err(reason) = self.session.query(QUERY_UPDATE.as_str(), values).await.map_err(err_query) {
+      if login_changed {
+        self.release_login(&user.login, &user.user_id).await?;
+      }
+      return Err(reason);
+    }
+    if login_changed {
+      self.release_login(&existing.login, &user.user_id).await?;
+    }
+    Ok(())
+  }
+  /// Deletes a user with specified identifier and releases the user's login.
+  async fn delete(&self, user_id: &str) -> Result<()> {
+    let Ok(user) = self.find_by_id(user_id).await else {
+      return Ok(());
+    };
+    let id = UserId { user_id: user_id.to_string() };
+    self.session.query(QUERY_DELETE.as_str(), id).await.map_err(err_query)?;
+    self.release_login(&user.login, user_id).await
+  }
+}
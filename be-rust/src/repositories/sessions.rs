@@ -0,0 +1,38 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for sessions.
+
+use crate::entities::session::SessionEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for sessions.
+///
+/// Sessions are saved with time to live, after which they are removed automatically.
+#[async_trait]
+pub trait SessionsRepository: Send + Sync {
+  /// Saves the session (creates new or replaces existing one), removed after `ttl` seconds.
+  async fn save(&self, session: SessionEntity, ttl: i32) -> Result<()>;
+  /// Searches for a session with specified identifier.
+  async fn find(&self, session_id: &str) -> Result<SessionEntity>;
+  /// Lists all sessions of the user.
+  async fn list_by_user(&self, user_id: &str) -> Result<Vec<SessionEntity>>;
+  /// Deletes a session with specified identifier.
+  async fn delete(&self, session: &SessionEntity) -> Result<()>;
+  /// Deletes all sessions of the user.
+  async fn delete_by_user(&self, user_id: &str) -> Result<()>;
+}
@@ -0,0 +1,41 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for note shares.
+
+use crate::entities::share::{GranteeType, ShareEntity};
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for note shares.
+///
+/// A note may be shared with a grantee only once, saving another share
+/// for the same note and grantee replaces the granted access.
+#[async_trait]
+pub trait SharesRepository: Send + Sync {
+  /// Saves the share (creates new or replaces existing one).
+  async fn save(&self, share: ShareEntity) -> Result<()>;
+  /// Lists all shares of the note.
+  async fn list_by_note(&self, note_id: &str) -> Result<Vec<ShareEntity>>;
+  /// Lists all shares granted to specified grantee.
+  async fn list_by_grantee(&self, grantee_type: GranteeType, grantee_id: &str) -> Result<Vec<ShareEntity>>;
+  /// Deletes the share of the note granted to specified grantee.
+  async fn delete(&self, note_id: &str, grantee_type: GranteeType, grantee_id: &str) -> Result<()>;
+  /// Deletes all shares of the note.
+  async fn delete_by_note(&self, note_id: &str) -> Result<()>;
+  /// Deletes all shares.
+  async fn delete_all(&self) -> Result<()>;
+}
@@ -0,0 +1,39 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for note tags.
+//!
+//! Tags of every note are indexed by the owner of the note, so the tags used by the user
+//! may be counted without scanning all notes. Notes with a tag are listed using timelines,
+//! see [tag_timeline](crate::repositories::timelines::tag_timeline).
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// Repository for note tags.
+#[async_trait]
+pub trait TagsRepository: Send + Sync {
+  /// Indexes all tags of the note, or refreshes their expiration time when already indexed.
+  async fn save(&self, note: &NoteEntity) -> Result<()>;
+  /// Returns the number of notes having each tag, for all tags of notes owned by specified user.
+  async fn count(&self, owner_id: &str) -> Result<BTreeMap<String, usize>>;
+  /// Removes all tags of the note from the index.
+  async fn delete(&self, note: &NoteEntity) -> Result<()>;
+  /// Removes all tags from the index.
+  async fn delete_all(&self) -> Result<()>;
+}
@@ -0,0 +1,88 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Definition of the repository for note timelines.
+//!
+//! A timeline is an index of notes ordered by creation time, used to list notes page by page.
+//! Every note is added to the timeline of all notes, to the timeline of its owner,
+//! to the timelines of users and roles the note is shared with, to the timeline of its notebook
+//! and to the timelines of its tags. Notes moved to trash are removed from these timelines
+//! and added to the timeline of all trashed notes and to the trash timeline of their owner.
+//! Trashed notes are also added to the timeline of deletions, ordered by the time the notes were moved to trash,
+//! used to find notes staying in trash longer than the retention period.
+
+use super::{Page, PageRequest, SortOrder};
+use crate::entities::note::NoteEntity;
+use crate::entities::share::GranteeType;
+use crate::errors::*;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+/// Name of the timeline containing all notes.
+pub const TIMELINE_ALL: &str = "*";
+
+/// Name of the timeline containing all notes moved to trash.
+pub const TIMELINE_TRASH: &str = "trash";
+
+/// Name of the timeline containing all notes moved to trash, ordered by the time they were moved to trash.
+pub const TIMELINE_DELETIONS: &str = "deletions";
+
+/// Returns the name of the timeline containing notes owned by the user
+/// or shared with the user or role.
+pub fn timeline(grantee_type: GranteeType, grantee_id: &str) -> String {
+  format!("{}:{}", grantee_type, grantee_id)
+}
+
+/// Returns the name of the timeline containing notes belonging to the notebook.
+pub fn notebook_timeline(notebook_id: &str) -> String {
+  format!("notebook:{}", notebook_id)
+}
+
+/// Returns the name of the timeline containing notes owned by the user having the tag.
+pub fn tag_timeline(owner_id: &str, tag: &str) -> String {
+  format!("tag:{}:{}", owner_id, tag)
+}
+
+/// Returns the name of the timeline containing notes owned by the user moved to trash.
+pub fn trash_timeline(owner_id: &str) -> String {
+  format!("trash:{}", owner_id)
+}
+
+/// Repository for note timelines.
+#[async_trait]
+pub trait TimelinesRepository: Send + Sync {
+  /// Adds the note to the timeline, or refreshes its expiration time when already added.
+  async fn save(&self, timeline: &str, note: &NoteEntity) -> Result<()> {
+    self.save_at(timeline, note.created_at, note).await
+  }
+  /// Adds the note to the timeline ordered by specified time instead of creation time,
+  /// or refreshes its expiration time when already added.
+  async fn save_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()>;
+  /// Lists identifiers of notes from specified timelines, merged and ordered by creation time
+  /// (or by the time the notes were added with).
+  /// Notes present in more than one timeline are listed once.
+  async fn list(&self, timelines: &[String], order: SortOrder, page: &PageRequest) -> Result<Page<String>>;
+  /// Returns `true` when no notes were added to any timeline.
+  async fn is_empty(&self) -> Result<bool>;
+  /// Removes the note from the timeline.
+  async fn delete(&self, timeline: &str, note: &NoteEntity) -> Result<()> {
+    self.delete_at(timeline, note.created_at, note).await
+  }
+  /// Removes the note added with specified time from the timeline.
+  async fn delete_at(&self, timeline: &str, at: OffsetDateTime, note: &NoteEntity) -> Result<()>;
+  /// Removes all notes from all timelines.
+  async fn delete_all(&self) -> Result<()>;
+}
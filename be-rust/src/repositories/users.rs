@@ -14,6 +14,25 @@
  * SOFTWARE.
  */
 
-//! Implementation of database repository for users.
+//! Definition of the repository for users.
 
-// TBD
+use crate::entities::user::UserEntity;
+use crate::errors::*;
+use async_trait::async_trait;
+
+/// Repository for users.
+#[async_trait]
+pub trait UsersRepository: Send + Sync {
+  /// Creates a new user, returns the identifier of the created user.
+  async fn create(&self, user: UserEntity) -> Result<String>;
+  /// Lists all users.
+  async fn list(&self) -> Result<Vec<UserEntity>>;
+  /// Searches for a user with specified identifier.
+  async fn find_by_id(&self, user_id: &str) -> Result<UserEntity>;
+  /// Searches for a user with specified login.
+  async fn find_by_login(&self, login: &str) -> Result<UserEntity>;
+  /// Updates an existing user.
+  async fn update(&self, user: UserEntity) -> Result<()>;
+  /// Deletes a user with specified identifier.
+  async fn delete(&self, user_id: &str) -> Result<()>;
+}
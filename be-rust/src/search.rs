@@ -0,0 +1,508 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of the embedded full-text search index of notes.
+//!
+//! Titles and contents of notes are split into terms at every character that is not a letter or a digit.
+//! Terms are lowercased and folded, so searching is insensitive to case and diacritics:
+//! letters with diacritics are replaced with base letters (`é` → `e`, `ü` → `u`),
+//! also Nordic letters (`å`, `ä`, `æ` → `a`, `ö`, `ø` → `o`),
+//! so `blåbærsyltetøj` and `blabarsyltetoj` are the same term.
+//!
+//! Queries consist of clauses separated with whitespace, all clauses must match:
+//! - `word` - a single term,
+//! - `"two words"` - a phrase, terms must occur one after another in the title or in the content,
+//! - `wor*` - a prefix, matches all terms starting with specified prefix.
+//!
+//! Matching notes are ranked using BM25, occurrences in titles weigh more than occurrences in contents.
+//! Every indexed note records timelines of users and roles it is visible to (its owner and grantees
+//! of its shares) and its expiration time, so matches are filtered by access before notes are read.
+//! The index is held in memory, kept in sync by [Storage](crate::storage::Storage) with every modification
+//! of notes and rebuilt from the notes repository when the application starts.
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::RwLock;
+use time::OffsetDateTime;
+
+/// BM25 term frequency saturation parameter.
+const K1: f64 = 1.2;
+
+/// BM25 document length normalization parameter.
+const B: f64 = 0.75;
+
+/// Weight of occurrences in titles, relative to occurrences in contents.
+const TITLE_WEIGHT: f64 = 2.0;
+
+/// Returns the folded form of a lowercase character: the base letter of letters with diacritics.
+fn fold_char(ch: char) -> &'static str {
+  match ch {
+    'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'æ' | 'ā' | 'ă' | 'ą' => "a",
+    'ç' | 'ć' | 'č' => "c",
+    'ð' | 'ď' | 'đ' => "d",
+    'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+    'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => "i",
+    'ł' | 'ľ' => "l",
+    'ñ' | 'ń' | 'ň' => "n",
+    'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' | 'œ' => "o",
+    'ř' => "r",
+    'ś' | 'š' | 'ş' => "s",
+    'ß' => "ss",
+    'ť' | 'ţ' => "t",
+    'þ' => "th",
+    'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+    'ý' | 'ÿ' => "y",
+    'ź' | 'ż' | 'ž' => "z",
+    _ => "",
+  }
+}
+
+/// Folds a word into a term: lowercases it and replaces letters with diacritics.
+fn fold(word: &str) -> String {
+  let mut term = String::with_capacity(word.len());
+  for ch in word.chars().flat_map(char::to_lowercase) {
+    match fold_char(ch) {
+      "" => term.push(ch),
+      base => term.push_str(base),
+    }
+  }
+  term
+}
+
+/// Splits the text into folded terms, in order of occurrence.
+pub fn tokenize(text: &str) -> Vec<String> {
+  text.split(|ch: char| !ch.is_alphanumeric()).filter(|word| !word.is_empty()).map(fold).collect()
+}
+
+/// Single clause of a search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+  /// Single term.
+  Term(String),
+  /// Terms occurring one after another.
+  Phrase(Vec<String>),
+  /// Prefix of terms.
+  Prefix(String),
+}
+
+/// Parsed search query, all clauses must match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+  /// Clauses of the query.
+  pub clauses: Vec<Clause>,
+}
+
+impl FromStr for SearchQuery {
+  type Err = NordNotesError;
+  /// Parses the search query, words and phrases without any term are ignored,
+  /// query without any clause is invalid. Unterminated phrase lasts until the end of the query.
+  fn from_str(query: &str) -> Result<Self, Self::Err> {
+    let mut clauses = vec![];
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+      let (text, is_phrase, remainder) = if let Some(phrase) = rest.strip_prefix('"') {
+        let end = phrase.find('"').unwrap_or(phrase.len());
+        (&phrase[..end], true, phrase.get(end + 1..).unwrap_or(""))
+      } else {
+        let end = rest.find(|ch: char| ch.is_whitespace() || ch == '"').unwrap_or(rest.len());
+        (&rest[..end], false, &rest[end..])
+      };
+      let mut terms = tokenize(text);
+      if !is_phrase && text.ends_with('*') && terms.len() == 1 {
+        clauses.push(Clause::Prefix(terms.remove(0)));
+      } else if terms.len() == 1 {
+        clauses.push(Clause::Term(terms.remove(0)));
+      } else if terms.len() > 1 {
+        clauses.push(Clause::Phrase(terms));
+      }
+      rest = remainder.trim_start();
+    }
+    if clauses.is_empty() {
+      return Err(err_invalid_search_query(query));
+    }
+    Ok(Self { clauses })
+  }
+}
+
+/// Positions of a term in the title and in the content of a single note.
+#[derive(Default)]
+struct Postings {
+  /// Positions of the term in the title.
+  title: Vec<u32>,
+  /// Positions of the term in the content.
+  content: Vec<u32>,
+}
+
+/// Indexed note.
+struct Document {
+  /// Number of terms in the title.
+  title_length: u32,
+  /// Number of terms in the content.
+  content_length: u32,
+  /// Distinct terms of the note.
+  terms: HashSet<String>,
+  /// Timelines of users and roles the note is visible to.
+  timelines: HashSet<String>,
+  /// Expiration time of the note.
+  expires_at: Option<OffsetDateTime>,
+}
+
+impl Document {
+  /// Returns `true` when the note has not expired and is visible in any of specified timelines,
+  /// or in any timeline when no timelines are specified.
+  fn is_visible(&self, timelines: Option<&[String]>, now: OffsetDateTime) -> bool {
+    self.expires_at.is_none_or(|expires_at| expires_at > now)
+      && timelines.is_none_or(|timelines| timelines.iter().any(|timeline| self.timelines.contains(timeline)))
+  }
+}
+
+/// Number of occurrences of a clause in the title and in the content of a single note.
+#[derive(Default, Clone, Copy)]
+struct Frequency {
+  /// Number of occurrences in the title.
+  title: u32,
+  /// Number of occurrences in the content.
+  content: u32,
+}
+
+/// Contents of the search index.
+#[derive(Default)]
+struct Index {
+  /// Postings of notes containing the term, indexed by term and note identifier.
+  terms: BTreeMap<String, HashMap<String, Postings>>,
+  /// Indexed notes, indexed by note identifier.
+  documents: HashMap<String, Document>,
+  /// Total number of terms in all titles.
+  title_lengths: u64,
+  /// Total number of terms in all contents.
+  content_lengths: u64,
+}
+
+impl Index {
+  /// Adds the note visible in specified timelines to the index, replacing the previously indexed version of the note.
+  fn add(&mut self, note: &NoteEntity, timelines: HashSet<String>) {
+    self.remove(&note.note_id);
+    let title = tokenize(&note.title);
+    let content = tokenize(&note.content);
+    let mut terms = HashSet::new();
+    for (position, term) in title.iter().enumerate() {
+      let postings = self.terms.entry(term.clone()).or_default().entry(note.note_id.clone()).or_default();
+      postings.title.push(position as u32);
+      terms.insert(term.clone());
+    }
+    for (position, term) in content.iter().enumerate() {
+      let postings = self.terms.entry(term.clone()).or_default().entry(note.note_id.clone()).or_default();
+      postings.content.push(position as u32);
+      terms.insert(term.clone());
+    }
+    self.title_lengths += title.len() as u64;
+    self.content_lengths += content.len() as u64;
+    let document = Document {
+      title_length: title.len() as u32,
+      content_length: content.len() as u32,
+      terms,
+      timelines,
+      expires_at: note.expires_at,
+    };
+    self.documents.insert(note.note_id.clone(), document);
+  }
+  /// Removes the note from the index.
+  fn remove(&mut self, note_id: &str) {
+    if let Some(document) = self.documents.remove(note_id) {
+      for term in &document.terms {
+        if let Some(notes) = self.terms.get_mut(term) {
+          notes.remove(note_id);
+          if notes.is_empty() {
+            self.terms.remove(term);
+          }
+        }
+      }
+      self.title_lengths -= document.title_length as u64;
+      self.content_lengths -= document.content_length as u64;
+    }
+  }
+  /// Returns the frequencies of the clause in notes containing it.
+  fn frequencies(&self, clause: &Clause) -> HashMap<&str, Frequency> {
+    let mut frequencies = HashMap::new();
+    match clause {
+      Clause::Term(term) => {
+        for (note_id, postings) in self.terms.get(term).into_iter().flatten() {
+          frequencies.insert(note_id.as_str(), frequency(postings));
+        }
+      }
+      Clause::Prefix(prefix) => {
+        for (_, notes) in self.terms.range(prefix.clone()..).take_while(|(term, _)| term.starts_with(prefix.as_str())) {
+          for (note_id, postings) in notes {
+            let entry: &mut Frequency = frequencies.entry(note_id.as_str()).or_default();
+            entry.title += postings.title.len() as u32;
+            entry.content += postings.content.len() as u32;
+          }
+        }
+      }
+      Clause::Phrase(terms) => {
+        let postings = terms.iter().map(|term| self.terms.get(term)).collect::<Option<Vec<_>>>().unwrap_or_default();
+        if let Some((first, others)) = postings.split_first() {
+          for (note_id, first_postings) in *first {
+            let note_postings = others.iter().map(|notes| notes.get(note_id)).collect::<Option<Vec<_>>>();
+            if let Some(note_postings) = note_postings {
+              let title = phrase_count(&first_postings.title, note_postings.iter().map(|postings| &postings.title));
+              let content = phrase_count(&first_postings.content, note_postings.iter().map(|postings| &postings.content));
+              if title + content > 0 {
+                frequencies.insert(note_id.as_str(), Frequency { title, content });
+              }
+            }
+          }
+        }
+      }
+    }
+    frequencies
+  }
+  /// Returns identifiers of notes visible in specified timelines and matching all clauses of the query
+  /// with their scores, best matches first.
+  fn search(&self, query: &SearchQuery, timelines: Option<&[String]>) -> Vec<(String, f64)> {
+    let now = OffsetDateTime::now_utc();
+    let count = self.documents.len() as f64;
+    let average_title_length = (self.title_lengths as f64 / count).max(1.0);
+    let average_content_length = (self.content_lengths as f64 / count).max(1.0);
+    let mut scores: Option<HashMap<&str, f64>> = None;
+    for clause in &query.clauses {
+      let frequencies = self.frequencies(clause);
+      let idf = (1.0 + (count - frequencies.len() as f64 + 0.5) / (frequencies.len() as f64 + 0.5)).ln();
+      let mut clause_scores = HashMap::new();
+      for (note_id, frequency) in frequencies {
+        let document = &self.documents[note_id];
+        if scores
+          .as_ref()
+          .map_or_else(|| document.is_visible(timelines, now), |scores| scores.contains_key(note_id))
+        {
+          let title = saturate(frequency.title, document.title_length as f64 / average_title_length);
+          let content = saturate(frequency.content, document.content_length as f64 / average_content_length);
+          let previous = scores.as_ref().map_or(0.0, |scores| scores[note_id]);
+          clause_scores.insert(note_id, previous + idf * (TITLE_WEIGHT * title + content));
+        }
+      }
+      scores = Some(clause_scores);
+    }
+    let mut results: Vec<(String, f64)> = scores
+      .unwrap_or_default()
+      .into_iter()
+      .map(|(note_id, score)| (note_id.to_string(), score))
+      .collect();
+    results.sort_by(|(a_id, a_score), (b_id, b_score)| b_score.partial_cmp(a_score).unwrap_or(Ordering::Equal).then_with(|| a_id.cmp(b_id)));
+    results
+  }
+}
+
+/// Returns the number of occurrences of a term in the title and in the content.
+fn frequency(postings: &Postings) -> Frequency {
+  Frequency {
+    title: postings.title.len() as u32,
+    content: postings.content.len() as u32,
+  }
+}
+
+/// Returns the number of positions of the first term followed by all other terms, one after another.
+fn phrase_count<'a>(first: &[u32], others: impl Iterator<Item = &'a Vec<u32>> + Clone) -> u32 {
+  first
+    .iter()
+    .filter(|position| {
+      others
+        .clone()
+        .enumerate()
+        .all(|(offset, positions)| positions.binary_search(&(**position + offset as u32 + 1)).is_ok())
+    })
+    .count() as u32
+}
+
+/// Returns the BM25 term frequency component for specified number of occurrences
+/// and the field length relative to the average length.
+fn saturate(occurrences: u32, relative_length: f64) -> f64 {
+  let occurrences = occurrences as f64;
+  occurrences * (K1 + 1.0) / (occurrences + K1 * (1.0 - B + B * relative_length))
+}
+
+/// Full-text search index of notes.
+#[derive(Default)]
+pub struct SearchIndex {
+  /// Contents of the index.
+  index: RwLock<Index>,
+  /// Flag set while the index is being built and its contents are incomplete.
+  building: AtomicBool,
+}
+
+impl SearchIndex {
+  /// Marks the index as being built (`false`) or complete (`true`).
+  pub fn set_ready(&self, ready: bool) {
+    self.building.store(!ready, AtomicOrdering::SeqCst);
+  }
+  /// Returns `true` when the index is complete and may be searched.
+  pub fn is_ready(&self) -> bool {
+    !self.building.load(AtomicOrdering::SeqCst)
+  }
+  /// Adds the note visible in specified timelines to the index, replacing the previously indexed version of the note.
+  pub fn add(&self, note: &NoteEntity, timelines: impl IntoIterator<Item = String>) {
+    self.index.write().unwrap().add(note, timelines.into_iter().collect());
+  }
+  /// Makes the indexed note visible in specified timeline.
+  pub fn show(&self, note_id: &str, timeline: &str) {
+    if let Some(document) = self.index.write().unwrap().documents.get_mut(note_id) {
+      document.timelines.insert(timeline.to_string());
+    }
+  }
+  /// Makes the indexed note no longer visible in specified timeline.
+  pub fn hide(&self, note_id: &str, timeline: &str) {
+    if let Some(document) = self.index.write().unwrap().documents.get_mut(note_id) {
+      document.timelines.remove(timeline);
+    }
+  }
+  /// Removes the note from the index.
+  pub fn remove(&self, note_id: &str) {
+    self.index.write().unwrap().remove(note_id);
+  }
+  /// Removes all notes from the index.
+  pub fn clear(&self) {
+    *self.index.write().unwrap() = Index::default();
+  }
+  /// Returns the number of indexed notes.
+  pub fn len(&self) -> usize {
+    self.index.read().unwrap().documents.len()
+  }
+  /// Returns `true` when no notes are indexed.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+  /// Returns identifiers of notes visible in any of specified timelines (in all timelines when `None`),
+  /// not expired and matching the query, with their scores, best matches first.
+  pub fn search(&self, query: &SearchQuery, timelines: Option<&[String]>) -> Vec<(String, f64)> {
+    self.index.read().unwrap().search(query, timelines)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn note(title: &str, content: &str) -> NoteEntity {
+    NoteEntity::new("alice", title, content, None)
+  }
+
+  fn query(query: &str) -> SearchQuery {
+    query.parse().unwrap()
+  }
+
+  fn timeline(user_id: &str) -> String {
+    format!("user:{}", user_id)
+  }
+
+  fn ids(results: Vec<(String, f64)>) -> Vec<String> {
+    results.into_iter().map(|(note_id, _)| note_id).collect()
+  }
+
+  #[test]
+  fn test_tokenize() {
+    assert_eq!(vec!["hello", "world", "2022"], tokenize("Hello, WORLD! (2022)"));
+    assert_eq!(vec!["blabarsyltetoj"], tokenize("Blåbærsyltetøj"));
+    assert_eq!(vec!["aarhus", "arhus", "koln", "moller"], tokenize("Aarhus Århus Köln Møller"));
+    assert_eq!(vec!["aaron", "goethe", "zoo"], tokenize("Aaron Goethe zoo"));
+    assert_eq!(vec!["cafe", "strasse", "thorsson"], tokenize("Café Straße Þórsson"));
+  }
+
+  #[test]
+  fn test_parse_query() {
+    assert_eq!(
+      vec![
+        Clause::Term("smorrebrod".to_string()),
+        Clause::Phrase(vec!["god".to_string(), "morgon".to_string()]),
+        Clause::Prefix("sma".to_string()),
+      ],
+      query("Smørrebrød \"god morgon\" Små*").clauses
+    );
+    assert_eq!(vec![Clause::Phrase(vec!["e".to_string(), "mail".to_string()])], query("e-mail").clauses);
+    assert_eq!(vec![Clause::Phrase(vec!["hej".to_string(), "da".to_string()])], query("\"hej då").clauses);
+    assert!("".parse::<SearchQuery>().is_err());
+    assert!(" \"\" * - ".parse::<SearchQuery>().is_err());
+  }
+
+  #[test]
+  fn test_search() {
+    let index = SearchIndex::default();
+    let first = note("Kanelbullar", "Recept på kanelbullar från mormor");
+    let second = note("Inköpslista", "Mjölk, smör, kanel och kardemumma");
+    let third = note("Resa till Århus", "Besök hos Jørgen i Aarhus");
+    for note in [&first, &second, &third] {
+      index.add(note, [timeline(&note.owner_id)]);
+    }
+    assert_eq!(3, index.len());
+    assert_eq!(vec![third.note_id.clone()], ids(index.search(&query("aarhus"), None)));
+    assert_eq!(vec![third.note_id.clone()], ids(index.search(&query("arhus"), None)));
+    assert_eq!(vec![third.note_id.clone()], ids(index.search(&query("JORGEN"), None)));
+    assert_eq!(vec![second.note_id.clone()], ids(index.search(&query("mjolk smor"), None)));
+    assert_eq!(vec![first.note_id.clone()], ids(index.search(&query("\"fran mormor\""), None)));
+    assert!(index.search(&query("\"mormor fran\""), None).is_empty());
+    assert!(index.search(&query("kanel mormor"), None).is_empty());
+    // occurrences in titles rank higher
+    assert_eq!(vec![first.note_id.clone(), second.note_id.clone()], ids(index.search(&query("kanel*"), None)));
+    // modified notes are indexed again
+    let mut modified = second.clone();
+    modified.content = "bröd".to_string();
+    index.add(&modified, [timeline(&modified.owner_id)]);
+    assert_eq!(vec![first.note_id.clone()], ids(index.search(&query("kanel*"), None)));
+    assert_eq!(vec![second.note_id.clone()], ids(index.search(&query("brod"), None)));
+    index.remove(&first.note_id);
+    assert!(index.search(&query("kanel*"), None).is_empty());
+    assert_eq!(2, index.len());
+    index.clear();
+    assert!(index.is_empty());
+    assert!(index.search(&query("brod"), None).is_empty());
+  }
+
+  #[test]
+  fn test_ranking() {
+    let index = SearchIndex::default();
+    let once = note("Notes", "one fjord among many other words in a long content");
+    let twice = note("Notes", "fjord and fjord");
+    let title = note("Fjord", "content");
+    for note in [&once, &twice, &title] {
+      index.add(note, [timeline(&note.owner_id)]);
+    }
+    let results = index.search(&query("fjord"), None);
+    assert_eq!(vec![title.note_id, twice.note_id, once.note_id], ids(results.clone()));
+    assert!(results.iter().all(|(_, score)| *score > 0.0));
+  }
+
+  #[test]
+  fn test_search_visible_notes() {
+    let index = SearchIndex::default();
+    let own = note("Fjord", "content");
+    let other = NoteEntity::new("bob", "Fjord", "content", None);
+    let expired = NoteEntity::new("alice", "Fjord", "content", Some(OffsetDateTime::now_utc() - time::Duration::seconds(1)));
+    for note in [&own, &other, &expired] {
+      index.add(note, [timeline(&note.owner_id)]);
+    }
+    let alice = [timeline("alice"), "role:user".to_string()];
+    assert_eq!(vec![own.note_id.clone()], ids(index.search(&query("fjord"), Some(&alice))));
+    assert_eq!(2, index.search(&query("fjord"), None).len());
+    // shared notes are visible to grantees
+    index.show(&other.note_id, "role:user");
+    assert_eq!(2, index.search(&query("fjord"), Some(&alice)).len());
+    index.hide(&other.note_id, "role:user");
+    assert_eq!(vec![own.note_id], ids(index.search(&query("fjord"), Some(&alice))));
+  }
+}
@@ -24,37 +24,102 @@
 
 use crate::errors::*;
 use crate::handlers;
-use crate::services::system::initialize_roles_and_users;
+use crate::repositories::Page;
+use crate::repositories::PageRequest;
+use crate::services::attachments::purge_expired as purge_expired_attachments;
+use crate::services::collaboration::save as save_documents;
+use crate::services::events::{publish_expired, schedule_expirations};
+use crate::services::system::{import_legacy_users, index_notes, index_search_page, initialize_roles_and_users, INDEX_PAGE_SIZE};
+use crate::services::trash::{list_expired, purge_expired, PURGE_PAGE_SIZE};
 use crate::storage::Storage;
 use actix_cors::Cors;
-use actix_web::web::Json;
-use actix_web::{web, App, HttpRequest, HttpServer};
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::http::StatusCode;
+use actix_web::web::ServiceConfig;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, ResponseError};
+use log::{error, info};
 use serde_derive::Serialize;
+use std::fmt::Display;
+use std::time::Duration;
 
-/// Data transfer object for an error.
+/// Media type of problem details.
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Interval between purges of notes staying in trash longer than the retention period.
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Interval between checks of scheduled expiration times of notes.
+const EXPIRATION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval between saves of documents edited in collaborative editing sessions.
+const COLLABORATION_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay before retrying to build the search index after a failure.
+const SEARCH_INDEX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Data transfer object for an error, reported as problem details (RFC 7807).
 #[derive(Serialize)]
-pub struct ErrorDto {
+pub struct ProblemDto {
+  /// Type of the problem, problems are identified by error codes, so the type is always `about:blank`.
+  #[serde(rename = "type")]
+  problem_type: &'static str,
+  /// Short summary of the problem, the reason phrase of the HTTP status.
+  #[serde(rename = "title")]
+  title: &'static str,
+  /// HTTP status of the response.
+  #[serde(rename = "status")]
+  status: u16,
   /// Error details.
-  #[serde(rename = "details")]
-  details: String,
+  #[serde(rename = "detail")]
+  detail: String,
+  /// Stable machine-readable error code.
+  #[serde(rename = "code")]
+  code: &'static str,
+}
+
+impl From<&NordNotesError> for ProblemDto {
+  /// Converts a [NordNotesError] into [ProblemDto].
+  fn from(err: &NordNotesError) -> Self {
+    let status = err.code().status();
+    Self {
+      problem_type: "about:blank",
+      title: status.canonical_reason().unwrap_or_default(),
+      status: status.as_u16(),
+      detail: err.details().to_string(),
+      code: err.code().name(),
+    }
+  }
+}
+
+impl ResponseError for NordNotesError {
+  /// Returns the HTTP status determined by the error code.
+  fn status_code(&self) -> StatusCode {
+    self.code().status()
+  }
+  /// Creates the response with problem details, details of internal errors are logged but not reported.
+  fn error_response(&self) -> HttpResponse {
+    if self.code() == ErrorCode::Internal {
+      error!("internal error: {}", self);
+    }
+    let mut response = HttpResponse::build(self.status_code());
+    if self.code() == ErrorCode::NotAuthorized {
+      response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+    }
+    response
+      .content_type(PROBLEM_JSON)
+      .body(serde_json::to_string(&ProblemDto::from(self)).unwrap_or_default())
+  }
 }
 
-/// Data transfer object for the result (valid data or error).
+/// Data transfer object for the result of a successful request.
 #[derive(Serialize)]
 pub struct ResultDto<T> {
   /// Result containing data.
   #[serde(rename = "data", skip_serializing_if = "Option::is_none")]
   data: Option<T>,
-  /// Result containing errors.
-  #[serde(rename = "errors", skip_serializing_if = "Vec::is_empty")]
-  errors: Vec<ErrorDto>,
-}
-
-impl<T> Default for ResultDto<T> {
-  /// Creates default result structure.
-  fn default() -> Self {
-    Self { data: None, errors: vec![] }
-  }
+  /// Opaque token of the next page, present only in paged listings having more items.
+  #[serde(rename = "nextPage", skip_serializing_if = "Option::is_none")]
+  next_page: Option<String>,
 }
 
 impl<T> ResultDto<T> {
@@ -62,14 +127,17 @@ impl<T> ResultDto<T> {
   pub fn data(d: T) -> ResultDto<T> {
     ResultDto {
       data: Some(d),
-      ..Default::default()
+      next_page: None,
     }
   }
-  /// Creates [ResultDto] with single error inside.
-  pub fn error(err: NordNotesError) -> ResultDto<T> {
+}
+
+impl<T> ResultDto<Vec<T>> {
+  /// Creates [ResultDto] with a single page of items and the token of the next page.
+  pub fn page(page: Page<T>) -> ResultDto<Vec<T>> {
     ResultDto {
-      errors: vec![ErrorDto { details: format!("{}", err) }],
-      ..Default::default()
+      data: Some(page.items),
+      next_page: page.next_page,
     }
   }
 }
@@ -80,44 +148,245 @@ pub struct ApplicationData {
   pub storage: tokio::sync::RwLock<Storage>,
 }
 
+impl ApplicationData {
+  /// Creates shared application data with access to specified storage.
+  pub fn new(storage: Storage) -> Self {
+    Self {
+      storage: tokio::sync::RwLock::new(storage),
+    }
+  }
+}
+
 /// Default handler (404 error).
-async fn handler_404(req: HttpRequest) -> std::io::Result<Json<ResultDto<()>>> {
-  Ok(Json(ResultDto::error(err_endpoint_not_found(req.path()))))
+async fn handler_404(req: HttpRequest) -> Result<HttpResponse> {
+  Err(err_endpoint_not_found(req.path()))
+}
+
+/// Reports errors of extracting request parameters as invalid request errors.
+fn extractor_error(err: impl Display, _req: &HttpRequest) -> actix_web::Error {
+  err_invalid_request(err).into()
+}
+
+/// Registers all request handlers.
+pub fn configure(cfg: &mut ServiceConfig) {
+  cfg
+    // errors of extracting request parameters
+    .app_data(web::JsonConfig::default().error_handler(extractor_error))
+    .app_data(web::QueryConfig::default().error_handler(extractor_error))
+    .app_data(web::PathConfig::default().error_handler(extractor_error))
+    // handlers for authorization
+    .service(handlers::auth::login)
+    .service(handlers::auth::refresh)
+    .service(handlers::auth::logout)
+    .service(handlers::auth::logout_all)
+    // handlers for note events
+    .service(handlers::events::subscribe)
+    // handlers for system operations
+    .service(handlers::system::info)
+    // handlers for roles
+    .service(handlers::roles::create)
+    .service(handlers::roles::list)
+    .service(handlers::roles::find)
+    .service(handlers::roles::delete_all)
+    // handlers for users
+    .service(handlers::users::create)
+    .service(handlers::users::list)
+    .service(handlers::users::find)
+    .service(handlers::users::update)
+    .service(handlers::users::delete)
+    // handlers for notes
+    .service(handlers::notes::list)
+    .service(handlers::notes::search)
+    .service(handlers::notes::rebuild_search_index)
+    .service(handlers::notes::get_by_id)
+    .service(handlers::notes::delete_all)
+    .service(handlers::notes::create)
+    .service(handlers::notes::replace)
+    .service(handlers::notes::update)
+    .service(handlers::notes::delete)
+    .service(handlers::notes::list_shares)
+    .service(handlers::notes::create_share)
+    .service(handlers::notes::delete_share)
+    // handlers for collaborative editing of notes
+    .service(handlers::collaboration::join)
+    // handlers for note revisions
+    .service(handlers::revisions::list)
+    .service(handlers::revisions::diff)
+    .service(handlers::revisions::find)
+    .service(handlers::revisions::restore)
+    // handlers for note attachments
+    .service(handlers::attachments::create)
+    .service(handlers::attachments::list)
+    .service(handlers::attachments::download)
+    .service(handlers::attachments::delete)
+    // handlers for notebooks
+    .service(handlers::notebooks::create)
+    .service(handlers::notebooks::list)
+    .service(handlers::notebooks::find)
+    .service(handlers::notebooks::rename)
+    .service(handlers::notebooks::delete)
+    .service(handlers::notebooks::list_notes)
+    // handlers for tags
+    .service(handlers::tags::list)
+    .service(handlers::tags::list_notes)
+    // handlers for trash
+    .service(handlers::trash::list)
+    .service(handlers::trash::restore)
+    .service(handlers::trash::purge)
+    // default handler
+    .default_service(web::route().to(handler_404));
+}
+
+/// Periodically deletes permanently notes staying in trash longer than the retention period
+/// and contents of expired attachments not removed by the storage itself.
+///
+/// Expired notes are listed page by page, the storage is locked for writing only while a page of notes is purged.
+async fn purge_trash(application_data: web::Data<ApplicationData>) {
+  let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+  loop {
+    interval.tick().await;
+    match purge_trash_pages(&application_data).await {
+      Ok(0) => {}
+      Ok(purged) => info!("purged {} note(s) from trash", purged),
+      Err(reason) => error!("purging trash failed: {}", reason),
+    }
+    let storage = application_data.storage.read().await;
+    match purge_expired_attachments(&storage).await {
+      Ok(0) => {}
+      Ok(purged) => info!("purged {} expired attachment(s)", purged),
+      Err(reason) => error!("purging attachments failed: {}", reason),
+    }
+  }
+}
+
+/// Purges notes staying in trash longer than the retention period page by page, returns the number of purged notes.
+async fn purge_trash_pages(application_data: &ApplicationData) -> Result<usize> {
+  let mut purged = 0;
+  let mut page = PageRequest {
+    size: PURGE_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let (expired, next_page) = list_expired(&page, &*application_data.storage.read().await).await?;
+    purged += purge_expired(&expired, &mut *application_data.storage.write().await).await?;
+    match next_page {
+      Some(next_page) => page.token = Some(next_page),
+      None => return Ok(purged),
+    }
+  }
+}
+
+/// Periodically publishes expiration events of notes that have expired.
+async fn watch_expirations(application_data: web::Data<ApplicationData>) {
+  let mut interval = tokio::time::interval(EXPIRATION_CHECK_INTERVAL);
+  loop {
+    interval.tick().await;
+    let storage = application_data.storage.read().await;
+    publish_expired(&storage);
+  }
+}
+
+/// Periodically saves documents edited in collaborative editing sessions into notes.
+async fn save_collaboration(application_data: web::Data<ApplicationData>) {
+  let mut interval = tokio::time::interval(COLLABORATION_SAVE_INTERVAL);
+  loop {
+    interval.tick().await;
+    if application_data.storage.read().await.collaboration.sessions().is_empty() {
+      continue;
+    }
+    let mut storage = application_data.storage.write().await;
+    if let Err(reason) = save_documents(&mut storage).await {
+      error!("saving collaborative editing sessions failed: {}", reason);
+    }
+  }
+}
+
+/// Builds the full-text search index from stored notes in the background, page by page.
+///
+/// The storage is locked only while a single page is indexed, so requests are served meanwhile,
+/// searching is reported as unavailable until the index is built. Failed pages are retried.
+async fn build_search_index(application_data: web::Data<ApplicationData>) {
+  let mut indexed = 0;
+  let mut page = PageRequest {
+    size: INDEX_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let storage = application_data.storage.read().await;
+    match index_search_page(&storage, &page).await {
+      Ok((count, next_page)) => {
+        indexed += count;
+        match next_page {
+          Some(next_page) => page.token = Some(next_page),
+          None => {
+            storage.search_index.set_ready(true);
+            info!("indexed {} note(s) for search", indexed);
+            return;
+          }
+        }
+      }
+      Err(reason) => {
+        error!("indexing notes for search failed: {}", reason);
+        drop(storage);
+        tokio::time::sleep(SEARCH_INDEX_RETRY_DELAY).await;
+      }
+    }
+  }
 }
 
 /// Starts the server.
 pub async fn start_server() -> Result<()> {
   let storage = Storage::new().await?;
-  initialize_roles_and_users(&storage).await?;
-  let application_data = web::Data::new(ApplicationData {
-    storage: tokio::sync::RwLock::new(storage),
-  });
+  for change in initialize_roles_and_users(&storage).await? {
+    info!("{}", change);
+  }
+  let imported = import_legacy_users(&storage).await?;
+  if imported > 0 {
+    info!("imported {} user(s) from legacy file", imported);
+  }
+  let indexed = index_notes(&storage).await?;
+  if indexed > 0 {
+    info!("added {} note(s) to timelines", indexed);
+  }
+  let scheduled = schedule_expirations(&storage).await?;
+  if scheduled > 0 {
+    info!("scheduled expiration events of {} note(s)", scheduled);
+  }
+  storage.search_index.set_ready(false);
+  let application_data = web::Data::new(ApplicationData::new(storage));
+  tokio::spawn(build_search_index(application_data.clone()));
+  tokio::spawn(purge_trash(application_data.clone()));
+  tokio::spawn(watch_expirations(application_data.clone()));
+  tokio::spawn(save_collaboration(application_data.clone()));
   let address = "0.0.0.0:8871";
-  println!("started nordnotes {}", address);
+  info!("started nordnotes {}", address);
   HttpServer::new(move || {
     let cors = Cors::permissive();
-    App::new()
-      .wrap(cors)
-      .app_data(application_data.clone())
-      // handlers for authorization
-      .service(handlers::auth::login)
-      // handlers for system operations
-      .service(handlers::system::info)
-      // handlers for roles
-      .service(handlers::roles::create)
-      .service(handlers::roles::list)
-      .service(handlers::roles::find)
-      .service(handlers::roles::delete_all)
-      // handlers for notes
-      .service(handlers::notes::list)
-      .service(handlers::notes::get_by_id)
-      .service(handlers::notes::delete_all)
-      .service(handlers::notes::create)
-      // default handler
-      .default_service(web::route().to(handler_404))
+    App::new().wrap(cors).app_data(application_data.clone()).configure(configure)
   })
   .bind(address)?
   .run()
   .await
   .map_err(err_server_internal)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use actix_web::body::MessageBody;
+
+  #[test]
+  fn test_internal_error_response() {
+    let err = err_server_internal(std::io::Error::other("connection refused, host = 10.0.0.7"));
+    assert_eq!("connection refused, host = 10.0.0.7", err.to_string());
+    assert_eq!("internal server error", err.details());
+    let response = err.error_response();
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+    let body = response.into_body().try_into_bytes().unwrap();
+    let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+      serde_json::json!({"type": "about:blank", "title": "Internal Server Error", "status": 500, "detail": "internal server error", "code": "internal-error"}),
+      problem
+    );
+  }
+}
@@ -0,0 +1,181 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note attachment services.
+//!
+//! Attachments are accessible to users with read access to the note,
+//! attaching and deleting files requires edit access.
+
+use crate::entities::attachment::{AttachmentEntity, MAX_ATTACHMENTS};
+use crate::entities::note::NoteEntity;
+use crate::entities::share::Access;
+use crate::errors::*;
+use crate::repositories::blobs::CHUNK_SIZE;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::sniff::sniff_content_type;
+use crate::storage::Storage;
+use std::ops::Range;
+
+/// File being uploaded to be attached to a note, the content is written in chunks as it is received.
+pub struct Upload {
+  /// Attachment of the file, the content type is set when the first chunk is written, the size when all chunks are written.
+  attachment: AttachmentEntity,
+  /// Received bytes not written yet.
+  buffer: Vec<u8>,
+  /// Number of the next chunk to be written.
+  chunk: i32,
+  /// Number of received bytes.
+  size: u64,
+}
+
+impl Upload {
+  /// Starts uploading the file with specified name, to be attached to the note.
+  pub fn new(note: &NoteEntity, file_name: &str) -> Self {
+    Self {
+      attachment: AttachmentEntity::new(note, file_name, "", 0),
+      buffer: vec![],
+      chunk: 0,
+      size: 0,
+    }
+  }
+  /// Returns the attachment of the uploaded file.
+  pub fn attachment(&self) -> &AttachmentEntity {
+    &self.attachment
+  }
+  /// Returns the number of received bytes.
+  pub fn size(&self) -> u64 {
+    self.size
+  }
+  /// Appends received bytes of the content.
+  pub fn receive(&mut self, bytes: &[u8]) {
+    self.buffer.extend_from_slice(bytes);
+    self.size += bytes.len() as u64;
+  }
+  /// Returns `true` when the received bytes not written yet fill a whole chunk.
+  pub fn has_whole_chunk(&self) -> bool {
+    self.buffer.len() >= CHUNK_SIZE
+  }
+  /// Writes the received bytes filling whole chunks.
+  pub async fn write_chunks(&mut self, storage: &Storage) -> Result<()> {
+    while self.has_whole_chunk() {
+      let rest = self.buffer.split_off(CHUNK_SIZE);
+      let data = std::mem::replace(&mut self.buffer, rest);
+      self.write(&data, storage).await?;
+    }
+    Ok(())
+  }
+  /// Writes the remaining received bytes and completes the content, returns the attachment of the uploaded file.
+  pub async fn complete(mut self, storage: &Storage) -> Result<AttachmentEntity> {
+    if !self.buffer.is_empty() || self.chunk == 0 {
+      let data = std::mem::take(&mut self.buffer);
+      self.write(&data, storage).await?;
+    }
+    storage.blobs_repository.complete(&self.attachment).await?;
+    self.attachment.size = self.size as i64;
+    Ok(self.attachment)
+  }
+  /// Writes the next chunk of the content, the content type is detected from the first chunk.
+  async fn write(&mut self, data: &[u8], storage: &Storage) -> Result<()> {
+    if self.chunk == 0 {
+      self.attachment.content_type = sniff_content_type(data).to_string();
+    }
+    storage.blobs_repository.write_chunk(&self.attachment, self.chunk, data).await?;
+    self.chunk += 1;
+    Ok(())
+  }
+}
+
+/// Service for listing attachments of a note accessible to the user, in the order they were attached.
+pub async fn list(note_id: &str, principal: &Principal, storage: &Storage) -> Result<Vec<AttachmentEntity>> {
+  services::notes::find(note_id, principal, Access::Read, storage).await?;
+  let mut attachments = storage.attachments_repository.list_by_note(note_id).await?;
+  attachments.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.file_name.cmp(&b.file_name)));
+  Ok(attachments)
+}
+
+/// Service for searching an attachment of a note accessible to the user.
+pub async fn find(note_id: &str, attachment_id: &str, principal: &Principal, storage: &Storage) -> Result<AttachmentEntity> {
+  services::notes::find(note_id, principal, Access::Read, storage).await?;
+  storage.attachments_repository.find(note_id, attachment_id).await
+}
+
+/// Service for reading specified range of bytes of the content of an attached file.
+pub async fn read(attachment: &AttachmentEntity, range: Range<u64>, storage: &Storage) -> Result<Vec<u8>> {
+  if range.is_empty() {
+    return Ok(vec![]);
+  }
+  storage.blobs_repository.read(attachment, range).await
+}
+
+/// Service for checking that files may be attached to a note editable by the user,
+/// returns the note and the number of its attachments.
+///
+/// Called before files are uploaded, so uploads to notes not editable by the user are not received.
+pub async fn prepare(note_id: &str, principal: &Principal, storage: &Storage) -> Result<(NoteEntity, usize)> {
+  let note = services::notes::find(note_id, principal, Access::Edit, storage).await?;
+  let count = storage.attachments_repository.list_by_note(note_id).await?.len();
+  if count >= MAX_ATTACHMENTS {
+    return Err(err_too_many_attachments(count + 1));
+  }
+  Ok((note, count))
+}
+
+/// Service for attaching uploaded files to a note editable by the user, returns added attachments.
+///
+/// Contents of files are written while they are uploaded, see [Upload], content types of files are detected
+/// from their contents, see [sniff](crate::sniff). Contents of uploaded files are deleted when they can not be attached.
+pub async fn create(note_id: &str, uploaded: Vec<AttachmentEntity>, principal: &Principal, storage: &Storage) -> Result<Vec<AttachmentEntity>> {
+  let result = add(note_id, &uploaded, principal, storage).await;
+  if result.is_err() {
+    discard(&uploaded, storage).await;
+  }
+  result
+}
+
+/// Adds attachments of uploaded files to a note editable by the user.
+async fn add(note_id: &str, uploaded: &[AttachmentEntity], principal: &Principal, storage: &Storage) -> Result<Vec<AttachmentEntity>> {
+  let note = services::notes::find(note_id, principal, Access::Edit, storage).await?;
+  let count = storage.attachments_repository.list_by_note(note_id).await?.len() + uploaded.len();
+  if count > MAX_ATTACHMENTS {
+    return Err(err_too_many_attachments(count));
+  }
+  let mut attachments = vec![];
+  for attachment in uploaded {
+    attachments.push(storage.add_attachment(&note, attachment.clone()).await?);
+  }
+  Ok(attachments)
+}
+
+/// Service for deleting contents of uploaded files that were not attached, failures are ignored.
+pub async fn discard<'a>(uploaded: impl IntoIterator<Item = &'a AttachmentEntity>, storage: &Storage) {
+  for attachment in uploaded {
+    let _ = storage.blobs_repository.delete(attachment).await;
+  }
+}
+
+/// Service for deleting an attachment of a note editable by the user.
+pub async fn delete(note_id: &str, attachment_id: &str, principal: &Principal, storage: &Storage) -> Result<()> {
+  services::notes::find(note_id, principal, Access::Edit, storage).await?;
+  let attachment = storage.attachments_repository.find(note_id, attachment_id).await?;
+  storage.delete_attachment(&attachment).await
+}
+
+/// Service for deleting contents of expired attachments not removed by the storage itself,
+/// returns the number of deleted contents.
+pub async fn purge_expired(storage: &Storage) -> Result<usize> {
+  storage.blobs_repository.purge_expired().await
+}
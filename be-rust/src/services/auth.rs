@@ -0,0 +1,134 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of authorization services.
+
+use crate::entities::role::Permission;
+use crate::entities::session::SessionEntity;
+use crate::errors::*;
+use crate::services::{sessions, tokens};
+use crate::storage::Storage;
+use crate::utils::{hash_password, is_password_hash, verify_dummy_password, verify_password};
+use std::collections::BTreeSet;
+
+/// Authenticated user with permissions granted by all user's roles.
+#[derive(Debug, Clone)]
+pub struct Principal {
+  /// Identifier of the session used to authenticate the user.
+  pub session_id: String,
+  /// Identifier of the authenticated user.
+  pub user_id: String,
+  /// User login.
+  pub login: String,
+  /// Identifiers of user's roles.
+  pub roles: BTreeSet<String>,
+  /// Permissions granted to the user.
+  pub permissions: BTreeSet<Permission>,
+}
+
+impl Principal {
+  /// Returns `true` when the user was granted specified permission.
+  pub fn has_permission(&self, permission: Permission) -> bool {
+    self.permissions.contains(&permission)
+  }
+  /// Checks if the user was granted specified permission.
+  pub fn check_permission(&self, permission: Permission) -> Result<()> {
+    if self.has_permission(permission) {
+      Ok(())
+    } else {
+      Err(err_forbidden(permission.name()))
+    }
+  }
+}
+
+/// Service for logging a user, returns a new session when login and password are correct.
+///
+/// Legacy plaintext password is replaced with its hash after the first successful login.
+/// The password is verified also when the login is unknown, so both cases take the same time.
+pub async fn login(login: &str, password: &str, storage: &Storage) -> Result<Option<SessionEntity>> {
+  let Ok(mut user) = storage.users_repository.find_by_login(login).await else {
+    verify_dummy_password(password);
+    return Ok(None);
+  };
+  if verify_password(password, &user.password) {
+    if !is_password_hash(&user.password) {
+      user.password = hash_password(password)?;
+      storage.users_repository.update(user.clone()).await?;
+    }
+    return Ok(Some(sessions::create(&user.user_id, storage).await?));
+  }
+  Ok(None)
+}
+
+/// Service for authenticating a user based on the token identifying an active session,
+/// or JWT access token issued in an active session.
+pub async fn authenticate(token: &str, storage: &Storage) -> Result<Principal> {
+  let session = match &storage.settings.jwt {
+    Some(jwt) if tokens::is_jwt(token) => {
+      let claims = tokens::decode(token, jwt)?;
+      let session = sessions::find_active(&claims.sid, storage).await?;
+      if session.user_id != claims.sub {
+        return Err(err_not_authorized());
+      }
+      session
+    }
+    _ => sessions::find_active(token, storage).await?,
+  };
+  let user = storage.users_repository.find_by_id(&session.user_id).await.map_err(|_| err_not_authorized())?;
+  let mut permissions = BTreeSet::new();
+  for role_id in &user.roles {
+    // roles deleted in the meantime grant no permissions
+    if let Ok(role) = storage.roles_repository.find_by_id(role_id).await {
+      permissions.extend(role.permissions());
+    }
+  }
+  Ok(Principal {
+    session_id: session.session_id,
+    user_id: user.user_id,
+    login: user.login,
+    roles: user.roles,
+    permissions,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::user::UserEntity;
+
+  #[tokio::test]
+  async fn test_legacy_password_is_rehashed_on_login() {
+    let storage = Storage::in_memory();
+    storage.users_repository.create(UserEntity::new("alice", "secret")).await.unwrap();
+    assert!(login("alice", "invalid", &storage).await.unwrap().is_none());
+    assert_eq!("secret", storage.users_repository.find_by_login("alice").await.unwrap().password);
+    let session = login("alice", "secret", &storage).await.unwrap().unwrap();
+    assert_eq!("alice", authenticate(&session.session_id, &storage).await.unwrap().login);
+    let password = storage.users_repository.find_by_login("alice").await.unwrap().password;
+    assert!(is_password_hash(&password));
+    assert!(verify_password("secret", &password));
+    assert!(login("alice", "secret", &storage).await.unwrap().is_some());
+  }
+
+  #[tokio::test]
+  async fn test_idle_session_is_rejected() {
+    let mut storage = Storage::in_memory();
+    storage.users_repository.create(UserEntity::new("alice", "secret")).await.unwrap();
+    let session = login("alice", "secret", &storage).await.unwrap().unwrap();
+    storage.settings.session_idle_timeout = time::Duration::ZERO;
+    assert!(authenticate(&session.session_id, &storage).await.is_err());
+  }
+}
@@ -0,0 +1,166 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of collaborative editing services.
+//!
+//! Users with read access to a note may join its collaborative editing session and follow changes,
+//! users with edit access (and `notes:write` permission) may also modify the document.
+
+use crate::collaboration::{Notification, Participant, Session};
+use crate::crdt::Operation;
+use crate::entities::note::NoteEntity;
+use crate::entities::role::Permission;
+use crate::entities::share::Access;
+use crate::errors::*;
+use crate::events::EventKind;
+use crate::services;
+use crate::services::auth::{authenticate, Principal};
+use crate::services::notes::NoteChanges;
+use crate::storage::Storage;
+use log::warn;
+use std::sync::Mutex;
+
+/// User participating in a collaborative editing session.
+pub struct Collaborator {
+  /// Participant of the session.
+  participant: Participant,
+  /// Identifier of the edited note.
+  note_id: String,
+  /// Token the user was authenticated with.
+  token: String,
+  /// Authenticated user.
+  principal: Principal,
+  /// Set when the user may modify the document.
+  can_edit: bool,
+}
+
+impl Collaborator {
+  /// Returns `true` when the user may modify the document.
+  pub fn can_edit(&self) -> bool {
+    self.can_edit
+  }
+  /// Waits for the next notification, `None` when the session has ended.
+  pub async fn next(&mut self) -> Option<Notification> {
+    self.participant.next().await
+  }
+  /// Applies operations sent by the user.
+  pub fn apply(&self, operations: Vec<Operation>) -> Result<()> {
+    if !self.can_edit {
+      return Err(err_note_access_forbidden(&self.note_id, Access::Edit.name()));
+    }
+    self.participant.apply(operations, &self.principal)
+  }
+  /// Authenticates the user and checks the access to the note again, so the user stops participating
+  /// when the session of the user ends or the note is no longer accessible.
+  pub async fn reauthenticate(&mut self, storage: &Storage) -> Result<()> {
+    let (principal, can_edit) = authorize(&self.note_id, &self.token, storage).await?;
+    self.principal = principal;
+    self.can_edit = can_edit;
+    Ok(())
+  }
+}
+
+/// Authenticates the user with specified token and checks the access to the note,
+/// returns the user and `true` when the user may modify the note.
+async fn authorize(note_id: &str, token: &str, storage: &Storage) -> Result<(Principal, bool)> {
+  let principal = authenticate(token, storage).await?;
+  principal.check_permission(Permission::NotesRead)?;
+  let note = services::notes::find(note_id, &principal, Access::Read, storage).await?;
+  let can_edit = principal.has_permission(Permission::NotesWrite) && services::notes::access(&note, &principal, storage).await? == Some(Access::Edit);
+  Ok((principal, can_edit))
+}
+
+/// Service for joining the collaborative editing session of a note accessible to the user authenticated with specified token.
+pub async fn join(note_id: &str, token: String, storage: &Storage) -> Result<Collaborator> {
+  let (principal, can_edit) = authorize(note_id, &token, storage).await?;
+  let note = storage.get_note(note_id).await?;
+  Ok(Collaborator {
+    participant: storage.collaboration.join(&note),
+    note_id: note_id.to_string(),
+    token,
+    principal,
+    can_edit,
+  })
+}
+
+/// Service for saving documents edited in collaborative editing sessions into notes, returns the number of saved notes.
+///
+/// Documents of notes modified outside the sessions are rebased onto contents of the notes,
+/// sessions of deleted and expired notes are closed, idle sessions are removed.
+/// Saves failing for other reasons stay pending and are retried later.
+pub async fn save(storage: &mut Storage) -> Result<usize> {
+  let mut saved = 0;
+  for session in storage.collaboration.sessions() {
+    let (note_id, version, pending_save, idle) = {
+      let session = session.lock().unwrap();
+      (session.note_id().to_string(), session.version(), session.pending_save(), session.is_idle())
+    };
+    let Some(note) = current_note(&note_id, &session, storage).await else {
+      continue;
+    };
+    if note.version != version {
+      session.lock().unwrap().rebase(&note);
+    } else if let Some(pending_save) = pending_save {
+      let changes = NoteChanges {
+        content: Some(pending_save.content.clone()),
+        ..Default::default()
+      };
+      match services::notes::update(&note_id, changes, Some(version), &pending_save.editor, storage).await {
+        Ok(note) => {
+          session.lock().unwrap().saved(pending_save, note.version);
+          services::events::publish(EventKind::Updated, &note, storage).await?;
+          saved += 1;
+        }
+        Err(reason) => match reason.code() {
+          ErrorCode::VersionConflict => {
+            // the note was modified concurrently, the document is rebased onto the modified note
+            if let Some(note) = current_note(&note_id, &session, storage).await {
+              session.lock().unwrap().rebase(&note);
+            }
+          }
+          ErrorCode::Forbidden | ErrorCode::NotFound => {
+            // the editor lost access to the note, changes of the editor are discarded
+            warn!("saving collaborative editing session failed: {}", reason);
+            if let Some(note) = current_note(&note_id, &session, storage).await {
+              session.lock().unwrap().reset(&note);
+            }
+          }
+          _ => warn!("saving collaborative editing session failed, retrying later: {}", reason),
+        },
+      }
+    } else if idle {
+      storage.collaboration.remove(&note_id);
+    }
+  }
+  Ok(saved)
+}
+
+/// Returns the edited note, `None` when the note could not be read.
+/// The session is closed and removed when the note no longer exists.
+async fn current_note(note_id: &str, session: &Mutex<Session>, storage: &Storage) -> Option<NoteEntity> {
+  match storage.get_note(note_id).await {
+    Ok(note) => Some(note),
+    Err(reason) if reason.code() == ErrorCode::NotFound => {
+      session.lock().unwrap().close();
+      storage.collaboration.remove(note_id);
+      None
+    }
+    Err(reason) => {
+      warn!("reading note of collaborative editing session failed, retrying later: {}", reason);
+      None
+    }
+  }
+}
@@ -0,0 +1,130 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note event services.
+//!
+//! Events of a note are delivered to its owner, to users and roles the note is shared with
+//! and to users granted `notes:admin` permission.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::role::Permission;
+use crate::entities::share::GranteeType;
+use crate::errors::*;
+use crate::events::{EventKind, NoteEvent};
+use crate::repositories::timelines::timeline;
+use crate::repositories::PageRequest;
+use crate::services::auth::{authenticate, Principal};
+use crate::services::notes::NOTES_PAGE_SIZE;
+use crate::storage::Storage;
+use crate::utils::now;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Returns names of timelines of users and roles allowed to read the note.
+async fn audience(note: &NoteEntity, storage: &Storage) -> Result<Vec<String>> {
+  let mut audience = vec![timeline(GranteeType::User, &note.owner_id)];
+  for share in storage.shares_repository.list_by_note(&note.note_id).await? {
+    audience.push(timeline(share.grantee_type, &share.grantee_id));
+  }
+  Ok(audience)
+}
+
+/// Service for publishing an event of the change of the note.
+pub async fn publish(kind: EventKind, note: &NoteEntity, storage: &Storage) -> Result<()> {
+  let audience = audience(note, storage).await?;
+  storage.events.publish(NoteEvent::new(kind, note, audience));
+  Ok(())
+}
+
+/// Service for updating the audience of the scheduled expiration event of the note, called when the note is shared or unshared.
+pub async fn refresh(note: &NoteEntity, storage: &Storage) -> Result<()> {
+  let audience = audience(note, storage).await?;
+  storage.events.schedule(note, audience);
+  Ok(())
+}
+
+/// Service for scheduling expiration events of all stored notes having expiration time,
+/// returns the number of scheduled notes.
+pub async fn schedule_expirations(storage: &Storage) -> Result<usize> {
+  let mut scheduled = 0;
+  let mut page = PageRequest {
+    size: NOTES_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let notes = storage.get_notes_page(&page).await?;
+    for note in &notes.items {
+      if note.expires_at.is_some() && !note.is_trashed() {
+        refresh(note, storage).await?;
+        scheduled += 1;
+      }
+    }
+    match notes.next_page {
+      Some(token) => page.token = Some(token),
+      None => return Ok(scheduled),
+    }
+  }
+}
+
+/// Service for publishing expiration events of notes that have expired, returns the number of published events.
+pub fn publish_expired(storage: &Storage) -> usize {
+  storage.events.publish_expired(now())
+}
+
+/// Subscriber receiving events of notes readable by the user.
+pub struct Subscriber {
+  /// Receiver of all published events.
+  receiver: broadcast::Receiver<Arc<NoteEvent>>,
+  /// Token the user was authenticated with.
+  token: String,
+  /// Authenticated user.
+  principal: Principal,
+}
+
+impl Subscriber {
+  /// Returns the next event of a note readable by the user, `None` when the event bus was closed.
+  ///
+  /// Events missed by a subscriber not keeping up with published events are skipped.
+  pub async fn next(&mut self) -> Option<Arc<NoteEvent>> {
+    loop {
+      match self.receiver.recv().await {
+        Ok(event) if event.is_visible_to(&self.principal) => return Some(event),
+        Ok(_) | Err(RecvError::Lagged(_)) => {}
+        Err(RecvError::Closed) => return None,
+      }
+    }
+  }
+  /// Authenticates the user again, so events stop being delivered when the session ends
+  /// or the user loses permission to read notes; changes of user's roles are applied to delivered events.
+  pub async fn reauthenticate(&mut self, storage: &Storage) -> Result<()> {
+    let principal = authenticate(&self.token, storage).await?;
+    principal.check_permission(Permission::NotesRead)?;
+    self.principal = principal;
+    Ok(())
+  }
+}
+
+/// Service for subscribing to events of notes readable by the user authenticated with specified token.
+pub async fn subscribe(token: String, storage: &Storage) -> Result<Subscriber> {
+  let principal = authenticate(&token, storage).await?;
+  principal.check_permission(Permission::NotesRead)?;
+  Ok(Subscriber {
+    receiver: storage.events.subscribe(),
+    token,
+    principal,
+  })
+}
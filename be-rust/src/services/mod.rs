@@ -20,5 +20,18 @@
 //! Services are used by controllers to implement more complex logic.
 //! Service may call other services to complete its tasks.
 
+pub mod attachments;
+pub mod auth;
+pub mod collaboration;
+pub mod events;
+pub mod notebooks;
+pub mod notes;
+pub mod revisions;
 pub mod roles;
+pub mod sessions;
+pub mod shares;
 pub mod system;
+pub mod tags;
+pub mod tokens;
+pub mod trash;
+pub mod users;
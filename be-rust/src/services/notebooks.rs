@@ -0,0 +1,86 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of notebook services.
+//!
+//! Notebooks are private to their owners, notebooks of other users are reported as not found.
+//! A note may belong only to a notebook of the owner of the note.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::notebook::NotebookEntity;
+use crate::errors::*;
+use crate::repositories::timelines::notebook_timeline;
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+
+/// Service for creating a new notebook owned by the user.
+pub async fn create(name: &str, principal: &Principal, storage: &Storage) -> Result<NotebookEntity> {
+  let notebook = NotebookEntity::new(&principal.user_id, name);
+  storage.notebooks_repository.save(notebook.clone()).await?;
+  Ok(notebook)
+}
+
+/// Service for listing all notebooks owned by the user.
+pub async fn list(principal: &Principal, storage: &Storage) -> Result<Vec<NotebookEntity>> {
+  storage.notebooks_repository.list_by_owner(&principal.user_id).await
+}
+
+/// Service for searching a notebook owned by the user.
+pub async fn find(notebook_id: &str, principal: &Principal, storage: &Storage) -> Result<NotebookEntity> {
+  storage.notebooks_repository.find(&principal.user_id, notebook_id).await
+}
+
+/// Service for renaming a notebook owned by the user, returns the renamed notebook.
+pub async fn rename(notebook_id: &str, name: &str, principal: &Principal, storage: &Storage) -> Result<NotebookEntity> {
+  let mut notebook = find(notebook_id, principal, storage).await?;
+  notebook.name = name.to_string();
+  storage.notebooks_repository.save(notebook.clone()).await?;
+  Ok(notebook)
+}
+
+/// Service for listing a page of notes belonging to a notebook owned by the user, ordered by creation time.
+pub async fn list_notes(notebook_id: &str, principal: &Principal, order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  find(notebook_id, principal, storage).await?;
+  services::notes::list_timelines(&[notebook_timeline(notebook_id)], order, page, storage).await
+}
+
+/// Service for deleting a notebook owned by the user, notes belonging to the notebook are not deleted,
+/// they are removed from the notebook instead. Returns the number of removed notes.
+pub async fn delete(notebook_id: &str, principal: &Principal, storage: &mut Storage) -> Result<usize> {
+  find(notebook_id, principal, storage).await?;
+  let timelines = [notebook_timeline(notebook_id)];
+  let mut notes = vec![];
+  let mut page = PageRequest::default();
+  loop {
+    let mut listed = services::notes::list_timelines(&timelines, SortOrder::Ascending, &page, storage).await?;
+    notes.append(&mut listed.items);
+    match listed.next_page {
+      Some(token) => page.token = Some(token),
+      None => break,
+    }
+  }
+  for previous in &notes {
+    let mut note = previous.clone();
+    note.notebook_id = None;
+    note.touch();
+    note.version += 1;
+    storage.update_note(previous, note, &principal.user_id).await?;
+  }
+  storage.notebooks_repository.delete(&principal.user_id, notebook_id).await?;
+  Ok(notes.len())
+}
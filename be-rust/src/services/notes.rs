@@ -0,0 +1,244 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note services.
+//!
+//! Notes are accessible to:
+//! - their owners, with full access,
+//! - users granted `notes:admin` permission, with full access to all notes,
+//! - users the note was shared with, directly or through one of their roles, with granted access.
+
+use crate::entities::note::{ContentFormat, NoteEntity};
+use crate::entities::role::Permission;
+use crate::entities::share::{Access, GranteeType, ShareEntity};
+use crate::errors::*;
+use crate::events::EventKind;
+use crate::repositories::timelines::{timeline, TIMELINE_ALL};
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::search::SearchQuery;
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+use crate::utils::{from_hex, to_hex};
+use std::collections::BTreeSet;
+use time::OffsetDateTime;
+
+/// Number of notes read from the notes repository at once when processing all notes.
+pub const NOTES_PAGE_SIZE: usize = 500;
+
+/// Changes of note attributes, `None` values are left unchanged.
+#[derive(Default)]
+pub struct NoteChanges {
+  /// New title of the note.
+  pub title: Option<String>,
+  /// New content of the note.
+  pub content: Option<String>,
+  /// New format of the content of the note.
+  pub content_format: Option<ContentFormat>,
+  /// New expiration time of the note, `Some(None)` means the note never expires.
+  pub expires_at: Option<Option<OffsetDateTime>>,
+  /// New tags of the note.
+  pub tags: Option<BTreeSet<String>>,
+  /// New notebook of the note, `Some(None)` means the note does not belong to any notebook.
+  pub notebook_id: Option<Option<String>>,
+}
+
+/// Returns `true` when the user may manage the note (including its shares).
+pub fn is_manager(note: &NoteEntity, principal: &Principal) -> bool {
+  note.is_owned_by(&principal.user_id) || principal.has_permission(Permission::NotesAdmin)
+}
+
+/// Returns `true` when the share grants access to the user.
+fn is_granted(share: &ShareEntity, principal: &Principal) -> bool {
+  match share.grantee_type {
+    GranteeType::User => share.grantee_id == principal.user_id,
+    GranteeType::Role => principal.roles.contains(&share.grantee_id),
+  }
+}
+
+/// Returns the access to the note granted to the user, `None` when the note is not accessible.
+pub async fn access(note: &NoteEntity, principal: &Principal, storage: &Storage) -> Result<Option<Access>> {
+  if is_manager(note, principal) {
+    return Ok(Some(Access::Edit));
+  }
+  let shares = storage.shares_repository.list_by_note(&note.note_id).await?;
+  Ok(shares.iter().filter(|share| is_granted(share, principal)).map(|share| share.access).max())
+}
+
+/// Service for listing a page of notes accessible to the user, ordered by creation time.
+///
+/// Users granted `notes:admin` permission list all notes, other users list notes they own
+/// and notes shared with them or with any of their roles.
+pub async fn list(principal: &Principal, order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  let timelines = if principal.has_permission(Permission::NotesAdmin) {
+    vec![TIMELINE_ALL.to_string()]
+  } else {
+    principal_timelines(principal)
+  };
+  list_timelines(&timelines, order, page, storage).await
+}
+
+/// Returns the timelines of notes visible to the user: the timeline of the user and timelines of user's roles.
+fn principal_timelines(principal: &Principal) -> Vec<String> {
+  let mut timelines = vec![timeline(GranteeType::User, &principal.user_id)];
+  timelines.extend(principal.roles.iter().map(|role_id| timeline(GranteeType::Role, role_id)));
+  timelines
+}
+
+/// Reads the note found in timelines or in the search index, returns `None` when the note
+/// has expired or was deleted in the meantime.
+async fn find_existing(note_id: &str, storage: &Storage) -> Result<Option<NoteEntity>> {
+  match storage.get_note(note_id).await {
+    Ok(note) => Ok(Some(note)),
+    Err(reason) if reason.code() == ErrorCode::NotFound => Ok(None),
+    Err(reason) => Err(reason),
+  }
+}
+
+/// Lists a page of notes from specified timelines, notes that expired or were deleted after the page
+/// was read from timelines are skipped, so the page may be shorter than requested.
+pub async fn list_timelines(timelines: &[String], order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  let note_ids = storage.timelines_repository.list(timelines, order, page).await?;
+  let mut notes = vec![];
+  for note_id in &note_ids.items {
+    notes.extend(find_existing(note_id, storage).await?);
+  }
+  Ok(Page {
+    items: notes,
+    next_page: note_ids.next_page,
+  })
+}
+
+/// Service for full-text searching of notes accessible to the user, returns a page of notes with their scores,
+/// best matches first.
+///
+/// Searching is not available until the search index is built after the application started.
+/// Matches are filtered by access in the search index, so only notes on the requested page are read.
+/// Notes deleted after they were found in the index are skipped, so the page may be shorter than requested.
+/// The page token encodes the number of accessible matches preceding the page,
+/// so notes modified between requests for subsequent pages may be skipped or repeated.
+pub async fn search(principal: &Principal, query: &SearchQuery, page: &PageRequest, storage: &Storage) -> Result<Page<(NoteEntity, f64)>> {
+  let offset = match &page.token {
+    Some(token) => from_hex(token)
+      .and_then(|bytes| String::from_utf8(bytes).ok())
+      .and_then(|offset| offset.parse::<usize>().ok())
+      .ok_or_else(err_invalid_page_token)?,
+    None => 0,
+  };
+  if !storage.search_index.is_ready() {
+    return Err(err_search_index_not_ready());
+  }
+  let timelines = (!principal.has_permission(Permission::NotesAdmin)).then(|| principal_timelines(principal));
+  let results = storage.search_index.search(query, timelines.as_deref());
+  let mut items = vec![];
+  for (note_id, score) in results.iter().skip(offset).take(page.size) {
+    if let Some(note) = find_existing(note_id, storage).await? {
+      items.push((note, *score));
+    }
+  }
+  let next_page = (results.len() > offset + page.size).then(|| to_hex((offset + page.size).to_string().as_bytes()));
+  Ok(Page { items, next_page })
+}
+
+/// Service for searching a note to which the user was granted at least required access.
+///
+/// Notes not accessible to the user are reported as not found, to not reveal their existence.
+/// Notes accessible with lower than required access are reported as forbidden.
+pub async fn find(note_id: &str, principal: &Principal, required: Access, storage: &Storage) -> Result<NoteEntity> {
+  let note = storage.get_note(note_id).await?;
+  match access(&note, principal, storage).await? {
+    Some(granted) if granted >= required => Ok(note),
+    Some(_) => Err(err_note_access_forbidden(note_id, required.name())),
+    None => Err(err_note_not_found(note_id)),
+  }
+}
+
+/// Service for searching a note which may be managed by the user.
+pub async fn find_managed(note_id: &str, principal: &Principal, storage: &Storage) -> Result<NoteEntity> {
+  let note = find(note_id, principal, Access::Read, storage).await?;
+  if is_manager(&note, principal) {
+    Ok(note)
+  } else {
+    Err(err_note_access_forbidden(note_id, "manage"))
+  }
+}
+
+/// Service for updating a note to which the user was granted edit access, returns the updated note.
+///
+/// When `expected_version` is specified and differs from the version of the note, a conflict is reported.
+/// The version is checked again when the note is stored, so concurrent modifications are never lost.
+pub async fn update(note_id: &str, changes: NoteChanges, expected_version: Option<i64>, principal: &Principal, storage: &mut Storage) -> Result<NoteEntity> {
+  let previous = find(note_id, principal, Access::Edit, storage).await?;
+  let current_version = previous.version;
+  if expected_version.is_some_and(|version| version != current_version) {
+    return Err(err_version_conflict(note_id, current_version));
+  }
+  let mut note = previous.clone();
+  if let Some(title) = changes.title {
+    note.title = title;
+  }
+  if let Some(content) = changes.content {
+    note.content = content;
+  }
+  if let Some(content_format) = changes.content_format {
+    note.content_format = content_format;
+  }
+  if let Some(expires_at) = changes.expires_at {
+    note.expires_at = expires_at;
+  }
+  if let Some(tags) = changes.tags {
+    note.tags = tags;
+  }
+  if let Some(notebook_id) = changes.notebook_id {
+    // notes may belong only to notebooks of their owners
+    if let Some(notebook_id) = &notebook_id {
+      storage.notebooks_repository.find(&note.owner_id, notebook_id).await?;
+    }
+    note.notebook_id = notebook_id;
+  }
+  note.touch();
+  note.version = current_version + 1;
+  storage.update_note(&previous, note.clone(), &principal.user_id).await?;
+  Ok(note)
+}
+
+/// Service for moving a note managed by the user to trash, returns the trashed note.
+pub async fn delete(note_id: &str, principal: &Principal, storage: &mut Storage) -> Result<NoteEntity> {
+  let note = find_managed(note_id, principal, storage).await?;
+  storage.trash_note(&note).await
+}
+
+/// Service for moving all notes to trash page by page, publishes deletion events of trashed notes.
+/// Returns the number of trashed notes.
+pub async fn delete_all(storage: &mut Storage) -> Result<usize> {
+  let mut trashed = 0;
+  let mut page = PageRequest {
+    size: NOTES_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let notes = storage.get_notes_page(&page).await?;
+    for note in notes.items.iter().filter(|note| !note.is_trashed()) {
+      let note = storage.trash_note(note).await?;
+      services::events::publish(EventKind::Deleted, &note, storage).await?;
+      trashed += 1;
+    }
+    match notes.next_page {
+      Some(token) => page.token = Some(token),
+      None => return Ok(trashed),
+    }
+  }
+}
@@ -0,0 +1,76 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note revision services.
+//!
+//! Revisions are accessible to users with read access to the note,
+//! restoring a revision requires edit access.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::revision::RevisionEntity;
+use crate::entities::share::Access;
+use crate::errors::*;
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::services;
+use crate::services::auth::Principal;
+use crate::services::notes::NoteChanges;
+use crate::storage::Storage;
+
+/// Returns the revision of the note with specified number.
+///
+/// The current version of notes stored before revisions were recorded is not saved as a revision,
+/// such version is returned as a revision authored by the owner of the note.
+async fn find_revision(note: &NoteEntity, revision: i64, storage: &Storage) -> Result<RevisionEntity> {
+  if revision == note.version && !storage.revisions_repository.exists(&note.note_id, revision).await? {
+    return Ok(RevisionEntity::new(note, &note.owner_id));
+  }
+  storage.revisions_repository.find(&note.note_id, revision).await
+}
+
+/// Service for listing a page of revisions of a note accessible to the user, ordered by revision number.
+pub async fn list(note_id: &str, principal: &Principal, order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<RevisionEntity>> {
+  services::notes::find(note_id, principal, Access::Read, storage).await?;
+  storage.revisions_repository.list(note_id, order, page).await
+}
+
+/// Service for searching a revision of a note accessible to the user.
+pub async fn find(note_id: &str, revision: i64, principal: &Principal, storage: &Storage) -> Result<RevisionEntity> {
+  let note = services::notes::find(note_id, principal, Access::Read, storage).await?;
+  find_revision(&note, revision, storage).await
+}
+
+/// Service for retrieving two revisions of a note accessible to the user to be compared,
+/// when the second revision is not specified, the current version of the note is used.
+pub async fn compare(note_id: &str, from: i64, to: Option<i64>, principal: &Principal, storage: &Storage) -> Result<(RevisionEntity, RevisionEntity)> {
+  let note = services::notes::find(note_id, principal, Access::Read, storage).await?;
+  let from = find_revision(&note, from, storage).await?;
+  let to = find_revision(&note, to.unwrap_or(note.version), storage).await?;
+  Ok((from, to))
+}
+
+/// Service for restoring the title, content and content format of a note to the state recorded in specified revision,
+/// the restored state is stored as a new version of the note. Returns the updated note.
+pub async fn restore(note_id: &str, revision: i64, expected_version: Option<i64>, principal: &Principal, storage: &mut Storage) -> Result<NoteEntity> {
+  let note = services::notes::find(note_id, principal, Access::Edit, storage).await?;
+  let revision = find_revision(&note, revision, storage).await?;
+  let changes = NoteChanges {
+    title: Some(revision.title),
+    content: Some(revision.content),
+    content_format: Some(revision.content_format),
+    ..NoteChanges::default()
+  };
+  services::notes::update(note_id, changes, expected_version, principal, storage).await
+}
@@ -15,15 +15,30 @@
  */
 
 use crate::entities::role::RoleEntity;
+use crate::entities::Entity;
 use crate::errors::Result;
+use crate::services::system::{ADMIN_ROLE, USER_ROLE};
 use crate::storage::Storage;
 
 /// Service for retrieving a single role by its identifier.
 pub async fn find_by_id(role_id: String, storage: &Storage) -> Result<RoleEntity> {
-  Ok(storage.roles_repository.find_by_id(&role_id).await?)
+  storage.roles_repository.find_by_id(&role_id).await
 }
 
 /// Service for retrieving a single role by its name.
 pub async fn find_by_name(role_name: String, storage: &Storage) -> Result<RoleEntity> {
-  Ok(storage.roles_repository.find_by_name(&role_name).await?)
+  storage.roles_repository.find_by_name(&role_name).await
+}
+
+/// Service for deleting all roles except the built-in `ADMIN` and `USER` roles,
+/// returns the number of deleted roles.
+pub async fn delete_all(storage: &Storage) -> Result<usize> {
+  let mut deleted = 0;
+  for role in storage.roles_repository.list().await? {
+    if role.name() != ADMIN_ROLE && role.name() != USER_ROLE {
+      storage.roles_repository.delete(&role.id()).await?;
+      deleted += 1;
+    }
+  }
+  Ok(deleted)
 }
@@ -0,0 +1,65 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of session services.
+//!
+//! Session expires after the absolute timeout counted from login,
+//! or earlier, when it was not used during the idle timeout.
+//! Both timeouts are configured in [Settings](crate::settings::Settings).
+
+use crate::entities::session::SessionEntity;
+use crate::errors::*;
+use crate::storage::Storage;
+use time::{Duration, OffsetDateTime};
+
+/// Minimum period between consecutive updates of the last usage of the session.
+const REFRESH_INTERVAL: Duration = Duration::minutes(1);
+
+/// Service for creating a new session for the user.
+pub async fn create(user_id: &str, storage: &Storage) -> Result<SessionEntity> {
+  let session = SessionEntity::new(user_id, storage.settings.session_timeout);
+  let ttl = session.ttl(storage.settings.session_idle_timeout);
+  storage.sessions_repository.save(session.clone(), ttl).await?;
+  Ok(session)
+}
+
+/// Service for searching an active session, the last usage of the found session is updated.
+pub async fn find_active(session_id: &str, storage: &Storage) -> Result<SessionEntity> {
+  let idle_timeout = storage.settings.session_idle_timeout;
+  let mut session = storage.sessions_repository.find(session_id).await.map_err(|_| err_not_authorized())?;
+  if session.has_expired(idle_timeout) {
+    storage.sessions_repository.delete(&session).await?;
+    return Err(err_not_authorized());
+  }
+  let now = OffsetDateTime::now_utc();
+  // limit the number of writes when the session is used frequently
+  if now - session.last_used_at >= REFRESH_INTERVAL {
+    session.last_used_at = now;
+    let ttl = session.ttl(idle_timeout);
+    storage.sessions_repository.save(session.clone(), ttl).await?;
+  }
+  Ok(session)
+}
+
+/// Service for deleting a session.
+pub async fn delete(session: &SessionEntity, storage: &Storage) -> Result<()> {
+  storage.sessions_repository.delete(session).await
+}
+
+/// Service for deleting all sessions of the user.
+pub async fn delete_all(user_id: &str, storage: &Storage) -> Result<()> {
+  storage.sessions_repository.delete_by_user(user_id).await
+}
@@ -0,0 +1,65 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of note share services.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::share::{Access, GranteeType, ShareEntity};
+use crate::errors::*;
+use crate::repositories::timelines::timeline;
+use crate::storage::Storage;
+
+/// Checks if the grantee exists.
+async fn check_grantee(grantee_type: GranteeType, grantee_id: &str, storage: &Storage) -> Result<()> {
+  match grantee_type {
+    GranteeType::User => storage.users_repository.find_by_id(grantee_id).await.map(|_| ()),
+    GranteeType::Role => storage.roles_repository.find_by_id(grantee_id).await.map(|_| ()),
+  }
+}
+
+/// Service for granting access to the note, replaces the access granted previously to the same grantee.
+/// The note is added to the timeline of the grantee and becomes visible to the grantee in search results.
+pub async fn grant(note: &NoteEntity, grantee_type: GranteeType, grantee_id: &str, access: Access, storage: &Storage) -> Result<ShareEntity> {
+  check_grantee(grantee_type, grantee_id, storage).await?;
+  let share = ShareEntity::new(&note.note_id, grantee_type, grantee_id, access);
+  storage.shares_repository.save(share.clone()).await?;
+  let timeline = timeline(grantee_type, grantee_id);
+  storage.timelines_repository.save(&timeline, note).await?;
+  storage.search_index.show(&note.note_id, &timeline);
+  Ok(share)
+}
+
+/// Service for revoking access to the note granted to specified grantee.
+/// The note is removed from the timeline and search results of the grantee, unless the grantee owns the note.
+pub async fn revoke(note: &NoteEntity, grantee_type: GranteeType, grantee_id: &str, storage: &Storage) -> Result<()> {
+  let note_id = &note.note_id;
+  let shares = storage.shares_repository.list_by_note(note_id).await?;
+  if !shares.iter().any(|share| share.grantee_type == grantee_type && share.grantee_id == grantee_id) {
+    return Err(err_share_not_found(note_id, grantee_type.name(), grantee_id));
+  }
+  storage.shares_repository.delete(note_id, grantee_type, grantee_id).await?;
+  if grantee_type == GranteeType::Role || !note.is_owned_by(grantee_id) {
+    let timeline = timeline(grantee_type, grantee_id);
+    storage.timelines_repository.delete(&timeline, note).await?;
+    storage.search_index.hide(note_id, &timeline);
+  }
+  Ok(())
+}
+
+/// Service for listing shares of the note.
+pub async fn list(note_id: &str, storage: &Storage) -> Result<Vec<ShareEntity>> {
+  storage.shares_repository.list_by_note(note_id).await
+}
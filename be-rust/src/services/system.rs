@@ -14,37 +14,352 @@
  * SOFTWARE.
  */
 
-//! Implementation od system services.
+//! Implementation of system services.
 
-use crate::errors::Result;
+use crate::entities::role::{Permission, RoleEntity};
+use crate::entities::user::UserEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::repositories::PageRequest;
+use crate::services;
 use crate::storage::Storage;
+use crate::utils::{hash_password, verify_password};
+use std::collections::BTreeSet;
+
+/// Number of notes read from the notes repository at once when indexing notes.
+pub const INDEX_PAGE_SIZE: usize = 500;
+
+/// Name of the legacy file with user logins and passwords.
+const LEGACY_USERS_FILE: &str = "users";
+
+/// Name of the role granted to the superuser.
+pub const ADMIN_ROLE: &str = "ADMIN";
+
+/// Name of the role granted to new users by default.
+pub const USER_ROLE: &str = "USER";
+
+/// Returns permissions granted by the default `USER` role.
+fn user_role_permissions() -> BTreeSet<Permission> {
+  BTreeSet::from([Permission::NotesRead, Permission::NotesWrite, Permission::RolesRead])
+}
 
 /// Initializes roles and users.
 ///
 /// This function checks the values of the following environment variables:
-/// - `NORDNOTES_SUPERUSER`
-/// - `NORDNOTES_PASSWORD`.
-/// The following logic is applied (presented below as decision table):
-/// ```text
-/// ┌─────────────────────┬
-/// │ NORDNOTES_SUPERUSER
+/// - `NORDNOTES_SUPERUSER` - login of the superuser,
+/// - `NORDNOTES_PASSWORD` - password of the superuser,
+/// - `NORDNOTES_RESET_PASSWORD` - when set to `true`, the password of already existing superuser
+///   is reset to the value of `NORDNOTES_PASSWORD`.
 ///
-/// ┌───┬──────────┬───────╥──────╥─────────────┐
-/// │ U │ Customer │ Order ║      ║ Description │
-/// ╞═══╪══════════╪═══════╬══════╬═════════════╡
-/// │ 1 │"Business"│  <10  ║ 0.10 ║ Small order │
-/// ├───┼──────────┼───────╫──────╫─────────────┤
-/// │ 2 │"Business"│ >=10  ║ 0.15 ║ Large order │
-/// ├───┼──────────┼───────╫──────╫─────────────┤
-/// │ 3 │"Private" │   -   ║ 0.05 ║ All orders  │
-/// └───┴──────────┴───────╨──────╨─────────────┘
+/// The role named `ADMIN` granting all permissions and the role named `USER` granting
+/// permissions to read and write notes are always created when they do not exist.
+/// When the `ADMIN` role exists but lacks some permissions, they are granted again.
+/// Then the following logic is applied (presented below as decision table):
+/// ```text
+/// ┌───┬─────────────────────┬─────────────┬──────────┬───────╥──────────────────────────────────┐
+/// │ U │ NORDNOTES_SUPERUSER │ User exists │ Password │ Reset ║ Action                           │
+/// ╞═══╪═════════════════════╪═════════════╪══════════╪═══════╬══════════════════════════════════╡
+/// │ 1 │ not set             │ -           │ -        │ -     ║ only ADMIN role is initialized   │
+/// ├───┼─────────────────────┼─────────────┼──────────┼───────╫──────────────────────────────────┤
+/// │ 2 │ set                 │ false       │ -        │ -     ║ create superuser with ADMIN role │
+/// ├───┼─────────────────────┼─────────────┼──────────┼───────╫──────────────────────────────────┤
+/// │ 3 │ set                 │ true        │ matches  │ -     ║ grant ADMIN role when missing    │
+/// ├───┼─────────────────────┼─────────────┼──────────┼───────╫──────────────────────────────────┤
+/// │ 4 │ set                 │ true        │ differs  │ false ║ refuse to start                  │
+/// ├───┼─────────────────────┼─────────────┼──────────┼───────╫──────────────────────────────────┤
+/// │ 5 │ set                 │ true        │ differs  │ true  ║ reset password, grant ADMIN role │
+/// └───┴─────────────────────┴─────────────┴──────────┴───────╨──────────────────────────────────┘
 /// ```
-pub async fn initialize_roles_and_users(_storage: &Storage) -> Result<()> {
-  if let Ok(_superuser) = std::env::var("NORDNOTES_SUPERUSER") {
-    if let Ok(_password) = std::env::var("NORDNOTES_PASSWORD") {
-      let _user_exists = false; //TODO check in database
-      let _role_exists = false; //TODO check in database
+/// Returns the descriptions of all changes made in storage.
+pub async fn initialize_roles_and_users(storage: &Storage) -> Result<Vec<String>> {
+  let superuser = std::env::var("NORDNOTES_SUPERUSER").ok();
+  let password = std::env::var("NORDNOTES_PASSWORD").ok();
+  let reset_password = std::env::var("NORDNOTES_RESET_PASSWORD").is_ok_and(|value| value == "true");
+  initialize(storage, superuser, password, reset_password).await
+}
+
+/// Initializes the `ADMIN` and `USER` roles and the superuser with specified login and password.
+pub async fn initialize(storage: &Storage, superuser: Option<String>, password: Option<String>, reset_password: bool) -> Result<Vec<String>> {
+  let mut report = vec![];
+  let all_permissions = BTreeSet::from(Permission::ALL);
+  let admin_role_id = match services::roles::find_by_name(ADMIN_ROLE.to_string(), storage).await {
+    Ok(mut role) => {
+      if *role.permissions() != all_permissions {
+        role.set_permissions(all_permissions);
+        storage.roles_repository.update(role.clone()).await?;
+        report.push(format!("granted all permissions to role {}", ADMIN_ROLE));
+      }
+      role.id()
+    }
+    Err(_) => {
+      let role_id = storage.roles_repository.create(RoleEntity::new(ADMIN_ROLE, all_permissions)).await?;
+      report.push(format!("created role {}", ADMIN_ROLE));
+      role_id
+    }
+  };
+  if services::roles::find_by_name(USER_ROLE.to_string(), storage).await.is_err() {
+    storage.roles_repository.create(RoleEntity::new(USER_ROLE, user_role_permissions())).await?;
+    report.push(format!("created role {}", USER_ROLE));
+  }
+  let login = match superuser {
+    Some(login) if !login.trim().is_empty() => login.trim().to_string(),
+    _ => return Ok(report),
+  };
+  let password = password
+    .filter(|value| !value.is_empty())
+    .ok_or_else(|| err_required_attribute_not_specified("NORDNOTES_PASSWORD"))?;
+  match storage.users_repository.find_by_login(&login).await {
+    Ok(mut user) => {
+      let mut modified = false;
+      if !verify_password(&password, &user.password) {
+        if !reset_password {
+          return Err(err_superuser_password_differs(&login));
+        }
+        user.password = hash_password(&password)?;
+        modified = true;
+        report.push(format!("reset password of superuser {}", login));
+      }
+      if !user.has_role(&admin_role_id) {
+        user.roles.insert(admin_role_id);
+        modified = true;
+        report.push(format!("granted role {} to superuser {}", ADMIN_ROLE, login));
+      }
+      if modified {
+        storage.users_repository.update(user).await?;
+      }
+    }
+    Err(_) => {
+      let mut user = UserEntity::new(&login, &hash_password(&password)?);
+      user.roles.insert(admin_role_id);
+      storage.users_repository.create(user).await?;
+      report.push(format!("created superuser {} with role {}", login, ADMIN_ROLE));
+    }
+  }
+  Ok(report)
+}
+
+/// Imports users from the legacy `users` file into users repository.
+///
+/// Each line of the legacy file has the format `login:password`.
+/// Users already present in the repository are left untouched,
+/// imported users are granted the default roles.
+/// Returns the number of imported users.
+pub async fn import_legacy_users(storage: &Storage) -> Result<usize> {
+  let mut imported = 0;
+  if let Ok(content) = std::fs::read_to_string(LEGACY_USERS_FILE) {
+    for line in content.lines() {
+      let mut split = line.split(':');
+      if let Some((login, password)) = split.next().zip(split.next()) {
+        if storage.users_repository.find_by_login(login).await.is_err() {
+          let mut user = UserEntity::new(login, password);
+          user.roles = services::users::default_roles(storage).await;
+          storage.users_repository.create(user).await?;
+          imported += 1;
+        }
+      }
+    }
+  }
+  Ok(imported)
+}
+
+/// Adds notes created before timelines were introduced to timelines.
+///
+/// Notes are indexed only when all timelines are empty, so this is done once after upgrade.
+/// Returns the number of indexed notes.
+pub async fn index_notes(storage: &Storage) -> Result<usize> {
+  if !storage.timelines_repository.is_empty().await? {
+    return Ok(0);
+  }
+  let mut indexed = 0;
+  let mut page = PageRequest {
+    size: INDEX_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let notes = storage.get_notes_page(&page).await?;
+    for note in &notes.items {
+      storage.index_note(note).await?;
+    }
+    indexed += notes.items.len();
+    match notes.next_page {
+      Some(next_page) => page.token = Some(next_page),
+      None => return Ok(indexed),
+    }
+  }
+}
+
+/// Rebuilds the full-text search index from all notes stored in the notes repository,
+/// reading notes page by page, notes moved to trash are not indexed.
+///
+/// The index is reported as not ready until it is rebuilt.
+/// Returns the number of indexed notes.
+pub async fn rebuild_search_index(storage: &Storage) -> Result<usize> {
+  storage.search_index.set_ready(false);
+  storage.search_index.clear();
+  let mut indexed = 0;
+  let mut page = PageRequest {
+    size: INDEX_PAGE_SIZE,
+    token: None,
+  };
+  loop {
+    let (count, next_page) = index_search_page(storage, &page).await?;
+    indexed += count;
+    match next_page {
+      Some(next_page) => page.token = Some(next_page),
+      None => break,
+    }
+  }
+  storage.search_index.set_ready(true);
+  Ok(indexed)
+}
+
+/// Adds a single page of stored notes to the full-text search index, notes moved to trash are not indexed.
+///
+/// Returns the number of indexed notes and the token of the next page, if there are more notes.
+pub async fn index_search_page(storage: &Storage, page: &PageRequest) -> Result<(usize, Option<String>)> {
+  let notes = storage.get_notes_page(page).await?;
+  let mut indexed = 0;
+  for note in notes.items.iter().filter(|note| !note.is_trashed()) {
+    storage.search_index.add(note, storage.visibility_timelines(note).await?);
+    indexed += 1;
+  }
+  Ok((indexed, notes.next_page))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::entities::note::NoteEntity;
+  use crate::entities::share::GranteeType;
+  use crate::repositories::timelines::timeline;
+  use crate::repositories::SortOrder;
+  use crate::search::SearchQuery;
+
+  fn superuser() -> Option<String> {
+    Some("root".to_string())
+  }
+
+  fn password(value: &str) -> Option<String> {
+    Some(value.to_string())
+  }
+
+  #[tokio::test]
+  async fn test_initialize_is_idempotent() {
+    let storage = Storage::in_memory();
+    let report = initialize(&storage, superuser(), password("secret"), false).await.unwrap();
+    assert_eq!(
+      vec!["created role ADMIN", "created role USER", "created superuser root with role ADMIN"],
+      report
+    );
+    let report = initialize(&storage, superuser(), password("secret"), false).await.unwrap();
+    assert!(report.is_empty());
+    let role = storage.roles_repository.find_by_name(ADMIN_ROLE).await.unwrap();
+    let user = storage.users_repository.find_by_login("root").await.unwrap();
+    assert!(user.has_role(&role.id()));
+    assert_eq!(2, storage.roles_repository.list().await.unwrap().len());
+    assert_eq!(Permission::ALL.len(), role.permissions().len());
+  }
+
+  #[tokio::test]
+  async fn test_initialize_without_superuser() {
+    let storage = Storage::in_memory();
+    let report = initialize(&storage, None, None, false).await.unwrap();
+    assert_eq!(vec!["created role ADMIN", "created role USER"], report);
+    assert!(storage.users_repository.list().await.unwrap().is_empty());
+    assert!(initialize(&storage, superuser(), None, false).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_initialize_password_differs() {
+    let storage = Storage::in_memory();
+    initialize(&storage, superuser(), password("secret"), false).await.unwrap();
+    let result = initialize(&storage, superuser(), password("other"), false).await;
+    assert_eq!("stored password of superuser differs, login = root", result.unwrap_err().to_string());
+    let report = initialize(&storage, superuser(), password("other"), true).await.unwrap();
+    assert_eq!(vec!["reset password of superuser root"], report);
+    let user = storage.users_repository.find_by_login("root").await.unwrap();
+    assert!(verify_password("other", &user.password));
+  }
+
+  #[tokio::test]
+  async fn test_initialize_restores_admin_permissions() {
+    let storage = Storage::in_memory();
+    storage.roles_repository.create(RoleEntity::new(ADMIN_ROLE, BTreeSet::new())).await.unwrap();
+    let report = initialize(&storage, None, None, false).await.unwrap();
+    assert_eq!(vec!["granted all permissions to role ADMIN", "created role USER"], report);
+  }
+
+  #[tokio::test]
+  async fn test_initialize_grants_admin_role_to_existing_user() {
+    let storage = Storage::in_memory();
+    storage.users_repository.create(UserEntity::new("root", "secret")).await.unwrap();
+    let report = initialize(&storage, superuser(), password("secret"), false).await.unwrap();
+    assert_eq!(vec!["created role ADMIN", "created role USER", "granted role ADMIN to superuser root"], report);
+  }
+
+  #[tokio::test]
+  async fn test_index_notes() {
+    let mut storage = Storage::in_memory();
+    assert_eq!(0, index_notes(&storage).await.unwrap());
+    let note = storage.create_note(NoteEntity::new("alice", "title", "content", None)).await.unwrap();
+    assert_eq!(0, index_notes(&storage).await.unwrap());
+    // notes created before timelines were introduced
+    storage.timelines_repository.delete_all().await.unwrap();
+    assert_eq!(1, index_notes(&storage).await.unwrap());
+    assert_eq!(0, index_notes(&storage).await.unwrap());
+    let page = storage
+      .timelines_repository
+      .list(&[timeline(GranteeType::User, "alice")], SortOrder::Descending, &PageRequest::default())
+      .await
+      .unwrap();
+    assert_eq!(vec![note.note_id], page.items);
+  }
+
+  #[tokio::test]
+  async fn test_rebuild_search_index() {
+    let mut storage = Storage::in_memory();
+    let note = storage.create_note(NoteEntity::new("alice", "Smörgåsbord", "content", None)).await.unwrap();
+    storage.create_note(NoteEntity::new("alice", "other", "content", None)).await.unwrap();
+    let trashed = storage.create_note(NoteEntity::new("alice", "trashed", "content", None)).await.unwrap();
+    storage.trash_note(&trashed).await.unwrap();
+    storage.search_index.clear();
+    let query: SearchQuery = "smorgasbord".parse().unwrap();
+    assert!(storage.search_index.search(&query, None).is_empty());
+    assert_eq!(2, rebuild_search_index(&storage).await.unwrap());
+    assert_eq!(2, storage.search_index.len());
+    let results = storage.search_index.search(&query, None);
+    assert_eq!(1, results.len());
+    assert_eq!(note.note_id, results[0].0);
+    assert!(storage.search_index.is_ready());
+  }
+
+  #[tokio::test]
+  async fn test_index_search_page_by_page() {
+    let mut storage = Storage::in_memory();
+    for i in 0..5 {
+      storage
+        .create_note(NoteEntity::new("alice", &format!("note {}", i), "content", None))
+        .await
+        .unwrap();
+    }
+    storage.search_index.clear();
+    let mut page = PageRequest { size: 2, token: None };
+    let mut pages = 0;
+    loop {
+      let (indexed, next_page) = index_search_page(&storage, &page).await.unwrap();
+      pages += 1;
+      match next_page {
+        Some(next_page) => {
+          assert_eq!(2, indexed);
+          page.token = Some(next_page);
+        }
+        None => {
+          assert_eq!(1, indexed);
+          break;
+        }
+      }
     }
+    assert_eq!(3, pages);
+    assert_eq!(5, storage.search_index.len());
   }
-  Ok(())
 }
@@ -0,0 +1,38 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of tag services.
+//!
+//! Tags organize notes of their owners, so users list and count only tags of notes they own.
+
+use crate::entities::note::NoteEntity;
+use crate::errors::*;
+use crate::repositories::timelines::tag_timeline;
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::services;
+use crate::services::auth::Principal;
+use crate::storage::Storage;
+use std::collections::BTreeMap;
+
+/// Service for counting notes owned by the user having each tag.
+pub async fn count(principal: &Principal, storage: &Storage) -> Result<BTreeMap<String, usize>> {
+  storage.tags_repository.count(&principal.user_id).await
+}
+
+/// Service for listing a page of notes owned by the user having specified tag, ordered by creation time.
+pub async fn list_notes(tag: &str, principal: &Principal, order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  services::notes::list_timelines(&[tag_timeline(&principal.user_id, tag)], order, page, storage).await
+}
@@ -0,0 +1,169 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of trash services.
+//!
+//! Deleted notes are moved to the trash of their owners, from where they may be restored
+//! or purged (deleted permanently). Notes are purged automatically when they stay in trash
+//! longer than the retention period defined in settings.
+//! Users granted `notes:admin` permission manage notes in trash of all users.
+
+use crate::entities::note::NoteEntity;
+use crate::entities::role::Permission;
+use crate::errors::*;
+use crate::repositories::timelines::{trash_timeline, TIMELINE_DELETIONS, TIMELINE_TRASH};
+use crate::repositories::{Page, PageRequest, SortOrder};
+use crate::services::auth::Principal;
+use crate::services::notes::is_manager;
+use crate::storage::Storage;
+use crate::utils::now;
+
+/// Number of notes in trash examined at once when purging notes staying in trash longer than the retention period.
+pub const PURGE_PAGE_SIZE: usize = 100;
+
+/// Lists a page of notes in specified trash timelines, notes purged or restored
+/// after the page was read from timelines are skipped.
+async fn list_timelines(timelines: &[String], order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  let note_ids = storage.timelines_repository.list(timelines, order, page).await?;
+  let mut notes = vec![];
+  for note_id in &note_ids.items {
+    if let Ok(note) = storage.get_trashed_note(note_id).await {
+      notes.push(note);
+    }
+  }
+  Ok(Page {
+    items: notes,
+    next_page: note_ids.next_page,
+  })
+}
+
+/// Service for listing a page of notes in trash of the user, ordered by creation time.
+pub async fn list(principal: &Principal, order: SortOrder, page: &PageRequest, storage: &Storage) -> Result<Page<NoteEntity>> {
+  let timeline = if principal.has_permission(Permission::NotesAdmin) {
+    TIMELINE_TRASH.to_string()
+  } else {
+    trash_timeline(&principal.user_id)
+  };
+  list_timelines(&[timeline], order, page, storage).await
+}
+
+/// Service for searching a note in trash which may be managed by the user,
+/// notes in trash of other users are reported as not found.
+pub async fn find(note_id: &str, principal: &Principal, storage: &Storage) -> Result<NoteEntity> {
+  let note = storage.get_trashed_note(note_id).await?;
+  if is_manager(&note, principal) {
+    Ok(note)
+  } else {
+    Err(err_note_not_found(note_id))
+  }
+}
+
+/// Service for restoring a note from trash, returns the restored note.
+///
+/// When the notebook of the note was deleted in the meantime, the note is restored outside any notebook.
+pub async fn restore(note_id: &str, principal: &Principal, storage: &mut Storage) -> Result<NoteEntity> {
+  let mut note = find(note_id, principal, storage).await?;
+  if let Some(notebook_id) = &note.notebook_id {
+    if storage.notebooks_repository.find(&note.owner_id, notebook_id).await.is_err() {
+      note.notebook_id = None;
+    }
+  }
+  storage.restore_note(&note).await
+}
+
+/// Service for deleting a note in trash permanently.
+pub async fn purge(note_id: &str, principal: &Principal, storage: &mut Storage) -> Result<()> {
+  let note = find(note_id, principal, storage).await?;
+  storage.delete_note(&note).await
+}
+
+/// Service for listing a page of notes staying in trash longer than the retention period,
+/// ordered by the time the notes were moved to trash. Returns listed notes and the token
+/// of the next page, `None` when there are no more such notes.
+pub async fn list_expired(page: &PageRequest, storage: &Storage) -> Result<(Vec<NoteEntity>, Option<String>)> {
+  let purged_before = now() - storage.settings.trash_retention;
+  let listed = list_timelines(&[TIMELINE_DELETIONS.to_string()], SortOrder::Ascending, page, storage).await?;
+  let mut expired = vec![];
+  for note in listed.items {
+    if note.deleted_at.is_some_and(|deleted_at| deleted_at > purged_before) {
+      // notes are ordered by the time they were moved to trash, following notes have not expired either
+      return Ok((expired, None));
+    }
+    expired.push(note);
+  }
+  Ok((expired, listed.next_page))
+}
+
+/// Service for deleting permanently listed notes staying in trash longer than the retention period,
+/// notes restored or purged in the meantime are skipped. Returns the number of purged notes.
+pub async fn purge_expired(notes: &[NoteEntity], storage: &mut Storage) -> Result<usize> {
+  let mut purged = 0;
+  for note in notes {
+    match storage.get_trashed_note(&note.note_id).await {
+      Ok(trashed) if trashed.deleted_at == note.deleted_at => {
+        storage.delete_note(&trashed).await?;
+        purged += 1;
+      }
+      Ok(_) => {}
+      Err(reason) if reason.code() == ErrorCode::NotFound => {}
+      Err(reason) => return Err(reason),
+    }
+  }
+  Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use time::Duration;
+
+  /// Purges expired notes page by page, returns the number of purged notes.
+  async fn purge_all(storage: &mut Storage) -> usize {
+    let mut purged = 0;
+    let mut page = PageRequest { size: 1, token: None };
+    loop {
+      let (expired, next_page) = list_expired(&page, storage).await.unwrap();
+      purged += purge_expired(&expired, storage).await.unwrap();
+      match next_page {
+        Some(token) => page.token = Some(token),
+        None => return purged,
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_purge_expired() {
+    let mut storage = Storage::in_memory();
+    let kept = storage.create_note(NoteEntity::new("alice", "kept", "content", None)).await.unwrap();
+    let recent = storage.create_note(NoteEntity::new("alice", "recent", "content", None)).await.unwrap();
+    let old = storage.create_note(NoteEntity::new("bob", "old", "content", None)).await.unwrap();
+    storage.trash_note(&recent).await.unwrap();
+    storage.trash_note(&old).await.unwrap();
+    // restored notes are not purged
+    let trashed = storage.trash_note(&kept).await.unwrap();
+    storage.restore_note(&trashed).await.unwrap();
+    assert_eq!(0, purge_all(&mut storage).await);
+    storage.settings.trash_retention = Duration::ZERO;
+    let (expired, _) = list_expired(&PageRequest::default(), &storage).await.unwrap();
+    let mut titles: Vec<&str> = expired.iter().map(|note| note.title.as_str()).collect();
+    titles.sort();
+    assert_eq!(vec!["old", "recent"], titles);
+    assert_eq!(2, purge_all(&mut storage).await);
+    assert!(storage.get_trashed_note(&old.note_id).await.is_err());
+    assert!(storage.get_trashed_note(&recent.note_id).await.is_err());
+    assert_eq!(kept.note_id, storage.get_note(&kept.note_id).await.unwrap().note_id);
+  }
+}
@@ -0,0 +1,94 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of services for users.
+
+use crate::entities::user::UserEntity;
+use crate::entities::Entity;
+use crate::errors::*;
+use crate::services;
+use crate::services::sessions;
+use crate::services::system::USER_ROLE;
+use crate::storage::Storage;
+use crate::utils::hash_password;
+use std::collections::BTreeSet;
+
+/// Changes applied to an existing user, attributes set to `None` are left unchanged.
+#[derive(Default)]
+pub struct UserChanges {
+  /// New user login.
+  pub login: Option<String>,
+  /// New user password (plaintext, hashed before storing).
+  pub password: Option<String>,
+  /// New identifiers of roles granted to the user.
+  pub roles: Option<BTreeSet<String>>,
+}
+
+/// Service for checking if all roles with specified identifiers exist.
+async fn check_roles(roles: &BTreeSet<String>, storage: &Storage) -> Result<()> {
+  for role_id in roles {
+    storage.roles_repository.find_by_id(role_id).await?;
+  }
+  Ok(())
+}
+
+/// Service for retrieving the identifiers of roles granted to new users by default.
+pub async fn default_roles(storage: &Storage) -> BTreeSet<String> {
+  services::roles::find_by_name(USER_ROLE.to_string(), storage)
+    .await
+    .map(|role| BTreeSet::from([role.id()]))
+    .unwrap_or_default()
+}
+
+/// Service for creating a new user with unique login, returns the identifier of the created user.
+/// When no roles are specified, the default roles are granted.
+///
+/// The uniqueness of the login is enforced by the users repository.
+pub async fn create(login: &str, password: &str, roles: Option<BTreeSet<String>>, storage: &Storage) -> Result<String> {
+  let mut user = UserEntity::new(login, &hash_password(password)?);
+  user.roles = match roles {
+    Some(roles) => {
+      check_roles(&roles, storage).await?;
+      roles
+    }
+    None => default_roles(storage).await,
+  };
+  storage.users_repository.create(user).await
+}
+
+/// Service for updating the login, password and/or roles of an existing user, returns the updated user.
+pub async fn update(user_id: &str, changes: UserChanges, storage: &Storage) -> Result<UserEntity> {
+  let mut user = storage.users_repository.find_by_id(user_id).await?;
+  if let Some(login) = changes.login {
+    user.login = login;
+  }
+  if let Some(password) = changes.password {
+    user.password = hash_password(&password)?;
+  }
+  if let Some(roles) = changes.roles {
+    check_roles(&roles, storage).await?;
+    user.roles = roles;
+  }
+  storage.users_repository.update(user.clone()).await?;
+  Ok(user)
+}
+
+/// Service for deleting an existing user, all sessions of this user are deleted.
+pub async fn delete(user_id: &str, storage: &mut Storage) -> Result<()> {
+  storage.users_repository.find_by_id(user_id).await?;
+  storage.users_repository.delete(user_id).await?;
+  sessions::delete_all(user_id, storage).await
+}
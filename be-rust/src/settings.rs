@@ -0,0 +1,250 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Implementation of application settings.
+//!
+//! Settings are read from environment variables when the server starts,
+//! unset variables are replaced with default values.
+
+use crate::errors::*;
+use crate::utils::parse_ttl;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use std::fmt;
+use time::Duration;
+
+/// Default absolute session timeout.
+const DEFAULT_SESSION_TIMEOUT: &str = "12h";
+
+/// Default idle session timeout.
+const DEFAULT_SESSION_IDLE_TIMEOUT: &str = "1h";
+
+/// Default lifetime of JWT access tokens.
+const DEFAULT_JWT_ACCESS_TIMEOUT: &str = "15m";
+
+/// Default period after which notes moved to trash are deleted permanently.
+const DEFAULT_TRASH_RETENTION: &str = "30d";
+
+/// Default maximum size of an attached file.
+const DEFAULT_ATTACHMENT_MAX_SIZE: &str = "10M";
+
+/// Default maximum size of a request uploading attached files.
+const DEFAULT_UPLOAD_MAX_SIZE: &str = "100M";
+
+/// Settings of signed JWT access tokens.
+#[derive(Clone)]
+pub struct JwtSettings {
+  /// Algorithm used to sign access tokens.
+  pub algorithm: Algorithm,
+  /// Key used to sign access tokens.
+  pub encoding_key: EncodingKey,
+  /// Key used to verify signatures of access tokens.
+  pub decoding_key: DecodingKey,
+  /// Lifetime of access tokens.
+  pub access_timeout: Duration,
+}
+
+impl fmt::Debug for JwtSettings {
+  /// Formats JWT settings without revealing the keys.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("JwtSettings")
+      .field("algorithm", &self.algorithm)
+      .field("access_timeout", &self.access_timeout)
+      .finish_non_exhaustive()
+  }
+}
+
+impl JwtSettings {
+  /// Creates settings of access tokens signed with HMAC SHA-256 using shared secret.
+  pub fn hs256(secret: &[u8]) -> Self {
+    Self {
+      algorithm: Algorithm::HS256,
+      encoding_key: EncodingKey::from_secret(secret),
+      decoding_key: DecodingKey::from_secret(secret),
+      access_timeout: parse_duration("", DEFAULT_JWT_ACCESS_TIMEOUT).unwrap(),
+    }
+  }
+  /// Creates settings of access tokens signed with Ed25519 keys in PEM format.
+  pub fn eddsa(private_key: &[u8], public_key: &[u8]) -> Result<Self> {
+    Ok(Self {
+      algorithm: Algorithm::EdDSA,
+      encoding_key: EncodingKey::from_ed_pem(private_key).map_err(err_jwt)?,
+      decoding_key: DecodingKey::from_ed_pem(public_key).map_err(err_jwt)?,
+      access_timeout: parse_duration("", DEFAULT_JWT_ACCESS_TIMEOUT).unwrap(),
+    })
+  }
+  /// Reads JWT settings from environment variables, returns `None` when JWT access tokens are disabled.
+  fn from_env() -> Result<Option<Self>> {
+    let mut settings = match env_var("NORDNOTES_JWT_ALGORITHM").as_deref() {
+      None => return Ok(None),
+      Some("HS256") => Self::hs256(env_required("NORDNOTES_JWT_SECRET")?.as_bytes()),
+      Some("EdDSA") => Self::eddsa(&read_key_file("NORDNOTES_JWT_PRIVATE_KEY")?, &read_key_file("NORDNOTES_JWT_PUBLIC_KEY")?)?,
+      Some(other) => return Err(err_invalid_setting("NORDNOTES_JWT_ALGORITHM", other)),
+    };
+    settings.access_timeout = env_duration("NORDNOTES_JWT_ACCESS_TIMEOUT", DEFAULT_JWT_ACCESS_TIMEOUT)?;
+    Ok(Some(settings))
+  }
+}
+
+/// Application settings.
+#[derive(Debug, Clone)]
+pub struct Settings {
+  /// Maximum lifetime of a session, counted from login.
+  pub session_timeout: Duration,
+  /// Maximum period of inactivity, after which the session expires.
+  pub session_idle_timeout: Duration,
+  /// Settings of JWT access tokens, `None` when JWT access tokens are disabled.
+  pub jwt: Option<JwtSettings>,
+  /// Period after which notes moved to trash are deleted permanently.
+  pub trash_retention: Duration,
+  /// Maximum size of an attached file, in bytes.
+  pub attachment_max_size: u64,
+  /// Maximum size of a request uploading attached files, in bytes.
+  pub upload_max_size: u64,
+  /// Directory where contents of attached files are stored, `None` when they are stored in the database.
+  pub attachment_dir: Option<String>,
+}
+
+impl Default for Settings {
+  /// Creates default settings.
+  fn default() -> Self {
+    Self {
+      session_timeout: parse_duration("", DEFAULT_SESSION_TIMEOUT).unwrap(),
+      session_idle_timeout: parse_duration("", DEFAULT_SESSION_IDLE_TIMEOUT).unwrap(),
+      jwt: None,
+      trash_retention: parse_duration("", DEFAULT_TRASH_RETENTION).unwrap(),
+      attachment_max_size: parse_size("", DEFAULT_ATTACHMENT_MAX_SIZE).unwrap(),
+      upload_max_size: parse_size("", DEFAULT_UPLOAD_MAX_SIZE).unwrap(),
+      attachment_dir: None,
+    }
+  }
+}
+
+impl Settings {
+  /// Reads settings from environment variables:
+  /// - `NORDNOTES_SESSION_TIMEOUT` - absolute session timeout (default `12h`),
+  /// - `NORDNOTES_SESSION_IDLE_TIMEOUT` - idle session timeout (default `1h`),
+  /// - `NORDNOTES_JWT_ALGORITHM` - `HS256` or `EdDSA`, enables JWT access tokens when set,
+  /// - `NORDNOTES_JWT_SECRET` - shared secret used with `HS256`,
+  /// - `NORDNOTES_JWT_PRIVATE_KEY`, `NORDNOTES_JWT_PUBLIC_KEY` - paths of Ed25519 PEM files used with `EdDSA`,
+  /// - `NORDNOTES_JWT_ACCESS_TIMEOUT` - lifetime of access tokens (default `15m`),
+  /// - `NORDNOTES_TRASH_RETENTION` - period after which notes moved to trash are deleted permanently (default `30d`),
+  /// - `NORDNOTES_ATTACHMENT_MAX_SIZE` - maximum size of an attached file (default `10M`),
+  /// - `NORDNOTES_UPLOAD_MAX_SIZE` - maximum size of a request uploading attached files (default `100M`),
+  /// - `NORDNOTES_ATTACHMENT_DIR` - directory where contents of attached files are stored instead of the database.
+  ///
+  /// Timeouts and periods have the same format as time to live of notes, e.g. `30m`, `8h` or `2d`.
+  /// Sizes are numbers of bytes, optionally followed by `K`, `M` or `G` (multiples of 1024).
+  pub fn from_env() -> Result<Self> {
+    Ok(Self {
+      session_timeout: env_duration("NORDNOTES_SESSION_TIMEOUT", DEFAULT_SESSION_TIMEOUT)?,
+      session_idle_timeout: env_duration("NORDNOTES_SESSION_IDLE_TIMEOUT", DEFAULT_SESSION_IDLE_TIMEOUT)?,
+      jwt: JwtSettings::from_env()?,
+      trash_retention: env_duration("NORDNOTES_TRASH_RETENTION", DEFAULT_TRASH_RETENTION)?,
+      attachment_max_size: parse_size(
+        "NORDNOTES_ATTACHMENT_MAX_SIZE",
+        &env_var("NORDNOTES_ATTACHMENT_MAX_SIZE").unwrap_or_else(|| DEFAULT_ATTACHMENT_MAX_SIZE.to_string()),
+      )?,
+      upload_max_size: parse_size(
+        "NORDNOTES_UPLOAD_MAX_SIZE",
+        &env_var("NORDNOTES_UPLOAD_MAX_SIZE").unwrap_or_else(|| DEFAULT_UPLOAD_MAX_SIZE.to_string()),
+      )?,
+      attachment_dir: env_var("NORDNOTES_ATTACHMENT_DIR").filter(|dir| !dir.is_empty()),
+    })
+  }
+}
+
+/// Reads the value of environment variable, returns `None` when the variable is not set.
+fn env_var(name: &str) -> Option<String> {
+  std::env::var(name).ok()
+}
+
+/// Reads the value of environment variable that must be set.
+fn env_required(name: &str) -> Result<String> {
+  env_var(name).ok_or_else(|| err_required_setting(name))
+}
+
+/// Reads the content of the key file, which path is given in environment variable.
+fn read_key_file(name: &str) -> Result<Vec<u8>> {
+  let path = env_required(name)?;
+  std::fs::read(&path).map_err(|e| err_read_file(&path, e))
+}
+
+/// Reads the duration from environment variable, returns default value when the variable is not set.
+fn env_duration(name: &str, default_value: &str) -> Result<Duration> {
+  parse_duration(name, &env_var(name).unwrap_or_else(|| default_value.to_string()))
+}
+
+/// Parses positive duration of the setting with specified name.
+fn parse_duration(name: &str, value: &str) -> Result<Duration> {
+  parse_ttl(value).map_err(|_| err_invalid_setting(name, value))
+}
+
+/// Parses positive size in bytes of the setting with specified name, the size may be followed
+/// by `K`, `M` or `G` suffix (multiples of 1024).
+fn parse_size(name: &str, value: &str) -> Result<u64> {
+  let trimmed = value.trim();
+  let (number, multiplier) = match trimmed.as_bytes().last() {
+    Some(b'K' | b'k') => (&trimmed[..trimmed.len() - 1], 1 << 10),
+    Some(b'M' | b'm') => (&trimmed[..trimmed.len() - 1], 1 << 20),
+    Some(b'G' | b'g') => (&trimmed[..trimmed.len() - 1], 1 << 30),
+    _ => (trimmed, 1),
+  };
+  number
+    .parse::<u64>()
+    .ok()
+    .and_then(|number| number.checked_mul(multiplier))
+    .filter(|size| *size > 0)
+    .ok_or_else(|| err_invalid_setting(name, value))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_default() {
+    let settings = Settings::default();
+    assert_eq!(Duration::hours(12), settings.session_timeout);
+    assert_eq!(Duration::hours(1), settings.session_idle_timeout);
+    assert_eq!(Duration::days(30), settings.trash_retention);
+    assert_eq!(10 * 1024 * 1024, settings.attachment_max_size);
+    assert_eq!(100 * 1024 * 1024, settings.upload_max_size);
+  }
+
+  #[test]
+  fn test_parse_size() {
+    assert_eq!(512, parse_size("SIZE", "512").unwrap());
+    assert_eq!(64 * 1024, parse_size("SIZE", "64K").unwrap());
+    assert_eq!(2 * 1024 * 1024 * 1024, parse_size("SIZE", "2g").unwrap());
+    assert_eq!("invalid setting SIZE = 0M", parse_size("SIZE", "0M").unwrap_err().to_string());
+    assert!(parse_size("SIZE", "M").is_err());
+    assert!(parse_size("SIZE", "-1").is_err());
+    assert!(parse_size("SIZE", "99999999999G").is_err());
+  }
+
+  #[test]
+  fn test_parse_duration() {
+    assert_eq!(Duration::minutes(30), parse_duration("TIMEOUT", "30m").unwrap());
+    assert_eq!("invalid setting TIMEOUT = 0m", parse_duration("TIMEOUT", "0m").unwrap_err().to_string());
+    assert!(parse_duration("TIMEOUT", "soon").is_err());
+  }
+
+  #[test]
+  fn test_jwt_keys() {
+    assert_eq!(Algorithm::HS256, JwtSettings::hs256(b"secret").algorithm);
+    assert!(JwtSettings::eddsa(b"invalid", b"invalid").is_err());
+  }
+}
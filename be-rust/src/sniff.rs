@@ -0,0 +1,95 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! # Content type sniffing
+//!
+//! The media type of an attached file is detected from its leading bytes (magic numbers),
+//! the content type declared by the client is not trusted. Content that is not recognized
+//! is reported as plain text when it is valid UTF-8 without NUL characters,
+//! otherwise as `application/octet-stream`.
+//!
+//! SVG and HTML documents are deliberately reported as plain text,
+//! so they are never interpreted by browsers when downloaded.
+
+/// Media type of plain text content.
+pub const TEXT_PLAIN: &str = "text/plain; charset=utf-8";
+
+/// Media type of unrecognized binary content.
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Number of leading bytes inspected when the content is not recognized by its magic number.
+const TEXT_SAMPLE_LENGTH: usize = 8192;
+
+/// Magic numbers at the beginning of recognized content, with their media types.
+const SIGNATURES: &[(&[u8], &str)] = &[
+  (b"\x89PNG\r\n\x1a\n", "image/png"),
+  (b"\xff\xd8\xff", "image/jpeg"),
+  (b"GIF87a", "image/gif"),
+  (b"GIF89a", "image/gif"),
+  (b"%PDF-", "application/pdf"),
+  (b"PK\x03\x04", "application/zip"),
+  (b"\x1f\x8b", "application/gzip"),
+];
+
+/// Detects the media type of the content from its leading bytes.
+pub fn sniff_content_type(content: &[u8]) -> &'static str {
+  if let Some((_, content_type)) = SIGNATURES.iter().find(|(magic, _)| content.starts_with(magic)) {
+    return content_type;
+  }
+  // RIFF container holding WebP image, the size of the container is stored between the markers
+  if content.starts_with(b"RIFF") && content.get(8..12) == Some(b"WEBP") {
+    return "image/webp";
+  }
+  if is_text(&content[..content.len().min(TEXT_SAMPLE_LENGTH)]) {
+    TEXT_PLAIN
+  } else {
+    OCTET_STREAM
+  }
+}
+
+/// Returns `true` when the sample is valid UTF-8 without NUL characters,
+/// a multibyte character cut at the end of the sample is accepted.
+fn is_text(sample: &[u8]) -> bool {
+  if sample.contains(&0) {
+    return false;
+  }
+  match std::str::from_utf8(sample) {
+    Ok(_) => true,
+    Err(e) => e.error_len().is_none(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sniff_content_type() {
+    assert_eq!("image/png", sniff_content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+    assert_eq!("image/jpeg", sniff_content_type(b"\xff\xd8\xff\xe0\0\x10JFIF"));
+    assert_eq!("image/gif", sniff_content_type(b"GIF89a\x01\0"));
+    assert_eq!("image/webp", sniff_content_type(b"RIFF\x24\0\0\0WEBPVP8 "));
+    assert_eq!(OCTET_STREAM, sniff_content_type(b"RIFF\x24\0\0\0WAVEfmt "));
+    assert_eq!("application/pdf", sniff_content_type(b"%PDF-1.7\n"));
+    assert_eq!("application/zip", sniff_content_type(b"PK\x03\x04\x14\0"));
+    assert_eq!(TEXT_PLAIN, sniff_content_type("Kanelbullar, kardemumma".as_bytes()));
+    assert_eq!(TEXT_PLAIN, sniff_content_type(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><script/></svg>"));
+    assert_eq!(TEXT_PLAIN, sniff_content_type(b""));
+    assert_eq!(TEXT_PLAIN, sniff_content_type(&"å".as_bytes()[..1]));
+    assert_eq!(OCTET_STREAM, sniff_content_type(b"\x7fELF\x02\x01\x01\0"));
+    assert_eq!(OCTET_STREAM, sniff_content_type(b"\xff\xfe\xfd"));
+  }
+}
@@ -15,152 +15,338 @@
  */
 
 //! Implementation of storage access.
+//!
+//! The storage backend is selected at startup using `NORDNOTES_STORAGE` environment variable:
+//! - `scylla` - data is persisted in ScyllaDB (default),
+//! - `memory` - data is held in memory and lost when the application stops.
+//!
+//! Contents of attached files are stored by the selected backend, or in the local file system
+//! when `NORDNOTES_ATTACHMENT_DIR` environment variable is set.
 
+use crate::collaboration::CollaborationHub;
+use crate::entities::attachment::AttachmentEntity;
 use crate::entities::note::NoteEntity;
-use crate::entities::user::UserEntity;
+use crate::entities::revision::RevisionEntity;
+use crate::entities::share::GranteeType;
 use crate::errors::*;
+use crate::events::EventBus;
+use crate::repositories::attachments::AttachmentsRepository;
+use crate::repositories::blobs::BlobsRepository;
+use crate::repositories::filesystem::blobs::FilesystemBlobsRepository;
+use crate::repositories::memory::attachments::InMemoryAttachmentsRepository;
+use crate::repositories::memory::blobs::InMemoryBlobsRepository;
+use crate::repositories::memory::notebooks::InMemoryNotebooksRepository;
+use crate::repositories::memory::notes::InMemoryNotesRepository;
+use crate::repositories::memory::refresh_tokens::InMemoryRefreshTokensRepository;
+use crate::repositories::memory::revisions::InMemoryRevisionsRepository;
+use crate::repositories::memory::roles::InMemoryRolesRepository;
+use crate::repositories::memory::sessions::InMemorySessionsRepository;
+use crate::repositories::memory::shares::InMemorySharesRepository;
+use crate::repositories::memory::tags::InMemoryTagsRepository;
+use crate::repositories::memory::timelines::InMemoryTimelinesRepository;
+use crate::repositories::memory::users::InMemoryUsersRepository;
+use crate::repositories::notebooks::NotebooksRepository;
 use crate::repositories::notes::NotesRepository;
+use crate::repositories::refresh_tokens::RefreshTokensRepository;
+use crate::repositories::revisions::RevisionsRepository;
 use crate::repositories::roles::RolesRepository;
-use crate::utils::uuid;
-use lazy_static::lazy_static;
-use scylla::transport::session::PoolSize;
-use scylla::{Session, SessionBuilder};
+use crate::repositories::scylla;
+use crate::repositories::scylla::attachments::ScyllaAttachmentsRepository;
+use crate::repositories::scylla::blobs::ScyllaBlobsRepository;
+use crate::repositories::scylla::notebooks::ScyllaNotebooksRepository;
+use crate::repositories::scylla::notes::ScyllaNotesRepository;
+use crate::repositories::scylla::refresh_tokens::ScyllaRefreshTokensRepository;
+use crate::repositories::scylla::revisions::ScyllaRevisionsRepository;
+use crate::repositories::scylla::roles::ScyllaRolesRepository;
+use crate::repositories::scylla::sessions::ScyllaSessionsRepository;
+use crate::repositories::scylla::shares::ScyllaSharesRepository;
+use crate::repositories::scylla::tags::ScyllaTagsRepository;
+use crate::repositories::scylla::timelines::ScyllaTimelinesRepository;
+use crate::repositories::scylla::users::ScyllaUsersRepository;
+use crate::repositories::sessions::SessionsRepository;
+use crate::repositories::shares::SharesRepository;
+use crate::repositories::tags::TagsRepository;
+use crate::repositories::timelines::{
+  notebook_timeline, tag_timeline, timeline, trash_timeline, TimelinesRepository, TIMELINE_ALL, TIMELINE_DELETIONS, TIMELINE_TRASH,
+};
+use crate::repositories::users::UsersRepository;
+use crate::repositories::{Page, PageRequest};
+use crate::search::SearchIndex;
+use crate::settings::Settings;
+use crate::utils::now;
 use std::env;
-use std::num::NonZeroUsize;
 use std::sync::Arc;
 
-/// Name of the keyspace.
-pub const KEYSPACE: &str = "nordnotes";
-
-/// Name of the table with notes.
-pub const TABLE_NOTES: &str = "notes";
-
-/// Name of the table with roles.
-pub const TABLE_ROLES: &str = "roles";
-
-/// Name of the table with users.
-pub const TABLE_USERS: &str = "users";
-
-lazy_static! {
-  static ref QUERY_CREATE_KEYSPACE: String = format!(
-    "CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}",
-    KEYSPACE
-  );
-  static ref QUERY_CREATE_TABLE_NOTES: String = format!(
-    "CREATE TABLE IF NOT EXISTS {}.{} (note_id text, title text, content text, created_at text, expires_at text, primary key (note_id))",
-    KEYSPACE, TABLE_NOTES
-  );
-  static ref QUERY_CREATE_TABLE_ROLES: String = format!(
-    "CREATE TABLE IF NOT EXISTS {}.{} (role_id text, name text, primary key (role_id))",
-    KEYSPACE, TABLE_ROLES
-  );
-  static ref QUERY_CREATE_TABLE_USERS: String = format!(
-    "CREATE TABLE IF NOT EXISTS {}.{} (user_id text, login text, password text, token text, primary key (user_id))",
-    KEYSPACE, TABLE_USERS
-  );
-}
-
 /// Shared application data.
 pub struct Storage {
-  /// ScyllaDB session.
-  session: Arc<Session>,
   /// Roles repository.
-  pub roles_repository: RolesRepository,
+  pub roles_repository: Box<dyn RolesRepository>,
   /// Notes repository.
-  notes_repository: NotesRepository,
-  /// Collection of users.
-  pub users: Vec<UserEntity>,
+  notes_repository: Box<dyn NotesRepository>,
+  /// Note shares repository.
+  pub shares_repository: Box<dyn SharesRepository>,
+  /// Users repository.
+  pub users_repository: Box<dyn UsersRepository>,
+  /// Sessions repository.
+  pub sessions_repository: Box<dyn SessionsRepository>,
+  /// Refresh tokens repository.
+  pub refresh_tokens_repository: Box<dyn RefreshTokensRepository>,
+  /// Note timelines repository.
+  pub timelines_repository: Box<dyn TimelinesRepository>,
+  /// Notebooks repository.
+  pub notebooks_repository: Box<dyn NotebooksRepository>,
+  /// Note tags repository.
+  pub tags_repository: Box<dyn TagsRepository>,
+  /// Note revisions repository.
+  pub revisions_repository: Box<dyn RevisionsRepository>,
+  /// Note attachments repository.
+  pub attachments_repository: Box<dyn AttachmentsRepository>,
+  /// Repository for contents of attached files.
+  pub blobs_repository: Box<dyn BlobsRepository>,
+  /// Full-text search index of notes.
+  pub search_index: SearchIndex,
+  /// Event bus publishing changes of notes.
+  pub events: EventBus,
+  /// Collaborative editing sessions of notes.
+  pub collaboration: CollaborationHub,
+  /// Application settings.
+  pub settings: Settings,
 }
 
 impl Storage {
-  /// Initializes the storage.
+  /// Initializes the storage backend selected by `NORDNOTES_STORAGE` environment variable,
+  /// with settings read from environment variables. Contents of attached files are stored
+  /// in the directory specified in settings, if any.
   pub async fn new() -> Result<Self> {
-    let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-    // connect to database
-    let session = Arc::new(
-      SessionBuilder::new()
-        .known_node(&uri)
-        .pool_size(PoolSize::PerHost(NonZeroUsize::new(4).unwrap()))
-        .build()
-        .await
-        .map_err(err_new_session)?,
-    );
-    // initialize database structure
-    session.query(QUERY_CREATE_KEYSPACE.as_str(), &[]).await.map_err(err_query)?;
-    session.query(QUERY_CREATE_TABLE_NOTES.as_str(), &[]).await.map_err(err_query)?;
-    session.query(QUERY_CREATE_TABLE_ROLES.as_str(), &[]).await.map_err(err_query)?;
-    println!("database initialized");
-    // initialize repositories
-    let roles_repository = RolesRepository::new(Arc::clone(&session));
+    let settings = Settings::from_env()?;
+    let mut storage = match env::var("NORDNOTES_STORAGE").unwrap_or_else(|_| "scylla".to_string()).as_str() {
+      "scylla" => Self::scylla().await?,
+      "memory" => Self::in_memory(),
+      other => return Err(err_invalid_storage(other)),
+    };
+    if let Some(dir) = &settings.attachment_dir {
+      storage.blobs_repository = Box::new(FilesystemBlobsRepository::new(dir));
+    }
+    storage.settings = settings;
+    Ok(storage)
+  }
+  /// Initializes the storage persisted in ScyllaDB.
+  pub async fn scylla() -> Result<Self> {
+    let session = scylla::connect().await?;
     Ok(Self {
-      session,
-      roles_repository,
-      notes_repository: NotesRepository,
-      users: load_users(),
+      roles_repository: Box::new(ScyllaRolesRepository::new(Arc::clone(&session))),
+      notes_repository: Box::new(ScyllaNotesRepository::new(Arc::clone(&session))),
+      shares_repository: Box::new(ScyllaSharesRepository::new(Arc::clone(&session))),
+      users_repository: Box::new(ScyllaUsersRepository::new(Arc::clone(&session))),
+      sessions_repository: Box::new(ScyllaSessionsRepository::new(Arc::clone(&session))),
+      refresh_tokens_repository: Box::new(ScyllaRefreshTokensRepository::new(Arc::clone(&session))),
+      timelines_repository: Box::new(ScyllaTimelinesRepository::new(Arc::clone(&session))),
+      notebooks_repository: Box::new(ScyllaNotebooksRepository::new(Arc::clone(&session))),
+      tags_repository: Box::new(ScyllaTagsRepository::new(Arc::clone(&session))),
+      revisions_repository: Box::new(ScyllaRevisionsRepository::new(Arc::clone(&session))),
+      attachments_repository: Box::new(ScyllaAttachmentsRepository::new(Arc::clone(&session))),
+      blobs_repository: Box::new(ScyllaBlobsRepository::new(session)),
+      search_index: SearchIndex::default(),
+      events: EventBus::default(),
+      collaboration: CollaborationHub::default(),
+      settings: Settings::default(),
     })
   }
-  /// Creates a new note, returns the identifier of newly created note.
-  pub async fn create_note(&mut self, title: &str, content: &str, ttl: &str) -> Result<String> {
-    let note = NoteEntity::new(title, content, ttl);
-    self.notes_repository.add(&self.session, note).await
+  /// Initializes the storage held in memory.
+  pub fn in_memory() -> Self {
+    Self {
+      roles_repository: Box::<InMemoryRolesRepository>::default(),
+      notes_repository: Box::<InMemoryNotesRepository>::default(),
+      shares_repository: Box::<InMemorySharesRepository>::default(),
+      users_repository: Box::<InMemoryUsersRepository>::default(),
+      sessions_repository: Box::<InMemorySessionsRepository>::default(),
+      refresh_tokens_repository: Box::<InMemoryRefreshTokensRepository>::default(),
+      timelines_repository: Box::<InMemoryTimelinesRepository>::default(),
+      notebooks_repository: Box::<InMemoryNotebooksRepository>::default(),
+      tags_repository: Box::<InMemoryTagsRepository>::default(),
+      revisions_repository: Box::<InMemoryRevisionsRepository>::default(),
+      attachments_repository: Box::<InMemoryAttachmentsRepository>::default(),
+      blobs_repository: Box::<InMemoryBlobsRepository>::default(),
+      search_index: SearchIndex::default(),
+      events: EventBus::default(),
+      collaboration: CollaborationHub::default(),
+      settings: Settings::default(),
+    }
+  }
+  /// Creates a new note and records its first revision authored by the owner, returns newly created note.
+  pub async fn create_note(&mut self, note: NoteEntity) -> Result<NoteEntity> {
+    self.notes_repository.add(note.clone()).await?;
+    self.revisions_repository.save(RevisionEntity::new(&note, &note.owner_id)).await?;
+    self.index_note(&note).await?;
+    self.search_index.add(&note, self.visibility_timelines(&note).await?);
+    Ok(note)
   }
-  /// Returns a list of notes that has not expired yet.
-  pub async fn get_notes(&self) -> Result<Vec<NoteEntity>> {
-    self.notes_repository.list(&self.session).await
+  /// Returns a single page of notes that have not expired yet.
+  pub async fn get_notes_page(&self, page: &PageRequest) -> Result<Page<NoteEntity>> {
+    self.notes_repository.list_page(page).await
   }
-  /// Returns a note with specified identifier.
+  /// Returns a list of notes owned by specified user that has not expired yet.
+  pub async fn get_notes_by_owner(&self, owner_id: &str) -> Result<Vec<NoteEntity>> {
+    self.notes_repository.list_by_owner(owner_id).await
+  }
+  /// Returns a note with specified identifier, notes moved to trash are not found.
   pub async fn get_note(&self, id: &str) -> Result<NoteEntity> {
-    self.notes_repository.find(&self.session, id).await
-  }
-  /// Deletes all notes.
-  pub async fn delete_notes(&mut self) -> Result<()> {
-    self.notes_repository.delete_all(&self.session).await
-  }
-  /// Generates a new token for a user when login and password are correct.
-  pub fn get_token(&mut self, login: &str, password: &str) -> Option<String> {
-    let token = uuid();
-    for user in &mut self.users {
-      if user.login == login && user.password == password {
-        user.token = Some(token.clone());
-        return Some(token);
+    let note = self.notes_repository.find(id).await?;
+    if note.is_trashed() {
+      return Err(err_note_not_found(id));
+    }
+    Ok(note)
+  }
+  /// Returns a note with specified identifier moved to trash.
+  pub async fn get_trashed_note(&self, id: &str) -> Result<NoteEntity> {
+    let note = self.notes_repository.find(id).await?;
+    if !note.is_trashed() {
+      return Err(err_note_not_found(id));
+    }
+    Ok(note)
+  }
+  /// Updates an existing note when the stored version equals the version of its previous state,
+  /// the new version of the note is recorded as a revision authored by specified user.
+  ///
+  /// Entries in timelines and tags are refreshed, because expiration time of the note may have changed,
+  /// entries of tags and notebook no longer assigned to the note are removed. Revisions and attachments
+  /// get the new expiration time of the note. The previous version
+  /// of notes stored before revisions were recorded is saved as a revision authored by the owner,
+  /// so the content of such notes is not lost.
+  pub async fn update_note(&mut self, previous: &NoteEntity, note: NoteEntity, author_id: &str) -> Result<()> {
+    self.notes_repository.update(note.clone(), previous.version).await?;
+    if !self.revisions_repository.exists(&previous.note_id, previous.version).await? {
+      self.revisions_repository.save(RevisionEntity::new(previous, &previous.owner_id)).await?;
+    }
+    if previous.expires_at != note.expires_at {
+      self.revisions_repository.refresh_expiration(&note).await?;
+      self.attachments_repository.refresh_expiration(&note).await?;
+      for attachment in self.attachments_repository.list_by_note(&note.note_id).await? {
+        self.blobs_repository.refresh_expiration(&attachment).await?;
       }
     }
-    None
+    self.revisions_repository.save(RevisionEntity::new(&note, author_id)).await?;
+    self.reindex_note(previous, &note).await
+  }
+  /// Moves the note to trash, the note is removed from its timelines, tags and search index,
+  /// its shares and revisions are retained. Returns the trashed note.
+  pub async fn trash_note(&mut self, note: &NoteEntity) -> Result<NoteEntity> {
+    let mut trashed = note.clone();
+    trashed.deleted_at = Some(now());
+    self.notes_repository.update(trashed.clone(), note.version).await?;
+    self.reindex_note(note, &trashed).await?;
+    Ok(trashed)
+  }
+  /// Restores the note from trash, returns the restored note.
+  pub async fn restore_note(&mut self, note: &NoteEntity) -> Result<NoteEntity> {
+    let mut restored = note.clone();
+    restored.deleted_at = None;
+    self.notes_repository.update(restored.clone(), note.version).await?;
+    self.reindex_note(note, &restored).await?;
+    Ok(restored)
   }
-  /// Checks if the token exists for any user.
-  pub fn check_token(&self, token: &str) -> bool {
-    for user in &self.users {
-      if user.token == Some(token.to_string()) {
-        return true;
+  /// Replaces entries of the previous state of the note in timelines, tags and search index
+  /// with entries of its current state.
+  async fn reindex_note(&mut self, previous: &NoteEntity, note: &NoteEntity) -> Result<()> {
+    if note.is_trashed() {
+      self.search_index.remove(&note.note_id);
+    } else {
+      self.search_index.add(note, self.visibility_timelines(note).await?);
+    }
+    let timelines = self.note_timelines(note).await?;
+    for timeline in self.note_timelines(previous).await? {
+      if !timelines.contains(&timeline) {
+        self.timelines_repository.delete(&timeline, previous).await?;
       }
     }
-    false
+    if let Some(deleted_at) = previous.deleted_at {
+      self.timelines_repository.delete_at(TIMELINE_DELETIONS, deleted_at, previous).await?;
+    }
+    self.tags_repository.delete(previous).await?;
+    self.index_note(note).await
   }
-}
-
-/// Loads user names and passwords from file.
-pub fn load_users() -> Vec<UserEntity> {
-  let mut users = vec![];
-  if let Ok(content) = std::fs::read_to_string("users") {
-    for line in content.lines() {
-      let mut split = line.split(':');
-      if let Some((login, password)) = split.next().zip(split.next()) {
-        users.push(UserEntity::new(login, password));
+  /// Adds the note to all timelines it belongs to (the timeline of all notes, the timeline of its owner,
+  /// timelines of users and roles the note is shared with, the timeline of its notebook and timelines of its tags)
+  /// and indexes its tags. Notes moved to trash are added only to trash timelines and to the timeline of deletions.
+  pub async fn index_note(&self, note: &NoteEntity) -> Result<()> {
+    for timeline in self.note_timelines(note).await? {
+      self.timelines_repository.save(&timeline, note).await?;
+    }
+    if let Some(deleted_at) = note.deleted_at {
+      return self.timelines_repository.save_at(TIMELINE_DELETIONS, deleted_at, note).await;
+    }
+    self.tags_repository.save(note).await
+  }
+  /// Deletes a note permanently together with its shares, tags, revisions, attachments, entries in timelines and in search index.
+  pub async fn delete_note(&mut self, note: &NoteEntity) -> Result<()> {
+    let timelines = self.note_timelines(note).await?;
+    let attachments = self.attachments_repository.list_by_note(&note.note_id).await?;
+    self.notes_repository.delete(&note.note_id).await?;
+    self.attachments_repository.delete_by_note(&note.note_id).await?;
+    for attachment in &attachments {
+      self.blobs_repository.delete(attachment).await?;
+    }
+    self.shares_repository.delete_by_note(&note.note_id).await?;
+    self.tags_repository.delete(note).await?;
+    self.revisions_repository.delete_by_note(&note.note_id).await?;
+    self.search_index.remove(&note.note_id);
+    for timeline in timelines {
+      self.timelines_repository.delete(&timeline, note).await?;
+    }
+    if let Some(deleted_at) = note.deleted_at {
+      self.timelines_repository.delete_at(TIMELINE_DELETIONS, deleted_at, note).await?;
+    }
+    Ok(())
+  }
+  /// Adds an attachment of the note, returns the added attachment.
+  ///
+  /// The content of the attached file must be already written, so listed attachments always have their contents.
+  /// When the expiration time of the note changed while the content was written, the content expires with the note.
+  pub async fn add_attachment(&self, note: &NoteEntity, mut attachment: AttachmentEntity) -> Result<AttachmentEntity> {
+    if attachment.expires_at != note.expires_at {
+      attachment.expires_at = note.expires_at;
+      self.blobs_repository.refresh_expiration(&attachment).await?;
+    }
+    self.attachments_repository.add(attachment.clone()).await?;
+    Ok(attachment)
+  }
+  /// Deletes an attachment together with the content of the attached file.
+  pub async fn delete_attachment(&self, attachment: &AttachmentEntity) -> Result<()> {
+    self.attachments_repository.delete(&attachment.note_id, &attachment.attachment_id).await?;
+    self.blobs_repository.delete(attachment).await
+  }
+  /// Returns names of all timelines the note belongs to, notes moved to trash belong only to trash timelines.
+  async fn note_timelines(&self, note: &NoteEntity) -> Result<Vec<String>> {
+    if note.is_trashed() {
+      let mut timelines = vec![TIMELINE_TRASH.to_string()];
+      if !note.owner_id.is_empty() {
+        timelines.push(trash_timeline(&note.owner_id));
       }
+      return Ok(timelines);
+    }
+    let mut timelines = vec![TIMELINE_ALL.to_string()];
+    if !note.owner_id.is_empty() {
+      timelines.push(timeline(GranteeType::User, &note.owner_id));
+    }
+    for share in self.shares_repository.list_by_note(&note.note_id).await? {
+      timelines.push(timeline(share.grantee_type, &share.grantee_id));
+    }
+    if let Some(notebook_id) = &note.notebook_id {
+      timelines.push(notebook_timeline(notebook_id));
     }
+    timelines.extend(note.tags.iter().map(|tag| tag_timeline(&note.owner_id, tag)));
+    Ok(timelines)
   }
-  users
-}
-
-#[cfg(test)]
-mod tests {
-  use super::*;
-
-  #[test]
-  fn test_queries() {
-    assert_eq!(
-      "CREATE KEYSPACE IF NOT EXISTS nordnotes WITH REPLICATION = {'class' : 'SimpleStrategy', 'replication_factor' : 1}",
-      QUERY_CREATE_KEYSPACE.as_str()
-    )
+  /// Returns timelines of users and roles the note is visible to: the timeline of its owner
+  /// and timelines of users and roles the note is shared with.
+  pub async fn visibility_timelines(&self, note: &NoteEntity) -> Result<Vec<String>> {
+    let mut timelines = vec![];
+    if !note.owner_id.is_empty() {
+      timelines.push(timeline(GranteeType::User, &note.owner_id));
+    }
+    for share in self.shares_repository.list_by_note(&note.note_id).await? {
+      timelines.push(timeline(share.grantee_type, &share.grantee_id));
+    }
+    Ok(timelines)
   }
 }
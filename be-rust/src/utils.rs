@@ -16,7 +16,13 @@
 
 //! Utility functions.
 
-use time::macros::format_description;
+use crate::errors::*;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use lazy_static::lazy_static;
+use rand_core::OsRng;
+use subtle::ConstantTimeEq;
+use time::format_description::well_known::Rfc3339;
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
@@ -25,47 +31,129 @@ pub fn uuid() -> String {
   Uuid::new_v4().to_string()
 }
 
-/// Returns a tuple of current UTC date and time, and optional expiration date and time,
-/// both formatted as strings. Expiration date and time is calculated basing on time to live marker.
-pub fn create_and_expiration_date_time(ttl: &str) -> (String, Option<String>) {
-  let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:00");
-  let created_at = OffsetDateTime::now_utc();
-  if let Some(minutes) = ttl_to_minutes(ttl) {
-    let expires_at = created_at + Duration::minutes(minutes);
-    (created_at.format(&format).unwrap(), Some(expires_at.format(&format).unwrap()))
+/// Hashes the password using Argon2id with random salt,
+/// returns the hash in PHC string format.
+pub fn hash_password(password: &str) -> Result<String> {
+  let salt = SaltString::generate(&mut OsRng);
+  Ok(
+    Argon2::default()
+      .hash_password(password.as_bytes(), &salt)
+      .map_err(err_password_hash)?
+      .to_string(),
+  )
+}
+
+lazy_static! {
+  /// Argon2id hash of a password no user has, computed with the same parameters as hashes of user passwords.
+  static ref DUMMY_PASSWORD_HASH: String = hash_password("nordnotes-dummy-password").unwrap_or_default();
+}
+
+/// Verifies the password against a dummy hash, the result is discarded.
+///
+/// Used when the user does not exist, so the verification takes as long as for existing users
+/// and the response time does not reveal which logins exist.
+pub fn verify_dummy_password(password: &str) {
+  std::hint::black_box(verify_password(password, &DUMMY_PASSWORD_HASH));
+}
+
+/// Returns `true` when the value is a password hash in PHC string format.
+pub fn is_password_hash(value: &str) -> bool {
+  PasswordHash::new(value).is_ok()
+}
+
+/// Verifies the password against stored value.
+/// Stored value is either a hash in PHC string format
+/// or a legacy plaintext password, in both cases compared in constant time.
+pub fn verify_password(password: &str, stored: &str) -> bool {
+  if let Ok(hash) = PasswordHash::new(stored) {
+    Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()
   } else {
-    (created_at.format(&format).unwrap(), None)
+    password.as_bytes().ct_eq(stored.as_bytes()).into()
   }
 }
 
-/// Converts time to live marker into minutes.
-/// Time to live has the format: `Nw`, `Nd`, `Nh` or `Nm`, where `N` is an integer
-/// and letters have the following meaning: `w` - weeks, `d` - days, `h` - hours, `m` - minutes.
-/// For example `ttl` == "10d" means 14400 minutes.
-fn ttl_to_minutes(ttl: &str) -> Option<i64> {
-  // ttl must have minimum two characters
-  if ttl.len() >= 2 {
-    // the last character must be a time marker
-    if let Some(time_marker) = ttl.chars().last() {
-      // character before time marker must form an integer number
-      let num_str = &ttl[..ttl.len() - 1];
-      if let Ok(num) = num_str.parse::<i64>() {
-        return match time_marker {
-          'w' => Some(num * 7 * 24 * 60),
-          'd' => Some(num * 24 * 60),
-          'h' => Some(num * 60),
-          'm' => Some(num),
-          _ => None,
-        };
-      }
-    }
+/// Returns current UTC date and time, truncated to milliseconds,
+/// which is the precision of timestamps stored in the database.
+pub fn now() -> OffsetDateTime {
+  let now = OffsetDateTime::now_utc();
+  now.replace_nanosecond(now.millisecond() as u32 * 1_000_000).unwrap_or(now)
+}
+
+/// Formats date and time as RFC 3339 string.
+pub fn to_rfc3339(date_time: OffsetDateTime) -> String {
+  date_time.format(&Rfc3339).unwrap_or_default()
+}
+
+/// Encodes bytes as a string of lowercase hexadecimal digits.
+pub fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes a string of hexadecimal digits into bytes, returns `None` when the string is not valid.
+pub fn from_hex(value: &str) -> Option<Vec<u8>> {
+  if value.len() % 2 != 0 || !value.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+    return None;
+  }
+  (0..value.len()).step_by(2).map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok()).collect()
+}
+
+/// Number of seconds in a day.
+const DAY: i64 = 24 * 60 * 60;
+
+/// Units of compound time to live, in required order, with their lengths in seconds.
+const TTL_UNITS: [(&str, i64); 7] = [("y", 365 * DAY), ("mo", 30 * DAY), ("w", 7 * DAY), ("d", DAY), ("h", 3600), ("m", 60), ("s", 1)];
+
+/// Date designators of ISO 8601 duration, in required order, with their lengths in seconds.
+const ISO_DATE_UNITS: [(&str, i64); 4] = [("Y", 365 * DAY), ("M", 30 * DAY), ("W", 7 * DAY), ("D", DAY)];
+
+/// Time designators of ISO 8601 duration, in required order, with their lengths in seconds.
+const ISO_TIME_UNITS: [(&str, i64); 3] = [("H", 3600), ("M", 60), ("S", 1)];
+
+/// Parses the time to live marker into positive duration.
+///
+/// Time to live is either a compound duration like `1d12h30m`, built from integers followed by units:
+/// `y` - years, `mo` - months, `w` - weeks, `d` - days, `h` - hours, `m` - minutes, `s` - seconds,
+/// specified in this order, or an ISO 8601 duration like `P1DT2H`.
+/// A year is counted as 365 days and a month as 30 days.
+pub fn parse_ttl(ttl: &str) -> Result<Duration> {
+  let seconds = match ttl.strip_prefix('P') {
+    Some(iso) => match iso.split_once('T') {
+      Some((_, "")) => None,
+      Some((date, time)) => sum_units(date, &ISO_DATE_UNITS)
+        .zip(sum_units(time, &ISO_TIME_UNITS))
+        .and_then(|(d, t)| d.checked_add(t)),
+      None => sum_units(iso, &ISO_DATE_UNITS),
+    },
+    None => sum_units(ttl, &TTL_UNITS),
+  };
+  match seconds {
+    Some(seconds) if seconds > 0 => Ok(Duration::seconds(seconds)),
+    _ => Err(err_invalid_ttl(ttl)),
   }
-  None
+}
+
+/// Sums the components of a duration, each built from an integer followed by a unit,
+/// units must appear in the order they are listed and at most once.
+/// Returns `None` when the duration is malformed or too long.
+fn sum_units(value: &str, units: &[(&str, i64)]) -> Option<i64> {
+  let mut units = units.iter();
+  let mut rest = value;
+  let mut total = 0_i64;
+  while !rest.is_empty() {
+    let digits = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+    let number = rest[..digits].parse::<i64>().ok()?;
+    rest = &rest[digits..];
+    let (unit, seconds) = units.find(|(unit, _)| rest.starts_with(unit))?;
+    rest = &rest[unit.len()..];
+    total = total.checked_add(number.checked_mul(*seconds)?)?;
+  }
+  Some(total)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use time::macros::datetime;
 
   #[test]
   fn test_uuid() {
@@ -73,36 +161,95 @@ mod tests {
   }
 
   #[test]
-  fn test_create_and_expiration_date_time() {
-    let now = OffsetDateTime::now_utc();
-    let created_at = format!(
-      "{:04}-{:02}-{:02}T{:02}:{:02}:00",
-      now.year(),
-      now.month() as u8,
-      now.day(),
-      now.hour(),
-      now.minute()
-    );
-    let expires_at = format!(
-      "{:04}-{:02}-{:02}T{:02}:{:02}:00",
-      now.year(),
-      now.month() as u8,
-      now.day(),
-      now.hour() + 2,
-      now.minute()
-    );
-    assert_eq!((created_at, Some(expires_at)), create_and_expiration_date_time("2h"))
+  fn test_hash_password() {
+    let hash = hash_password("secret").unwrap();
+    assert!(hash.starts_with("$argon2id$"));
+    assert!(is_password_hash(&hash));
+    assert!(verify_password("secret", &hash));
+    assert!(!verify_password("Secret", &hash));
+    assert_ne!(hash, hash_password("secret").unwrap());
+  }
+
+  #[test]
+  fn test_dummy_password_hash() {
+    assert!(DUMMY_PASSWORD_HASH.starts_with("$argon2id$"));
+    assert!(!verify_password("", &DUMMY_PASSWORD_HASH));
+    verify_dummy_password("secret");
+  }
+
+  #[test]
+  fn test_verify_legacy_password() {
+    assert!(!is_password_hash("secret"));
+    assert!(verify_password("secret", "secret"));
+    assert!(!verify_password("secret", "secret2"));
+    assert!(!verify_password("", "secret"));
+  }
+
+  #[test]
+  fn test_now() {
+    assert_eq!(0, now().nanosecond() % 1_000_000);
   }
 
   #[test]
-  fn test_ttl_to_minutes() {
-    assert_eq!(2 * 7 * 24 * 60, ttl_to_minutes("2w").unwrap());
-    assert_eq!(18 * 24 * 60, ttl_to_minutes("18d").unwrap());
-    assert_eq!(56 * 60, ttl_to_minutes("56h").unwrap());
-    assert_eq!(276, ttl_to_minutes("276m").unwrap());
-    assert_eq!(1, ttl_to_minutes("1m").unwrap());
-    assert_eq!(None, ttl_to_minutes("1y"));
-    assert_eq!(None, ttl_to_minutes("1"));
-    assert_eq!(None, ttl_to_minutes(""));
+  fn test_to_rfc3339() {
+    assert_eq!("2022-04-01T12:30:15Z", to_rfc3339(datetime!(2022-04-01 12:30:15 UTC)));
+    assert_eq!("2022-04-01T12:30:15.123Z", to_rfc3339(datetime!(2022-04-01 12:30:15.123 UTC)));
+  }
+
+  #[test]
+  fn test_hex() {
+    assert_eq!("00ff7f", to_hex(&[0, 255, 127]));
+    assert_eq!(Some(vec![0, 255, 127]), from_hex("00FF7f"));
+    assert_eq!(Some(vec![]), from_hex(""));
+    assert_eq!(None, from_hex("0"));
+    assert_eq!(None, from_hex("zz"));
+    assert_eq!(None, from_hex("+1"));
+  }
+
+  #[test]
+  fn test_parse_ttl() {
+    assert_eq!(Duration::weeks(2), parse_ttl("2w").unwrap());
+    assert_eq!(Duration::days(18), parse_ttl("18d").unwrap());
+    assert_eq!(Duration::hours(56), parse_ttl("56h").unwrap());
+    assert_eq!(Duration::minutes(276), parse_ttl("276m").unwrap());
+    assert_eq!(Duration::seconds(90), parse_ttl("90s").unwrap());
+    assert_eq!(Duration::days(30), parse_ttl("1mo").unwrap());
+    assert_eq!(Duration::days(365), parse_ttl("1y").unwrap());
+    assert_eq!(Duration::minutes(2190), parse_ttl("1d12h30m").unwrap());
+    assert_eq!(Duration::days(425) + Duration::seconds(5), parse_ttl("1y2mo5s").unwrap());
+  }
+
+  #[test]
+  fn test_parse_iso_ttl() {
+    assert_eq!(Duration::hours(26), parse_ttl("P1DT2H").unwrap());
+    assert_eq!(Duration::days(395), parse_ttl("P1Y1M").unwrap());
+    assert_eq!(Duration::weeks(3), parse_ttl("P3W").unwrap());
+    assert_eq!(Duration::seconds(3661), parse_ttl("PT1H1M1S").unwrap());
+    assert_eq!(Duration::minutes(5), parse_ttl("PT5M").unwrap());
+  }
+
+  #[test]
+  fn test_parse_invalid_ttl() {
+    for ttl in [
+      "",
+      "1",
+      "d",
+      "-1h",
+      "0m",
+      "1x",
+      "1h1d",
+      "1h1h",
+      "1d 2h",
+      "1.5h",
+      "P",
+      "PT",
+      "P1H",
+      "P1DT",
+      "PT1D",
+      "p1d",
+      "999999999999y",
+    ] {
+      assert_eq!(format!("invalid time to live, ttl = {}", ttl), parse_ttl(ttl).unwrap_err().to_string());
+    }
   }
 }
@@ -0,0 +1,243 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for note attachments.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, Error};
+use common::*;
+use nordnotes::settings::Settings;
+use serde_json::{json, Value};
+
+/// Boundary separating parts of multipart requests sent in tests.
+const BOUNDARY: &str = "nordnotes-boundary";
+
+/// Builds the body of a multipart request uploading files with specified names and contents.
+fn multipart_body(files: &[(&str, &[u8])]) -> Vec<u8> {
+  let mut body = vec![];
+  for (file_name, content) in files {
+    body.extend_from_slice(
+      format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        BOUNDARY, file_name
+      )
+      .as_bytes(),
+    );
+    body.extend_from_slice(content);
+    body.extend_from_slice(b"\r\n");
+  }
+  body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+  body
+}
+
+/// Uploads files to the note, returns the result of the request.
+async fn upload(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note_id: &str, files: &[(&str, &[u8])]) -> Value {
+  upload_body(app, token, note_id, multipart_body(files)).await
+}
+
+/// Sends the body of a multipart request to the note, returns the result of the request.
+async fn upload_body(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note_id: &str, body: Vec<u8>) -> Value {
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/attachments", note_id))
+    .insert_header(bearer(token))
+    .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY)))
+    .set_payload(body)
+    .to_request();
+  test::call_and_read_body_json(app, req).await
+}
+
+/// Downloads the content of an attached file, optionally requesting a range of bytes.
+async fn download(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, uri: &str, range: Option<&str>) -> ServiceResponse {
+  let mut req = test::TestRequest::get().uri(uri).insert_header(bearer(token));
+  if let Some(range) = range {
+    req = req.insert_header(("Range", range));
+  }
+  test::call_service(app, req.to_request()).await
+}
+
+/// Returns the value of the header of the response.
+fn header<'a>(response: &'a ServiceResponse, name: &str) -> &'a str {
+  response.headers().get(name).unwrap().to_str().unwrap()
+}
+
+#[actix_web::test]
+async fn test_upload_list_and_download() {
+  let app = init_app().await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let (_, eve_token) = create_user(&app, "eve").await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "recipe").await;
+  let png: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+  let result = upload(
+    &app,
+    &token,
+    &note_id,
+    &[("../photos/bullar.png", png), ("recept.html", b"<script>alert(1)</script>")],
+  )
+  .await;
+  let attachments = result["data"].as_array().unwrap();
+  assert_eq!(2, attachments.len());
+  assert_eq!("bullar.png", attachments[0]["fileName"]);
+  assert_eq!("image/png", attachments[0]["contentType"]);
+  assert_eq!(png.len(), attachments[0]["size"].as_u64().unwrap() as usize);
+  assert_eq!("recept.html", attachments[1]["fileName"]);
+  assert_eq!("text/plain; charset=utf-8", attachments[1]["contentType"]);
+  let uri = format!("/api/v1/notes/{}/attachments", note_id);
+  let result = get(&app, &token, &uri).await;
+  assert_eq!(2, result["data"].as_array().unwrap().len());
+  // image is displayed inline
+  let image_uri = format!("{}/{}", uri, attachments[0]["attachmentId"].as_str().unwrap());
+  let response = download(&app, &token, &image_uri, None).await;
+  assert_eq!(StatusCode::OK, response.status());
+  assert_eq!("image/png", header(&response, "Content-Type"));
+  assert_eq!("inline; filename=\"bullar.png\"", header(&response, "Content-Disposition"));
+  assert_eq!("nosniff", header(&response, "X-Content-Type-Options"));
+  assert_eq!("bytes", header(&response, "Accept-Ranges"));
+  assert_eq!(png, &test::read_body(response).await[..]);
+  // other files are downloaded
+  let text_uri = format!("{}/{}", uri, attachments[1]["attachmentId"].as_str().unwrap());
+  let response = download(&app, &token, &text_uri, None).await;
+  assert_eq!("attachment; filename=\"recept.html\"", header(&response, "Content-Disposition"));
+  // attachments are accessible to users with access to the note
+  let result = get(&app, &bob_token, &uri).await;
+  assert_eq!(problem(404, "not-found", format!("note not found, id = {}", note_id)), result);
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let result = get(&app, &bob_token, &uri).await;
+  assert_eq!(2, result["data"].as_array().unwrap().len());
+  let response = download(&app, &bob_token, &image_uri, None).await;
+  assert_eq!(png, &test::read_body(response).await[..]);
+  let result = upload(&app, &bob_token, &note_id, &[("notes.txt", b"text")]).await;
+  assert_eq!(
+    problem(403, "forbidden", format!("forbidden, required access = edit, note id = {}", note_id)),
+    result
+  );
+  let result = get(&app, &eve_token, &uri).await;
+  assert_eq!(problem(404, "not-found", format!("note not found, id = {}", note_id)), result);
+}
+
+#[actix_web::test]
+async fn test_download_range() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "recipe").await;
+  let result = upload(&app, &token, &note_id, &[("kanelbullar.txt", b"0123456789")]).await;
+  let uri = format!("/api/v1/notes/{}/attachments/{}", note_id, result["data"][0]["attachmentId"].as_str().unwrap());
+  let response = download(&app, &token, &uri, Some("bytes=2-5")).await;
+  assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+  assert_eq!("bytes 2-5/10", header(&response, "Content-Range"));
+  assert_eq!(b"2345", &test::read_body(response).await[..]);
+  let response = download(&app, &token, &uri, Some("bytes=7-")).await;
+  assert_eq!("bytes 7-9/10", header(&response, "Content-Range"));
+  assert_eq!(b"789", &test::read_body(response).await[..]);
+  let response = download(&app, &token, &uri, Some("bytes=-2")).await;
+  assert_eq!(b"89", &test::read_body(response).await[..]);
+  let response = download(&app, &token, &uri, Some("bytes=5-100")).await;
+  assert_eq!("bytes 5-9/10", header(&response, "Content-Range"));
+  assert_eq!(b"56789", &test::read_body(response).await[..]);
+  // multiple and malformed ranges are ignored
+  let response = download(&app, &token, &uri, Some("bytes=0-1,4-5")).await;
+  assert_eq!(StatusCode::OK, response.status());
+  assert_eq!(b"0123456789", &test::read_body(response).await[..]);
+  let response = download(&app, &token, &uri, Some("lines=1-2")).await;
+  assert_eq!(StatusCode::OK, response.status());
+  // range outside the content
+  let response = download(&app, &token, &uri, Some("bytes=10-20")).await;
+  assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, response.status());
+  assert_eq!("bytes */10", header(&response, "Content-Range"));
+  let result: Value = test::read_body_json(response).await;
+  assert_eq!(problem(416, "range-not-satisfiable", "range not satisfiable, size = 10"), result);
+}
+
+#[actix_web::test]
+async fn test_upload_limits() {
+  let app = init_app_with(Settings {
+    attachment_max_size: 8,
+    upload_max_size: 128,
+    ..Default::default()
+  })
+  .await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "recipe").await;
+  let result = upload(&app, &token, &note_id, &[("small.txt", b"12345678"), ("large.txt", b"123456789")]).await;
+  assert_eq!(problem(413, "payload-too-large", "attachment too large, maximum size = 8 bytes"), result);
+  let files: Vec<(&str, &[u8])> = (0..17).map(|_| ("file.txt", b"12345678".as_slice())).collect();
+  let result = upload(&app, &token, &note_id, &files).await;
+  assert_eq!(problem(413, "payload-too-large", "upload too large, maximum size = 128 bytes"), result);
+  // access to the note is checked before the body is read
+  let (_, eve_token) = create_user(&app, "eve").await;
+  let result = upload_body(&app, &eve_token, &note_id, b"not a multipart body".to_vec()).await;
+  assert_eq!(problem(404, "not-found", format!("note not found, id = {}", note_id)), result);
+  // parts without file names are not files
+  let body = format!("--{0}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nrecipe\r\n--{0}--\r\n", BOUNDARY);
+  let result = upload_body(&app, &token, &note_id, body.into_bytes()).await;
+  assert_eq!(problem(400, "invalid-request", "required attribute not specified, name = file"), result);
+  let result = get(&app, &token, &format!("/api/v1/notes/{}/attachments", note_id)).await;
+  assert_eq!(json!({"data": []}), result);
+  let files: Vec<(&str, &[u8])> = (0..100).map(|_| ("file.txt", b"1".as_slice())).collect();
+  let result = upload(&app, &token, &note_id, &files).await;
+  assert_eq!(100, result["data"].as_array().unwrap().len());
+  let result = upload(&app, &token, &note_id, &[("file.txt", b"1")]).await;
+  assert_eq!(problem(409, "limit-exceeded", "too many attachments, count = 101, maximum = 100"), result);
+}
+
+#[actix_web::test]
+async fn test_upload_large_file() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "backup").await;
+  let content: Vec<u8> = (0..600_000u32).map(|i| (i % 251) as u8).collect();
+  let result = upload(&app, &token, &note_id, &[("backup.bin", &content)]).await;
+  assert_eq!("application/octet-stream", result["data"][0]["contentType"]);
+  assert_eq!(600_000, result["data"][0]["size"]);
+  let uri = format!("/api/v1/notes/{}/attachments/{}", note_id, result["data"][0]["attachmentId"].as_str().unwrap());
+  let response = download(&app, &token, &uri, None).await;
+  assert_eq!(content, test::read_body(response).await.to_vec());
+  // the range spans chunks of the stored content
+  let response = download(&app, &token, &uri, Some("bytes=262000-262199")).await;
+  assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+  assert_eq!(&content[262_000..262_200], &test::read_body(response).await[..]);
+}
+
+#[actix_web::test]
+async fn test_delete_attachments() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "recipe").await;
+  let result = upload(&app, &token, &note_id, &[("a.txt", b"a"), ("b.txt", b"b")]).await;
+  let uri = format!("/api/v1/notes/{}/attachments", note_id);
+  let first_uri = format!("{}/{}", uri, result["data"][0]["attachmentId"].as_str().unwrap());
+  let second_id = result["data"][1]["attachmentId"].as_str().unwrap();
+  let result = send(&app, test::TestRequest::delete(), &token, &first_uri).await;
+  assert_eq!(json!({"data": "attachment deleted"}), result);
+  let result = get(&app, &token, &first_uri).await;
+  assert!(result["detail"].as_str().unwrap().starts_with("attachment not found"));
+  let result = get(&app, &token, &uri).await;
+  assert_eq!(second_id, result["data"][0]["attachmentId"]);
+  // attachments are kept in trash and deleted together with the note
+  send(&app, test::TestRequest::delete(), &token, &format!("/api/v1/notes/{}", note_id)).await;
+  send(&app, test::TestRequest::delete(), &token, &format!("/api/v1/trash/{}", note_id)).await;
+  let result = get(&app, &token, &uri).await;
+  assert_eq!(problem(404, "not-found", format!("note not found, id = {}", note_id)), result);
+}
@@ -0,0 +1,145 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for authorization.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use nordnotes::settings::{JwtSettings, Settings};
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn test_login() {
+  let app = init_app().await;
+  assert_eq!(36, login(&app).await.len());
+}
+
+#[actix_web::test]
+async fn test_login_invalid_password() {
+  let app = init_app().await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": LOGIN, "password": "invalid" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("invalid login or password", result["detail"]);
+}
+
+/// Calls the endpoint requiring authentication, returns `true` when the token was accepted.
+async fn is_authorized(
+  app: &impl actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+  token: &str,
+) -> bool {
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(token)).to_request();
+  let result: Value = test::call_and_read_body_json(app, req).await;
+  result["data"].is_array()
+}
+
+#[actix_web::test]
+async fn test_concurrent_sessions() {
+  let app = init_app().await;
+  let first = login(&app).await;
+  let second = login(&app).await;
+  assert_ne!(first, second);
+  assert!(is_authorized(&app, &first).await);
+  assert!(is_authorized(&app, &second).await);
+}
+
+#[actix_web::test]
+async fn test_logout() {
+  let app = init_app().await;
+  let first = login(&app).await;
+  let second = login(&app).await;
+  let req = test::TestRequest::post().uri("/api/v1/logout").insert_header(bearer(&first)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("logged out", result["data"]);
+  assert!(!is_authorized(&app, &first).await);
+  assert!(is_authorized(&app, &second).await);
+}
+
+#[actix_web::test]
+async fn test_logout_all() {
+  let app = init_app().await;
+  let first = login(&app).await;
+  let second = login(&app).await;
+  let (_, other) = create_user(&app, "alice").await;
+  let req = test::TestRequest::post().uri("/api/v1/logout/all").insert_header(bearer(&first)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("all sessions logged out", result["data"]);
+  assert!(!is_authorized(&app, &first).await);
+  assert!(!is_authorized(&app, &second).await);
+  assert!(is_authorized(&app, &other).await);
+}
+
+#[actix_web::test]
+async fn test_logout_requires_session() {
+  let app = init_app().await;
+  let req = test::TestRequest::post().uri("/api/v1/logout").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_login_without_jwt() {
+  let app = init_app().await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": LOGIN, "password": PASSWORD }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["data"]["accessToken"].is_null());
+  let req = test::TestRequest::post()
+    .uri("/api/v1/refresh")
+    .set_json(json!({ "refreshToken": "token" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("JWT access tokens are disabled", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_jwt_access_and_refresh_tokens() {
+  let settings = Settings {
+    jwt: Some(JwtSettings::hs256(b"secret")),
+    ..Settings::default()
+  };
+  let app = init_app_with(settings).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": LOGIN, "password": PASSWORD }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let access_token = result["data"]["accessToken"].as_str().unwrap().to_string();
+  let refresh_token = result["data"]["refreshToken"].as_str().unwrap().to_string();
+  assert_eq!(900, result["data"]["expiresIn"]);
+  assert!(is_authorized(&app, &access_token).await);
+  let req = test::TestRequest::post()
+    .uri("/api/v1/refresh")
+    .set_json(json!({ "refreshToken": refresh_token }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let new_access_token = result["data"]["accessToken"].as_str().unwrap().to_string();
+  assert!(is_authorized(&app, &new_access_token).await);
+  // reusing the rotated refresh token revokes the whole session
+  let req = test::TestRequest::post()
+    .uri("/api/v1/refresh")
+    .set_json(json!({ "refreshToken": refresh_token }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("refresh token reused, session revoked", result["detail"]);
+  assert!(!is_authorized(&app, &new_access_token).await);
+}
@@ -0,0 +1,213 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for collaborative editing of notes.
+
+mod common;
+
+use actix_http::ws::{CloseCode, Frame, Message};
+use actix_web::{test, App};
+use common::*;
+use nordnotes::server::configure;
+use nordnotes::services::collaboration::save;
+use nordnotes::settings::Settings;
+use serde_json::{json, Value};
+
+/// Joins the collaborative editing session of the note, returns the client and the synchronization message.
+async fn join(address: &str, note_id: &str, token: &str) -> (WebSocketClient, Value) {
+  let (mut client, head) = WebSocketClient::connect(address, &format!("/api/v1/notes/{}/collaboration", note_id), token).await;
+  assert!(head.starts_with("http/1.1 101"), "{}", head);
+  let message = receive(&mut client).await;
+  assert_eq!("sync", message["type"]);
+  (client, message)
+}
+
+/// Receives the next text message, returns the parsed message.
+async fn receive(client: &mut WebSocketClient) -> Value {
+  match client.receive().await {
+    Some(Frame::Text(text)) => serde_json::from_slice(&text).unwrap(),
+    other => panic!("unexpected frame {:?}", other),
+  }
+}
+
+/// Sends operations to the session.
+async fn send(client: &mut WebSocketClient, operations: Value) {
+  let message = json!({ "type": "update", "operations": operations });
+  client.send(Message::Text(message.to_string().into())).await;
+}
+
+#[actix_web::test]
+async fn test_collaborative_editing() {
+  let data = init_data(Settings::default()).await;
+  let (address, handle) = start_server(data.clone());
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "meeting").await;
+  let (mut alice, sync) = join(&address, &note_id, &token).await;
+  assert_eq!("1", sync["siteId"]);
+  assert_eq!(1, sync["version"]);
+  assert_eq!(false, sync["readOnly"]);
+  assert_eq!(7, sync["snapshot"].as_array().unwrap().len());
+  assert_eq!(json!({"id": [1, ""], "value": "c", "deleted": false}), sync["snapshot"][0]);
+  assert_eq!(json!([]), sync["updates"]);
+  let (mut bob, sync) = join(&address, &note_id, &token).await;
+  assert_eq!("2", sync["siteId"]);
+  // "content" -> "Content:"
+  let operations = json!([
+    {"op": "insert", "id": [8, "1"], "after": null, "value": "C"},
+    {"op": "delete", "id": [1, ""]},
+    {"op": "insert", "id": [9, "1"], "after": [7, ""], "value": ":"}
+  ]);
+  send(&mut alice, operations.clone()).await;
+  let update = receive(&mut bob).await;
+  assert_eq!(json!({"type": "update", "siteId": "1", "operations": operations}), update);
+  // late joiner receives the snapshot and pending updates
+  let (mut carol, sync) = join(&address, &note_id, &token).await;
+  assert_eq!("3", sync["siteId"]);
+  assert_eq!(7, sync["snapshot"].as_array().unwrap().len());
+  assert_eq!(operations, sync["updates"]);
+  // the document is saved into the note
+  assert_eq!(1, save(&mut *data.storage.write().await).await.unwrap());
+  for client in [&mut alice, &mut bob, &mut carol] {
+    assert_eq!(json!({"type": "saved", "version": 2}), receive(client).await);
+  }
+  let note = get_note(&app, &token, &note_id).await["data"].clone();
+  assert_eq!("Content:", note["content"]);
+  assert_eq!(2, note["version"]);
+  assert_eq!(0, save(&mut *data.storage.write().await).await.unwrap());
+  let (mut dave, sync) = join(&address, &note_id, &token).await;
+  assert_eq!(2, sync["version"]);
+  assert_eq!(9, sync["snapshot"].as_array().unwrap().len());
+  assert_eq!(json!([]), sync["updates"]);
+  // the note modified outside the session replaces the document
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "content": "agenda" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  send(&mut dave, json!([{"op": "insert", "id": [10, "4"], "after": null, "value": "#"}])).await;
+  assert_eq!("update", receive(&mut alice).await["type"]);
+  assert_eq!(0, save(&mut *data.storage.write().await).await.unwrap());
+  let sync = receive(&mut alice).await;
+  assert_eq!("sync", sync["type"]);
+  assert_eq!(3, sync["version"]);
+  assert_eq!(json!([]), sync["updates"]);
+  assert_eq!(6, sync["snapshot"].as_array().unwrap().len());
+  assert_eq!("agenda", get_note(&app, &token, &note_id).await["data"]["content"]);
+  for client in [&mut alice, &mut bob, &mut carol, &mut dave] {
+    client.send(Message::Close(Some(CloseCode::Normal.into()))).await;
+  }
+  handle.stop(false).await;
+}
+
+#[actix_web::test]
+async fn test_invalid_messages() {
+  let data = init_data(Settings::default()).await;
+  let (address, handle) = start_server(data.clone());
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "meeting").await;
+  let (mut client, _) = join(&address, &note_id, &token).await;
+  client.send(Message::Text("{".into())).await;
+  let error = receive(&mut client).await;
+  assert_eq!("error", error["type"]);
+  assert!(error["details"].as_str().unwrap().starts_with("invalid message: "), "{}", error);
+  let cases = [
+    (
+      json!([{"op": "insert", "id": [8, "2"], "after": null, "value": "x"}]),
+      "invalid operation: foreign site identifier, id = 8@2",
+    ),
+    (
+      json!([{"op": "insert", "id": [8, "1"], "after": null, "value": "xy"}]),
+      "invalid operation: value is not a single character, value = xy",
+    ),
+  ];
+  for (operations, details) in cases {
+    send(&mut client, operations).await;
+    assert_eq!(
+      json!({"type": "error", "code": "invalid-request", "details": details}),
+      receive(&mut client).await
+    );
+  }
+  let operations: Vec<Value> = (0..1001).map(|_| json!({"op": "delete", "id": [1, ""]})).collect();
+  send(&mut client, json!(operations)).await;
+  let error = receive(&mut client).await;
+  assert_eq!("limit-exceeded", error["code"]);
+  assert_eq!("too many operations, count = 1001, maximum = 1000", error["details"]);
+  // the note deleted while edited ends the session
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(0, save(&mut *data.storage.write().await).await.unwrap());
+  match client.receive().await {
+    Some(Frame::Close(Some(reason))) => assert_eq!(CloseCode::Away, reason.code),
+    other => panic!("unexpected frame {:?}", other),
+  }
+  handle.stop(false).await;
+}
+
+#[actix_web::test]
+async fn test_read_only_participant() {
+  let data = init_data(Settings::default()).await;
+  let (address, handle) = start_server(data.clone());
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_note(&app, &alice_token, "meeting").await;
+  let uri = format!("/api/v1/notes/{}/collaboration", note_id);
+  let (_, head) = WebSocketClient::connect(&address, &uri, &bob_token).await;
+  assert!(head.starts_with("http/1.1 404"), "{}", head);
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&alice_token))
+    .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let (mut bob, sync) = join(&address, &note_id, &bob_token).await;
+  assert_eq!(true, sync["readOnly"]);
+  send(&mut bob, json!([{"op": "delete", "id": [1, ""]}])).await;
+  let error = receive(&mut bob).await;
+  assert_eq!("error", error["type"]);
+  assert_eq!("forbidden", error["code"]);
+  assert_eq!(0, save(&mut *data.storage.write().await).await.unwrap());
+  handle.stop(false).await;
+}
+
+#[actix_web::test]
+async fn test_join_not_authorized() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "meeting").await;
+  let uri = format!("/api/v1/notes/{}/collaboration", note_id);
+  let req = test::TestRequest::get().uri(&uri).insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(problem(426, "upgrade-required", "WebSocket connection required"), result);
+  for uri in [uri.clone(), format!("{}?token=invalid", uri)] {
+    let req = test::TestRequest::get()
+      .uri(&uri)
+      .insert_header(("Upgrade", "websocket"))
+      .insert_header(("Connection", "Upgrade"))
+      .insert_header(("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="))
+      .insert_header(("Sec-WebSocket-Version", "13"))
+      .to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem(401, "not-authorized", "not authorized"), result);
+  }
+}
@@ -0,0 +1,227 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Utilities shared by HTTP API tests.
+
+// not every test crate uses all utilities
+#![allow(dead_code)]
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{Codec, Frame, Message};
+use actix_http::Request;
+use actix_web::dev::{ServerHandle, Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::web::BytesMut;
+use actix_web::{test, web, App, Error, HttpServer};
+use nordnotes::server::{configure, ApplicationData};
+use nordnotes::services::system::initialize;
+use nordnotes::settings::Settings;
+use nordnotes::storage::Storage;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// Maximum time of waiting for data sent by the server.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Login of the superuser registered in every test application.
+pub const LOGIN: &str = "tester";
+
+/// Password of the superuser registered in every test application.
+pub const PASSWORD: &str = "secret";
+
+/// Initializes the application with in-memory storage, default roles and a superuser.
+pub async fn init_app() -> impl Service<Request, Response = ServiceResponse, Error = Error> {
+  init_app_with(Settings::default()).await
+}
+
+/// Initializes the application like [init_app], using specified settings.
+pub async fn init_app_with(settings: Settings) -> impl Service<Request, Response = ServiceResponse, Error = Error> {
+  test::init_service(App::new().app_data(init_data(settings).await).configure(configure)).await
+}
+
+/// Initializes application data with in-memory storage using specified settings, default roles and a superuser.
+pub async fn init_data(settings: Settings) -> web::Data<ApplicationData> {
+  let mut storage = Storage::in_memory();
+  storage.settings = settings;
+  initialize(&storage, Some(LOGIN.to_string()), Some(PASSWORD.to_string()), false).await.unwrap();
+  web::Data::new(ApplicationData::new(storage))
+}
+
+/// Logs in the superuser and returns the authorization token.
+pub async fn login(app: &impl Service<Request, Response = ServiceResponse, Error = Error>) -> String {
+  login_as(app, LOGIN, PASSWORD).await
+}
+
+/// Logs in the user with specified login and password, returns the authorization token.
+pub async fn login_as(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, login: &str, password: &str) -> String {
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": login, "password": password }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(app, req).await;
+  result["data"]["token"].as_str().unwrap().to_string()
+}
+
+/// Creates a user with default roles and logs the user in, returns the identifier of the user and authorization token.
+pub async fn create_user(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, login: &str) -> (String, String) {
+  let req = test::TestRequest::post()
+    .uri("/api/v1/users")
+    .insert_header(bearer(&self::login(app).await))
+    .set_json(json!({ "login": login, "password": login }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(app, req).await;
+  let user_id = result["data"]["userId"].as_str().unwrap().to_string();
+  (user_id, login_as(app, login, login).await)
+}
+
+/// Creates a note on behalf of the user identified by token, returns the identifier of the note.
+pub async fn create_note(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, title: &str) -> String {
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(token))
+    .set_json(json!({ "title": title, "content": "content" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(app, req).await;
+  result["data"]["noteId"].as_str().unwrap().to_string()
+}
+
+/// Creates a note with specified attributes, returns the result of the request.
+pub async fn create(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note: Value) -> Value {
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(token))
+    .set_json(note)
+    .to_request();
+  test::call_and_read_body_json(app, req).await
+}
+
+/// Sends a GET request, returns the result of the request.
+pub async fn get(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, uri: &str) -> Value {
+  let req = test::TestRequest::get().uri(uri).insert_header(bearer(token)).to_request();
+  test::call_and_read_body_json(app, req).await
+}
+
+/// Sends a request with specified method, returns the result of the request.
+pub async fn send(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, req: test::TestRequest, token: &str, uri: &str) -> Value {
+  test::call_and_read_body_json(app, req.uri(uri).insert_header(bearer(token)).to_request()).await
+}
+
+/// Reads the note with specified identifier, returns the result of the request.
+pub async fn get_note(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note_id: &str) -> Value {
+  get(app, token, &format!("/api/v1/notes/{}", note_id)).await
+}
+
+/// Returns sorted titles of listed notes.
+pub fn titles(result: &Value) -> Vec<String> {
+  let mut titles: Vec<String> = result["data"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|note| note["title"].as_str().unwrap().to_string())
+    .collect();
+  titles.sort();
+  titles
+}
+
+/// Returns identifiers of listed notes.
+pub fn note_ids(result: &Value) -> Vec<String> {
+  result["data"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|note| note["noteId"].as_str().unwrap().to_string())
+    .collect()
+}
+
+/// Returns problem details reported for an error with specified HTTP status, code and details.
+pub fn problem(status: u16, code: &str, detail: impl Into<Value>) -> Value {
+  let title = StatusCode::from_u16(status).unwrap().canonical_reason().unwrap();
+  json!({ "type": "about:blank", "title": title, "status": status, "detail": detail.into(), "code": code })
+}
+
+/// Returns the value of the authorization header for specified token.
+pub fn bearer(token: &str) -> (&'static str, String) {
+  ("Authorization", format!("Bearer {}", token))
+}
+
+/// Starts the server listening on a random local port, returns the address of the server and its handle.
+pub fn start_server(data: web::Data<ApplicationData>) -> (String, ServerHandle) {
+  let server = HttpServer::new(move || App::new().app_data(data.clone()).configure(configure))
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+  let address = server.addrs()[0].to_string();
+  let server = server.run();
+  let handle = server.handle();
+  actix_web::rt::spawn(server);
+  (address, handle)
+}
+
+/// Client side of a WebSocket connection used in tests.
+pub struct WebSocketClient {
+  /// Connection to the server.
+  stream: TcpStream,
+  /// Client WebSocket codec.
+  codec: Codec,
+  /// Received bytes not decoded yet.
+  received: BytesMut,
+}
+
+impl WebSocketClient {
+  /// Connects to the endpoint at specified path using WebSocket, returns the client and the response head (lowercase).
+  pub async fn connect(address: &str, path: &str, token: &str) -> (Self, String) {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let request = format!(
+      "GET {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+      path, address, token
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut received = BytesMut::new();
+    let head = loop {
+      if let Some(end) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+        break String::from_utf8(received.split_to(end + 4).to_vec()).unwrap();
+      }
+      assert_ne!(0, timeout(TIMEOUT, stream.read_buf(&mut received)).await.unwrap().unwrap());
+    };
+    let client = Self {
+      stream,
+      codec: Codec::new().client_mode(),
+      received,
+    };
+    (client, head.to_lowercase())
+  }
+  /// Sends a message to the server.
+  pub async fn send(&mut self, message: Message) {
+    let mut encoded = BytesMut::new();
+    self.codec.encode(message, &mut encoded).unwrap();
+    self.stream.write_all(&encoded).await.unwrap();
+  }
+  /// Receives the next frame from the server, `None` when the connection was closed.
+  pub async fn receive(&mut self) -> Option<Frame> {
+    loop {
+      if let Some(frame) = self.codec.decode(&mut self.received).unwrap() {
+        return Some(frame);
+      }
+      if timeout(TIMEOUT, self.stream.read_buf(&mut self.received)).await.unwrap().unwrap() == 0 {
+        return None;
+      }
+    }
+  }
+}
@@ -0,0 +1,106 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of errors reported by the HTTP API.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, Error};
+use common::*;
+use serde_json::{json, Value};
+
+/// Sends the request, checks that the error is reported as problem details with specified status,
+/// returns the headers and the body of the response.
+async fn call_failing(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, req: test::TestRequest, status: u16) -> (Option<String>, Value) {
+  let response = test::call_service(app, req.to_request()).await;
+  assert_eq!(status, response.status().as_u16());
+  assert_eq!("application/problem+json", response.headers().get("Content-Type").unwrap());
+  let authenticate = response.headers().get("WWW-Authenticate").map(|value| value.to_str().unwrap().to_string());
+  (authenticate, test::read_body_json(response).await)
+}
+
+#[actix_web::test]
+async fn test_authorization_errors() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (authenticate, result) = call_failing(&app, test::TestRequest::get().uri("/api/v1/notes"), 401).await;
+  assert_eq!(Some("Bearer".to_string()), authenticate);
+  assert_eq!(problem(401, "not-authorized", "not authorized"), result);
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": "alice", "password": "wrong" }));
+  let (authenticate, result) = call_failing(&app, req, 401).await;
+  assert_eq!(None, authenticate);
+  assert_eq!(problem(401, "invalid-credentials", "invalid login or password"), result);
+  let req = test::TestRequest::delete().uri("/api/v1/notes").insert_header(bearer(&alice_token));
+  let (_, result) = call_failing(&app, req, 403).await;
+  assert_eq!(problem(403, "forbidden", "forbidden, required permission = notes:admin"), result);
+}
+
+#[actix_web::test]
+async fn test_request_errors() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let (_, result) = call_failing(&app, test::TestRequest::get().uri("/api/v1/unknown"), 404).await;
+  assert_eq!(problem(404, "not-found", "endpoint not found: /api/v1/unknown"), result);
+  let req = test::TestRequest::get().uri("/api/v1/notes/unknown").insert_header(bearer(&token));
+  let (_, result) = call_failing(&app, req, 404).await;
+  assert_eq!(problem(404, "not-found", "note not found, id = unknown"), result);
+  let req = test::TestRequest::get().uri("/api/v1/notes?pageSize=0").insert_header(bearer(&token));
+  let (_, result) = call_failing(&app, req, 400).await;
+  assert_eq!("invalid-request", result["code"]);
+  // malformed request bodies are reported as problem details too
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .insert_header(("Content-Type", "application/json"))
+    .set_payload("{\"title\":");
+  let (_, result) = call_failing(&app, req, 400).await;
+  assert_eq!("invalid-request", result["code"]);
+  assert!(result["detail"].as_str().unwrap().starts_with("invalid request: "), "{}", result);
+  let req = test::TestRequest::post()
+    .uri("/api/v1/users")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "login": LOGIN, "password": "secret" }));
+  let (_, result) = call_failing(&app, req, 409).await;
+  assert_eq!(problem(409, "already-exists", "user already exists, login = tester"), result);
+}
+
+#[actix_web::test]
+async fn test_version_errors() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let note_id = create_note(&app, &token, "meeting").await;
+  let uri = format!("/api/v1/notes/{}", note_id);
+  let req = test::TestRequest::patch()
+    .uri(&uri)
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "standup" }));
+  let (_, result) = call_failing(&app, req, 428).await;
+  assert_eq!("precondition-required", result["code"]);
+  let req = test::TestRequest::patch()
+    .uri(&uri)
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "\"5\""))
+    .set_json(json!({ "title": "standup" }));
+  let (_, result) = call_failing(&app, req, 409).await;
+  assert_eq!(
+    problem(409, "version-conflict", format!("version conflict, note id = {}, current version = 1", note_id)),
+    result
+  );
+}
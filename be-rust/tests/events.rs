@@ -0,0 +1,165 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for note events.
+
+mod common;
+
+use actix_http::ws::{CloseCode, Frame, Message};
+use actix_http::Request;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::web::Bytes;
+use actix_web::{test, App, Error};
+use common::*;
+use nordnotes::server::configure;
+use nordnotes::services::events::publish_expired;
+use nordnotes::settings::Settings;
+use serde_json::{json, Value};
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Subscribes to events as Server-Sent Events, returns the body of the response.
+async fn subscribe(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str) -> BoxBody {
+  let req = test::TestRequest::get().uri(&format!("/api/v1/events?token={}", token)).to_request();
+  let response = test::call_service(app, req).await;
+  assert_eq!("text/event-stream", response.headers().get("Content-Type").unwrap());
+  response.into_body()
+}
+
+/// Reads the next Server-Sent Event from the body of the response, returns the type and the data of the event.
+async fn next_event(body: &mut BoxBody) -> (String, Value) {
+  let chunk = timeout(TIMEOUT, poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)))
+    .await
+    .unwrap()
+    .unwrap()
+    .unwrap();
+  let text = String::from_utf8(chunk.to_vec()).unwrap();
+  let (kind, data) = text.trim_end().split_once('\n').unwrap();
+  (
+    kind.strip_prefix("event: ").unwrap().to_string(),
+    serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+  )
+}
+
+#[actix_web::test]
+async fn test_server_sent_events() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let mut admin_events = subscribe(&app, &token).await;
+  let mut bob_events = subscribe(&app, &bob_token).await;
+  let note_id = create_note(&app, &alice_token, "meeting").await;
+  let (kind, event) = next_event(&mut admin_events).await;
+  assert_eq!("created", kind);
+  assert_eq!("created", event["type"]);
+  assert_eq!(note_id, event["noteId"]);
+  assert_eq!("meeting", event["note"]["title"]);
+  assert!(event["note"]["content"].is_null());
+  // bob receives events of the note when it is shared with him
+  let uri = format!("/api/v1/notes/{}", note_id);
+  let share = json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" });
+  send(&app, test::TestRequest::post().set_json(share), &alice_token, &format!("{}/shares", uri)).await;
+  let req = test::TestRequest::patch()
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "title": "standup" }));
+  send(&app, req, &alice_token, &uri).await;
+  let (kind, event) = next_event(&mut bob_events).await;
+  assert_eq!("updated", kind);
+  assert_eq!(note_id, event["noteId"]);
+  assert_eq!("standup", event["note"]["title"]);
+  assert_eq!(2, event["note"]["version"]);
+  assert_eq!("updated", next_event(&mut admin_events).await.0);
+  let req = test::TestRequest::delete().uri(&uri).insert_header(bearer(&alice_token)).to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let (kind, event) = next_event(&mut bob_events).await;
+  assert_eq!("deleted", kind);
+  assert_eq!(json!({"type": "deleted", "noteId": note_id, "occurredAt": event["occurredAt"]}), event);
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/trash/{}/restore", note_id))
+    .insert_header(bearer(&alice_token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("created", next_event(&mut bob_events).await.0);
+}
+
+#[actix_web::test]
+async fn test_expiration_events() {
+  let data = init_data(Settings::default()).await;
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let token = login(&app).await;
+  let mut events = subscribe(&app, &token).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "expiring", "content": "content", "ttl": "1s" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap();
+  assert_eq!("created", next_event(&mut events).await.0);
+  assert_eq!(0, publish_expired(&*data.storage.read().await));
+  tokio::time::sleep(Duration::from_millis(1100)).await;
+  assert_eq!(1, publish_expired(&*data.storage.read().await));
+  let (kind, event) = next_event(&mut events).await;
+  assert_eq!("expired", kind);
+  assert_eq!(note_id, event["noteId"]);
+  assert_eq!(result["data"]["expiresAt"], event["occurredAt"]);
+}
+
+#[actix_web::test]
+async fn test_subscribe_not_authorized() {
+  let app = init_app().await;
+  for uri in ["/api/v1/events", "/api/v1/events?token=invalid"] {
+    let req = test::TestRequest::get().uri(uri).to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem(401, "not-authorized", "not authorized"), result);
+  }
+}
+
+#[actix_web::test]
+async fn test_web_socket() {
+  let data = init_data(Settings::default()).await;
+  let (address, handle) = start_server(data.clone());
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let token = login(&app).await;
+  let (mut client, head) = WebSocketClient::connect(&address, "/api/v1/events", &token).await;
+  assert!(head.starts_with("http/1.1 101"), "{}", head);
+  assert!(head.contains("sec-websocket-accept: s3pplmbitxaq9kygzzhzrbk+xoo="), "{}", head);
+  let note_id = create_note(&app, &token, "meeting").await;
+  match client.receive().await {
+    Some(Frame::Text(text)) => {
+      let event: Value = serde_json::from_slice(&text).unwrap();
+      assert_eq!("created", event["type"]);
+      assert_eq!(note_id, event["noteId"]);
+    }
+    other => panic!("unexpected frame {:?}", other),
+  }
+  client.send(Message::Ping(Bytes::from_static(b"hej"))).await;
+  match client.receive().await {
+    Some(Frame::Pong(message)) => assert_eq!(Bytes::from_static(b"hej"), message),
+    other => panic!("unexpected frame {:?}", other),
+  }
+  client.send(Message::Close(Some(CloseCode::Normal.into()))).await;
+  match client.receive().await {
+    Some(Frame::Close(Some(reason))) => assert_eq!(CloseCode::Normal, reason.code),
+    other => panic!("unexpected frame {:?}", other),
+  }
+  assert!(client.receive().await.is_none());
+  handle.stop(true).await;
+}
@@ -0,0 +1,133 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for notebooks.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, Error};
+use common::*;
+use serde_json::{json, Value};
+
+/// Creates a notebook with specified name, returns the identifier of the notebook.
+async fn create_notebook(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, name: &str) -> String {
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notebooks")
+    .insert_header(bearer(token))
+    .set_json(json!({ "name": name }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(app, req).await;
+  result["data"]["notebookId"].as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn test_manage_notebooks() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let notebook_id = create_notebook(&app, &token, " Recipes ").await;
+  let result = get(&app, &token, &format!("/api/v1/notebooks/{}", notebook_id)).await;
+  assert_eq!("Recipes", result["data"]["name"]);
+  assert!(result["data"]["createdAt"].is_string());
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/notebooks/{}", notebook_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "name": "Cooking" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("Cooking", result["data"]["name"]);
+  create_notebook(&app, &token, "Travel").await;
+  let result = get(&app, &token, "/api/v1/notebooks").await;
+  let mut names: Vec<&str> = result["data"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|notebook| notebook["name"].as_str().unwrap())
+    .collect();
+  names.sort();
+  assert_eq!(vec!["Cooking", "Travel"], names);
+  // notebooks are private
+  let (_, bob_token) = create_user(&app, "bob").await;
+  assert!(get(&app, &bob_token, "/api/v1/notebooks").await["data"].as_array().unwrap().is_empty());
+  let result = get(&app, &bob_token, &format!("/api/v1/notebooks/{}", notebook_id)).await;
+  assert_eq!(format!("notebook not found, id = {}", notebook_id), result["detail"]);
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notebooks")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "name": "  " }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("required attribute not specified, name = name", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_notes_in_notebook() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let notebook_id = create_notebook(&app, &token, "Recipes").await;
+  let result = create(&app, &token, json!({ "title": "Kanelbullar", "content": "", "notebookId": notebook_id })).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  create(&app, &token, json!({ "title": "Elsewhere", "content": "" })).await;
+  let result = get(&app, &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!(notebook_id, result["data"]["notebookId"]);
+  let uri = format!("/api/v1/notebooks/{}/notes", notebook_id);
+  assert_eq!(vec!["Kanelbullar"], titles(&get(&app, &token, &uri).await));
+  // moving the note into another notebook
+  let other_id = create_notebook(&app, &token, "Baking").await;
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "notebookId": other_id }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(other_id, result["data"]["notebookId"]);
+  assert!(titles(&get(&app, &token, &uri).await).is_empty());
+  assert_eq!(
+    vec!["Kanelbullar"],
+    titles(&get(&app, &token, &format!("/api/v1/notebooks/{}/notes", other_id)).await)
+  );
+  // deleting the notebook keeps its notes
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notebooks/{}", other_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("notebook deleted, removed notes = 1", result["data"]);
+  let result = get(&app, &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!("Kanelbullar", result["data"]["title"]);
+  assert!(result["data"]["notebookId"].is_null());
+  assert_eq!(3, result["data"]["version"]);
+}
+
+#[actix_web::test]
+async fn test_notebook_of_other_user() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (_, bob_token) = create_user(&app, "bob").await;
+  let notebook_id = create_notebook(&app, &alice_token, "Private").await;
+  let result = create(&app, &bob_token, json!({ "title": "note", "content": "", "notebookId": notebook_id })).await;
+  assert_eq!(format!("notebook not found, id = {}", notebook_id), result["detail"]);
+  let result = get(&app, &bob_token, &format!("/api/v1/notebooks/{}/notes", notebook_id)).await;
+  assert_eq!(format!("notebook not found, id = {}", notebook_id), result["detail"]);
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notebooks/{}", notebook_id))
+    .insert_header(bearer(&bob_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("notebook not found, id = {}", notebook_id), result["detail"]);
+}
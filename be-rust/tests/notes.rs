@@ -0,0 +1,474 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for notes.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Parses the date and time attribute of the note, formatted in RFC 3339.
+fn date_time(value: &Value) -> OffsetDateTime {
+  OffsetDateTime::parse(value.as_str().unwrap(), &Rfc3339).unwrap()
+}
+
+#[actix_web::test]
+async fn test_create_and_get_note() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "Shopping", "content": "milk, bread", "ttl": "1d" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("Shopping", result["data"]["title"]);
+  assert_eq!("milk, bread", result["data"]["content"]);
+  let created_at = date_time(&result["data"]["createdAt"]);
+  assert!((OffsetDateTime::now_utc() - created_at).whole_seconds() < 60);
+  assert_eq!(created_at, date_time(&result["data"]["updatedAt"]));
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(1, result["data"].as_array().unwrap().len());
+}
+
+#[actix_web::test]
+async fn test_create_note_not_authorized() {
+  let app = init_app().await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .set_json(json!({ "title": "Shopping", "content": "milk, bread" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_delete_all_notes() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for title in ["first", "second"] {
+    let req = test::TestRequest::post()
+      .uri("/api/v1/notes")
+      .insert_header(bearer(&token))
+      .set_json(json!({ "title": title, "content": "content" }))
+      .to_request();
+    test::call_service(&app, req).await;
+  }
+  let req = test::TestRequest::delete().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("all notes moved to trash, notes = 2", result["data"]);
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["data"].as_array().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_delete_all_notes_forbidden() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let req = test::TestRequest::delete().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("forbidden, required permission = notes:admin", result["detail"]);
+  let req = test::TestRequest::delete().uri("/api/v1/notes").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_list_notes_not_authorized() {
+  let app = init_app().await;
+  let req = test::TestRequest::get().uri("/api/v1/notes").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_notes_are_visible_to_owners_and_admins() {
+  let app = init_app().await;
+  let admin_token = login(&app).await;
+  let (alice_id, alice_token) = create_user(&app, "alice").await;
+  let (_, bob_token) = create_user(&app, "bob").await;
+  let alice_note_id = create_note(&app, &alice_token, "alice's note").await;
+  create_note(&app, &bob_token, "bob's note").await;
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&alice_token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let notes = result["data"].as_array().unwrap();
+  assert_eq!(1, notes.len());
+  assert_eq!(alice_note_id, notes[0]["noteId"]);
+  assert_eq!(alice_id, notes[0]["ownerId"]);
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}", alice_note_id))
+    .insert_header(bearer(&bob_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("note not found, id = {}", alice_note_id), result["detail"]);
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&admin_token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(2, result["data"].as_array().unwrap().len());
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}", alice_note_id))
+    .insert_header(bearer(&admin_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("alice's note", result["data"]["title"]);
+}
+
+#[actix_web::test]
+async fn test_replace_and_update_note() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &token, "draft").await;
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "\"1\""))
+    .set_json(json!({ "title": "final", "content": "replaced" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("final", result["data"]["title"]);
+  assert_eq!("replaced", result["data"]["content"]);
+  assert!(date_time(&result["data"]["updatedAt"]) >= date_time(&result["data"]["createdAt"]));
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "\"2\""))
+    .set_json(json!({ "content": "patched" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("final", result["data"]["title"]);
+  assert_eq!("patched", result["data"]["content"]);
+  assert_eq!(3, result["data"]["version"]);
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "title": "missing content" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("required attribute not specified, name = content", result["detail"]);
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({}))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(
+    "required attribute not specified, name = title, content, contentFormat, ttl, expiresAt, tags or notebookId",
+    result["detail"]
+  );
+}
+
+#[actix_web::test]
+async fn test_update_shared_note() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_note(&app, &alice_token, "shared").await;
+  let share = |access: &str| {
+    test::TestRequest::post()
+      .uri(&format!("/api/v1/notes/{}/shares", note_id))
+      .insert_header(bearer(&alice_token))
+      .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": access }))
+      .to_request()
+  };
+  let patch = || {
+    test::TestRequest::patch()
+      .uri(&format!("/api/v1/notes/{}", note_id))
+      .insert_header(bearer(&bob_token))
+      .insert_header(("If-Match", "*"))
+      .set_json(json!({ "title": "edited by bob" }))
+      .to_request()
+  };
+  test::call_service(&app, share("read")).await;
+  let result: Value = test::call_and_read_body_json(&app, patch()).await;
+  assert_eq!(format!("forbidden, required access = edit, note id = {}", note_id), result["detail"]);
+  test::call_service(&app, share("edit")).await;
+  let result: Value = test::call_and_read_body_json(&app, patch()).await;
+  assert_eq!("edited by bob", result["data"]["title"]);
+  // editors may not delete notes
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&bob_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("forbidden, required access = manage, note id = {}", note_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_delete_note() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &token, "first").await;
+  create_note(&app, &token, "second").await;
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("note moved to trash", result["data"]);
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(1, result["data"].as_array().unwrap().len());
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_concurrent_updates_conflict() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &token, "draft").await;
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let res = test::call_service(&app, req).await;
+  assert_eq!("\"1\"", res.headers().get("ETag").unwrap());
+  let patch = |if_match: Option<&str>, title: &str| {
+    let mut req = test::TestRequest::patch()
+      .uri(&format!("/api/v1/notes/{}", note_id))
+      .insert_header(bearer(&token))
+      .set_json(json!({ "title": title }));
+    if let Some(value) = if_match {
+      req = req.insert_header(("If-Match", value.to_string()));
+    }
+    req.to_request()
+  };
+  let res = test::call_service(&app, patch(Some("\"1\""), "first")).await;
+  assert_eq!("\"2\"", res.headers().get("ETag").unwrap());
+  // the second client still holds the first version
+  let result: Value = test::call_and_read_body_json(&app, patch(Some("\"1\""), "second")).await;
+  assert_eq!(format!("version conflict, note id = {}, current version = 2", note_id), result["detail"]);
+  let result: Value = test::call_and_read_body_json(&app, patch(None, "second")).await;
+  assert_eq!("precondition required, specify the expected version in If-Match header", result["detail"]);
+  let result: Value = test::call_and_read_body_json(&app, patch(Some("latest"), "second")).await;
+  assert_eq!("invalid If-Match header, value = latest", result["detail"]);
+  let result: Value = test::call_and_read_body_json(&app, patch(Some("W/\"2\""), "second")).await;
+  assert_eq!("second", result["data"]["title"]);
+}
+
+#[actix_web::test]
+async fn test_remaining_ttl() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "expiring", "content": "content", "ttl": "1h" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let remaining_ttl = result["data"]["remainingTtl"].as_i64().unwrap();
+  assert!((3540..=3600).contains(&remaining_ttl), "{}", remaining_ttl);
+  let note_id = create_note(&app, &token, "permanent").await;
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["data"]["remainingTtl"].is_null());
+}
+
+#[actix_web::test]
+async fn test_compound_ttl_and_expires_at() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "compound", "content": "content", "ttl": "1d12h30m" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let remaining_ttl = result["data"]["remainingTtl"].as_i64().unwrap();
+  assert!((131340..=131400).contains(&remaining_ttl), "{}", remaining_ttl);
+  let expires_at = (OffsetDateTime::now_utc() + time::Duration::days(2)).replace_nanosecond(0).unwrap();
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "absolute", "content": "content", "expiresAt": expires_at.format(&Rfc3339).unwrap() }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(expires_at, date_time(&result["data"]["expiresAt"]));
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "ttl": "PT2H" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let remaining_ttl = result["data"]["remainingTtl"].as_i64().unwrap();
+  assert!((7140..=7200).contains(&remaining_ttl), "{}", remaining_ttl);
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "ttl": "" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["data"]["expiresAt"].is_null());
+  assert!(result["data"]["remainingTtl"].is_null());
+}
+
+#[actix_web::test]
+async fn test_invalid_expiration() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for (params, details) in [
+    (json!({ "ttl": "-1h" }), "invalid time to live, ttl = -1h"),
+    (json!({ "ttl": "1x" }), "invalid time to live, ttl = 1x"),
+    (
+      json!({ "ttl": "1h", "expiresAt": "2100-01-01T00:00:00Z" }),
+      "conflicting attributes specified, names = ttl, expiresAt",
+    ),
+    (json!({ "expiresAt": "tomorrow" }), "invalid expiration time, expiresAt = tomorrow"),
+    (
+      json!({ "expiresAt": "2000-01-01T01:00:00+01:00" }),
+      "expiration time out of range, expires at = 2000-01-01T00:00:00Z",
+    ),
+  ] {
+    let mut body = json!({ "title": "title", "content": "content" });
+    body.as_object_mut().unwrap().extend(params.as_object().unwrap().clone());
+    let req = test::TestRequest::post()
+      .uri("/api/v1/notes")
+      .insert_header(bearer(&token))
+      .set_json(&body)
+      .to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(details, result["detail"], "{}", body);
+  }
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "title": "title", "content": "content", "ttl": "21y" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["detail"].as_str().unwrap().starts_with("expiration time out of range"));
+}
+
+#[actix_web::test]
+async fn test_list_notes_page_by_page() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let mut note_ids = vec![];
+  for i in 0..5 {
+    note_ids.push(create_note(&app, &token, &format!("note {}", i)).await);
+  }
+  let mut listed = vec![];
+  let mut uri = "/api/v1/notes?pageSize=2".to_string();
+  loop {
+    let req = test::TestRequest::get().uri(&uri).insert_header(bearer(&token)).to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    let notes = result["data"].as_array().unwrap();
+    assert!(notes.len() <= 2);
+    listed.extend(
+      notes
+        .iter()
+        .map(|note| (date_time(&note["createdAt"]), note["noteId"].as_str().unwrap().to_string())),
+    );
+    match result["nextPage"].as_str() {
+      Some(token) => uri = format!("/api/v1/notes?pageSize=2&page={}", token),
+      None => break,
+    }
+  }
+  // notes are listed from the newest, notes created at the same time are ordered by identifier
+  assert!(listed.windows(2).all(|pair| pair[0] > pair[1]));
+  let mut listed_ids: Vec<String> = listed.iter().map(|(_, note_id)| note_id.clone()).collect();
+  listed_ids.sort();
+  note_ids.sort();
+  assert_eq!(note_ids, listed_ids);
+  let req = test::TestRequest::get()
+    .uri("/api/v1/notes?order=asc&pageSize=1")
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(listed[4].1, result["data"][0]["noteId"]);
+  assert!(result["nextPage"].is_string());
+  let req = test::TestRequest::get().uri("/api/v1/notes").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(5, result["data"].as_array().unwrap().len());
+  assert!(result["nextPage"].is_null());
+}
+
+#[actix_web::test]
+async fn test_list_notes_invalid_page() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for (query, details) in [
+    ("pageSize=0", "invalid page size, size = 0, maximum = 1000"),
+    ("pageSize=1001", "invalid page size, size = 1001, maximum = 1000"),
+    ("pageSize=many", "invalid page size, size = many, maximum = 1000"),
+    ("page=xyz", "invalid page token"),
+    ("order=newest", "invalid sort order, name = newest"),
+  ] {
+    let req = test::TestRequest::get()
+      .uri(&format!("/api/v1/notes?{}", query))
+      .insert_header(bearer(&token))
+      .to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(details, result["detail"], "{}", query);
+  }
+}
+
+#[actix_web::test]
+async fn test_rendered_content() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note = json!({ "title": "Fika", "content": "# Fika\n\n- [x] *kanelbullar*\n\n<script>alert(1)</script>", "contentFormat": "markdown" });
+  let result = create(&app, &token, note).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  let result = get_note(&app, &token, &note_id).await;
+  assert_eq!("markdown", result["data"]["contentFormat"]);
+  assert!(result["data"]["renderedContent"].is_null());
+  let result = get(&app, &token, &format!("/api/v1/notes/{}?render=html", note_id)).await;
+  assert_eq!(
+    "<h1>Fika</h1>\n<ul>\n<li><input type=\"checkbox\" checked=\"\" disabled=\"\">\n<em>kanelbullar</em></li>\n</ul>\n",
+    result["data"]["renderedContent"]
+  );
+  // plain text is escaped
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "contentFormat": "plain" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("plain", result["data"]["contentFormat"]);
+  let result = get(&app, &token, &format!("/api/v1/notes/{}?render=html", note_id)).await;
+  assert_eq!(
+    "<p># Fika</p>\n<p>- [x] *kanelbullar*</p>\n<p>&lt;script&gt;alert(1)&lt;/script&gt;</p>\n",
+    result["data"]["renderedContent"]
+  );
+  let result = get(&app, &token, &format!("/api/v1/notes/{}?render=pdf", note_id)).await;
+  assert_eq!("invalid render format, value = pdf", result["detail"]);
+  let result = create(&app, &token, json!({ "title": "Fika", "content": "", "contentFormat": "rtf" })).await;
+  assert_eq!("invalid content format, name = rtf", result["detail"]);
+}
@@ -0,0 +1,177 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for note revisions.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, Error};
+use common::*;
+use serde_json::{json, Value};
+
+/// Modifies the content of the note, returns the result of the request.
+async fn patch(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note_id: &str, content: &str) -> Value {
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "content": content }))
+    .to_request();
+  test::call_and_read_body_json(app, req).await
+}
+
+#[actix_web::test]
+async fn test_list_and_get_revisions() {
+  let app = init_app().await;
+  let (alice_id, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_note(&app, &alice_token, "recipe").await;
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&alice_token))
+    .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": "edit" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  patch(&app, &alice_token, &note_id, "flour\nsugar").await;
+  patch(&app, &bob_token, &note_id, "flour\nbutter\nsugar").await;
+  let uri = format!("/api/v1/notes/{}/revisions", note_id);
+  let result = get(&app, &alice_token, &uri).await;
+  let revisions = result["data"].as_array().unwrap();
+  assert_eq!(
+    vec![3, 2, 1],
+    revisions.iter().map(|revision| revision["revision"].as_i64().unwrap()).collect::<Vec<i64>>()
+  );
+  assert_eq!(bob_id, revisions[0]["authorId"]);
+  assert_eq!(alice_id, revisions[1]["authorId"]);
+  assert_eq!("recipe", revisions[2]["title"]);
+  assert!(revisions[0]["content"].is_null());
+  // pages of revisions
+  let result = get(&app, &alice_token, &format!("{}?pageSize=2&order=asc", uri)).await;
+  assert_eq!(
+    json!([1, 2]),
+    json!(result["data"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|revision| &revision["revision"])
+      .collect::<Vec<&Value>>())
+  );
+  let next_page = result["nextPage"].as_str().unwrap();
+  let result = get(&app, &alice_token, &format!("{}?pageSize=2&page={}", uri, next_page)).await;
+  assert_eq!(3, result["data"][0]["revision"]);
+  assert!(result["nextPage"].is_null());
+  // single revision
+  let result = get(&app, &bob_token, &format!("{}/2", uri)).await;
+  assert_eq!("flour\nsugar", result["data"]["content"]);
+  let result = get(&app, &alice_token, &format!("{}/1", uri)).await;
+  assert_eq!("content", result["data"]["content"]);
+  let result = get(&app, &alice_token, &format!("{}/4", uri)).await;
+  assert_eq!(format!("revision not found, note id = {}, revision = 4", note_id), result["detail"]);
+  let result = get(&app, &alice_token, &format!("{}/latest", uri)).await;
+  assert_eq!("invalid revision, value = latest", result["detail"]);
+  // revisions of notes not accessible to the user are not found
+  let (_, carol_token) = create_user(&app, "carol").await;
+  let result = get(&app, &carol_token, &uri).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_diff_revisions() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &token, "recipe").await;
+  patch(&app, &token, &note_id, "flour\nsugar\neggs").await;
+  patch(&app, &token, &note_id, "flour\nbutter\nsugar").await;
+  let uri = format!("/api/v1/notes/{}/revisions/diff", note_id);
+  let result = get(&app, &token, &format!("{}?from=2&to=3", uri)).await;
+  assert_eq!(2, result["data"]["from"]);
+  assert_eq!(3, result["data"]["to"]);
+  assert_eq!(json!([{ "op": "equal", "text": "recipe" }]), result["data"]["title"]);
+  assert_eq!(
+    json!([
+      { "op": "equal", "text": "flour" },
+      { "op": "insert", "text": "butter" },
+      { "op": "equal", "text": "sugar" },
+      { "op": "delete", "text": "eggs" }
+    ]),
+    result["data"]["content"]
+  );
+  // compared with the current version by default
+  let result = get(&app, &token, &format!("{}?from=1", uri)).await;
+  assert_eq!(3, result["data"]["to"]);
+  assert_eq!(json!({ "op": "delete", "text": "content" }), result["data"]["content"][0]);
+  let result = get(&app, &token, &uri).await;
+  assert_eq!("required attribute not specified, name = from", result["detail"]);
+  let result = get(&app, &token, &format!("{}?from=-1", uri)).await;
+  assert_eq!("invalid revision, value = -1", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_restore_revision() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &alice_token, "recipe").await;
+  patch(&app, &alice_token, &note_id, "overwritten").await;
+  let uri = format!("/api/v1/notes/{}/revisions/1/restore", note_id);
+  let req = test::TestRequest::post().uri(&uri).insert_header(bearer(&alice_token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("precondition required, specify the expected version in If-Match header", result["detail"]);
+  let req = test::TestRequest::post()
+    .uri(&uri)
+    .insert_header(bearer(&alice_token))
+    .insert_header(("If-Match", "\"1\""))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("version conflict, note id = {}, current version = 2", note_id), result["detail"]);
+  let req = test::TestRequest::post()
+    .uri(&uri)
+    .insert_header(bearer(&alice_token))
+    .insert_header(("If-Match", "\"2\""))
+    .to_request();
+  let resp = test::call_service(&app, req).await;
+  assert_eq!("\"3\"", resp.headers().get("ETag").unwrap());
+  let result: Value = test::read_body_json(resp).await;
+  assert_eq!("content", result["data"]["content"]);
+  assert_eq!(3, result["data"]["version"]);
+  // restoring is recorded as a new revision, earlier revisions are kept
+  let result = get(&app, &alice_token, &format!("/api/v1/notes/{}/revisions", note_id)).await;
+  assert_eq!(3, result["data"].as_array().unwrap().len());
+  // users with read access may not restore revisions
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&alice_token))
+    .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let req = test::TestRequest::post()
+    .uri(&uri)
+    .insert_header(bearer(&bob_token))
+    .insert_header(("If-Match", "*"))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("forbidden, required access = edit, note id = {}", note_id), result["detail"]);
+  // revisions are deleted together with the note
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&alice_token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let result = get(&app, &alice_token, &format!("/api/v1/notes/{}/revisions/1", note_id)).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+}
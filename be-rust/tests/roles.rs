@@ -0,0 +1,141 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for roles.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn test_create_and_find_role() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/roles")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "name": "editor", "permissions": ["notes:read", "notes:write"] }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let role_id = result["data"]["roleId"].as_str().unwrap().to_string();
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/roles/{}", role_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("EDITOR", result["data"]["name"]);
+  assert_eq!(json!(["notes:read", "notes:write"]), result["data"]["permissions"]);
+}
+
+#[actix_web::test]
+async fn test_create_role_invalid_permission() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/roles")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "name": "editor", "permissions": ["notes:delete"] }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("invalid permission, name = notes:delete", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_roles_permissions() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let req = test::TestRequest::get().uri("/api/v1/roles").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(2, result["data"].as_array().unwrap().len());
+  let req = test::TestRequest::delete().uri("/api/v1/roles").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("forbidden, required permission = roles:admin", result["detail"]);
+  let req = test::TestRequest::delete().uri("/api/v1/roles").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_endpoint_not_found() {
+  let app = init_app().await;
+  let req = test::TestRequest::get().uri("/api/v1/unknown").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("endpoint not found: /api/v1/unknown", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_list_roles_page_by_page() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for name in ["editor", "reviewer", "guest"] {
+    let req = test::TestRequest::post()
+      .uri("/api/v1/roles")
+      .insert_header(bearer(&token))
+      .set_json(json!({ "name": name, "permissions": ["notes:read"] }))
+      .to_request();
+    let _: Value = test::call_and_read_body_json(&app, req).await;
+  }
+  let mut names = vec![];
+  let mut uri = "/api/v1/roles?pageSize=2".to_string();
+  loop {
+    let req = test::TestRequest::get().uri(&uri).insert_header(bearer(&token)).to_request();
+    let result: Value = test::call_and_read_body_json(&app, req).await;
+    let roles = result["data"].as_array().unwrap();
+    assert!(roles.len() <= 2);
+    names.extend(roles.iter().map(|role| role["name"].as_str().unwrap().to_string()));
+    match result["nextPage"].as_str() {
+      Some(token) => uri = format!("/api/v1/roles?pageSize=2&page={}", token),
+      None => break,
+    }
+  }
+  names.sort();
+  assert_eq!(vec!["ADMIN", "EDITOR", "GUEST", "REVIEWER", "USER"], names);
+}
+
+#[actix_web::test]
+async fn test_list_roles_rejects_order() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let result = get(&app, &token, "/api/v1/roles?order=asc").await;
+  assert_eq!(400, result["status"]);
+  assert_eq!("sort order not supported, name = asc", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_delete_all_roles_keeps_built_in_roles() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/roles")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "name": "editor", "permissions": ["notes:read"] }));
+  let _: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+  let result = send(&app, test::TestRequest::delete(), &token, "/api/v1/roles").await;
+  assert_eq!("all roles except built-in roles deleted, roles = 1", result["data"]);
+  let mut names: Vec<String> = get(&app, &token, "/api/v1/roles").await["data"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|role| role["name"].as_str().unwrap().to_string())
+    .collect();
+  names.sort();
+  assert_eq!(vec!["ADMIN", "USER"], names);
+  // the administrator keeps permissions granted by the ADMIN role
+  let result = send(&app, test::TestRequest::delete(), &token, "/api/v1/roles").await;
+  assert_eq!("all roles except built-in roles deleted, roles = 0", result["data"]);
+}
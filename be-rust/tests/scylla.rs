@@ -0,0 +1,155 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of upgrading the database created by previous versions of the application.
+//!
+//! These tests drop the whole `nordnotes` keyspace, so they are ignored by default
+//! and must be run explicitly against a disposable ScyllaDB node:
+//!
+//! ```text
+//! SCYLLA_URI=127.0.0.1:9042 cargo test --test scylla -- --ignored --test-threads=1
+//! ```
+
+use nordnotes::entities::note::NoteEntity;
+use nordnotes::entities::Entity;
+use nordnotes::repositories::timelines::TIMELINE_ALL;
+use nordnotes::repositories::{PageRequest, SortOrder};
+use nordnotes::services::roles::find_by_name;
+use nordnotes::services::system::{initialize, ADMIN_ROLE};
+use nordnotes::storage::Storage;
+use scylla::{Session, SessionBuilder};
+use std::env;
+use time::macros::datetime;
+
+/// Creates the database structure of the first version of the application, dropping all existing data.
+async fn create_baseline_schema() -> Session {
+  let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+  let session = SessionBuilder::new().known_node(&uri).build().await.unwrap();
+  for query in [
+    "DROP KEYSPACE IF EXISTS nordnotes",
+    "CREATE KEYSPACE nordnotes WITH REPLICATION = {'class' : 'SimpleStrategy', 'replication_factor' : 1}",
+    "CREATE TABLE nordnotes.notes (note_id text, title text, content text, created_at text, expires_at text, primary key (note_id))",
+    "CREATE TABLE nordnotes.roles (role_id text, name text, primary key (role_id))",
+  ] {
+    session.query(query, &[]).await.unwrap();
+  }
+  session
+}
+
+#[tokio::test]
+#[ignore = "requires a disposable ScyllaDB node"]
+async fn test_upgrade_roles_from_baseline_schema() {
+  let session = create_baseline_schema().await;
+  session
+    .query("INSERT INTO nordnotes.roles (role_id, name) VALUES ('legacy', 'LEGACY')", &[])
+    .await
+    .unwrap();
+  let storage = Storage::scylla().await.unwrap();
+  initialize(&storage, Some("tester".to_string()), Some("secret".to_string()), false)
+    .await
+    .unwrap();
+  let role = storage.roles_repository.find_by_id("legacy").await.unwrap();
+  assert!(role.permissions().is_empty());
+  let admin = find_by_name(ADMIN_ROLE.to_string(), &storage).await.unwrap();
+  assert!(!admin.permissions().is_empty());
+  let user = storage.users_repository.find_by_login("tester").await.unwrap();
+  assert!(user.has_role(&admin.id()));
+}
+
+#[tokio::test]
+#[ignore = "requires a disposable ScyllaDB node"]
+async fn test_claim_logins_of_existing_users() {
+  let session = create_baseline_schema().await;
+  for query in [
+    "CREATE TABLE nordnotes.users (user_id text, login text, password text, roles set<text>, primary key (user_id))",
+    "CREATE TABLE nordnotes.users_by_login (login text, user_id text, primary key (login))",
+    "INSERT INTO nordnotes.users (user_id, login, password) VALUES ('legacy', 'alice', 'secret')",
+  ] {
+    session.query(query, &[]).await.unwrap();
+  }
+  // logins are claimed even though the table of claimed logins already exists
+  let storage = Storage::scylla().await.unwrap();
+  assert_eq!("legacy", storage.users_repository.find_by_login("alice").await.unwrap().user_id);
+  let result = session.query("SELECT name FROM nordnotes.migrations", &[]).await.unwrap();
+  assert_eq!(1, result.rows.unwrap().len());
+}
+
+#[tokio::test]
+#[ignore = "requires a disposable ScyllaDB node"]
+async fn test_update_note_migrated_from_baseline_schema() {
+  let session = create_baseline_schema().await;
+  session
+    .query(
+      "INSERT INTO nordnotes.notes (note_id, title, content, created_at, expires_at) VALUES ('legacy', 'Title', 'Content', '2022-04-01T12:30:00', '')",
+      &[],
+    )
+    .await
+    .unwrap();
+  let mut storage = Storage::scylla().await.unwrap();
+  let previous = storage.get_note("legacy").await.unwrap();
+  assert_eq!(0, previous.version);
+  let mut note = previous.clone();
+  note.content = "Changed".to_string();
+  note.version = previous.version + 1;
+  storage.update_note(&previous, note.clone(), "tester").await.unwrap();
+  let updated = storage.get_note("legacy").await.unwrap();
+  assert_eq!(1, updated.version);
+  assert_eq!("Changed", updated.content);
+  // updating the stale version is rejected
+  assert!(storage.update_note(&previous, note, "tester").await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires a disposable ScyllaDB node"]
+async fn test_list_timeline_across_months() {
+  create_baseline_schema().await;
+  let storage = Storage::scylla().await.unwrap();
+  let mut expected = vec![];
+  for (index, created_at) in [
+    datetime!(2022-01-31 23:59:59 UTC),
+    datetime!(2022-02-01 00:00:00 UTC),
+    datetime!(2022-02-15 12:00:00 UTC),
+    datetime!(2022-06-01 08:30:00 UTC),
+    datetime!(2023-01-01 00:00:00 UTC),
+  ]
+  .into_iter()
+  .enumerate()
+  {
+    let mut note = NoteEntity::new("alice", &format!("Note {}", index), "", None);
+    note.created_at = created_at;
+    for timeline in [TIMELINE_ALL, "user:alice", "tag:alice:a~b"] {
+      storage.timelines_repository.save(timeline, &note).await.unwrap();
+    }
+    expected.push(note.note_id);
+  }
+  let timelines = [TIMELINE_ALL.to_string(), "user:alice".to_string(), "tag:alice:a~b".to_string()];
+  for order in [SortOrder::Ascending, SortOrder::Descending] {
+    let mut listed = vec![];
+    let mut page = PageRequest { size: 2, token: None };
+    loop {
+      let result = storage.timelines_repository.list(&timelines, order, &page).await.unwrap();
+      listed.extend(result.items);
+      match result.next_page {
+        Some(token) => page.token = Some(token),
+        None => break,
+      }
+    }
+    if order == SortOrder::Descending {
+      listed.reverse();
+    }
+    assert_eq!(expected, listed);
+  }
+}
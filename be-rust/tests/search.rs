@@ -0,0 +1,172 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for full-text search of notes.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, App, Error};
+use common::*;
+use nordnotes::server::configure;
+use nordnotes::services::system::rebuild_search_index;
+use nordnotes::settings::Settings;
+use serde_json::{json, Value};
+
+/// Creates a note with specified title and content, returns the identifier of the note.
+async fn create_with(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, title: &str, content: &str) -> String {
+  let result = create(app, token, json!({ "title": title, "content": content })).await;
+  result["data"]["noteId"].as_str().unwrap().to_string()
+}
+
+/// Searches notes with specified query string, returns the result of the request.
+async fn search(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, query: &str) -> Value {
+  get(app, token, &format!("/api/v1/notes/search?{}", query)).await
+}
+
+#[actix_web::test]
+async fn test_search_notes() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let jam = create_with(&app, &token, "Blåbærsyltetøj", "Mormors recept på sylt").await;
+  let trip = create_with(&app, &token, "Resa till Århus", "Besök hos Jørgen, köp blåbär").await;
+  let result = search(&app, &token, "q=blabarsyltetoj").await;
+  assert_eq!(vec![jam.clone()], note_ids(&result));
+  assert_eq!("Blåbærsyltetøj", result["data"][0]["title"]);
+  assert!(result["data"][0]["content"].is_null());
+  assert!(result["data"][0]["score"].as_f64().unwrap() > 0.0);
+  assert_eq!(vec![trip.clone()], note_ids(&search(&app, &token, "q=ARHUS+j%C3%B8rgen").await));
+  assert_eq!(vec![jam.clone()], note_ids(&search(&app, &token, "q=%22mormors+recept%22").await));
+  assert!(note_ids(&search(&app, &token, "q=%22recept+mormors%22").await).is_empty());
+  // occurrences in titles rank higher
+  assert_eq!(vec![jam.clone(), trip.clone()], note_ids(&search(&app, &token, "q=bla*").await));
+  // modified and deleted notes are indexed again
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", trip))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "content": "Besök hos Jørgen" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(vec![jam.clone()], note_ids(&search(&app, &token, "q=bla*").await));
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", jam))
+    .insert_header(bearer(&token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(note_ids(&search(&app, &token, "q=bla*").await).is_empty());
+}
+
+#[actix_web::test]
+async fn test_search_only_accessible_notes() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_with(&app, &alice_token, "Fjällvandring", "Packlista").await;
+  assert!(note_ids(&search(&app, &bob_token, "q=fjallvandring").await).is_empty());
+  assert_eq!(vec![note_id.clone()], note_ids(&search(&app, &login(&app).await, "q=fjallvandring").await));
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&alice_token))
+    .set_json(json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(vec![note_id.clone()], note_ids(&search(&app, &bob_token, "q=fjallvandring").await));
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}/shares/user/{}", note_id, bob_id))
+    .insert_header(bearer(&alice_token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(note_ids(&search(&app, &bob_token, "q=fjallvandring").await).is_empty());
+}
+
+#[actix_web::test]
+async fn test_search_page_by_page() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for i in 0..5 {
+    create_with(&app, &token, &format!("Lista {}", i), "smör").await;
+  }
+  let mut found = vec![];
+  let mut query = "q=smor&pageSize=2".to_string();
+  loop {
+    let result = search(&app, &token, &query).await;
+    found.extend(note_ids(&result));
+    match result["nextPage"].as_str() {
+      Some(next_page) => query = format!("q=smor&pageSize=2&page={}", next_page),
+      None => break,
+    }
+  }
+  found.sort();
+  found.dedup();
+  assert_eq!(5, found.len());
+}
+
+#[actix_web::test]
+async fn test_invalid_search() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let result = search(&app, &token, "").await;
+  assert_eq!("required attribute not specified, name = q", result["detail"]);
+  let result = search(&app, &token, "q=+-+*").await;
+  assert_eq!("invalid search query, q =  - *", result["detail"]);
+  let result = search(&app, &token, "q=note&page=xyz").await;
+  assert_eq!("invalid page token", result["detail"]);
+  let req = test::TestRequest::get().uri("/api/v1/notes/search?q=note").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_search_while_index_is_built() {
+  let data = init_data(Settings::default()).await;
+  let app = test::init_service(App::new().app_data(data.clone()).configure(configure)).await;
+  let token = login(&app).await;
+  let note_id = create_with(&app, &token, "Pepparkakor", "").await;
+  data.storage.read().await.search_index.set_ready(false);
+  let req = test::TestRequest::get()
+    .uri("/api/v1/notes/search?q=pepparkakor")
+    .insert_header(bearer(&token))
+    .to_request();
+  let response = test::call_service(&app, req).await;
+  assert_eq!(503, response.status().as_u16());
+  let result: Value = test::read_body_json(response).await;
+  assert_eq!("unavailable", result["code"]);
+  assert_eq!("search index is being built, try again later", result["detail"]);
+  assert_eq!(1, rebuild_search_index(&*data.storage.read().await).await.unwrap());
+  assert_eq!(vec![note_id], note_ids(&search(&app, &token, "q=pepparkakor").await));
+}
+
+#[actix_web::test]
+async fn test_rebuild_search_index() {
+  let app = init_app().await;
+  let (_, user_token) = create_user(&app, "alice").await;
+  let note_id = create_with(&app, &user_token, "Kanelbullar", "").await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes/search/rebuild")
+    .insert_header(bearer(&user_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("forbidden, required permission = notes:admin", result["detail"]);
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes/search/rebuild")
+    .insert_header(bearer(&login(&app).await))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("search index rebuilt, notes = 1", result["data"]);
+  assert_eq!(vec![note_id], note_ids(&search(&app, &user_token, "q=kanelbullar").await));
+}
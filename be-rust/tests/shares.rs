@@ -0,0 +1,140 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for note shares.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, Error};
+use common::*;
+use serde_json::{json, Value};
+
+/// Shares the note, returns the result of the request.
+async fn share(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str, note_id: &str, share: Value) -> Value {
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(token))
+    .set_json(share)
+    .to_request();
+  test::call_and_read_body_json(app, req).await
+}
+
+/// Returns the number of notes accessible to the user.
+async fn count_notes(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, token: &str) -> usize {
+  get(app, token, "/api/v1/notes").await["data"].as_array().unwrap().len()
+}
+
+#[actix_web::test]
+async fn test_share_with_user() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (bob_id, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_note(&app, &alice_token, "shared").await;
+  assert_eq!(0, count_notes(&app, &bob_token).await);
+  let result = share(
+    &app,
+    &alice_token,
+    &note_id,
+    json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }),
+  )
+  .await;
+  assert_eq!(json!({ "granteeType": "user", "granteeId": bob_id, "access": "read" }), result["data"]);
+  assert_eq!(1, count_notes(&app, &bob_token).await);
+  assert_eq!("shared", get_note(&app, &bob_token, &note_id).await["data"]["title"]);
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/notes/{}/shares", note_id))
+    .insert_header(bearer(&bob_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(bob_id, result["data"][0]["granteeId"]);
+  // only the owner may manage shares
+  let result = share(
+    &app,
+    &bob_token,
+    &note_id,
+    json!({ "granteeType": "user", "granteeId": bob_id, "access": "edit" }),
+  )
+  .await;
+  assert_eq!(format!("forbidden, required access = manage, note id = {}", note_id), result["detail"]);
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}/shares/user/{}", note_id, bob_id))
+    .insert_header(bearer(&alice_token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("share revoked", result["data"]);
+  assert_eq!(0, count_notes(&app, &bob_token).await);
+  let result = get_note(&app, &bob_token, &note_id).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_share_with_role() {
+  let app = init_app().await;
+  let admin_token = login(&app).await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (_, bob_token) = create_user(&app, "bob").await;
+  let req = test::TestRequest::get().uri("/api/v1/roles").insert_header(bearer(&admin_token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let role = result["data"].as_array().unwrap().iter().find(|role| role["name"] == "USER").unwrap();
+  let role_id = role["roleId"].as_str().unwrap();
+  let note_id = create_note(&app, &alice_token, "for all users").await;
+  let result = share(
+    &app,
+    &alice_token,
+    &note_id,
+    json!({ "granteeType": "role", "granteeId": role_id, "access": "edit" }),
+  )
+  .await;
+  assert_eq!("edit", result["data"]["access"]);
+  assert_eq!(1, count_notes(&app, &bob_token).await);
+  assert_eq!("for all users", get_note(&app, &bob_token, &note_id).await["data"]["title"]);
+  // the owner also has the role, but the note is listed once
+  assert_eq!(1, count_notes(&app, &alice_token).await);
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}/shares/role/{}", note_id, role_id))
+    .insert_header(bearer(&alice_token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(0, count_notes(&app, &bob_token).await);
+  assert_eq!(1, count_notes(&app, &alice_token).await);
+}
+
+#[actix_web::test]
+async fn test_share_invalid_grantee() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &alice_token, "note").await;
+  let result = share(
+    &app,
+    &alice_token,
+    &note_id,
+    json!({ "granteeType": "group", "granteeId": "x", "access": "read" }),
+  )
+  .await;
+  assert_eq!("invalid grantee type, name = group", result["detail"]);
+  let result = share(
+    &app,
+    &alice_token,
+    &note_id,
+    json!({ "granteeType": "user", "granteeId": "x", "access": "read" }),
+  )
+  .await;
+  assert_eq!("user not found: x", result["detail"]);
+  let result = share(&app, &alice_token, &note_id, json!({ "granteeType": "user", "granteeId": "x" })).await;
+  assert_eq!("required attribute not specified, name = access", result["detail"]);
+}
@@ -0,0 +1,95 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for tags.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn test_tags() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let result = create(&app, &token, json!({ "title": "first", "content": "", "tags": ["Home", " work ", "home"] })).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  create(&app, &token, json!({ "title": "second", "content": "", "tags": ["work"] })).await;
+  let (_, bob_token) = create_user(&app, "bob").await;
+  create(&app, &bob_token, json!({ "title": "other", "content": "", "tags": ["home"] })).await;
+  let result = get(&app, &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!(json!(["home", "work"]), result["data"]["tags"]);
+  let result = get(&app, &token, "/api/v1/tags").await;
+  assert_eq!(json!([{ "tag": "home", "count": 1 }, { "tag": "work", "count": 2 }]), result["data"]);
+  assert_eq!(vec!["first", "second"], titles(&get(&app, &token, "/api/v1/tags/work/notes").await));
+  assert_eq!(vec!["first", "second"], titles(&get(&app, &token, "/api/v1/tags/WORK/notes?pageSize=5").await));
+  assert_eq!(vec!["first"], titles(&get(&app, &token, "/api/v1/tags/home/notes").await));
+  // replacing tags
+  let req = test::TestRequest::patch()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .insert_header(("If-Match", "*"))
+    .set_json(json!({ "tags": ["travel"] }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(json!(["travel"]), result["data"]["tags"]);
+  let result = get(&app, &token, "/api/v1/tags").await;
+  assert_eq!(json!([{ "tag": "travel", "count": 1 }, { "tag": "work", "count": 1 }]), result["data"]);
+  assert!(titles(&get(&app, &token, "/api/v1/tags/home/notes").await).is_empty());
+  // deleted notes are not counted
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/notes/{}", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let _: Value = test::call_and_read_body_json(&app, req).await;
+  let result = get(&app, &token, "/api/v1/tags").await;
+  assert_eq!(json!([{ "tag": "work", "count": 1 }]), result["data"]);
+}
+
+#[actix_web::test]
+async fn test_invalid_tags() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let result = create(&app, &token, json!({ "title": "note", "content": "", "tags": ["  "] })).await;
+  assert_eq!("invalid tag, tag =   , maximum length = 64", result["detail"]);
+  let result = create(&app, &token, json!({ "title": "note", "content": "", "tags": ["x".repeat(65)] })).await;
+  assert_eq!(format!("invalid tag, tag = {}, maximum length = 64", "x".repeat(65)), result["detail"]);
+  let tags: Vec<String> = (0..33).map(|i| format!("tag{}", i)).collect();
+  let result = create(&app, &token, json!({ "title": "note", "content": "", "tags": tags })).await;
+  assert_eq!("too many tags, count = 33, maximum = 32", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_tag_notes_paging() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  for index in 0..5 {
+    create(&app, &token, json!({ "title": format!("note {}", index), "content": "", "tags": ["a~b"] })).await;
+  }
+  let mut listed = vec![];
+  let mut uri = "/api/v1/tags/a~b/notes?pageSize=2".to_string();
+  loop {
+    let result = get(&app, &token, &uri).await;
+    listed.extend(titles(&result));
+    match result["nextPage"].as_str() {
+      Some(page) => uri = format!("/api/v1/tags/a~b/notes?pageSize=2&page={}", page),
+      None => break,
+    }
+  }
+  listed.sort();
+  assert_eq!(vec!["note 0", "note 1", "note 2", "note 3", "note 4"], listed);
+}
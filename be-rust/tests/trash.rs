@@ -0,0 +1,113 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for trash.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use serde_json::Value;
+
+#[actix_web::test]
+async fn test_trash_and_restore() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let note_id = create_note(&app, &token, "Kanelbullar").await;
+  let kept_id = create_note(&app, &token, "kept").await;
+  let result = send(&app, test::TestRequest::delete(), &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!("note moved to trash", result["data"]);
+  // trashed notes are hidden
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/notes").await;
+  assert_eq!(vec![kept_id.clone()], note_ids(&result));
+  let result = send(&app, test::TestRequest::get(), &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/notes/search?q=kanelbullar").await;
+  assert!(result["data"].as_array().unwrap().is_empty());
+  // listed in trash
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/trash").await;
+  assert_eq!(vec![note_id.clone()], note_ids(&result));
+  assert!(result["data"][0]["deletedAt"].is_string());
+  // restored
+  let req = test::TestRequest::post()
+    .uri(&format!("/api/v1/trash/{}/restore", note_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let resp = test::call_service(&app, req).await;
+  assert_eq!("\"1\"", resp.headers().get("ETag").unwrap());
+  let result: Value = test::read_body_json(resp).await;
+  assert_eq!("Kanelbullar", result["data"]["title"]);
+  assert!(result["data"]["deletedAt"].is_null());
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/trash").await;
+  assert!(result["data"].as_array().unwrap().is_empty());
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/notes/search?q=kanelbullar").await;
+  assert_eq!(vec![note_id.clone()], note_ids(&result));
+  let result = send(&app, test::TestRequest::get(), &token, "/api/v1/notes").await;
+  assert_eq!(2, note_ids(&result).len());
+  // notes not in trash can not be restored
+  let result = send(&app, test::TestRequest::post(), &token, &format!("/api/v1/trash/{}/restore", kept_id)).await;
+  assert_eq!(format!("note not found, id = {}", kept_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_purge() {
+  let app = init_app().await;
+  let (_, alice_token) = create_user(&app, "alice").await;
+  let (_, bob_token) = create_user(&app, "bob").await;
+  let note_id = create_note(&app, &alice_token, "draft").await;
+  send(&app, test::TestRequest::delete(), &alice_token, &format!("/api/v1/notes/{}", note_id)).await;
+  // trash of other users is not accessible
+  let result = send(&app, test::TestRequest::get(), &bob_token, "/api/v1/trash").await;
+  assert!(result["data"].as_array().unwrap().is_empty());
+  let result = send(&app, test::TestRequest::delete(), &bob_token, &format!("/api/v1/trash/{}", note_id)).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+  // administrators list trash of all users
+  let result = send(&app, test::TestRequest::get(), &login(&app).await, "/api/v1/trash").await;
+  assert_eq!(vec![note_id.clone()], note_ids(&result));
+  let result = send(&app, test::TestRequest::delete(), &alice_token, &format!("/api/v1/trash/{}", note_id)).await;
+  assert_eq!("note purged", result["data"]);
+  let result = send(&app, test::TestRequest::get(), &alice_token, "/api/v1/trash").await;
+  assert!(result["data"].as_array().unwrap().is_empty());
+  let result = send(&app, test::TestRequest::post(), &alice_token, &format!("/api/v1/trash/{}/restore", note_id)).await;
+  assert_eq!(format!("note not found, id = {}", note_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_restore_outside_deleted_notebook() {
+  let app = init_app().await;
+  let (_, token) = create_user(&app, "alice").await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notebooks")
+    .insert_header(bearer(&token))
+    .set_json(serde_json::json!({ "name": "Recipes" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let notebook_id = result["data"]["notebookId"].as_str().unwrap().to_string();
+  let req = test::TestRequest::post()
+    .uri("/api/v1/notes")
+    .insert_header(bearer(&token))
+    .set_json(serde_json::json!({ "title": "Kanelbullar", "content": "", "notebookId": notebook_id }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let note_id = result["data"]["noteId"].as_str().unwrap().to_string();
+  send(&app, test::TestRequest::delete(), &token, &format!("/api/v1/notes/{}", note_id)).await;
+  let result = send(&app, test::TestRequest::delete(), &token, &format!("/api/v1/notebooks/{}", notebook_id)).await;
+  assert_eq!("notebook deleted, removed notes = 0", result["data"]);
+  let result = send(&app, test::TestRequest::post(), &token, &format!("/api/v1/trash/{}/restore", note_id)).await;
+  assert!(result["data"]["notebookId"].is_null());
+  let result = send(&app, test::TestRequest::get(), &token, &format!("/api/v1/notes/{}", note_id)).await;
+  assert_eq!("Kanelbullar", result["data"]["title"]);
+}
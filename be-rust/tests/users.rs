@@ -0,0 +1,145 @@
+/*
+ * nordnotes
+ *
+ * MIT license
+ *
+ * Copyright (c) 2022 Dariusz Depta Engos Software
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! Tests of the HTTP API for users.
+
+mod common;
+
+use actix_web::test;
+use common::*;
+use futures_util::future::join;
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn test_user_lifecycle() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  // create a new user
+  let req = test::TestRequest::post()
+    .uri("/api/v1/users")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "login": "alice", "password": "wonderland" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  let user_id = result["data"]["userId"].as_str().unwrap().to_string();
+  // the list contains the registered user and the new one
+  let req = test::TestRequest::get().uri("/api/v1/users").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(2, result["data"].as_array().unwrap().len());
+  // change the password
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "password": "looking-glass" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("alice", result["data"]["login"]);
+  assert!(result["data"]["password"].is_null());
+  let req = test::TestRequest::post()
+    .uri("/api/v1/login")
+    .set_json(json!({ "login": "alice", "password": "looking-glass" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert!(result["data"]["token"].is_string());
+  // delete the user
+  let req = test::TestRequest::delete()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("user deleted", result["data"]);
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(format!("user not found: {}", user_id), result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_create_user_duplicated_login() {
+  let app = init_app().await;
+  let token = login(&app).await;
+  let req = test::TestRequest::post()
+    .uri("/api/v1/users")
+    .insert_header(bearer(&token))
+    .set_json(json!({ "login": LOGIN, "password": "other" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("user already exists, login = tester", result["detail"]);
+  // renaming to an existing login is rejected
+  let (user_id, _) = create_user(&app, "alice").await;
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "login": LOGIN }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("user already exists, login = tester", result["detail"]);
+  // only one of concurrent requests registers the login
+  let create = || {
+    let req = test::TestRequest::post()
+      .uri("/api/v1/users")
+      .insert_header(bearer(&token))
+      .set_json(json!({ "login": "bob", "password": "bob" }))
+      .to_request();
+    test::call_service(&app, req)
+  };
+  let (first, second) = join(create(), create()).await;
+  let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+  statuses.sort_unstable();
+  assert_eq!([200, 409], statuses);
+}
+
+#[actix_web::test]
+async fn test_list_users_not_authorized() {
+  let app = init_app().await;
+  let req = test::TestRequest::get().uri("/api/v1/users").to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("not authorized", result["detail"]);
+}
+
+#[actix_web::test]
+async fn test_user_manages_own_account() {
+  let app = init_app().await;
+  let (user_id, token) = create_user(&app, "alice").await;
+  let req = test::TestRequest::get()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!(1, result["data"]["roles"].as_array().unwrap().len());
+  // changing own password is allowed
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "password": "changed" }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("alice", result["data"]["login"]);
+  // changing own roles is not allowed
+  let req = test::TestRequest::put()
+    .uri(&format!("/api/v1/users/{}", user_id))
+    .insert_header(bearer(&token))
+    .set_json(json!({ "roles": [] }))
+    .to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("forbidden, required permission = users:admin", result["detail"]);
+  // listing users is not allowed
+  let req = test::TestRequest::get().uri("/api/v1/users").insert_header(bearer(&token)).to_request();
+  let result: Value = test::call_and_read_body_json(&app, req).await;
+  assert_eq!("forbidden, required permission = users:admin", result["detail"]);
+}
@@ -2,7 +2,7 @@ import './NoteDetails.css';
 import template from './NoteDetails.html';
 import {Note} from '../../model/Note';
 import {Component} from 'simpa/lib/component';
-import {$innerHTML, $md, $touch} from 'simpa/lib/utils';
+import {$id, $innerHTML, $touch} from 'simpa/lib/utils';
 import app from '../../app/App';
 
 /** Component for presenting the details of a note. */
@@ -28,7 +28,12 @@ export class NoteDetails extends Component {
   public doInit(): void {
     super.doInit();
     $innerHTML(this.titleId, this.note.title);
-    $innerHTML(this.contentId, $md(this.note.content));
+    if (this.note.renderedContent !== undefined) {
+      // content is rendered and sanitized by the server
+      $innerHTML(this.contentId, this.note.renderedContent);
+    } else {
+      $id(this.contentId).textContent = this.note.content;
+    }
     $touch(this.buttonBackId, () => {
       app.showNoteListView();
     });
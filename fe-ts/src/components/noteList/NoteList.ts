@@ -14,6 +14,7 @@ export class NoteList extends Component {
   private readonly buttonCreateNoteId = '';
   private readonly containerId = '';
   private containerEl: HTMLElement;
+  private eventSource: EventSource | null = null;
 
   /** Creates note list component. */
   constructor() {
@@ -37,6 +38,10 @@ export class NoteList extends Component {
       (notes) => {
         me.addNotes(notes);
       }, onError, onUnrecoverableError);
+    this.subscribeEvents();
+    authorizationService.onAuthorizationChanged(() => {
+      me.subscribeEvents();
+    });
   }
 
   /** Reloads the list of notes. */
@@ -49,6 +54,17 @@ export class NoteList extends Component {
       }, onError, onUnrecoverableError);
   }
 
+  /** Subscribes events of notes of the logged-in user, closes the previous subscription. */
+  private subscribeEvents(): void {
+    const me = this;
+    if (this.eventSource) {
+      this.eventSource.close();
+    }
+    this.eventSource = noteService.subscribeEvents(() => {
+      me.refresh();
+    });
+  }
+
   /** Adds a note to the list. */
   private addNotes(notes: Note[]): void {
     for (let note of notes) {
@@ -20,6 +20,8 @@ export class CreateNoteParams {
   public title: string;
   /** Content of the note. */
   public content: string;
+  /** Format of the content: plain, markdown or html. */
+  public contentFormat: string;
   /** Expiration time. */
   public ttl: string;
 }
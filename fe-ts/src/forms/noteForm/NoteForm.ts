@@ -116,6 +116,7 @@ export class NoteForm extends Form {
     let params = new CreateNoteParams();
     params.title = title;
     params.content = content;
+    params.contentFormat = 'markdown';
     params.ttl = ttl;
     this.clearForm();
     return params;
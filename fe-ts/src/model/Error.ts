@@ -26,16 +26,18 @@ export interface IUnrecoverableCallback {
   (reason: Object): void;
 }
 
-/** An error returned from backend. */
+/** An error returned from backend, reported as problem details (RFC 7807). */
 export class Error {
+  /** Machine-readable error code. */
+  public code: string;
   /** Detailed error description. */
-  public details: string;
+  public detail: string;
 }
 
 /** Converts an array of errors into single string. */
 export function flattenErrors(errors: Error[]): string {
   return errors.reduce((previousValue, currentValue) => {
-    return previousValue + ' ' + currentValue.details;
+    return previousValue + ' ' + currentValue.detail;
   }, '');
 }
 
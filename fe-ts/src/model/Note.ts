@@ -22,4 +22,8 @@ export class Note {
   public title: string;
   /** Content of the note. */
   public content: string;
+  /** Format of the content: plain, markdown or html. */
+  public contentFormat?: string;
+  /** Content rendered into sanitized HTML by the server. */
+  public renderedContent?: string;
 }
@@ -25,8 +25,16 @@ export interface ILoginCallback {
   (token: Token): void;
 }
 
+/** Definition of callback notified when the user logs in or logs out. */
+export interface IAuthorizationCallback {
+  (authorized: boolean): void;
+}
+
 /** Service for handling authorization. */
 class AuthorizationService {
+  /** Callbacks notified when the user logs in or logs out. */
+  private readonly callbacks: IAuthorizationCallback[] = [];
+
   /** Retrieves a collection of notes from server. */
   public login(credentials: LoginParams, dataCallback: ILoginCallback, errorCallback: IErrorCallback, unrecoverableCallback: IUnrecoverableCallback): void {
     fetch(configuration().API_URL + '/login', $POST(credentials))
@@ -35,8 +43,8 @@ class AuthorizationService {
         if (response.data) {
           dataCallback(response.data);
         }
-        if (response.errors) {
-          errorCallback(response.errors);
+        if (response.detail) {
+          errorCallback([response]);
         }
       })
       .catch(reason => {
@@ -48,12 +56,19 @@ class AuthorizationService {
   public setAuthorized(token: string): void {
     localStorage.setItem(configuration().AUTHORIZED_STORAGE_KEY, 'authorized');
     localStorage.setItem(configuration().TOKEN_STORAGE_KEY, token);
+    this.notify(true);
   }
 
   /** Clears authorization token from local storage. */
   public clearAuthorized(): void {
     localStorage.setItem(configuration().AUTHORIZED_STORAGE_KEY, null);
     localStorage.setItem(configuration().TOKEN_STORAGE_KEY, null);
+    this.notify(false);
+  }
+
+  /** Registers a callback notified when the user logs in or logs out. */
+  public onAuthorizationChanged(callback: IAuthorizationCallback): void {
+    this.callbacks.push(callback);
   }
 
   /** Checks if current user is authorized. */
@@ -62,17 +77,30 @@ class AuthorizationService {
     return authorized && authorized === 'authorized';
   }
 
-  /** Returns the value of the authorization header. */
-  public getAuthorizationHeaderValue(): string | null {
+  /** Returns the authorization token. */
+  public getToken(): string | null {
     if (this.isAuthorized()) {
       const token = localStorage.getItem(configuration().TOKEN_STORAGE_KEY);
       if (token) {
-        return 'Bearer ' + token;
+        return token;
       }
     }
     return null;
   }
 
+  /** Returns the value of the authorization header. */
+  public getAuthorizationHeaderValue(): string | null {
+    const token = this.getToken();
+    return token ? 'Bearer ' + token : null;
+  }
+
+  /** Notifies registered callbacks about changed authorization. */
+  private notify(authorized: boolean): void {
+    for (let callback of this.callbacks) {
+      callback(authorized);
+    }
+  }
+
 }
 
 /** Global authorization object. */
@@ -19,6 +19,7 @@ import {configuration} from '../common/Configuration';
 import {$GET, $POST} from '../common/Utils';
 import {CreateNoteParams} from '../forms/noteForm/CreateNoteParams';
 import {IErrorCallback, IUnrecoverableCallback} from '../model/Error';
+import authorizationService from './AuthorizationService';
 
 /** Definition od callback with the list of notes. */
 export interface IGetNotesCallback {
@@ -30,8 +31,31 @@ export interface IGetNoteCallback {
   (note: Note): void;
 }
 
+/** Definition od callback with the type of note event and the identifier of the note. */
+export interface INoteEventCallback {
+  (type: string, noteId: string): void;
+}
+
+/** Types of note events published by the server. */
+const NOTE_EVENT_TYPES = ['created', 'updated', 'deleted', 'expired'];
+
 /** Service for handling operations on notes. */
 class NoteService {
+  /** Subscribes to events of notes readable by the authorized user, returns `null` when the user is not authorized. */
+  public subscribeEvents(eventCallback: INoteEventCallback): EventSource | null {
+    const token = authorizationService.getToken();
+    if (!token) {
+      return null;
+    }
+    const eventSource = new EventSource(configuration().API_URL + '/events?token=' + encodeURIComponent(token));
+    for (let type of NOTE_EVENT_TYPES) {
+      eventSource.addEventListener(type, (event: MessageEvent) => {
+        eventCallback(type, JSON.parse(event.data).noteId);
+      });
+    }
+    return eventSource;
+  }
+
   /** Retrieves a collection of notes from server. */
   public getNotes(dataCallback: IGetNotesCallback, errorCallback: IErrorCallback, unrecoverableCallback: IUnrecoverableCallback) {
     fetch(configuration().API_URL + '/notes', $GET())
@@ -40,8 +64,8 @@ class NoteService {
         if (response.data) {
           dataCallback(response.data);
         }
-        if (response.errors) {
-          errorCallback(response.errors);
+        if (response.detail) {
+          errorCallback([response]);
         }
       })
       .catch(reason => {
@@ -49,16 +73,16 @@ class NoteService {
       });
   }
 
-  /** Retrieves a note with specified identifier. */
+  /** Retrieves a note with specified identifier, with the content rendered into sanitized HTML. */
   public getNote(noteId: string, dataCallback: IGetNoteCallback, errorCallback: IErrorCallback, unrecoverableCallback: IUnrecoverableCallback) {
-    fetch(configuration().API_URL + '/notes/' + noteId, $GET())
+    fetch(configuration().API_URL + '/notes/' + noteId + '?render=html', $GET())
       .then(result => result.json())
       .then(response => {
         if (response.data) {
           dataCallback(response.data);
         }
-        if (response.errors) {
-          errorCallback(response.errors);
+        if (response.detail) {
+          errorCallback([response]);
         }
       })
       .catch(reason => {
@@ -74,8 +98,8 @@ class NoteService {
         if (response.data) {
           dataCallback(response.data);
         }
-        if (response.errors) {
-          errorCallback(response.errors);
+        if (response.detail) {
+          errorCallback([response]);
         }
       })
       .catch(reason => {
@@ -1,5 +1,9 @@
 #!/usr/bin/env bash
 
+# Runs acceptance tests against the server listening on port 8871, started with in-memory storage
+# and the superuser `admin` (NORDNOTES_SUPERUSER=admin NORDNOTES_PASSWORD=admin123),
+# with the user `bob` (password `bob123`) imported from the legacy `users` file.
+
 RUN_FILE=run.sh
 WORK_DIR=$(pwd)
 
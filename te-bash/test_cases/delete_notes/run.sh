@@ -1,8 +1,26 @@
 #!/usr/bin/env bash
 
+# login as administrator
+echo -n '{"login":"admin", "password":"admin123"}' > data.json
+ADMIN_RESULT=$(curl -s -d '@data.json' -H "Content-Type: application/json" -X POST http://0.0.0.0:8871/api/v1/login)
+ADMIN_TOKEN=${ADMIN_RESULT:18:36}
+
+# delete all notes
+curl -s -H "Authorization: Bearer $ADMIN_TOKEN" -X DELETE http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
+
+# login as authorized user
+echo -n '{"login":"bob", "password":"bob123"}' > data.json
+LOGIN_RESULT=$(curl -s -d '@data.json' -H "Content-Type: application/json" -X POST http://0.0.0.0:8871/api/v1/login)
+TOKEN=${LOGIN_RESULT:18:36}
+AUTH_HEADER="Authorization: Bearer $TOKEN"
+JSON_HEADER="Content-Type: application/json"
+
 # create a new note
 echo -n '{"title":"Note1","content":"Content1"}' > data.json
-curl -s -d '@data.json' -H "Content-Type: application/json" -X POST http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
+curl -s -d '@data.json' -H "$AUTH_HEADER" -H "$JSON_HEADER" -X POST http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
 
 # delete all notes
-curl -s -X DELETE http://0.0.0.0:8871/api/v1/notes
+curl -s -H "Authorization: Bearer $ADMIN_TOKEN" -X DELETE http://0.0.0.0:8871/api/v1/notes
+
+# delete data file
+rm data.json
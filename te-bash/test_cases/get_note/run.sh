@@ -1,7 +1,12 @@
 #!/usr/bin/env bash
 
+# login as administrator
+echo -n '{"login":"admin", "password":"admin123"}' > data.json
+ADMIN_RESULT=$(curl -s -d '@data.json' -H "Content-Type: application/json" -X POST http://0.0.0.0:8871/api/v1/login)
+ADMIN_TOKEN=${ADMIN_RESULT:18:36}
+
 # delete all notes
-curl -s -X DELETE http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
+curl -s -H "Authorization: Bearer $ADMIN_TOKEN" -X DELETE http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
 
 # login as authorized user
 echo -n '{"login":"bob", "password":"bob123"}' > data.json
@@ -19,7 +24,7 @@ NOTE_ID=${NOTE:19:36}
 NOTE_DETAILS=$(curl -s -H "$AUTH_HEADER" "http://0.0.0.0:8871/api/v1/notes/$NOTE_ID")
 
 # test if the response matches expected result
-if [[ "$NOTE_DETAILS" =~ ^\{\"data\":\{\"noteId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"title\":\"Note1\",\"content\":\"Content1\"\}\}$ ]]; then
+if [[ "$NOTE_DETAILS" =~ ^\{\"data\":\{\"noteId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"ownerId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"title\":\"Note1\",\"content\":\"Content1\",\"contentFormat\":\"plain\",\"createdAt\":\"[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}\.[0-9]{3}Z\",\"updatedAt\":\"[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}\.[0-9]{3}Z\",\"version\":1,\"tags\":\[\]\}\}$ ]]; then
   echo "YES"
 else
   echo "NO"
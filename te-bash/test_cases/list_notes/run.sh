@@ -1,7 +1,12 @@
 #!/usr/bin/env bash
 
+# login as administrator
+echo -n '{"login":"admin", "password":"admin123"}' > data.json
+ADMIN_RESULT=$(curl -s -d '@data.json' -H "Content-Type: application/json" -X POST http://0.0.0.0:8871/api/v1/login)
+ADMIN_TOKEN=${ADMIN_RESULT:18:36}
+
 # delete all notes
-curl -s -X DELETE http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
+curl -s -H "Authorization: Bearer $ADMIN_TOKEN" -X DELETE http://0.0.0.0:8871/api/v1/notes > /dev/null 2>&1
 
 # login as authorized user
 echo -n '{"login":"bob", "password":"bob123"}' > data.json
@@ -17,11 +22,11 @@ curl -s -d '@data.json' -H "$AUTH_HEADER" -H "$JSON_HEADER" -X POST http://0.0.0
 # delete data file
 rm data.json
 
-# get the list of shared notes
-A=$(curl -s http://0.0.0.0:8871/api/v1/notes)
+# get the list of notes accessible to the user
+A=$(curl -s -H "$AUTH_HEADER" http://0.0.0.0:8871/api/v1/notes)
 
 # test if the response matches expected result
-if [[ "$A" =~ ^\{\"data\":\[\{\"noteId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"title\":\"Note1\"\}\]\}$ ]]; then
+if [[ "$A" =~ ^\{\"data\":\[\{\"noteId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"ownerId\":\"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\",\"title\":\"Note1\",\"contentFormat\":\"plain\",\"createdAt\":\"[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}\.[0-9]{3}Z\",\"updatedAt\":\"[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}\.[0-9]{3}Z\",\"version\":1,\"tags\":\[\]\}\]\}$ ]]; then
   echo "YES"
 else
   echo "NO"